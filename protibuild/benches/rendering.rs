@@ -0,0 +1,170 @@
+//! Headless frame timing of atom and bond rendering.
+//!
+//! Spawns a synthetic chain and times the frames of an app running only the
+//! transform and rendering systems, so the numbers cover building and updating
+//! the residue meshes but not the GPU. Every scenario prints how many residue
+//! meshes were rebuilt per frame and what each rebuild costs on top of an idle
+//! frame. Run with `cargo bench -p protibuild --bench rendering [residues]`.
+
+use std::time::{Duration, Instant};
+
+use bevy::ecs::world::CommandQueue;
+use protibuild::chemistry::rendering::RenderingPlugin;
+use protibuild::prelude::*;
+
+/// Residues of the default chain, about 10,000 atoms with hydrogens.
+const DEFAULT_RESIDUES: usize = 700;
+
+/// Residues repeated along the synthetic chain.
+const SEQUENCE: &str = "ACDEFGHIKLMNPQRSTVWY";
+
+const IDLE_FRAMES: u32 = 100;
+const DRAG_FRAMES: u32 = 100;
+const DYNAMICS_FRAMES: u32 = 20;
+
+/// Residue meshes added or rebuilt since the last frame was timed.
+#[derive(Resource, Default)]
+struct RebuiltMeshes(u32);
+
+/// Average frame time of a scenario and the residue meshes rebuilt per frame.
+struct FrameTiming {
+    frame: Duration,
+    rebuilt: f32,
+}
+
+impl FrameTiming {
+    /// Prints the frame time, and the rebuilds with their cost above `idle`.
+    fn report(&self, name: &str, idle: Option<Duration>) {
+        print!("{:<20}{:>10.2?}", name, self.frame);
+        print!("  {:>8.1} meshes rebuilt per frame", self.rebuilt);
+        if let Some(idle) = idle
+            && self.rebuilt > 0.0
+            && self.frame > idle
+        {
+            let cost = (self.frame - idle).div_f32(self.rebuilt);
+            print!(", {:.2?} per rebuild", cost);
+        }
+        println!();
+    }
+}
+
+fn main() {
+    let residue_count = std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse().ok())
+        .unwrap_or(DEFAULT_RESIDUES);
+    let sequence: String = SEQUENCE.chars().cycle().take(residue_count).collect();
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, TransformPlugin, AssetPlugin::default()))
+        .init_asset::<Mesh>()
+        .init_asset::<StandardMaterial>()
+        .add_plugins(bevy::gizmos::GizmoPlugin)
+        .add_plugins(RenderingPlugin)
+        .init_resource::<RebuiltMeshes>()
+        .add_systems(Last, count_rebuilt_meshes);
+    app.update();
+
+    let residues = {
+        let world = app.world_mut();
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, world);
+        let residues = PeptideBuilder::spawn(
+            &mut commands,
+            &sequence,
+            Vec3::ZERO,
+            "A",
+            &Conformation::AlphaHelix,
+        )
+        .expect("Failed to build the benchmark chain");
+        queue.apply(world);
+        residues
+    };
+
+    let first = time_frames(&mut app, 1, |_, _| {});
+    report_scene(&mut app);
+    first.report("first frame", None);
+
+    let idle = time_frames(&mut app, IDLE_FRAMES, |_, _| {});
+    idle.report("idle frame", None);
+
+    // Drags one residue in the middle of the chain, which redraws it and the
+    // residues it is bonded to.
+    let dragged = residues[residues.len() / 2];
+    let drag = time_frames(&mut app, DRAG_FRAMES, |world, frame| {
+        if let Some(mut transform) = world.get_mut::<Transform>(dragged) {
+            transform.translation.x += (frame as f32 * 0.1).sin() * 0.1;
+        }
+    });
+    drag.report("dragging a residue", Some(idle.frame));
+
+    // Moves every atom, as molecular dynamics does on each tick.
+    let dynamics = time_frames(&mut app, DYNAMICS_FRAMES, |world, frame| {
+        let offset = Vec3::splat((frame as f32).sin() * 0.01);
+        let mut query = world.query_filtered::<&mut Transform, With<Atom>>();
+        for mut transform in query.iter_mut(world) {
+            transform.translation += offset;
+        }
+    });
+    dynamics.report("moving every atom", Some(idle.frame));
+}
+
+fn count_rebuilt_meshes(
+    mut messages: MessageReader<AssetEvent<Mesh>>,
+    mut rebuilt: ResMut<RebuiltMeshes>,
+) {
+    let count = messages
+        .read()
+        .filter(|event| {
+            matches!(
+                event,
+                AssetEvent::Added { .. } | AssetEvent::Modified { .. }
+            )
+        })
+        .count();
+    rebuilt.0 += count as u32;
+}
+
+/// Average duration of `frames` updates, each preceded by `change`, and the
+/// meshes rebuilt per update.
+fn time_frames(app: &mut App, frames: u32, mut change: impl FnMut(&mut World, u32)) -> FrameTiming {
+    app.world_mut().resource_mut::<RebuiltMeshes>().0 = 0;
+    let mut total = Duration::ZERO;
+    for frame in 0..frames {
+        change(app.world_mut(), frame);
+        let start = Instant::now();
+        app.update();
+        total += start.elapsed();
+    }
+    // Mesh asset events reach readers one frame late.
+    app.update();
+    FrameTiming {
+        frame: total / frames,
+        rebuilt: app.world().resource::<RebuiltMeshes>().0 as f32 / frames as f32,
+    }
+}
+
+/// Prints the size of the scene and how many meshes draw it.
+fn report_scene(app: &mut App) {
+    let world = app.world_mut();
+    let atoms = world.query::<&Atom>().iter(world).count();
+    let bonds = world.query::<&Bond>().iter(world).count();
+    let meshes: Vec<Handle<Mesh>> = world
+        .query::<&Mesh3d>()
+        .iter(world)
+        .map(|mesh| mesh.0.clone())
+        .collect();
+    let assets = world.resource::<Assets<Mesh>>();
+    let vertices: usize = meshes
+        .iter()
+        .filter_map(|mesh| assets.get(mesh))
+        .map(Mesh::count_vertices)
+        .sum();
+    println!(
+        "{} atoms and {} bonds drawn with {} meshes of {} vertices",
+        atoms,
+        bonds,
+        meshes.len(),
+        vertices
+    );
+}
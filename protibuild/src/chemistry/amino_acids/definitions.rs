@@ -2,7 +2,12 @@ use bevy::prelude::*;
 
 use super::types::AminoAcidCode;
 use crate::chemistry::atoms::Atom;
-use crate::config::AMINO_ACIDS_CONFIG;
+use crate::chemistry::error::ChemistryError;
+use crate::chemistry::internal_coordinates::InternalCoordinate;
+use crate::config::{AMINO_ACIDS_CONFIG, RESIDUE_GEOMETRY_CONFIG};
+
+/// Atoms lost from the carboxyl group when a residue's C forms a peptide bond.
+pub const C_TERMINAL_LEAVING_ATOMS: [&str; 2] = ["OXT", "HXT"];
 
 /// Complete amino acid definition with atoms and bond connectivity.
 pub struct AminoAcidDefinition {
@@ -12,12 +17,12 @@ pub struct AminoAcidDefinition {
 }
 
 impl AminoAcidDefinition {
-    pub fn get(code: AminoAcidCode) -> Result<Self, String> {
+    pub fn get(code: AminoAcidCode) -> Result<Self, ChemistryError> {
         let config_data = AMINO_ACIDS_CONFIG
             .as_ref()
-            .map_err(|e| format!("Failed to load amino acids config: {}", e))?
+            .map_err(|e| ChemistryError::Config(e.to_string()))?
             .get(&code)
-            .ok_or_else(|| format!("Amino acid {:?} not found in config", code))?;
+            .ok_or(ChemistryError::MissingDefinition(code))?;
 
         let atoms: Vec<Atom> = config_data
             .atoms
@@ -37,4 +42,35 @@ impl AminoAcidDefinition {
 
         Ok(Self { code, atoms, bonds })
     }
+
+    /// Atoms lost from the amine group when this residue's N forms a peptide
+    /// bond. Proline has a single amine hydrogen, so it loses `H` instead of `H2`.
+    pub fn n_terminal_leaving_atoms(&self) -> &'static [&'static str] {
+        match self.code {
+            AminoAcidCode::Pro => &["H"],
+            _ => &["H2"],
+        }
+    }
+
+    /// Internal coordinates of the side-chain and alpha hydrogen atoms, in
+    /// placement order, relative to the backbone N, CA and C.
+    pub fn side_chain_geometry(&self) -> Result<Vec<InternalCoordinate>, ChemistryError> {
+        let config_data = RESIDUE_GEOMETRY_CONFIG
+            .as_ref()
+            .map_err(|e| ChemistryError::Config(e.to_string()))?
+            .get(&self.code)
+            .ok_or(ChemistryError::MissingDefinition(self.code))?;
+
+        Ok(config_data
+            .atoms
+            .iter()
+            .map(|atom_config| atom_config.to_internal_coordinate())
+            .collect())
+    }
+
+    pub fn atom_index(&self, atom_name: &str) -> Option<usize> {
+        self.atoms
+            .iter()
+            .position(|atom| atom.atom_name == atom_name)
+    }
 }
@@ -1,22 +1,34 @@
 use bevy::prelude::*;
 
 pub mod definitions;
+pub mod peptide;
+pub mod rotamers;
 pub mod types;
 
+pub use peptide::{BackboneDihedrals, Conformation, PeptideBuilder};
+pub use rotamers::{ActiveRotamer, RotamerChange, RotamerLibrary, SetRotamerMessage};
 pub use types::AminoAcidCode;
 
-use crate::chemistry::atoms::Bond;
+use crate::chemistry::atoms::{Atom, Bond};
+use crate::chemistry::error::ChemistryError;
+use crate::user::interaction::Movable;
 use definitions::AminoAcidDefinition;
 
-#[derive(Component)]
+/// Chain assigned to residues that are not loaded from a structure file.
+pub const DEFAULT_CHAIN_ID: &str = "A";
+
+#[derive(Component, Clone, Copy, Debug)]
 pub struct AminoAcid {
     pub code: AminoAcidCode,
 }
 
-#[derive(Component)]
+/// Residue numbering and chain membership.
+///
+/// Chain IDs are strings because mmCIF files allow multi-character chain IDs.
+#[derive(Component, Clone, Debug, PartialEq, Eq)]
 pub struct Residue {
-    pub residue_number: u32,
-    pub chain_id: char,
+    pub residue_number: i32,
+    pub chain_id: String,
 }
 
 #[derive(Bundle)]
@@ -31,12 +43,17 @@ pub struct AminoAcidBundle {
 }
 
 impl AminoAcidBundle {
-    pub fn new(code: AminoAcidCode, position: Vec3, residue_number: u32) -> Self {
+    pub fn new(
+        code: AminoAcidCode,
+        position: Vec3,
+        residue_number: i32,
+        chain_id: impl Into<String>,
+    ) -> Self {
         Self {
             amino_acid: AminoAcid { code },
             residue: Residue {
                 residue_number,
-                chain_id: 'A',
+                chain_id: chain_id.into(),
             },
             transform: Transform::from_translation(position),
             global_transform: GlobalTransform::default(),
@@ -54,18 +71,47 @@ impl AminoAcidBuilder {
         commands: &mut Commands,
         code: AminoAcidCode,
         position: Vec3,
-        residue_number: u32,
-    ) -> Result<Entity, String> {
+        residue_number: i32,
+    ) -> Result<Entity, ChemistryError> {
         let definition = AminoAcidDefinition::get(code)?;
 
+        let residue = Residue {
+            residue_number,
+            chain_id: DEFAULT_CHAIN_ID.to_string(),
+        };
+        let (parent, atom_entities) =
+            Self::spawn_residue(commands, code, residue, position, &definition.atoms);
+
+        for (idx1, idx2) in &definition.bonds {
+            Self::spawn_bond(
+                commands,
+                parent,
+                atom_entities[*idx1],
+                atom_entities[*idx2],
+                format!("Bond_{}_{}", idx1, idx2),
+            );
+        }
+
+        Ok(parent)
+    }
+
+    /// Spawns a residue parent with one child entity per atom.
+    ///
+    /// Atom positions are local to `position`. Returns the parent entity and the
+    /// atom entities in the same order as `atoms`.
+    pub fn spawn_residue(
+        commands: &mut Commands,
+        code: AminoAcidCode,
+        residue: Residue,
+        position: Vec3,
+        atoms: &[Atom],
+    ) -> (Entity, Vec<Entity>) {
         let parent = commands
             .spawn((
                 Name::new(format!("AminoAcid_{}", code.three_letter())),
                 AminoAcid { code },
-                Residue {
-                    residue_number,
-                    chain_id: 'A',
-                },
+                residue,
+                Movable,
                 Transform::from_translation(position),
                 GlobalTransform::default(),
                 Visibility::default(),
@@ -75,7 +121,7 @@ impl AminoAcidBuilder {
             .id();
 
         let mut atom_entities: Vec<Entity> = Vec::new();
-        for atom in &definition.atoms {
+        for atom in atoms {
             let atom_entity = commands
                 .spawn((
                     Name::new(format!("Atom_{}", atom.atom_name)),
@@ -92,25 +138,30 @@ impl AminoAcidBuilder {
             atom_entities.push(atom_entity);
         }
 
-        for (idx1, idx2) in &definition.bonds {
-            let bond_entity = commands
-                .spawn((
-                    Name::new(format!("Bond_{}_{}", idx1, idx2)),
-                    Bond {
-                        atom1: atom_entities[*idx1],
-                        atom2: atom_entities[*idx2],
-                    },
-                    Transform::default(),
-                    GlobalTransform::default(),
-                    Visibility::default(),
-                    InheritedVisibility::default(),
-                    ViewVisibility::default(),
-                ))
-                .id();
+        (parent, atom_entities)
+    }
 
-            commands.entity(parent).add_child(bond_entity);
-        }
+    /// Spawns a bond between two atom entities as a child of `parent`.
+    pub fn spawn_bond(
+        commands: &mut Commands,
+        parent: Entity,
+        atom1: Entity,
+        atom2: Entity,
+        name: impl Into<String>,
+    ) -> Entity {
+        let bond_entity = commands
+            .spawn((
+                Name::new(name.into()),
+                Bond { atom1, atom2 },
+                Transform::default(),
+                GlobalTransform::default(),
+                Visibility::default(),
+                InheritedVisibility::default(),
+                ViewVisibility::default(),
+            ))
+            .id();
 
-        Ok(parent)
+        commands.entity(parent).add_child(bond_entity);
+        bond_entity
     }
 }
@@ -0,0 +1,420 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+use super::definitions::{AminoAcidDefinition, C_TERMINAL_LEAVING_ATOMS};
+use super::types::AminoAcidCode;
+use crate::chemistry::error::ChemistryError;
+use crate::chemistry::internal_coordinates::{self, place_atom};
+use crate::chemistry::io::{Structure, StructureAtom, StructureResidue};
+
+/// Standard backbone bond lengths in Ångström.
+const N_CA_LENGTH: f32 = 1.458;
+const CA_C_LENGTH: f32 = 1.525;
+const C_N_LENGTH: f32 = 1.329;
+const C_O_LENGTH: f32 = 1.231;
+const C_OXT_LENGTH: f32 = 1.31;
+const N_H_LENGTH: f32 = 1.01;
+const O_H_LENGTH: f32 = 0.96;
+
+/// Standard backbone bond angles in degrees.
+const N_CA_C_ANGLE: f32 = 111.2;
+const CA_C_N_ANGLE: f32 = 116.2;
+const C_N_CA_ANGLE: f32 = 121.7;
+const CA_C_O_ANGLE: f32 = 120.5;
+const CA_C_OXT_ANGLE: f32 = 114.0;
+const TETRAHEDRAL_ANGLE: f32 = 109.5;
+
+/// Backbone dihedral angles of a residue in degrees.
+///
+/// `phi` is C(i-1)-N-CA-C, `psi` is N-CA-C-N(i+1) and `omega` is the peptide
+/// bond torsion CA-C-N(i+1)-CA(i+1) following the residue.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BackboneDihedrals {
+    pub phi: f32,
+    pub psi: f32,
+    pub omega: f32,
+}
+
+impl BackboneDihedrals {
+    pub const EXTENDED: Self = Self::new(180.0, 180.0, 180.0);
+    pub const ALPHA_HELIX: Self = Self::new(-57.0, -47.0, 180.0);
+    pub const BETA_STRAND: Self = Self::new(-139.0, 135.0, 180.0);
+    pub const POLYPROLINE_II: Self = Self::new(-75.0, 145.0, 180.0);
+
+    pub const fn new(phi: f32, psi: f32, omega: f32) -> Self {
+        Self { phi, psi, omega }
+    }
+}
+
+/// Backbone conformation of a built peptide.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Conformation {
+    #[default]
+    Extended,
+    AlphaHelix,
+    BetaStrand,
+    PolyprolineII,
+    /// One set of dihedrals per residue.
+    Custom(Vec<BackboneDihedrals>),
+}
+
+impl Conformation {
+    /// Backbone dihedrals for each of `residue_count` residues.
+    pub fn dihedrals(
+        &self,
+        residue_count: usize,
+    ) -> Result<Vec<BackboneDihedrals>, ChemistryError> {
+        let uniform = match self {
+            Conformation::Extended => BackboneDihedrals::EXTENDED,
+            Conformation::AlphaHelix => BackboneDihedrals::ALPHA_HELIX,
+            Conformation::BetaStrand => BackboneDihedrals::BETA_STRAND,
+            Conformation::PolyprolineII => BackboneDihedrals::POLYPROLINE_II,
+            Conformation::Custom(dihedrals) if dihedrals.len() == residue_count => {
+                return Ok(dihedrals.clone());
+            }
+            Conformation::Custom(dihedrals) => {
+                return Err(ChemistryError::Geometry(format!(
+                    "{} backbone dihedrals given for {} residues",
+                    dihedrals.len(),
+                    residue_count
+                )));
+            }
+        };
+        Ok(vec![uniform; residue_count])
+    }
+}
+
+/// Builds polypeptide chains from one-letter sequences.
+pub struct PeptideBuilder;
+
+impl PeptideBuilder {
+    /// Parses a one-letter sequence such as `"MKTAYIAKQR"`, ignoring whitespace.
+    pub fn parse_sequence(sequence: &str) -> Result<Vec<AminoAcidCode>, ChemistryError> {
+        sequence
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| {
+                AminoAcidCode::from_one_letter(c)
+                    .ok_or_else(|| ChemistryError::UnknownResidue(c.to_string()))
+            })
+            .collect()
+    }
+
+    /// Builds a linear chain with the given backbone conformation.
+    ///
+    /// Residues are numbered from 1 and share `chain_id`. The leaving-group
+    /// atoms of every peptide bond are removed. Backbone atoms are placed from
+    /// standard bond lengths and angles and the conformation's dihedrals, and
+    /// side chains from the internal coordinates in the residue geometry
+    /// configuration.
+    pub fn build(
+        sequence: &str,
+        chain_id: &str,
+        conformation: &Conformation,
+    ) -> Result<Structure, ChemistryError> {
+        let codes = Self::parse_sequence(sequence)?;
+        let dihedrals = conformation.dihedrals(codes.len())?;
+        let backbone = Self::backbone(&dihedrals);
+
+        let mut structure = Structure::default();
+        let mut serial = 0;
+        let mut previous_carbon: Option<u32> = None;
+
+        for (index, &code) in codes.iter().enumerate() {
+            let definition = AminoAcidDefinition::get(code)?;
+            let is_first = index == 0;
+            let is_last = index + 1 == codes.len();
+
+            let mut leaving: Vec<&str> = Vec::new();
+            if !is_first {
+                leaving.extend(definition.n_terminal_leaving_atoms());
+            }
+            if !is_last {
+                leaving.extend(C_TERMINAL_LEAVING_ATOMS);
+            }
+
+            let positions = Self::residue_positions(
+                &definition,
+                backbone[index],
+                index.checked_sub(1).map(|previous| backbone[previous][2]),
+                backbone.get(index + 1).map(|next| next[0]),
+                dihedrals[index].psi,
+            )?;
+
+            let mut serials: Vec<Option<u32>> = Vec::with_capacity(definition.atoms.len());
+            let mut atoms = Vec::new();
+            for atom in &definition.atoms {
+                if leaving.contains(&atom.atom_name.as_str()) {
+                    serials.push(None);
+                    continue;
+                }
+
+                let position = *positions.get(&atom.atom_name).ok_or_else(|| {
+                    ChemistryError::Geometry(format!(
+                        "no internal coordinates for {} {}",
+                        code.three_letter(),
+                        atom.atom_name
+                    ))
+                })?;
+
+                serial += 1;
+                serials.push(Some(serial));
+                atoms.push(StructureAtom {
+                    serial,
+                    atom_name: atom.atom_name.clone(),
+                    element: atom.element,
+                    position,
+                    b_factor: 0.0,
+                });
+            }
+
+            for &(idx1, idx2) in &definition.bonds {
+                if let (Some(serial1), Some(serial2)) = (serials[idx1], serials[idx2]) {
+                    structure.add_bond(serial1, serial2);
+                }
+            }
+
+            let serial_of = |name: &str| definition.atom_index(name).and_then(|i| serials[i]);
+            if let (Some(carbon), Some(nitrogen)) = (previous_carbon, serial_of("N")) {
+                structure.add_bond(carbon, nitrogen);
+            }
+            previous_carbon = serial_of("C");
+
+            structure.residues.push(StructureResidue {
+                code,
+                residue_number: index as i32 + 1,
+                chain_id: chain_id.to_string(),
+                atoms,
+            });
+        }
+
+        Ok(structure)
+    }
+
+    /// Builds a peptide and spawns one `AminoAcid` entity per residue, with the
+    /// chain's centroid placed at `position`.
+    pub fn spawn(
+        commands: &mut Commands,
+        sequence: &str,
+        position: Vec3,
+        chain_id: &str,
+        conformation: &Conformation,
+    ) -> Result<Vec<Entity>, ChemistryError> {
+        Ok(Self::build(sequence, chain_id, conformation)?.spawn(commands, position))
+    }
+
+    /// N, CA and C positions of each residue, chained together from the
+    /// backbone dihedrals.
+    fn backbone(dihedrals: &[BackboneDihedrals]) -> Vec<[Vec3; 3]> {
+        let Some(first) = dihedrals.first() else {
+            return Vec::new();
+        };
+
+        let n = Vec3::ZERO;
+        let ca = Vec3::X * N_CA_LENGTH;
+        let c = ca + Quat::from_rotation_z(PI - N_CA_C_ANGLE.to_radians()) * Vec3::X * CA_C_LENGTH;
+
+        let mut residues = vec![[n, ca, c]];
+        let mut previous_dihedrals = first;
+        for current in &dihedrals[1..] {
+            let [previous_n, previous_ca, previous_c] = residues[residues.len() - 1];
+            let n = place_atom(
+                previous_n,
+                previous_ca,
+                previous_c,
+                C_N_LENGTH,
+                CA_C_N_ANGLE,
+                previous_dihedrals.psi,
+            );
+            let ca = place_atom(
+                previous_ca,
+                previous_c,
+                n,
+                N_CA_LENGTH,
+                C_N_CA_ANGLE,
+                previous_dihedrals.omega,
+            );
+            let c = place_atom(previous_c, n, ca, CA_C_LENGTH, N_CA_C_ANGLE, current.phi);
+            residues.push([n, ca, c]);
+            previous_dihedrals = current;
+        }
+
+        residues
+    }
+
+    /// Positions of every template atom of a residue, keyed by atom name.
+    ///
+    /// The carbonyl O and amide H lie in the peptide planes shared with the
+    /// neighbouring residues. At the termini, where there is no neighbour, the
+    /// free carboxyl follows `psi` and the amine hydrogens are staggered.
+    fn residue_positions(
+        definition: &AminoAcidDefinition,
+        [n, ca, c]: [Vec3; 3],
+        previous_carbon: Option<Vec3>,
+        next_nitrogen: Option<Vec3>,
+        psi: f32,
+    ) -> Result<HashMap<String, Vec3>, ChemistryError> {
+        let mut positions = HashMap::from([
+            ("N".to_string(), n),
+            ("CA".to_string(), ca),
+            ("C".to_string(), c),
+        ]);
+        internal_coordinates::build(&definition.side_chain_geometry()?, &mut positions)?;
+
+        match next_nitrogen {
+            Some(next_n) => {
+                let o = place_atom(next_n, ca, c, C_O_LENGTH, CA_C_O_ANGLE, 180.0);
+                positions.insert("O".to_string(), o);
+            }
+            None => {
+                let o = place_atom(n, ca, c, C_O_LENGTH, CA_C_O_ANGLE, psi + 180.0);
+                let oxt = place_atom(n, ca, c, C_OXT_LENGTH, CA_C_OXT_ANGLE, psi);
+                let hxt = place_atom(o, c, oxt, O_H_LENGTH, TETRAHEDRAL_ANGLE, 0.0);
+                positions.insert("O".to_string(), o);
+                positions.insert("OXT".to_string(), oxt);
+                positions.insert("HXT".to_string(), hxt);
+            }
+        }
+
+        match previous_carbon {
+            Some(previous_c) => {
+                let angle = (360.0 - C_N_CA_ANGLE) / 2.0;
+                let h = place_atom(previous_c, ca, n, N_H_LENGTH, angle, 180.0);
+                positions.insert("H".to_string(), h);
+            }
+            None => match (definition.code, positions.get("CD")) {
+                // Proline's ring CD takes the place of the second amine hydrogen.
+                (AminoAcidCode::Pro, Some(&cd)) => {
+                    let h = place_atom(cd, ca, n, N_H_LENGTH, TETRAHEDRAL_ANGLE, 120.0);
+                    positions.insert("H".to_string(), h);
+                }
+                _ => {
+                    let h = place_atom(c, ca, n, N_H_LENGTH, TETRAHEDRAL_ANGLE, 180.0);
+                    let h2 = place_atom(c, ca, n, N_H_LENGTH, TETRAHEDRAL_ANGLE, 60.0);
+                    positions.insert("H".to_string(), h);
+                    positions.insert("H2".to_string(), h2);
+                }
+            },
+        }
+
+        Ok(positions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chemistry::internal_coordinates::torsion_angle;
+
+    fn atom<'a>(structure: &'a Structure, residue: usize, name: &str) -> Option<&'a StructureAtom> {
+        structure.residues[residue]
+            .atoms
+            .iter()
+            .find(|atom| atom.atom_name == name)
+    }
+
+    fn names(structure: &Structure, residue: usize) -> Vec<&str> {
+        structure.residues[residue]
+            .atoms
+            .iter()
+            .map(|atom| atom.atom_name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn dipeptide_is_joined_by_a_planar_peptide_bond() {
+        let structure = PeptideBuilder::build("GA", "A", &Conformation::Extended).unwrap();
+        assert_eq!(structure.residues.len(), 2);
+
+        // The leaving atoms are only removed from the joined ends.
+        let (first, second) = (names(&structure, 0), names(&structure, 1));
+        assert!(!first.contains(&"OXT") && !first.contains(&"HXT"));
+        assert!(first.contains(&"H") && first.contains(&"H2"));
+        assert!(!second.contains(&"H2"));
+        assert!(second.contains(&"H") && second.contains(&"OXT") && second.contains(&"HXT"));
+
+        let c = atom(&structure, 0, "C").unwrap();
+        let n = atom(&structure, 1, "N").unwrap();
+        assert!(
+            structure
+                .bonds()
+                .iter()
+                .any(|&bond| bond == (c.serial, n.serial) || bond == (n.serial, c.serial))
+        );
+        assert!((c.position.distance(n.position) - C_N_LENGTH).abs() < 1e-3);
+
+        let omega = torsion_angle(
+            atom(&structure, 0, "CA").unwrap().position,
+            c.position,
+            n.position,
+            atom(&structure, 1, "CA").unwrap().position,
+        );
+        assert!((omega.abs() - 180.0).abs() < 0.1, "omega {}", omega);
+
+        // Every bond joins atoms that were kept.
+        let serials: Vec<u32> = structure.atoms().map(|atom| atom.serial).collect();
+        assert!(
+            structure
+                .bonds()
+                .iter()
+                .all(|(serial1, serial2)| serials.contains(serial1) && serials.contains(serial2))
+        );
+    }
+
+    #[test]
+    fn custom_dihedrals_are_measured_back() {
+        let dihedrals = vec![
+            BackboneDihedrals::new(-60.0, -45.0, 175.0),
+            BackboneDihedrals::new(-120.0, 130.0, 180.0),
+            BackboneDihedrals::new(-75.0, 145.0, 180.0),
+        ];
+        let structure =
+            PeptideBuilder::build("AAA", "A", &Conformation::Custom(dihedrals.clone())).unwrap();
+        let position =
+            |residue: usize, name: &str| atom(&structure, residue, name).unwrap().position;
+
+        for (residue, expected) in dihedrals.iter().enumerate().skip(1) {
+            let phi = torsion_angle(
+                position(residue - 1, "C"),
+                position(residue, "N"),
+                position(residue, "CA"),
+                position(residue, "C"),
+            );
+            assert!((phi - expected.phi).abs() < 0.1, "phi {}", phi);
+        }
+        for (residue, expected) in dihedrals.iter().enumerate().take(2) {
+            let psi = torsion_angle(
+                position(residue, "N"),
+                position(residue, "CA"),
+                position(residue, "C"),
+                position(residue + 1, "N"),
+            );
+            let omega = torsion_angle(
+                position(residue, "CA"),
+                position(residue, "C"),
+                position(residue + 1, "N"),
+                position(residue + 1, "CA"),
+            );
+            assert!((psi - expected.psi).abs() < 0.1, "psi {}", psi);
+            assert!(
+                ((omega - expected.omega + 180.0).rem_euclid(360.0) - 180.0).abs() < 0.1,
+                "omega {}",
+                omega
+            );
+        }
+
+        assert!(matches!(
+            PeptideBuilder::build("AA", "A", &Conformation::Custom(dihedrals)),
+            Err(ChemistryError::Geometry(_))
+        ));
+    }
+
+    #[test]
+    fn proline_loses_its_amide_hydrogen_when_joined() {
+        let structure = PeptideBuilder::build("AP", "A", &Conformation::Extended).unwrap();
+        let proline = names(&structure, 1);
+        assert!(!proline.contains(&"H") && !proline.contains(&"H2"));
+        assert!(proline.contains(&"CD"));
+    }
+}
@@ -0,0 +1,437 @@
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+use super::AminoAcid;
+use super::types::AminoAcidCode;
+use crate::chemistry::atoms::{Atom, Bond};
+use crate::chemistry::error::ChemistryError;
+use crate::chemistry::internal_coordinates::torsion_angle;
+use crate::config::ROTAMER_LIBRARY_CONFIG;
+
+/// A named side-chain conformation with one value per chi angle, in degrees.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rotamer {
+    pub name: String,
+    pub chi: Vec<f32>,
+}
+
+/// Chi angle definitions and common rotamers of one amino acid.
+///
+/// Residues without rotatable side-chain bonds (Gly, Ala) and proline, whose
+/// ring cannot be rotated rigidly, have no chi angles and no rotamers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RotamerLibrary {
+    pub code: AminoAcidCode,
+    /// The four atoms defining each chi angle; rotation is about the middle bond.
+    pub chi_angles: Vec<[String; 4]>,
+    pub rotamers: Vec<Rotamer>,
+}
+
+impl RotamerLibrary {
+    pub fn get(code: AminoAcidCode) -> Result<Self, ChemistryError> {
+        let config = ROTAMER_LIBRARY_CONFIG
+            .as_ref()
+            .map_err(|e| ChemistryError::Config(e.to_string()))?;
+
+        let Some(config_data) = config.get(&code) else {
+            return Ok(Self {
+                code,
+                chi_angles: Vec::new(),
+                rotamers: Vec::new(),
+            });
+        };
+
+        Ok(Self {
+            code,
+            chi_angles: config_data.chi_angles.clone(),
+            rotamers: config_data
+                .rotamers
+                .iter()
+                .map(|rotamer| Rotamer {
+                    name: rotamer.name.clone(),
+                    chi: rotamer.chi.clone(),
+                })
+                .collect(),
+        })
+    }
+
+    pub fn rotamer_index(&self, name: &str) -> Option<usize> {
+        self.rotamers
+            .iter()
+            .position(|rotamer| rotamer.name == name)
+    }
+
+    /// Sets the leading chi angles of a residue to `chi` by rotating every atom
+    /// beyond each chi bond about that bond.
+    ///
+    /// `atom_names` and `positions` describe the residue's atoms and `bonds`
+    /// index into them. Chi angles beyond the length of `chi` are left as
+    /// they are.
+    pub fn set_chi_angles(
+        &self,
+        atom_names: &[&str],
+        positions: &mut [Vec3],
+        bonds: &[(usize, usize)],
+        chi: &[f32],
+    ) -> Result<(), ChemistryError> {
+        if chi.len() > self.chi_angles.len() {
+            return Err(ChemistryError::Geometry(format!(
+                "{} has {} chi angles, {} given",
+                self.code.three_letter(),
+                self.chi_angles.len(),
+                chi.len()
+            )));
+        }
+
+        let index_of = |name: &str| {
+            atom_names
+                .iter()
+                .position(|atom_name| *atom_name == name)
+                .ok_or_else(|| {
+                    ChemistryError::Geometry(format!(
+                        "{} has no {} atom",
+                        self.code.three_letter(),
+                        name
+                    ))
+                })
+        };
+
+        for (definition, &target) in self.chi_angles.iter().zip(chi) {
+            let [a, b, c, d] = [
+                index_of(&definition[0])?,
+                index_of(&definition[1])?,
+                index_of(&definition[2])?,
+                index_of(&definition[3])?,
+            ];
+            let moving = Self::atoms_beyond(bonds, b, c).ok_or_else(|| {
+                ChemistryError::Geometry(format!(
+                    "{}-{} bond of {} is part of a ring",
+                    definition[1],
+                    definition[2],
+                    self.code.three_letter()
+                ))
+            })?;
+
+            let current = torsion_angle(positions[a], positions[b], positions[c], positions[d]);
+            let axis = (positions[c] - positions[b]).normalize();
+            let rotation = Quat::from_axis_angle(axis, (target - current).to_radians());
+            let pivot = positions[c];
+            for index in moving {
+                positions[index] = pivot + rotation * (positions[index] - pivot);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Atoms reachable from `far` without crossing the `near`-`far` bond, or
+    /// `None` if the bond is part of a ring.
+    fn atoms_beyond(bonds: &[(usize, usize)], near: usize, far: usize) -> Option<HashSet<usize>> {
+        let mut visited = HashSet::from([far]);
+        let mut stack = vec![far];
+        while let Some(atom) = stack.pop() {
+            for &(atom1, atom2) in bonds {
+                let neighbour = match (atom1 == atom, atom2 == atom) {
+                    (true, _) => atom2,
+                    (_, true) => atom1,
+                    _ => continue,
+                };
+                if neighbour == near {
+                    if atom != far {
+                        return None;
+                    }
+                    continue;
+                }
+                if visited.insert(neighbour) {
+                    stack.push(neighbour);
+                }
+            }
+        }
+        Some(visited)
+    }
+}
+
+/// Index into the residue's [`RotamerLibrary`] of the rotamer last applied to
+/// it, or `None` after arbitrary chi angles were set.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ActiveRotamer {
+    pub index: Option<usize>,
+}
+
+/// Side-chain change requested by a [`SetRotamerMessage`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum RotamerChange {
+    /// A rotamer from the library by name.
+    Named(String),
+    /// The rotamer after the active one, wrapping around.
+    Next,
+    /// The rotamer before the active one, wrapping around.
+    Previous,
+    /// Arbitrary chi angles in degrees, starting at chi1.
+    ChiAngles(Vec<f32>),
+}
+
+/// Message to change the side-chain conformation of a residue entity.
+#[derive(Message, Debug, Clone)]
+pub struct SetRotamerMessage {
+    pub residue: Entity,
+    pub change: RotamerChange,
+}
+
+pub struct RotamerPlugin;
+
+impl Plugin for RotamerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<SetRotamerMessage>()
+            .add_systems(Update, handle_set_rotamer);
+    }
+}
+
+fn handle_set_rotamer(
+    mut commands: Commands,
+    mut messages: MessageReader<SetRotamerMessage>,
+    residue_query: Query<(&AminoAcid, &Children, Option<&ActiveRotamer>)>,
+    mut atom_query: Query<(&mut Atom, &mut Transform)>,
+    bond_query: Query<&Bond>,
+) {
+    for message in messages.read() {
+        let Ok((amino_acid, children, active)) = residue_query.get(message.residue) else {
+            continue;
+        };
+
+        let result = RotamerLibrary::get(amino_acid.code).and_then(|library| {
+            let (chi, index) = target_chi_angles(&library, &message.change, active)?;
+
+            let mut atoms = Vec::new();
+            let mut names = Vec::new();
+            let mut positions = Vec::new();
+            let mut residue_bonds = Vec::new();
+            for child in children.iter() {
+                if let Ok((atom, transform)) = atom_query.get(child) {
+                    atoms.push(child);
+                    names.push(atom.atom_name.as_str());
+                    positions.push(transform.translation);
+                } else if let Ok(bond) = bond_query.get(child) {
+                    residue_bonds.push(bond);
+                }
+            }
+
+            let indices: HashMap<Entity, usize> = atoms
+                .iter()
+                .enumerate()
+                .map(|(index, &entity)| (entity, index))
+                .collect();
+            let bonds: Vec<(usize, usize)> = residue_bonds
+                .into_iter()
+                .filter_map(|bond| Some((*indices.get(&bond.atom1)?, *indices.get(&bond.atom2)?)))
+                .collect();
+
+            library.set_chi_angles(&names, &mut positions, &bonds, &chi)?;
+            Ok((atoms, positions, index))
+        });
+
+        let (atoms, positions, index) = match result {
+            Ok(updated) => updated,
+            Err(e) => {
+                error!("Failed to set rotamer: {}", e);
+                continue;
+            }
+        };
+
+        for (entity, position) in atoms.into_iter().zip(positions) {
+            if let Ok((mut atom, mut transform)) = atom_query.get_mut(entity)
+                && transform.translation != position
+            {
+                atom.position = position;
+                transform.translation = position;
+            }
+        }
+
+        commands
+            .entity(message.residue)
+            .insert(ActiveRotamer { index });
+    }
+}
+
+/// Chi angles to apply for a change, and the library index of the rotamer
+/// they come from.
+fn target_chi_angles(
+    library: &RotamerLibrary,
+    change: &RotamerChange,
+    active: Option<&ActiveRotamer>,
+) -> Result<(Vec<f32>, Option<usize>), ChemistryError> {
+    let count = library.rotamers.len();
+    let current = active.and_then(|active| active.index);
+    let index = match change {
+        RotamerChange::ChiAngles(chi) => return Ok((chi.clone(), None)),
+        RotamerChange::Next | RotamerChange::Previous if count == 0 => {
+            return Err(ChemistryError::Geometry(format!(
+                "{} has no rotamers",
+                library.code.three_letter()
+            )));
+        }
+        RotamerChange::Named(name) => library.rotamer_index(name).ok_or_else(|| {
+            ChemistryError::Geometry(format!(
+                "{} has no rotamer {}",
+                library.code.three_letter(),
+                name
+            ))
+        })?,
+        RotamerChange::Next => current.map_or(0, |index| (index + 1) % count),
+        RotamerChange::Previous => current.map_or(count - 1, |index| (index + count - 1) % count),
+    };
+
+    Ok((library.rotamers[index].chi.clone(), Some(index)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chemistry::amino_acids::{Conformation, PeptideBuilder};
+
+    /// Atom names, positions and bonds of a single residue built from the
+    /// residue geometry, whose side chain is not planar like the templates'.
+    fn residue(code: AminoAcidCode) -> (Vec<String>, Vec<Vec3>, Vec<(usize, usize)>) {
+        let sequence = code.one_letter().to_string();
+        let structure = PeptideBuilder::build(&sequence, "A", &Conformation::Extended).unwrap();
+        let atoms = &structure.residues[0].atoms;
+        let index = |serial: u32| atoms.iter().position(|atom| atom.serial == serial);
+        let bonds = structure
+            .bonds()
+            .iter()
+            .filter_map(|&(serial1, serial2)| Some((index(serial1)?, index(serial2)?)))
+            .collect();
+        (
+            atoms.iter().map(|atom| atom.atom_name.clone()).collect(),
+            atoms.iter().map(|atom| atom.position).collect(),
+            bonds,
+        )
+    }
+
+    fn measure_chi(library: &RotamerLibrary, names: &[&str], positions: &[Vec3]) -> Vec<f32> {
+        let position = |name: &String| positions[names.iter().position(|n| n == name).unwrap()];
+        library
+            .chi_angles
+            .iter()
+            .map(|[a, b, c, d]| torsion_angle(position(a), position(b), position(c), position(d)))
+            .collect()
+    }
+
+    #[test]
+    fn set_chi_angles_measure_back() {
+        let library = RotamerLibrary::get(AminoAcidCode::Lys).unwrap();
+        let (names, mut positions, bonds) = residue(AminoAcidCode::Lys);
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+
+        for rotamer in &library.rotamers {
+            library
+                .set_chi_angles(&names, &mut positions, &bonds, &rotamer.chi)
+                .unwrap();
+            let measured = measure_chi(&library, &names, &positions);
+            for (measured, target) in measured.iter().zip(&rotamer.chi) {
+                let difference = (measured - target + 180.0).rem_euclid(360.0) - 180.0;
+                assert!(
+                    difference.abs() < 0.05,
+                    "{}: chi {} measured as {}",
+                    rotamer.name,
+                    target,
+                    measured
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn only_atoms_beyond_the_chi_bond_move() {
+        let library = RotamerLibrary::get(AminoAcidCode::Lys).unwrap();
+        let (names, original, bonds) = residue(AminoAcidCode::Lys);
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        let index = |name: &str| names.iter().position(|n| *n == name).unwrap();
+
+        // Changing chi2 alone rotates about CB-CG.
+        let mut chi = measure_chi(&library, &names, &original);
+        chi.truncate(2);
+        chi[1] += 90.0;
+        let mut positions = original.clone();
+        library
+            .set_chi_angles(&names, &mut positions, &bonds, &chi)
+            .unwrap();
+
+        let beyond = RotamerLibrary::atoms_beyond(&bonds, index("CB"), index("CG")).unwrap();
+        for name in ["CD", "CE", "NZ"] {
+            assert!(beyond.contains(&index(name)), "{} is beyond CB-CG", name);
+        }
+        for name in ["N", "CA", "C", "O", "CB"] {
+            assert!(
+                !beyond.contains(&index(name)),
+                "{} is not beyond CB-CG",
+                name
+            );
+        }
+        for (atom, (before, after)) in original.iter().zip(&positions).enumerate() {
+            if !beyond.contains(&atom) {
+                assert!(before.distance(*after) < 1e-5, "{} moved", names[atom]);
+            }
+        }
+        assert!(original[index("NZ")].distance(positions[index("NZ")]) > 0.5);
+
+        // Bond lengths within the moved side chain are kept.
+        for &(atom1, atom2) in &bonds {
+            let before = original[atom1].distance(original[atom2]);
+            let after = positions[atom1].distance(positions[atom2]);
+            assert!((before - after).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn ring_bonds_and_extra_chi_angles_are_rejected() {
+        let (names, mut positions, bonds) = residue(AminoAcidCode::Phe);
+        let index = |name: &str| names.iter().position(|n| n == name).unwrap();
+        assert!(RotamerLibrary::atoms_beyond(&bonds, index("CG"), index("CD1")).is_none());
+        assert!(RotamerLibrary::atoms_beyond(&bonds, index("CB"), index("CG")).is_some());
+
+        let library = RotamerLibrary::get(AminoAcidCode::Phe).unwrap();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        assert!(
+            library
+                .set_chi_angles(&names, &mut positions, &bonds, &[60.0, 90.0, 0.0])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn target_chi_angles_follow_the_change() {
+        let library = RotamerLibrary::get(AminoAcidCode::Ser).unwrap();
+        let active = |index| ActiveRotamer { index: Some(index) };
+        let target = |change: RotamerChange, active: Option<ActiveRotamer>| {
+            target_chi_angles(&library, &change, active.as_ref()).map(|(_, index)| index)
+        };
+
+        assert_eq!(target(RotamerChange::Next, None).unwrap(), Some(0));
+        assert_eq!(
+            target(RotamerChange::Next, Some(active(2))).unwrap(),
+            Some(0)
+        );
+        assert_eq!(target(RotamerChange::Previous, None).unwrap(), Some(2));
+        assert_eq!(
+            target(RotamerChange::Previous, Some(active(0))).unwrap(),
+            Some(2)
+        );
+        assert_eq!(
+            target(RotamerChange::Named("m".to_string()), None).unwrap(),
+            Some(2)
+        );
+        assert!(target(RotamerChange::Named("x".to_string()), None).is_err());
+
+        let (chi, index) = target_chi_angles(
+            &library,
+            &RotamerChange::ChiAngles(vec![-40.0]),
+            Some(&active(1)),
+        )
+        .unwrap();
+        assert_eq!((chi, index), (vec![-40.0], None));
+
+        let alanine = RotamerLibrary::get(AminoAcidCode::Ala).unwrap();
+        assert!(target_chi_angles(&alanine, &RotamerChange::Next, None).is_err());
+    }
+}
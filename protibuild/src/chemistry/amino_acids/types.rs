@@ -24,6 +24,43 @@ pub enum AminoAcidCode {
 }
 
 impl AminoAcidCode {
+    pub const ALL: [AminoAcidCode; 20] = [
+        AminoAcidCode::Gly,
+        AminoAcidCode::Ala,
+        AminoAcidCode::Ser,
+        AminoAcidCode::Cys,
+        AminoAcidCode::Pro,
+        AminoAcidCode::Val,
+        AminoAcidCode::Ile,
+        AminoAcidCode::Leu,
+        AminoAcidCode::Met,
+        AminoAcidCode::Phe,
+        AminoAcidCode::Tyr,
+        AminoAcidCode::Trp,
+        AminoAcidCode::Asn,
+        AminoAcidCode::Gln,
+        AminoAcidCode::Thr,
+        AminoAcidCode::Asp,
+        AminoAcidCode::Glu,
+        AminoAcidCode::Lys,
+        AminoAcidCode::Arg,
+        AminoAcidCode::His,
+    ];
+
+    /// Looks up a residue by its (case-insensitive) three-letter code.
+    pub fn from_three_letter(code: &str) -> Option<Self> {
+        let code = code.trim();
+        Self::ALL
+            .into_iter()
+            .find(|aa| aa.three_letter().eq_ignore_ascii_case(code))
+    }
+
+    /// Looks up a residue by its (case-insensitive) one-letter code.
+    pub fn from_one_letter(code: char) -> Option<Self> {
+        let code = code.to_ascii_uppercase();
+        Self::ALL.into_iter().find(|aa| aa.one_letter() == code)
+    }
+
     pub fn name(&self) -> &'static str {
         match self {
             AminoAcidCode::Gly => "Glycine",
@@ -98,4 +135,31 @@ impl AminoAcidCode {
             AminoAcidCode::His => "HIS",
         }
     }
+
+    /// Kyte-Doolittle hydropathy index, from -4.5 (most hydrophilic) to 4.5
+    /// (most hydrophobic).
+    pub fn hydropathy(&self) -> f32 {
+        match self {
+            AminoAcidCode::Gly => -0.4,
+            AminoAcidCode::Ala => 1.8,
+            AminoAcidCode::Ser => -0.8,
+            AminoAcidCode::Cys => 2.5,
+            AminoAcidCode::Pro => -1.6,
+            AminoAcidCode::Val => 4.2,
+            AminoAcidCode::Ile => 4.5,
+            AminoAcidCode::Leu => 3.8,
+            AminoAcidCode::Met => 1.9,
+            AminoAcidCode::Phe => 2.8,
+            AminoAcidCode::Tyr => -1.3,
+            AminoAcidCode::Trp => -0.9,
+            AminoAcidCode::Asn => -3.5,
+            AminoAcidCode::Gln => -3.5,
+            AminoAcidCode::Thr => -0.7,
+            AminoAcidCode::Asp => -3.5,
+            AminoAcidCode::Glu => -3.5,
+            AminoAcidCode::Lys => -3.9,
+            AminoAcidCode::Arg => -4.5,
+            AminoAcidCode::His => -3.2,
+        }
+    }
 }
@@ -1,3 +1,6 @@
+use bevy::ecs::lifecycle::HookContext;
+use bevy::ecs::system::SystemParam;
+use bevy::ecs::world::DeferredWorld;
 use bevy::prelude::*;
 
 /// Chemical elements supported by the simulator.
@@ -11,6 +14,38 @@ pub enum Element {
 }
 
 impl Element {
+    /// Looks up an element by its (case-insensitive) chemical symbol.
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        match symbol.trim().to_ascii_uppercase().as_str() {
+            "H" | "D" => Some(Element::Hydrogen),
+            "C" => Some(Element::Carbon),
+            "N" => Some(Element::Nitrogen),
+            "O" => Some(Element::Oxygen),
+            "S" => Some(Element::Sulfur),
+            _ => None,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Element::Hydrogen => "H",
+            Element::Carbon => "C",
+            Element::Nitrogen => "N",
+            Element::Oxygen => "O",
+            Element::Sulfur => "S",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Element::Hydrogen => "Hydrogen",
+            Element::Carbon => "Carbon",
+            Element::Nitrogen => "Nitrogen",
+            Element::Oxygen => "Oxygen",
+            Element::Sulfur => "Sulfur",
+        }
+    }
+
     pub fn covalent_radius(&self) -> f32 {
         match self {
             Element::Hydrogen => 0.31,
@@ -21,6 +56,17 @@ impl Element {
         }
     }
 
+    /// Van der Waals radius in Å (Bondi), used for space-filling models.
+    pub fn van_der_waals_radius(&self) -> f32 {
+        match self {
+            Element::Hydrogen => 1.20,
+            Element::Carbon => 1.70,
+            Element::Nitrogen => 1.55,
+            Element::Oxygen => 1.52,
+            Element::Sulfur => 1.80,
+        }
+    }
+
     pub fn color(&self) -> Color {
         match self {
             Element::Hydrogen => Color::srgb(1.0, 1.0, 1.0),
@@ -34,10 +80,13 @@ impl Element {
 
 /// A single atom with element type, position, and name.
 #[derive(Component, Clone, Debug)]
+#[require(AtomBonds)]
 pub struct Atom {
     pub element: Element,
     pub position: Vec3,
     pub atom_name: String,
+    /// Temperature factor read from a structure file, zero otherwise.
+    pub b_factor: f32,
 }
 
 impl Atom {
@@ -46,8 +95,14 @@ impl Atom {
             element,
             position,
             atom_name: atom_name.into(),
+            b_factor: 0.0,
         }
     }
+
+    pub fn with_b_factor(mut self, b_factor: f32) -> Self {
+        self.b_factor = b_factor;
+        self
+    }
 }
 
 #[derive(Bundle)]
@@ -74,13 +129,56 @@ impl AtomBundle {
     }
 }
 
-/// A bond connecting two atoms.
+/// A bond connecting two atoms. Inserting or removing it updates the
+/// [`AtomBonds`] of both atoms.
 #[derive(Component)]
+#[component(on_insert = index_bond, on_replace = unindex_bond)]
 pub struct Bond {
     pub atom1: Entity,
     pub atom2: Entity,
 }
 
+/// Bond entities an atom takes part in, so systems reacting to moved atoms
+/// only visit their bonds instead of every bond in the scene.
+#[derive(Component, Clone, Debug, Default, PartialEq)]
+pub struct AtomBonds(Vec<Entity>);
+
+impl AtomBonds {
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.0.iter().copied()
+    }
+}
+
+fn index_bond(mut world: DeferredWorld, context: HookContext) {
+    let Some(atoms) = world
+        .get::<Bond>(context.entity)
+        .map(|bond| [bond.atom1, bond.atom2])
+    else {
+        return;
+    };
+    for atom in atoms {
+        if let Some(mut bonds) = world.get_mut::<AtomBonds>(atom)
+            && !bonds.0.contains(&context.entity)
+        {
+            bonds.0.push(context.entity);
+        }
+    }
+}
+
+fn unindex_bond(mut world: DeferredWorld, context: HookContext) {
+    let Some(atoms) = world
+        .get::<Bond>(context.entity)
+        .map(|bond| [bond.atom1, bond.atom2])
+    else {
+        return;
+    };
+    for atom in atoms {
+        if let Some(mut bonds) = world.get_mut::<AtomBonds>(atom) {
+            bonds.0.retain(|&entity| entity != context.entity);
+        }
+    }
+}
+
 #[derive(Bundle)]
 pub struct BondBundle {
     pub bond: Bond,
@@ -103,3 +201,60 @@ impl BondBundle {
         }
     }
 }
+
+/// World-space access to atom positions, which are stored relative to the
+/// atom's residue.
+#[derive(SystemParam)]
+pub struct AtomPositions<'w, 's> {
+    atoms: Query<'w, 's, (&'static mut Atom, &'static mut Transform, &'static ChildOf)>,
+    parents: Query<'w, 's, &'static GlobalTransform>,
+}
+
+impl AtomPositions<'_, '_> {
+    /// World-space position of an atom entity.
+    pub fn get(&self, entity: Entity) -> Option<Vec3> {
+        let (_, transform, child_of) = self.atoms.get(entity).ok()?;
+        Some(
+            self.parent_transform(child_of)
+                .transform_point(transform.translation),
+        )
+    }
+
+    /// The residue entity an atom belongs to.
+    pub fn residue(&self, entity: Entity) -> Option<Entity> {
+        self.atoms
+            .get(entity)
+            .ok()
+            .map(|(_, _, child_of)| child_of.parent())
+    }
+
+    /// Moves atoms to world-space positions, updating both `Atom::position`
+    /// and the `Transform`. Atoms already in place are left untouched, so
+    /// only moved atoms are seen as changed.
+    pub fn set_all(&mut self, positions: impl IntoIterator<Item = (Entity, Vec3)>) {
+        for (entity, position) in positions {
+            let Ok((_, _, child_of)) = self.atoms.get(entity) else {
+                continue;
+            };
+            let local = self
+                .parent_transform(child_of)
+                .affine()
+                .inverse()
+                .transform_point3(position);
+
+            if let Ok((mut atom, mut transform, _)) = self.atoms.get_mut(entity)
+                && transform.translation != local
+            {
+                atom.position = local;
+                transform.translation = local;
+            }
+        }
+    }
+
+    fn parent_transform(&self, child_of: &ChildOf) -> GlobalTransform {
+        self.parents
+            .get(child_of.parent())
+            .copied()
+            .unwrap_or_default()
+    }
+}
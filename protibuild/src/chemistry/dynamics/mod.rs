@@ -0,0 +1,484 @@
+//! Molecular dynamics of the scene's atoms.
+//!
+//! Atoms are integrated with velocity Verlet in `FixedUpdate` using the forces
+//! of the force field. Positions are in Ångström, time in picoseconds and
+//! velocities in Å/ps.
+
+use bevy::prelude::*;
+use rand::Rng;
+use rand_distr::{Distribution, StandardNormal};
+
+use crate::chemistry::atoms::AtomPositions;
+use crate::chemistry::force_field::{AtomForce, MolecularSystem, Topology};
+
+/// Converts an acceleration in kcal/(mol·Å·amu) to Å/ps².
+const ACCELERATION_UNIT: f32 = 418.4;
+
+/// Boltzmann constant in kcal/(mol·K).
+pub const BOLTZMANN: f32 = 0.001_987_204;
+
+/// Bounds on the Berendsen velocity scaling factor of a single step, keeping
+/// far-from-equilibrium starts from rescaling violently.
+const MIN_BERENDSEN_SCALE: f32 = 0.8;
+const MAX_BERENDSEN_SCALE: f32 = 1.25;
+
+/// Temperature coupling applied after every step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Thermostat {
+    /// Constant energy.
+    None,
+    /// Scales velocities towards `temperature` (K) with the relaxation time
+    /// `coupling_time` (ps).
+    Berendsen {
+        temperature: f32,
+        coupling_time: f32,
+    },
+}
+
+impl Thermostat {
+    /// Temperature the thermostat couples to, in K.
+    pub fn temperature(&self) -> Option<f32> {
+        match self {
+            Thermostat::None => None,
+            Thermostat::Berendsen { temperature, .. } => Some(*temperature),
+        }
+    }
+}
+
+/// Integration settings.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct DynamicsSettings {
+    /// Integration timestep in picoseconds.
+    pub timestep: f32,
+    /// Steps integrated per `FixedUpdate` tick while running.
+    pub steps_per_update: u32,
+    pub thermostat: Thermostat,
+    /// Speed limit in Å/ps. Strained starting structures have huge forces
+    /// that would otherwise throw atoms apart in a single step. This is a
+    /// non-conservative safety limit: velocities that exceed it are scaled
+    /// down, removing kinetic energy, and a warning is logged when it starts
+    /// applying.
+    pub max_speed: f32,
+}
+
+impl Default for DynamicsSettings {
+    fn default() -> Self {
+        Self {
+            timestep: 0.001,
+            steps_per_update: 10,
+            thermostat: Thermostat::Berendsen {
+                temperature: 300.0,
+                coupling_time: 0.1,
+            },
+            max_speed: 50.0,
+        }
+    }
+}
+
+/// Progress of the simulation.
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct DynamicsState {
+    pub running: bool,
+    /// Single steps requested while paused.
+    pub pending_steps: u32,
+    pub step: u64,
+    /// Simulated time in picoseconds.
+    pub time: f64,
+    /// Kinetic and potential energy after the last step, in kcal/mol.
+    pub kinetic_energy: f64,
+    pub potential_energy: f64,
+    /// Instantaneous temperature after the last step, in K.
+    pub temperature: f32,
+    /// Whether [`DynamicsSettings::max_speed`] limited any velocity during the
+    /// last update.
+    pub speed_limited: bool,
+}
+
+/// World-space velocity of an atom in Å/ps.
+///
+/// Atoms without a velocity start from a Maxwell–Boltzmann distribution at the
+/// thermostat's temperature, or at rest without a thermostat.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct AtomVelocity(pub Vec3);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DynamicsControl {
+    Start,
+    Pause,
+    /// Starts a paused simulation or pauses a running one.
+    Toggle,
+    /// Integrates a single step while paused.
+    Step,
+}
+
+/// Message to start, pause or step the simulation.
+#[derive(Message, Debug, Clone)]
+pub struct DynamicsControlMessage {
+    pub control: DynamicsControl,
+}
+
+pub struct DynamicsPlugin;
+
+impl Plugin for DynamicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DynamicsSettings>()
+            .init_resource::<DynamicsState>()
+            .add_message::<DynamicsControlMessage>()
+            .add_systems(Update, handle_dynamics_control)
+            .add_systems(FixedUpdate, integrate);
+    }
+}
+
+fn handle_dynamics_control(
+    mut messages: MessageReader<DynamicsControlMessage>,
+    mut state: ResMut<DynamicsState>,
+) {
+    for message in messages.read() {
+        match message.control {
+            DynamicsControl::Start => state.running = true,
+            DynamicsControl::Pause => state.running = false,
+            DynamicsControl::Toggle => state.running = !state.running,
+            DynamicsControl::Step if !state.running => state.pending_steps += 1,
+            DynamicsControl::Step => {}
+        }
+    }
+}
+
+/// Integrator working on world-space copies of the atom positions.
+struct Integrator<'a> {
+    topology: &'a Topology,
+    settings: DynamicsSettings,
+    positions: Vec<Vec3>,
+    velocities: Vec<Vec3>,
+    forces: Vec<Vec3>,
+    potential_energy: f64,
+    /// Velocities limited to the maximum speed so far.
+    speed_limited: usize,
+}
+
+impl Integrator<'_> {
+    fn step(&mut self) {
+        let dt = self.settings.timestep;
+        self.half_kick(dt);
+        for (position, velocity) in self.positions.iter_mut().zip(&self.velocities) {
+            *position += *velocity * dt;
+        }
+
+        let evaluation = self.topology.evaluate(&self.positions);
+        self.forces = evaluation.forces;
+        self.potential_energy = evaluation.energy.total();
+        self.half_kick(dt);
+
+        if let Thermostat::Berendsen {
+            temperature,
+            coupling_time,
+        } = self.settings.thermostat
+        {
+            let current = self.temperature();
+            if current > 0.0 {
+                let scale = (1.0 + dt / coupling_time * (temperature / current - 1.0))
+                    .max(0.0)
+                    .sqrt()
+                    .clamp(MIN_BERENDSEN_SCALE, MAX_BERENDSEN_SCALE);
+                for velocity in &mut self.velocities {
+                    *velocity *= scale;
+                }
+            }
+        }
+    }
+
+    /// Advances velocities by half a step under the current forces.
+    fn half_kick(&mut self, dt: f32) {
+        for ((velocity, force), atom) in self
+            .velocities
+            .iter_mut()
+            .zip(&self.forces)
+            .zip(&self.topology.atoms)
+        {
+            *velocity += 0.5 * dt * ACCELERATION_UNIT * *force / atom.mass;
+            if velocity.length_squared() > self.settings.max_speed * self.settings.max_speed {
+                *velocity = velocity.clamp_length_max(self.settings.max_speed);
+                self.speed_limited += 1;
+            }
+        }
+    }
+
+    fn kinetic_energy(&self) -> f64 {
+        self.velocities
+            .iter()
+            .zip(&self.topology.atoms)
+            .map(|(velocity, atom)| (0.5 * atom.mass * velocity.length_squared()) as f64)
+            .sum::<f64>()
+            / ACCELERATION_UNIT as f64
+    }
+
+    fn temperature(&self) -> f32 {
+        let degrees_of_freedom = (3 * self.positions.len()).saturating_sub(3).max(1);
+        (2.0 * self.kinetic_energy() / (degrees_of_freedom as f64 * BOLTZMANN as f64)) as f32
+    }
+}
+
+/// Draws velocities from the Maxwell–Boltzmann distribution at `temperature`
+/// (K) for atoms of the given masses (amu), without net momentum.
+fn maxwell_boltzmann_velocities(masses: &[f32], temperature: f32, rng: &mut impl Rng) -> Vec<Vec3> {
+    let mut velocities: Vec<Vec3> = masses
+        .iter()
+        .map(|&mass| {
+            let sigma = (BOLTZMANN * temperature * ACCELERATION_UNIT / mass).sqrt();
+            let mut component = || sigma * Distribution::<f32>::sample(&StandardNormal, rng);
+            Vec3::new(component(), component(), component())
+        })
+        .collect();
+
+    let total_mass: f32 = masses.iter().sum();
+    if velocities.len() > 1 && total_mass > 0.0 {
+        let momentum: Vec3 = velocities
+            .iter()
+            .zip(masses)
+            .map(|(velocity, &mass)| *velocity * mass)
+            .sum();
+        let drift = momentum / total_mass;
+        for velocity in &mut velocities {
+            *velocity -= drift;
+        }
+    }
+    velocities
+}
+
+fn integrate(
+    mut commands: Commands,
+    settings: Res<DynamicsSettings>,
+    mut state: ResMut<DynamicsState>,
+    system: Res<MolecularSystem>,
+    mut atom_positions: AtomPositions,
+    mut motion_query: Query<(Option<&mut AtomVelocity>, Option<&mut AtomForce>)>,
+) {
+    let steps = if state.running {
+        settings.steps_per_update
+    } else {
+        std::mem::take(&mut state.pending_steps)
+    };
+    if steps == 0 {
+        return;
+    }
+
+    let Some(topology) = &system.topology else {
+        return;
+    };
+
+    // The topology is rebuilt once despawned atoms are gone.
+    let Some(positions) = system
+        .atoms
+        .iter()
+        .map(|&entity| atom_positions.get(entity))
+        .collect::<Option<Vec<Vec3>>>()
+    else {
+        return;
+    };
+    let mut velocities: Vec<Vec3> = system
+        .atoms
+        .iter()
+        .map(|&entity| {
+            motion_query
+                .get(entity)
+                .ok()
+                .and_then(|(velocity, _)| velocity)
+                .map_or(Vec3::ZERO, |velocity| velocity.0)
+        })
+        .collect();
+
+    let new_atoms: Vec<usize> = (0..system.atoms.len())
+        .filter(|&index| {
+            motion_query
+                .get(system.atoms[index])
+                .is_ok_and(|(velocity, _)| velocity.is_none())
+        })
+        .collect();
+    if let Some(temperature) = settings.thermostat.temperature()
+        && !new_atoms.is_empty()
+    {
+        let masses: Vec<f32> = new_atoms
+            .iter()
+            .map(|&index| topology.atoms[index].mass)
+            .collect();
+        let sampled = maxwell_boltzmann_velocities(&masses, temperature, &mut rand::rng());
+        for (&index, velocity) in new_atoms.iter().zip(sampled) {
+            velocities[index] = velocity;
+        }
+    }
+
+    let evaluation = topology.evaluate(&positions);
+    let mut integrator = Integrator {
+        topology,
+        settings: *settings,
+        positions,
+        velocities,
+        forces: evaluation.forces,
+        potential_energy: evaluation.energy.total(),
+        speed_limited: 0,
+    };
+    for _ in 0..steps {
+        integrator.step();
+    }
+
+    atom_positions.set_all(
+        system
+            .atoms
+            .iter()
+            .copied()
+            .zip(integrator.positions.iter().copied()),
+    );
+    for (index, &entity) in system.atoms.iter().enumerate() {
+        let velocity = integrator.velocities[index];
+        let force = integrator.forces[index];
+        match motion_query.get_mut(entity) {
+            Ok((Some(mut atom_velocity), Some(mut atom_force))) => {
+                atom_velocity.0 = velocity;
+                atom_force.0 = force;
+            }
+            Ok(_) => {
+                commands
+                    .entity(entity)
+                    .try_insert((AtomVelocity(velocity), AtomForce(force)));
+            }
+            Err(_) => {}
+        }
+    }
+
+    state.step += steps as u64;
+    state.time += (steps as f32 * settings.timestep) as f64;
+    state.kinetic_energy = integrator.kinetic_energy();
+    state.potential_energy = integrator.potential_energy;
+    state.temperature = integrator.temperature();
+
+    let speed_limited = integrator.speed_limited > 0;
+    if speed_limited {
+        if !state.speed_limited {
+            warn!(
+                "Limiting atom speeds to {} Å/ps, so energy is not conserved",
+                settings.max_speed
+            );
+        }
+        debug!(
+            "Limited {} velocities to {} Å/ps",
+            integrator.speed_limited, settings.max_speed
+        );
+    }
+    state.speed_limited = speed_limited;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chemistry::force_field::{
+        BondParameters, BondStretch, NonbondedParameters, TopologyAtom,
+    };
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use std::collections::HashSet;
+
+    /// Two carbon atoms joined by a harmonic bond, without nonbonded terms.
+    fn diatomic_topology() -> Topology {
+        let mut topology = Topology::new(NonbondedParameters {
+            cutoff: 0.0,
+            switch_distance: 0.0,
+            dielectric: 1.0,
+            scale_14_electrostatic: 1.0,
+            scale_14_van_der_waals: 1.0,
+        });
+        topology.atoms = vec![
+            TopologyAtom {
+                atom_type: "C3".to_string(),
+                charge: 0.0,
+                mass: 12.011,
+                rmin_half: 0.0,
+                epsilon: 0.0,
+            };
+            2
+        ];
+        topology.neighbours = vec![Vec::new(); 2];
+        topology.exclusions = vec![HashSet::new(); 2];
+        topology.bonds.push(BondStretch {
+            atoms: [0, 1],
+            parameters: BondParameters {
+                k: 300.0,
+                length: 1.5,
+            },
+        });
+        topology
+    }
+
+    #[test]
+    fn velocity_verlet_conserves_energy_without_thermostat() {
+        let topology = diatomic_topology();
+        let positions = vec![Vec3::ZERO, Vec3::new(1.7, 0.0, 0.0)];
+        let evaluation = topology.evaluate(&positions);
+        let mut integrator = Integrator {
+            topology: &topology,
+            // About 60 steps per period of the bond vibration.
+            settings: DynamicsSettings {
+                timestep: 0.0005,
+                thermostat: Thermostat::None,
+                ..default()
+            },
+            positions,
+            velocities: vec![Vec3::ZERO; 2],
+            forces: evaluation.forces,
+            potential_energy: evaluation.energy.total(),
+            speed_limited: 0,
+        };
+        let initial = integrator.potential_energy;
+        assert!(initial > 1.0);
+
+        // About 65 periods of the bond vibration.
+        let mut max_kinetic_energy: f64 = 0.0;
+        for _ in 0..4000 {
+            integrator.step();
+            let total = integrator.kinetic_energy() + integrator.potential_energy;
+            assert!(
+                (total - initial).abs() < 0.01 * initial,
+                "total energy {} drifted from {}",
+                total,
+                initial
+            );
+            max_kinetic_energy = max_kinetic_energy.max(integrator.kinetic_energy());
+        }
+        assert_eq!(integrator.speed_limited, 0);
+        // The bond vibrates through its rest length, turning the strain into
+        // kinetic energy and back.
+        assert!(max_kinetic_energy > 0.9 * initial);
+
+        let momentum: Vec3 = integrator.velocities.iter().map(|v| *v * 12.011).sum();
+        assert!(momentum.length() < 1e-3, "net momentum {}", momentum);
+    }
+
+    #[test]
+    fn maxwell_boltzmann_velocities_match_temperature() {
+        let masses: Vec<f32> = (0..3000)
+            .map(|index| [1.008, 12.011, 14.007, 15.999][index % 4])
+            .collect();
+        let velocities =
+            maxwell_boltzmann_velocities(&masses, 300.0, &mut StdRng::seed_from_u64(7));
+
+        let momentum: Vec3 = velocities
+            .iter()
+            .zip(&masses)
+            .map(|(velocity, &mass)| *velocity * mass)
+            .sum();
+        // Without the drift removed it would be around a thousand amu·Å/ps.
+        assert!(momentum.length() < 0.5, "net momentum {}", momentum);
+
+        let kinetic_energy: f32 = velocities
+            .iter()
+            .zip(&masses)
+            .map(|(velocity, &mass)| 0.5 * mass * velocity.length_squared())
+            .sum::<f32>()
+            / ACCELERATION_UNIT;
+        let temperature = 2.0 * kinetic_energy / (3.0 * masses.len() as f32 * BOLTZMANN);
+        assert!(
+            (temperature - 300.0).abs() < 15.0,
+            "temperature {} K",
+            temperature
+        );
+    }
+}
@@ -0,0 +1,58 @@
+use std::fmt;
+
+use crate::chemistry::amino_acids::types::AminoAcidCode;
+
+/// Errors produced while building or loading molecular structures.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChemistryError {
+    /// An element symbol that the simulator does not support.
+    UnknownElement(String),
+    /// A residue name that does not map to a standard amino acid.
+    UnknownResidue(String),
+    /// The amino acid configuration could not be loaded.
+    Config(String),
+    /// The amino acid configuration has no entry for a residue.
+    MissingDefinition(AminoAcidCode),
+    /// A malformed record in a structure file.
+    Parse { line: usize, message: String },
+    /// The structure file could not be read or written.
+    Io(String),
+    /// Atom positions could not be derived from internal coordinates.
+    Geometry(String),
+    /// The force field has no parameters for a bonded term.
+    MissingParameter(String),
+    /// The structure cannot be represented in the requested file format.
+    Export(String),
+}
+
+impl fmt::Display for ChemistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChemistryError::UnknownElement(symbol) => write!(f, "Unknown element: {}", symbol),
+            ChemistryError::UnknownResidue(name) => write!(f, "Unknown residue: {}", name),
+            ChemistryError::Config(message) => {
+                write!(f, "Failed to load amino acids config: {}", message)
+            }
+            ChemistryError::MissingDefinition(code) => {
+                write!(f, "Amino acid {:?} not found in config", code)
+            }
+            ChemistryError::Parse { line, message } => {
+                write!(f, "Parse error on line {}: {}", line, message)
+            }
+            ChemistryError::Io(message) => write!(f, "I/O error: {}", message),
+            ChemistryError::Geometry(message) => write!(f, "Invalid geometry: {}", message),
+            ChemistryError::MissingParameter(term) => {
+                write!(f, "No force field parameters for {}", term)
+            }
+            ChemistryError::Export(message) => write!(f, "Failed to export structure: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ChemistryError {}
+
+impl From<std::io::Error> for ChemistryError {
+    fn from(error: std::io::Error) -> Self {
+        ChemistryError::Io(error.to_string())
+    }
+}
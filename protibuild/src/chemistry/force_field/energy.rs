@@ -0,0 +1,505 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::fmt;
+
+use super::NonbondedParameters;
+use super::topology::{Topology, Torsion};
+
+/// Coulomb's constant in kcal·Å/(mol·e²).
+pub(crate) const COULOMB_CONSTANT: f32 = 332.0637;
+
+/// Smallest sine of an angle used in gradients, avoiding the singularity of
+/// straight angles.
+const MIN_SINE: f32 = 1e-6;
+
+/// Potential energy of a molecular system split by term, in kcal/mol.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EnergyTerms {
+    pub bond: f64,
+    pub angle: f64,
+    pub torsion: f64,
+    pub improper: f64,
+    pub van_der_waals: f64,
+    pub electrostatic: f64,
+}
+
+impl EnergyTerms {
+    pub fn total(&self) -> f64 {
+        self.bond
+            + self.angle
+            + self.torsion
+            + self.improper
+            + self.van_der_waals
+            + self.electrostatic
+    }
+}
+
+impl fmt::Display for EnergyTerms {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.2} kcal/mol (bond {:.2}, angle {:.2}, torsion {:.2}, improper {:.2}, \
+             van der Waals {:.2}, electrostatic {:.2})",
+            self.total(),
+            self.bond,
+            self.angle,
+            self.torsion,
+            self.improper,
+            self.van_der_waals,
+            self.electrostatic
+        )
+    }
+}
+
+/// Energy and per-atom forces of a molecular system.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evaluation {
+    pub energy: EnergyTerms,
+    /// Force on each atom in kcal/(mol·Å), the negative energy gradient.
+    pub forces: Vec<Vec3>,
+}
+
+impl Topology {
+    /// Computes the energy and forces for atom `positions` in Ångström.
+    pub fn evaluate(&self, positions: &[Vec3]) -> Evaluation {
+        let mut energy = EnergyTerms::default();
+        let mut forces = vec![Vec3::ZERO; positions.len()];
+
+        for bond in &self.bonds {
+            let [i, j] = bond.atoms;
+            let delta = positions[i] - positions[j];
+            let distance = delta.length();
+            let stretch = distance - bond.parameters.length;
+            energy.bond += (bond.parameters.k * stretch * stretch) as f64;
+
+            if distance > 0.0 {
+                let force = -2.0 * bond.parameters.k * stretch / distance * delta;
+                forces[i] += force;
+                forces[j] -= force;
+            }
+        }
+
+        for angle in &self.angles {
+            let [i, j, k] = angle.atoms;
+            let u = positions[i] - positions[j];
+            let v = positions[k] - positions[j];
+            let (u_length, v_length) = (u.length(), v.length());
+            if u_length == 0.0 || v_length == 0.0 {
+                continue;
+            }
+            let (u_unit, v_unit) = (u / u_length, v / v_length);
+            let cosine = u_unit.dot(v_unit).clamp(-1.0, 1.0);
+            let theta = cosine.acos();
+            let bend = theta - angle.parameters.angle.to_radians();
+            energy.angle += (angle.parameters.k * bend * bend) as f64;
+
+            let sine = theta.sin().max(MIN_SINE);
+            let d_energy = 2.0 * angle.parameters.k * bend;
+            let gradient_i = -(v_unit - cosine * u_unit) / (u_length * sine);
+            let gradient_k = -(u_unit - cosine * v_unit) / (v_length * sine);
+            forces[i] -= d_energy * gradient_i;
+            forces[k] -= d_energy * gradient_k;
+            forces[j] += d_energy * (gradient_i + gradient_k);
+        }
+
+        energy.torsion = Self::torsions(&self.torsions, positions, &mut forces);
+        energy.improper = Self::torsions(&self.impropers, positions, &mut forces);
+
+        let nonbonded = self.nonbonded;
+        let scale_14 = [
+            nonbonded.scale_14_van_der_waals,
+            nonbonded.scale_14_electrostatic,
+        ];
+        for &pair in &self.pairs_14 {
+            let (van_der_waals, electrostatic) = self.pair(pair, scale_14, positions, &mut forces);
+            energy.van_der_waals += van_der_waals;
+            energy.electrostatic += electrostatic;
+        }
+
+        for pair in self.nonbonded_pairs(positions) {
+            let (van_der_waals, electrostatic) =
+                self.pair(pair, [1.0, 1.0], positions, &mut forces);
+            energy.van_der_waals += van_der_waals;
+            energy.electrostatic += electrostatic;
+        }
+
+        Evaluation { energy, forces }
+    }
+
+    /// Total energy without forces.
+    pub fn energy(&self, positions: &[Vec3]) -> EnergyTerms {
+        self.evaluate(positions).energy
+    }
+
+    fn torsions(torsions: &[Torsion], positions: &[Vec3], forces: &mut [Vec3]) -> f64 {
+        let mut energy = 0.0;
+        for torsion in torsions {
+            let [i, j, k, l] = torsion.atoms;
+            let b1 = positions[j] - positions[i];
+            let b2 = positions[k] - positions[j];
+            let b3 = positions[l] - positions[k];
+            let m = b1.cross(b2);
+            let n = b2.cross(b3);
+            let (m_squared, n_squared) = (m.length_squared(), n.length_squared());
+            let b2_length = b2.length();
+            if m_squared < f32::EPSILON || n_squared < f32::EPSILON || b2_length == 0.0 {
+                continue;
+            }
+            let phi = (b2_length * b1.dot(n)).atan2(m.dot(n));
+
+            let mut d_energy = 0.0;
+            for term in &torsion.terms {
+                let periodicity = term.periodicity as f32;
+                let argument = periodicity * phi - term.phase.to_radians();
+                energy += (term.k * (1.0 + argument.cos())) as f64;
+                d_energy -= term.k * periodicity * argument.sin();
+            }
+
+            let gradient_i = -b2_length / m_squared * m;
+            let gradient_l = b2_length / n_squared * n;
+            let b2_squared = b2_length * b2_length;
+            let (p, q) = (b1.dot(b2) / b2_squared, b3.dot(b2) / b2_squared);
+            let gradient_j = q * gradient_l - (1.0 + p) * gradient_i;
+            let gradient_k = p * gradient_i - (1.0 + q) * gradient_l;
+            forces[i] -= d_energy * gradient_i;
+            forces[j] -= d_energy * gradient_j;
+            forces[k] -= d_energy * gradient_k;
+            forces[l] -= d_energy * gradient_l;
+        }
+        energy
+    }
+
+    /// Lennard-Jones and Coulomb energies of one pair, each multiplied by its
+    /// entry of `[lj_scale, coulomb_scale]`, adding their forces.
+    fn pair(
+        &self,
+        [i, j]: [usize; 2],
+        [lj_scale, coulomb_scale]: [f32; 2],
+        positions: &[Vec3],
+        forces: &mut [Vec3],
+    ) -> (f64, f64) {
+        let (a, b) = (&self.atoms[i], &self.atoms[j]);
+        let delta = positions[i] - positions[j];
+        let distance = delta.length();
+        if distance == 0.0 {
+            return (0.0, 0.0);
+        }
+
+        let rmin = a.rmin_half + b.rmin_half;
+        let epsilon = (a.epsilon * b.epsilon).sqrt();
+        let ratio6 = (rmin / distance).powi(6);
+        let ratio12 = ratio6 * ratio6;
+        let coulomb =
+            COULOMB_CONSTANT * a.charge * b.charge / (self.nonbonded.dielectric * distance);
+
+        let van_der_waals = lj_scale * epsilon * (ratio12 - 2.0 * ratio6);
+        let electrostatic = coulomb_scale * coulomb;
+        let d_energy =
+            lj_scale * 12.0 * epsilon * (ratio6 - ratio12) / distance - electrostatic / distance;
+
+        // Smoothly turn the interaction off towards the cutoff.
+        let (switch, d_switch) = self.switch(distance);
+        let d_energy = switch * d_energy + d_switch * (van_der_waals + electrostatic);
+        let (van_der_waals, electrostatic) = (switch * van_der_waals, switch * electrostatic);
+
+        let force = -d_energy / distance * delta;
+        forces[i] += force;
+        forces[j] -= force;
+        (van_der_waals as f64, electrostatic as f64)
+    }
+
+    /// Switching function scaling nonbonded energies from 1 at
+    /// `switch_distance` to 0 at `cutoff`, and its derivative.
+    fn switch(&self, distance: f32) -> (f32, f32) {
+        let NonbondedParameters {
+            cutoff,
+            switch_distance,
+            ..
+        } = self.nonbonded;
+        if cutoff <= 0.0 || distance <= switch_distance {
+            return (1.0, 0.0);
+        }
+        if distance >= cutoff {
+            return (0.0, 0.0);
+        }
+
+        let (off, on, r) = (
+            cutoff * cutoff,
+            switch_distance * switch_distance,
+            distance * distance,
+        );
+        let denominator = (off - on).powi(3);
+        let switch = (off - r).powi(2) * (off + 2.0 * r - 3.0 * on) / denominator;
+        let d_switch = 12.0 * distance * (off - r) * (on - r) / denominator;
+        (switch, d_switch)
+    }
+
+    /// Unexcluded atom pairs within the cutoff, found with a cell grid.
+    fn nonbonded_pairs(&self, positions: &[Vec3]) -> Vec<[usize; 2]> {
+        let cutoff = self.nonbonded.cutoff;
+        let mut pairs = Vec::new();
+
+        if cutoff <= 0.0 {
+            for i in 0..positions.len() {
+                for j in i + 1..positions.len() {
+                    if !self.is_excluded(i, j) {
+                        pairs.push([i, j]);
+                    }
+                }
+            }
+            return pairs;
+        }
+
+        let cell_of = |position: Vec3| (position / cutoff).floor().as_ivec3();
+        let mut cells: HashMap<IVec3, Vec<usize>> = HashMap::new();
+        for (index, &position) in positions.iter().enumerate() {
+            cells.entry(cell_of(position)).or_default().push(index);
+        }
+
+        let cutoff_squared = cutoff * cutoff;
+        for (i, &position) in positions.iter().enumerate() {
+            let cell = cell_of(position);
+            for x in -1..=1 {
+                for y in -1..=1 {
+                    for z in -1..=1 {
+                        let Some(others) = cells.get(&(cell + IVec3::new(x, y, z))) else {
+                            continue;
+                        };
+                        for &j in others {
+                            if j > i
+                                && positions[j].distance_squared(position) <= cutoff_squared
+                                && !self.is_excluded(i, j)
+                            {
+                                pairs.push([i, j]);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        pairs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chemistry::force_field::topology::{AngleBend, BondStretch, TopologyAtom};
+    use crate::chemistry::force_field::{AngleParameters, BondParameters, TorsionTerm};
+    use std::collections::HashSet;
+
+    /// Topology of `count` atoms without any terms or nonbonded interactions.
+    fn empty_topology(count: usize) -> Topology {
+        Topology {
+            atoms: vec![
+                TopologyAtom {
+                    atom_type: "C3".to_string(),
+                    charge: 0.0,
+                    mass: 12.011,
+                    rmin_half: 0.0,
+                    epsilon: 0.0,
+                };
+                count
+            ],
+            neighbours: vec![Vec::new(); count],
+            bonds: Vec::new(),
+            angles: Vec::new(),
+            torsions: Vec::new(),
+            impropers: Vec::new(),
+            pairs_14: Vec::new(),
+            exclusions: vec![HashSet::new(); count],
+            nonbonded: NonbondedParameters {
+                cutoff: 0.0,
+                switch_distance: 0.0,
+                dielectric: 1.0,
+                scale_14_electrostatic: 1.0,
+                scale_14_van_der_waals: 1.0,
+            },
+        }
+    }
+
+    /// Checks every force component against a central difference of the
+    /// total energy.
+    fn assert_forces_match_gradient(topology: &Topology, positions: &[Vec3]) {
+        const H: f32 = 1e-3;
+        let forces = topology.evaluate(positions).forces;
+        for atom in 0..positions.len() {
+            for axis in 0..3 {
+                let mut plus = positions.to_vec();
+                plus[atom][axis] += H;
+                let mut minus = positions.to_vec();
+                minus[atom][axis] -= H;
+                let gradient = (topology.energy(&plus).total() - topology.energy(&minus).total())
+                    / (2.0 * H as f64);
+                let force = forces[atom][axis] as f64;
+                assert!(
+                    (force + gradient).abs() < 1e-2 * (1.0 + gradient.abs()),
+                    "atom {} axis {}: force {}, negative gradient {}",
+                    atom,
+                    axis,
+                    force,
+                    -gradient
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn bond_forces_match_energy_gradient() {
+        let mut topology = empty_topology(2);
+        topology.bonds.push(BondStretch {
+            atoms: [0, 1],
+            parameters: BondParameters {
+                k: 310.0,
+                length: 1.526,
+            },
+        });
+        let positions = [Vec3::new(0.1, -0.2, 0.3), Vec3::new(1.5, 0.4, -0.2)];
+
+        assert!(topology.energy(&positions).bond > 0.0);
+        assert_forces_match_gradient(&topology, &positions);
+    }
+
+    #[test]
+    fn angle_forces_match_energy_gradient() {
+        let mut topology = empty_topology(3);
+        topology.angles.push(AngleBend {
+            atoms: [0, 1, 2],
+            parameters: AngleParameters {
+                k: 50.0,
+                angle: 109.5,
+            },
+        });
+        let positions = [
+            Vec3::new(1.4, 0.2, 0.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(-0.3, 1.5, 0.4),
+        ];
+
+        assert!(topology.energy(&positions).angle > 0.0);
+        assert_forces_match_gradient(&topology, &positions);
+    }
+
+    #[test]
+    fn torsion_forces_match_energy_gradient() {
+        let mut topology = empty_topology(4);
+        topology.torsions.push(Torsion {
+            atoms: [0, 1, 2, 3],
+            terms: vec![
+                TorsionTerm {
+                    k: 1.4,
+                    periodicity: 3,
+                    phase: 0.0,
+                },
+                TorsionTerm {
+                    k: 0.5,
+                    periodicity: 1,
+                    phase: 180.0,
+                },
+            ],
+        });
+        let positions = [
+            Vec3::new(1.2, 1.1, 0.3),
+            Vec3::new(0.0, 0.4, 0.0),
+            Vec3::new(0.1, -1.1, 0.2),
+            Vec3::new(-0.6, -1.7, 1.4),
+        ];
+
+        assert!(topology.energy(&positions).torsion > 0.0);
+        assert_forces_match_gradient(&topology, &positions);
+    }
+
+    #[test]
+    fn improper_forces_match_energy_gradient() {
+        let mut topology = empty_topology(4);
+        topology.impropers.push(Torsion {
+            atoms: [0, 1, 2, 3],
+            terms: vec![TorsionTerm {
+                k: 10.5,
+                periodicity: 2,
+                phase: 180.0,
+            }],
+        });
+        // A slightly pyramidal centre, atom 2, bonded to the other three.
+        let positions = [
+            Vec3::new(1.2, 0.0, 0.2),
+            Vec3::new(-0.6, 1.0, 0.2),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(-0.6, -1.0, 0.25),
+        ];
+
+        assert!(topology.energy(&positions).improper > 0.0);
+        assert_forces_match_gradient(&topology, &positions);
+    }
+
+    #[test]
+    fn lennard_jones_forces_match_energy_gradient() {
+        let mut topology = empty_topology(2);
+        for atom in &mut topology.atoms {
+            atom.rmin_half = 1.9;
+            atom.epsilon = 0.1;
+        }
+
+        // Repulsive inside Rmin, attractive outside.
+        for distance in [3.2, 4.5] {
+            let positions = [Vec3::ZERO, Vec3::new(distance, 0.3, -0.2)];
+            assert!(topology.energy(&positions).van_der_waals != 0.0);
+            assert_forces_match_gradient(&topology, &positions);
+        }
+    }
+
+    #[test]
+    fn coulomb_forces_match_energy_gradient() {
+        let mut topology = empty_topology(2);
+        topology.atoms[0].charge = 0.5;
+        topology.atoms[1].charge = -0.3;
+        let positions = [Vec3::new(0.2, 0.1, 0.0), Vec3::new(2.8, -0.4, 1.1)];
+
+        assert!(topology.energy(&positions).electrostatic < 0.0);
+        assert_forces_match_gradient(&topology, &positions);
+    }
+
+    #[test]
+    fn switched_forces_match_energy_gradient() {
+        let mut topology = empty_topology(2);
+        topology.nonbonded.cutoff = 10.0;
+        topology.nonbonded.switch_distance = 8.0;
+        topology.atoms[0].charge = 0.5;
+        topology.atoms[1].charge = -0.3;
+        for atom in &mut topology.atoms {
+            atom.rmin_half = 1.9;
+            atom.epsilon = 0.1;
+        }
+        let positions = [Vec3::ZERO, Vec3::new(8.7, 1.2, -0.5)];
+
+        assert!(topology.energy(&positions).electrostatic < 0.0);
+        assert_forces_match_gradient(&topology, &positions);
+    }
+
+    #[test]
+    fn switch_turns_interactions_off_smoothly() {
+        let mut topology = empty_topology(0);
+        topology.nonbonded.cutoff = 10.0;
+        topology.nonbonded.switch_distance = 8.0;
+
+        assert_eq!(topology.switch(7.5), (1.0, 0.0));
+        assert_eq!(topology.switch(10.0), (0.0, 0.0));
+        let (start, start_slope) = topology.switch(8.0 + 1e-3);
+        let (end, end_slope) = topology.switch(10.0 - 1e-3);
+        assert!((start - 1.0).abs() < 1e-4 && start_slope.abs() < 1e-2);
+        assert!(end.abs() < 1e-4 && end_slope.abs() < 1e-2);
+
+        const H: f32 = 1e-3;
+        for distance in [8.3, 9.0, 9.6] {
+            let (_, slope) = topology.switch(distance);
+            let difference =
+                (topology.switch(distance + H).0 - topology.switch(distance - H).0) / (2.0 * H);
+            assert!(
+                (slope - difference).abs() < 1e-3,
+                "{} != {}",
+                slope,
+                difference
+            );
+        }
+    }
+}
@@ -0,0 +1,477 @@
+//! Molecular mechanics force field.
+//!
+//! Energies are in kcal/mol, distances in Ångström, charges in elementary
+//! charges and forces in kcal/(mol·Å). The functional form follows AMBER:
+//!
+//! - bonds `k (r - r0)²` and angles `k (θ - θ0)²`
+//! - proper and improper torsions `Σ k (1 + cos(nφ - phase))`
+//! - Lennard-Jones `ε ((Rmin/r)¹² - 2 (Rmin/r)⁶)` and Coulomb `332.06 qi qj / (D r)`
+//!   between atoms more than two bonds apart, scaled for 1-4 pairs
+
+use bevy::prelude::*;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+mod energy;
+mod topology;
+
+pub(crate) use energy::COULOMB_CONSTANT;
+pub use energy::{EnergyTerms, Evaluation};
+pub use topology::{AngleBend, BondStretch, Topology, TopologyAtom, Torsion};
+
+use crate::chemistry::atoms::{Atom, Bond, Element};
+use crate::chemistry::error::ChemistryError;
+use crate::config::FORCE_FIELD_CONFIG;
+
+/// Type name matching any atom type in angle and torsion parameters.
+const WILDCARD: &str = "X";
+
+/// Mass and Lennard-Jones parameters of an atom type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtomType {
+    pub name: String,
+    pub element: Element,
+    pub mass: f32,
+    pub rmin_half: f32,
+    pub epsilon: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BondParameters {
+    pub k: f32,
+    pub length: f32,
+}
+
+/// Angle bend parameters; `angle` is in degrees.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AngleParameters {
+    pub k: f32,
+    pub angle: f32,
+}
+
+/// One cosine term of a torsion; `phase` is in degrees.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TorsionTerm {
+    pub k: f32,
+    pub periodicity: u32,
+    pub phase: f32,
+}
+
+/// Nonbonded settings. Interactions are switched off smoothly between
+/// `switch_distance` and `cutoff`; a `cutoff` of zero disables the cutoff.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NonbondedParameters {
+    pub cutoff: f32,
+    pub switch_distance: f32,
+    pub dielectric: f32,
+    pub scale_14_electrostatic: f32,
+    pub scale_14_van_der_waals: f32,
+}
+
+/// Force field parameters loaded from the force field configuration.
+///
+/// Atom types are assigned from each atom's element and bonded neighbours,
+/// so structures need explicit hydrogens to be typed correctly. Partial
+/// charges are the sum of bond charge increments, which keeps every molecule
+/// neutral.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForceField {
+    pub atom_types: Vec<AtomType>,
+    pub bonds: Vec<([String; 2], BondParameters)>,
+    pub angles: Vec<([String; 3], AngleParameters)>,
+    pub torsions: Vec<([String; 4], Vec<TorsionTerm>)>,
+    pub impropers: Vec<(String, TorsionTerm)>,
+    pub charge_increments: Vec<([String; 2], f32)>,
+    pub nonbonded: NonbondedParameters,
+}
+
+impl ForceField {
+    pub fn get() -> Result<Self, ChemistryError> {
+        let config = FORCE_FIELD_CONFIG
+            .as_ref()
+            .map_err(|e| ChemistryError::Config(e.to_string()))?;
+
+        let atom_types = config
+            .atom_types
+            .iter()
+            .map(|atom_type| {
+                Ok(AtomType {
+                    name: atom_type.name.clone(),
+                    element: Element::from_symbol(&atom_type.element)
+                        .ok_or_else(|| ChemistryError::UnknownElement(atom_type.element.clone()))?,
+                    mass: atom_type.mass,
+                    rmin_half: atom_type.rmin_half,
+                    epsilon: atom_type.epsilon,
+                })
+            })
+            .collect::<Result<_, ChemistryError>>()?;
+
+        Ok(Self {
+            atom_types,
+            bonds: config
+                .bonds
+                .iter()
+                .map(|bond| {
+                    let parameters = BondParameters {
+                        k: bond.k,
+                        length: bond.length,
+                    };
+                    (bond.types.clone(), parameters)
+                })
+                .collect(),
+            angles: config
+                .angles
+                .iter()
+                .map(|angle| {
+                    let parameters = AngleParameters {
+                        k: angle.k,
+                        angle: angle.angle,
+                    };
+                    (angle.types.clone(), parameters)
+                })
+                .collect(),
+            torsions: config
+                .torsions
+                .iter()
+                .map(|torsion| {
+                    let terms = torsion
+                        .terms
+                        .iter()
+                        .map(|term| TorsionTerm {
+                            k: term.k,
+                            periodicity: term.periodicity,
+                            phase: term.phase,
+                        })
+                        .collect();
+                    (torsion.types.clone(), terms)
+                })
+                .collect(),
+            impropers: config
+                .impropers
+                .iter()
+                .map(|improper| {
+                    let term = TorsionTerm {
+                        k: improper.k,
+                        periodicity: improper.periodicity,
+                        phase: improper.phase,
+                    };
+                    (improper.center.clone(), term)
+                })
+                .collect(),
+            charge_increments: config
+                .bond_charge_increments
+                .iter()
+                .map(|increment| (increment.types.clone(), increment.increment))
+                .collect(),
+            nonbonded: NonbondedParameters {
+                cutoff: config.nonbonded.cutoff,
+                switch_distance: config.nonbonded.switch_distance,
+                dielectric: config.nonbonded.dielectric,
+                scale_14_electrostatic: config.nonbonded.scale_14_electrostatic,
+                scale_14_van_der_waals: config.nonbonded.scale_14_van_der_waals,
+            },
+        })
+    }
+
+    pub fn atom_type(&self, name: &str) -> Option<&AtomType> {
+        self.atom_types
+            .iter()
+            .find(|atom_type| atom_type.name == name)
+    }
+
+    /// Assigns an atom type name to every atom of a bond graph.
+    ///
+    /// - carbon: `C3` with four neighbours, otherwise `C2`
+    /// - nitrogen: `N4` with four neighbours, `NB` with two or fewer, `N2` when
+    ///   bonded to an sp2 carbon (amides, rings, guanidinium), otherwise `N3`
+    /// - oxygen: `O` with one neighbour, otherwise `OH`
+    /// - sulfur: `S`
+    /// - hydrogen: `HC`, `HN`, `HO` or `HS` after the atom it is bonded to
+    pub fn assign_types(elements: &[Element], neighbours: &[Vec<usize>]) -> Vec<&'static str> {
+        let is_sp2_carbon =
+            |index: usize| elements[index] == Element::Carbon && neighbours[index].len() < 4;
+
+        elements
+            .iter()
+            .zip(neighbours)
+            .map(|(element, bonded)| match element {
+                Element::Carbon if bonded.len() >= 4 => "C3",
+                Element::Carbon => "C2",
+                Element::Nitrogen if bonded.len() >= 4 => "N4",
+                Element::Nitrogen if bonded.len() <= 2 => "NB",
+                Element::Nitrogen if bonded.iter().any(|&other| is_sp2_carbon(other)) => "N2",
+                Element::Nitrogen => "N3",
+                Element::Oxygen if bonded.len() <= 1 => "O",
+                Element::Oxygen => "OH",
+                Element::Sulfur => "S",
+                Element::Hydrogen => match bonded.first().map(|&other| elements[other]) {
+                    Some(Element::Nitrogen) => "HN",
+                    Some(Element::Oxygen) => "HO",
+                    Some(Element::Sulfur) => "HS",
+                    _ => "HC",
+                },
+            })
+            .collect()
+    }
+
+    pub fn bond(&self, a: &str, b: &str) -> Result<BondParameters, ChemistryError> {
+        self.bonds
+            .iter()
+            .find(|(types, _)| (types[0] == a && types[1] == b) || (types[0] == b && types[1] == a))
+            .map(|(_, parameters)| *parameters)
+            .ok_or_else(|| ChemistryError::MissingParameter(format!("bond {}-{}", a, b)))
+    }
+
+    /// Angle parameters, preferring entries with fewer wildcards.
+    pub fn angle(&self, a: &str, b: &str, c: &str) -> Result<AngleParameters, ChemistryError> {
+        self.angles
+            .iter()
+            .filter_map(|(types, parameters)| {
+                let specificity = [[a, b, c], [c, b, a]]
+                    .iter()
+                    .filter_map(|candidate| match_types(types, candidate))
+                    .max()?;
+                Some((specificity, *parameters))
+            })
+            .max_by_key(|(specificity, _)| *specificity)
+            .map(|(_, parameters)| parameters)
+            .ok_or_else(|| ChemistryError::MissingParameter(format!("angle {}-{}-{}", a, b, c)))
+    }
+
+    /// Proper torsion terms, preferring entries with fewer wildcards.
+    ///
+    /// Torsions about bonds without parameters have no barrier, so this returns
+    /// `None` rather than an error.
+    pub fn torsion(&self, a: &str, b: &str, c: &str, d: &str) -> Option<&[TorsionTerm]> {
+        self.torsions
+            .iter()
+            .filter_map(|(types, terms)| {
+                let specificity = [[a, b, c, d], [d, c, b, a]]
+                    .iter()
+                    .filter_map(|candidate| match_types(types, candidate))
+                    .max()?;
+                Some((specificity, terms.as_slice()))
+            })
+            .max_by_key(|(specificity, _)| *specificity)
+            .map(|(_, terms)| terms)
+    }
+
+    /// Improper torsion keeping a centre of the given type planar, if any.
+    pub fn improper(&self, center: &str) -> Option<TorsionTerm> {
+        self.impropers
+            .iter()
+            .find(|(name, _)| name == center)
+            .map(|(_, term)| *term)
+    }
+
+    /// Charge gained by an atom of type `a` from its bond to an atom of type `b`.
+    pub fn charge_increment(&self, a: &str, b: &str) -> f32 {
+        self.charge_increments
+            .iter()
+            .find_map(|(types, increment)| {
+                if types[0] == a && types[1] == b {
+                    Some(*increment)
+                } else if types[0] == b && types[1] == a {
+                    Some(-*increment)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or(0.0)
+    }
+}
+
+/// Number of non-wildcard types in `pattern` if it matches `types`.
+fn match_types<const N: usize>(pattern: &[String; N], types: &[&str; N]) -> Option<usize> {
+    pattern
+        .iter()
+        .zip(types)
+        .try_fold(0, |specificity, (expected, actual)| {
+            if expected == WILDCARD {
+                Some(specificity)
+            } else if expected == actual {
+                Some(specificity + 1)
+            } else {
+                None
+            }
+        })
+}
+
+/// Force field type, partial charge and mass assigned to an atom entity.
+#[derive(Component, Clone, Debug, PartialEq)]
+pub struct AtomParameters {
+    pub atom_type: String,
+    pub charge: f32,
+    pub mass: f32,
+}
+
+/// World-space force on an atom from the last energy evaluation, in
+/// kcal/(mol·Å).
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct AtomForce(pub Vec3);
+
+/// Force field topology of every atom in the scene.
+///
+/// Updated whenever atoms or bonds are added or removed, re-deriving only the
+/// terms around them. `atoms[i]` is the entity of topology atom `i`.
+#[derive(Resource, Default)]
+pub struct MolecularSystem {
+    pub atoms: Vec<Entity>,
+    pub topology: Option<Topology>,
+}
+
+/// Energy terms of the last evaluation of the scene.
+#[derive(Resource, Default)]
+pub struct PotentialEnergy {
+    pub terms: Option<EnergyTerms>,
+}
+
+/// Message to evaluate the force field on the current atom positions.
+#[derive(Message, Debug, Clone)]
+pub struct ComputeEnergyMessage;
+
+pub struct ForceFieldPlugin;
+
+impl Plugin for ForceFieldPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MolecularSystem>()
+            .init_resource::<PotentialEnergy>()
+            .add_message::<ComputeEnergyMessage>()
+            .add_systems(Update, (update_topology, handle_compute_energy).chain());
+    }
+}
+
+/// Updates the topology around added and removed atoms and bonds. Atoms keep
+/// their topology index while they exist and new atoms are appended.
+#[allow(clippy::type_complexity)]
+fn update_topology(
+    mut commands: Commands,
+    mut system: ResMut<MolecularSystem>,
+    atom_query: Query<(Entity, &Atom, Option<&AtomParameters>)>,
+    bond_query: Query<&Bond>,
+    added_query: Query<(), Or<(Added<Atom>, Added<Bond>)>>,
+    mut removed_atoms: RemovedComponents<Atom>,
+    mut removed_bonds: RemovedComponents<Bond>,
+) {
+    let removed = removed_atoms.read().count() + removed_bonds.read().count() > 0;
+    if added_query.is_empty() && !removed {
+        return;
+    }
+
+    let system = &mut *system;
+    let force_field = match ForceField::get() {
+        Ok(force_field) => force_field,
+        Err(e) => {
+            error!("Failed to load the force field: {}", e);
+            system.atoms.clear();
+            system.topology = None;
+            return;
+        }
+    };
+    let (mut topology, previous) = match system.topology.take() {
+        Some(topology) => (topology, std::mem::take(&mut system.atoms)),
+        None => (Topology::new(force_field.nonbonded), Vec::new()),
+    };
+
+    let mut atoms = Vec::new();
+    let mut kept = Vec::with_capacity(previous.len());
+    for entity in previous {
+        if atom_query.contains(entity) {
+            kept.push(Some(atoms.len()));
+            atoms.push(entity);
+        } else {
+            kept.push(None);
+        }
+    }
+    let known: HashSet<Entity> = atoms.iter().copied().collect();
+    atoms.extend(
+        atom_query
+            .iter()
+            .map(|(entity, ..)| entity)
+            .filter(|entity| !known.contains(entity)),
+    );
+
+    let indices: HashMap<Entity, usize> = atoms
+        .iter()
+        .enumerate()
+        .map(|(index, &entity)| (entity, index))
+        .collect();
+    let elements: Vec<Element> = atoms
+        .iter()
+        .filter_map(|&entity| atom_query.get(entity).ok())
+        .map(|(_, atom, _)| atom.element)
+        .collect();
+    let bonds: Vec<(usize, usize)> = bond_query
+        .iter()
+        .filter_map(|bond| Some((*indices.get(&bond.atom1)?, *indices.get(&bond.atom2)?)))
+        .collect();
+
+    let missing = match topology.update(&force_field, &kept, &elements, &bonds) {
+        Ok(missing) => missing,
+        Err(e) => {
+            error!("Failed to assign force field parameters: {}", e);
+            system.atoms = atoms;
+            system.topology = None;
+            return;
+        }
+    };
+    let missing: BTreeSet<String> = missing.iter().map(ToString::to_string).collect();
+    for term in missing {
+        warn!("{}, leaving the term out", term);
+    }
+
+    for (&entity, atom) in atoms.iter().zip(&topology.atoms) {
+        let parameters = AtomParameters {
+            atom_type: atom.atom_type.clone(),
+            charge: atom.charge,
+            mass: atom.mass,
+        };
+        if let Ok((.., current)) = atom_query.get(entity)
+            && current != Some(&parameters)
+        {
+            commands.entity(entity).insert(parameters);
+        }
+    }
+
+    system.atoms = atoms;
+    system.topology = Some(topology);
+}
+
+fn handle_compute_energy(
+    mut commands: Commands,
+    mut messages: MessageReader<ComputeEnergyMessage>,
+    system: Res<MolecularSystem>,
+    mut energy: ResMut<PotentialEnergy>,
+    transform_query: Query<&GlobalTransform>,
+) {
+    if messages.read().count() == 0 {
+        return;
+    }
+
+    let Some(topology) = &system.topology else {
+        warn!("No force field topology to evaluate");
+        return;
+    };
+
+    let positions: Vec<Vec3> = system
+        .atoms
+        .iter()
+        .map(|&entity| {
+            transform_query
+                .get(entity)
+                .map(|transform| transform.translation())
+                .unwrap_or_default()
+        })
+        .collect();
+
+    let evaluation = topology.evaluate(&positions);
+    for (&entity, &force) in system.atoms.iter().zip(&evaluation.forces) {
+        commands.entity(entity).try_insert(AtomForce(force));
+    }
+
+    info!(
+        "Potential energy of {} atoms: {}",
+        system.atoms.len(),
+        evaluation.energy
+    );
+    energy.terms = Some(evaluation.energy);
+}
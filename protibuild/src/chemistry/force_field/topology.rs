@@ -0,0 +1,432 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+
+use super::{AngleParameters, BondParameters, ForceField, NonbondedParameters, TorsionTerm};
+use crate::chemistry::atoms::Element;
+use crate::chemistry::error::ChemistryError;
+
+/// Parameters of a single atom in a [`Topology`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopologyAtom {
+    pub atom_type: String,
+    pub charge: f32,
+    pub mass: f32,
+    pub rmin_half: f32,
+    pub epsilon: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BondStretch {
+    pub atoms: [usize; 2],
+    pub parameters: BondParameters,
+}
+
+/// Angle bend about the middle atom.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AngleBend {
+    pub atoms: [usize; 3],
+    pub parameters: AngleParameters,
+}
+
+/// Proper torsion about the middle bond, or improper torsion with the planar
+/// centre as the third atom.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Torsion {
+    pub atoms: [usize; 4],
+    pub terms: Vec<TorsionTerm>,
+}
+
+/// Every bonded and nonbonded term of a molecular system with its parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Topology {
+    pub atoms: Vec<TopologyAtom>,
+    /// Bonded neighbours of each atom.
+    pub neighbours: Vec<Vec<usize>>,
+    pub bonds: Vec<BondStretch>,
+    pub angles: Vec<AngleBend>,
+    pub torsions: Vec<Torsion>,
+    pub impropers: Vec<Torsion>,
+    /// Atom pairs three bonds apart, whose nonbonded terms are scaled.
+    pub pairs_14: Vec<[usize; 2]>,
+    /// Atoms within three bonds of each atom, skipped by the full nonbonded terms.
+    pub exclusions: Vec<HashSet<usize>>,
+    pub nonbonded: NonbondedParameters,
+}
+
+impl Topology {
+    /// Topology without atoms.
+    pub fn new(nonbonded: NonbondedParameters) -> Self {
+        Self {
+            atoms: Vec::new(),
+            neighbours: Vec::new(),
+            bonds: Vec::new(),
+            angles: Vec::new(),
+            torsions: Vec::new(),
+            impropers: Vec::new(),
+            pairs_14: Vec::new(),
+            exclusions: Vec::new(),
+            nonbonded,
+        }
+    }
+
+    /// Types the atoms of a bond graph and derives its angles, torsions and
+    /// nonbonded exclusions.
+    ///
+    /// `bonds` index into `elements`. Bonds and angles without parameters are
+    /// left out and returned as [`ChemistryError::MissingParameter`]s. Fails
+    /// only if the force field lacks an atom type.
+    pub fn build(
+        force_field: &ForceField,
+        elements: &[Element],
+        bonds: &[(usize, usize)],
+    ) -> Result<(Self, Vec<ChemistryError>), ChemistryError> {
+        let mut topology = Self::new(force_field.nonbonded);
+        let missing = topology.update(force_field, &[], elements, bonds)?;
+        Ok((topology, missing))
+    }
+
+    /// Updates the topology after atoms or bonds were added or removed.
+    ///
+    /// `kept[i]` is the new index of previous atom `i`, or `None` if it was
+    /// removed; atoms of `elements` without a previous index are new. Only the
+    /// terms of atoms whose type or neighbours changed, and the exclusions
+    /// within three bonds of them, are derived again. Returns the terms left
+    /// out for lack of parameters, like [`Topology::build`].
+    pub fn update(
+        &mut self,
+        force_field: &ForceField,
+        kept: &[Option<usize>],
+        elements: &[Element],
+        bonds: &[(usize, usize)],
+    ) -> Result<Vec<ChemistryError>, ChemistryError> {
+        let count = elements.len();
+        let mut previous_atoms = vec![None; count];
+        let mut previous_neighbours = vec![Vec::new(); count];
+        let mut previous_exclusions = vec![HashSet::new(); count];
+        for (old, &new) in kept.iter().enumerate() {
+            if let Some(new) = new {
+                previous_atoms[new] = Some(self.atoms[old].clone());
+                previous_neighbours[new] = remap(&self.neighbours[old], kept);
+                previous_exclusions[new] = remap(&self.exclusions[old], kept);
+            }
+        }
+        let remap_term = |atoms: &mut [usize]| -> bool {
+            for atom in atoms.iter_mut() {
+                match kept.get(*atom).copied().flatten() {
+                    Some(new) => *atom = new,
+                    None => return false,
+                }
+            }
+            true
+        };
+        self.bonds.retain_mut(|term| remap_term(&mut term.atoms));
+        self.angles.retain_mut(|term| remap_term(&mut term.atoms));
+        self.torsions.retain_mut(|term| remap_term(&mut term.atoms));
+        self.impropers
+            .retain_mut(|term| remap_term(&mut term.atoms));
+        self.pairs_14.retain_mut(|pair| remap_term(pair));
+
+        let mut neighbours = vec![Vec::new(); count];
+        for &(atom1, atom2) in bonds {
+            if atom1 != atom2 && !neighbours[atom1].contains(&atom2) {
+                neighbours[atom1].push(atom2);
+                neighbours[atom2].push(atom1);
+            }
+        }
+        let types = ForceField::assign_types(elements, &neighbours);
+
+        // Terms only depend on the bond graph and the types of their atoms, so
+        // those of atoms with the same type and neighbours stay as they are.
+        let regraphed: HashSet<usize> = (0..count)
+            .filter(|&atom| {
+                let previous = &previous_neighbours[atom];
+                previous_atoms[atom].is_none()
+                    || previous.len() != neighbours[atom].len()
+                    || previous
+                        .iter()
+                        .any(|other| !neighbours[atom].contains(other))
+            })
+            .collect();
+        let changed: HashSet<usize> = (0..count)
+            .filter(|&atom| {
+                regraphed.contains(&atom)
+                    || previous_atoms[atom]
+                        .as_ref()
+                        .is_none_or(|previous| previous.atom_type != types[atom])
+            })
+            .collect();
+        let near: HashSet<usize> = changed
+            .iter()
+            .flat_map(|&atom| neighbours[atom].iter().copied().chain([atom]))
+            .collect();
+        let touches = |atoms: &[usize]| atoms.iter().any(|atom| changed.contains(atom));
+
+        // Charges are summed over the bonds of each atom, so they change with
+        // the types of the neighbours too.
+        let mut atoms = Vec::with_capacity(count);
+        for (index, &type_name) in types.iter().enumerate() {
+            if !near.contains(&index)
+                && let Some(atom) = previous_atoms[index].take()
+            {
+                atoms.push(atom);
+                continue;
+            }
+            let atom_type = force_field.atom_type(type_name).ok_or_else(|| {
+                ChemistryError::MissingParameter(format!("atom type {}", type_name))
+            })?;
+            let charge = neighbours[index]
+                .iter()
+                .map(|&other| force_field.charge_increment(type_name, types[other]))
+                .sum();
+            atoms.push(TopologyAtom {
+                atom_type: type_name.to_string(),
+                charge,
+                mass: atom_type.mass,
+                rmin_half: atom_type.rmin_half,
+                epsilon: atom_type.epsilon,
+            });
+        }
+
+        self.bonds.retain(|term| !touches(&term.atoms));
+        self.angles.retain(|term| !touches(&term.atoms));
+        self.torsions.retain(|term| !touches(&term.atoms));
+        self.impropers.retain(|term| !touches(&term.atoms));
+
+        let mut missing = Vec::new();
+        for &atom1 in &changed {
+            for &atom2 in &neighbours[atom1] {
+                if atom1 < atom2 || !changed.contains(&atom2) {
+                    let atoms = [atom1.min(atom2), atom1.max(atom2)];
+                    match force_field.bond(types[atoms[0]], types[atoms[1]]) {
+                        Ok(parameters) => self.bonds.push(BondStretch { atoms, parameters }),
+                        Err(e) => missing.push(e),
+                    }
+                }
+            }
+        }
+
+        for &center in &near {
+            let bonded = &neighbours[center];
+            for (position, &atom1) in bonded.iter().enumerate() {
+                for &atom3 in &bonded[position + 1..] {
+                    let atoms = [atom1, center, atom3];
+                    if !touches(&atoms) {
+                        continue;
+                    }
+                    match force_field.angle(types[atom1], types[center], types[atom3]) {
+                        Ok(parameters) => self.angles.push(AngleBend { atoms, parameters }),
+                        Err(e) => missing.push(e),
+                    }
+                }
+            }
+
+            if let [atom1, atom2, atom4] = bonded[..]
+                && touches(&[atom1, atom2, center, atom4])
+                && let Some(term) = force_field.improper(types[center])
+            {
+                self.impropers.push(Torsion {
+                    atoms: [atom1, atom2, center, atom4],
+                    terms: vec![term],
+                });
+            }
+        }
+
+        // Every torsion with a changed atom is about a bond with an end near
+        // a changed atom.
+        for &atom2 in &near {
+            for &atom3 in &neighbours[atom2] {
+                if atom3 < atom2 && near.contains(&atom3) {
+                    continue;
+                }
+                for &atom1 in neighbours[atom2].iter().filter(|&&atom| atom != atom3) {
+                    for &atom4 in neighbours[atom3]
+                        .iter()
+                        .filter(|&&atom| atom != atom2 && atom != atom1)
+                    {
+                        let atoms = [atom1, atom2, atom3, atom4];
+                        if !touches(&atoms) {
+                            continue;
+                        }
+                        let terms = force_field.torsion(
+                            types[atom1],
+                            types[atom2],
+                            types[atom3],
+                            types[atom4],
+                        );
+                        if let Some(terms) = terms {
+                            self.torsions.push(Torsion {
+                                atoms,
+                                terms: terms.to_vec(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        // Exclusions change within three bonds of a changed bond, before or
+        // after the change.
+        let mut regions: HashSet<usize> = regraphed.clone();
+        for &atom in &regraphed {
+            regions.extend(&previous_exclusions[atom]);
+            regions.extend(within_bonds(&neighbours, atom, 3).into_keys());
+        }
+        self.pairs_14
+            .retain(|[atom1, atom2]| !regions.contains(atom1) && !regions.contains(atom2));
+        let mut exclusions = previous_exclusions;
+        for &atom in &regions {
+            let close = within_bonds(&neighbours, atom, 3);
+            // Atoms three bonds away, and not closer through a ring, are 1-4
+            // pairs; nearer ones are fully excluded.
+            for (&other, &distance) in &close {
+                if distance == 3 && (atom < other || !regions.contains(&other)) {
+                    self.pairs_14.push([atom.min(other), atom.max(other)]);
+                }
+            }
+            exclusions[atom] = close.into_keys().collect();
+        }
+
+        self.atoms = atoms;
+        self.neighbours = neighbours;
+        self.exclusions = exclusions;
+        self.nonbonded = force_field.nonbonded;
+        Ok(missing)
+    }
+
+    pub fn len(&self) -> usize {
+        self.atoms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.atoms.is_empty()
+    }
+
+    /// Whether the full nonbonded terms between two atoms are skipped.
+    pub fn is_excluded(&self, atom1: usize, atom2: usize) -> bool {
+        self.exclusions[atom1].contains(&atom2)
+    }
+}
+
+/// New indices of the atoms still present.
+fn remap<'a, C: FromIterator<usize>>(
+    atoms: impl IntoIterator<Item = &'a usize>,
+    kept: &[Option<usize>],
+) -> C {
+    atoms
+        .into_iter()
+        .filter_map(|&atom| kept.get(atom).copied().flatten())
+        .collect()
+}
+
+/// Atoms at most `depth` bonds from `atom`, other than itself, with their
+/// distance in bonds.
+fn within_bonds(neighbours: &[Vec<usize>], atom: usize, depth: usize) -> HashMap<usize, usize> {
+    let mut distances = HashMap::from([(atom, 0)]);
+    let mut shell = vec![atom];
+    for distance in 1..=depth {
+        let mut next = Vec::new();
+        for &current in &shell {
+            for &neighbour in &neighbours[current] {
+                if let Entry::Vacant(entry) = distances.entry(neighbour) {
+                    entry.insert(distance);
+                    next.push(neighbour);
+                }
+            }
+        }
+        shell = next;
+    }
+    distances.remove(&atom);
+    distances
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chemistry::amino_acids::definitions::AminoAcidDefinition;
+    use crate::chemistry::amino_acids::types::AminoAcidCode;
+
+    #[test]
+    fn every_amino_acid_template_is_parameterized() {
+        let force_field = ForceField::get().unwrap();
+        for code in AminoAcidCode::ALL {
+            let definition = AminoAcidDefinition::get(code).unwrap();
+            let elements: Vec<Element> = definition.atoms.iter().map(|atom| atom.element).collect();
+
+            let (topology, missing) = Topology::build(&force_field, &elements, &definition.bonds)
+                .unwrap_or_else(|e| panic!("{:?}: {}", code, e));
+            assert!(missing.is_empty(), "{:?}: {:?}", code, missing);
+            assert_eq!(topology.len(), elements.len(), "{:?}", code);
+            assert_eq!(topology.bonds.len(), definition.bonds.len(), "{:?}", code);
+            assert!(
+                topology.atoms.iter().all(|atom| atom.mass > 0.0),
+                "{:?}",
+                code
+            );
+        }
+    }
+
+    /// Copy with the terms in a canonical order and orientation.
+    fn normalized(topology: &Topology) -> Topology {
+        let mut topology = topology.clone();
+        for angle in &mut topology.angles {
+            if angle.atoms[0] > angle.atoms[2] {
+                angle.atoms.reverse();
+            }
+        }
+        for torsion in &mut topology.torsions {
+            if torsion.atoms[0] > torsion.atoms[3] {
+                torsion.atoms.reverse();
+            }
+        }
+        topology.bonds.sort_by_key(|term| term.atoms);
+        topology.angles.sort_by_key(|term| term.atoms);
+        topology.torsions.sort_by_key(|term| term.atoms);
+        topology.impropers.sort_by_key(|term| term.atoms);
+        topology.pairs_14.sort();
+        topology
+    }
+
+    #[test]
+    fn update_matches_build() {
+        let force_field = ForceField::get().unwrap();
+        let alanine = AminoAcidDefinition::get(AminoAcidCode::Ala).unwrap();
+        let serine = AminoAcidDefinition::get(AminoAcidCode::Ser).unwrap();
+        let offset = alanine.atoms.len();
+        let elements: Vec<Element> = alanine
+            .atoms
+            .iter()
+            .chain(&serine.atoms)
+            .map(|atom| atom.element)
+            .collect();
+        let mut bonds = alanine.bonds.clone();
+        bonds.extend(serine.bonds.iter().map(|&(a, b)| (a + offset, b + offset)));
+
+        let (mut topology, _) =
+            Topology::build(&force_field, &elements[..offset], &alanine.bonds).unwrap();
+        let kept: Vec<Option<usize>> = (0..offset).map(Some).collect();
+        topology
+            .update(&force_field, &kept, &elements, &bonds)
+            .unwrap();
+        let (built, _) = Topology::build(&force_field, &elements, &bonds).unwrap();
+        assert_eq!(normalized(&topology), normalized(&built));
+
+        // Join the alanine C to the serine N.
+        let kept: Vec<Option<usize>> = (0..elements.len()).map(Some).collect();
+        bonds.push((alanine.atom_index("C").unwrap(), offset));
+        topology
+            .update(&force_field, &kept, &elements, &bonds)
+            .unwrap();
+        let (built, _) = Topology::build(&force_field, &elements, &bonds).unwrap();
+        assert_eq!(normalized(&topology), normalized(&built));
+
+        // Remove the alanine again.
+        let kept: Vec<Option<usize>> = (0..elements.len())
+            .map(|atom| atom.checked_sub(offset))
+            .collect();
+        topology
+            .update(&force_field, &kept, &elements[offset..], &serine.bonds)
+            .unwrap();
+        let (built, _) = Topology::build(&force_field, &elements[offset..], &serine.bonds).unwrap();
+        assert_eq!(normalized(&topology), normalized(&built));
+    }
+}
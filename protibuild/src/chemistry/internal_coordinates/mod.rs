@@ -0,0 +1,198 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+use crate::chemistry::error::ChemistryError;
+
+/// Position of an atom relative to three previously placed reference atoms.
+///
+/// With references `[a, b, c]` the atom is bonded to `c` at `bond_length`,
+/// forms `bond_angle` with `b` at `c`, and has the dihedral `torsion` about the
+/// `b`-`c` axis measured from `a`. Angles are in degrees.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InternalCoordinate {
+    pub atom_name: String,
+    pub references: [String; 3],
+    pub bond_length: f32,
+    pub bond_angle: f32,
+    pub torsion: f32,
+}
+
+impl InternalCoordinate {
+    /// Computes the atom position from already placed reference atoms.
+    pub fn place(&self, positions: &HashMap<String, Vec3>) -> Result<Vec3, ChemistryError> {
+        let reference = |name: &String| {
+            positions.get(name).copied().ok_or_else(|| {
+                ChemistryError::Geometry(format!(
+                    "{} references unplaced atom {}",
+                    self.atom_name, name
+                ))
+            })
+        };
+        let [a, b, c] = &self.references;
+
+        Ok(place_atom(
+            reference(a)?,
+            reference(b)?,
+            reference(c)?,
+            self.bond_length,
+            self.bond_angle,
+            self.torsion,
+        ))
+    }
+}
+
+/// Places each atom in order, so every coordinate may reference atoms placed
+/// by earlier entries as well as those already in `positions`.
+pub fn build(
+    coordinates: &[InternalCoordinate],
+    positions: &mut HashMap<String, Vec3>,
+) -> Result<(), ChemistryError> {
+    for coordinate in coordinates {
+        let position = coordinate.place(positions)?;
+        positions.insert(coordinate.atom_name.clone(), position);
+    }
+    Ok(())
+}
+
+/// Natural extension reference frame (NeRF) placement of atom `d` from
+/// `a`, `b` and `c`: `|cd| = bond_length`, the angle `b-c-d` is `bond_angle`
+/// and the dihedral `a-b-c-d` is `torsion`, both in degrees.
+pub fn place_atom(
+    a: Vec3,
+    b: Vec3,
+    c: Vec3,
+    bond_length: f32,
+    bond_angle: f32,
+    torsion: f32,
+) -> Vec3 {
+    let (bond_angle, torsion) = (bond_angle.to_radians(), torsion.to_radians());
+
+    let bc = (c - b).normalize();
+    // Collinear references leave the torsion undefined; any perpendicular works.
+    let normal = (b - a)
+        .cross(bc)
+        .try_normalize()
+        .unwrap_or_else(|| bc.any_orthonormal_vector());
+    let in_plane = normal.cross(bc);
+
+    let local = Vec3::new(
+        -bond_length * bond_angle.cos(),
+        bond_length * bond_angle.sin() * torsion.cos(),
+        bond_length * bond_angle.sin() * torsion.sin(),
+    );
+
+    c + bc * local.x + in_plane * local.y + normal * local.z
+}
+
+/// Angle `a-b-c` at `b` in degrees.
+pub fn bond_angle(a: Vec3, b: Vec3, c: Vec3) -> f32 {
+    (a - b).angle_between(c - b).to_degrees()
+}
+
+/// Dihedral angle `a-b-c-d` about the `b`-`c` axis in degrees, in `(-180, 180]`.
+pub fn torsion_angle(a: Vec3, b: Vec3, c: Vec3, d: Vec3) -> f32 {
+    let axis = (c - b).normalize();
+    let v = (a - b).reject_from_normalized(axis);
+    let w = (d - c).reject_from_normalized(axis);
+    axis.cross(v).dot(w).atan2(v.dot(w)).to_degrees()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Non-collinear reference atoms roughly like a peptide backbone.
+    const A: Vec3 = Vec3::new(-1.2, 1.1, 0.3);
+    const B: Vec3 = Vec3::new(0.0, 0.4, -0.2);
+    const C: Vec3 = Vec3::new(1.45, 0.5, 0.1);
+
+    /// Difference of two angles in degrees, wrapped into `[-180, 180)`.
+    fn angle_difference(a: f32, b: f32) -> f32 {
+        (a - b + 180.0).rem_euclid(360.0) - 180.0
+    }
+
+    #[test]
+    fn placed_atoms_measure_back_their_internal_coordinates() {
+        for bond_length in [0.96, 1.33, 1.53] {
+            for bond_angle in [60.0, 109.5, 121.7, 175.0] {
+                for torsion in [-179.0, -120.0, -60.0, 0.0, 45.0, 120.0, 179.0] {
+                    let d = place_atom(A, B, C, bond_length, bond_angle, torsion);
+
+                    assert!((C.distance(d) - bond_length).abs() < 1e-4);
+                    assert!(
+                        (super::bond_angle(B, C, d) - bond_angle).abs() < 1e-2,
+                        "angle {} measured as {}",
+                        bond_angle,
+                        super::bond_angle(B, C, d)
+                    );
+                    let measured = torsion_angle(A, B, C, d);
+                    assert!(
+                        angle_difference(measured, torsion).abs() < 1e-2,
+                        "torsion {} measured as {}",
+                        torsion,
+                        measured
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn torsions_wrap_at_180_degrees() {
+        let plus = place_atom(A, B, C, 1.5, 110.0, 180.0);
+        let minus = place_atom(A, B, C, 1.5, 110.0, -180.0);
+        assert!(plus.distance(minus) < 1e-4);
+        assert!((torsion_angle(A, B, C, plus).abs() - 180.0).abs() < 1e-2);
+
+        // Torsions past 180° come back on the negative side.
+        let past = place_atom(A, B, C, 1.5, 110.0, 190.0);
+        assert!(angle_difference(torsion_angle(A, B, C, past), -170.0).abs() < 1e-2);
+        let before = place_atom(A, B, C, 1.5, 110.0, -190.0);
+        assert!(angle_difference(torsion_angle(A, B, C, before), 170.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn angles_follow_the_iupac_conventions() {
+        let (a, b, c) = (Vec3::Y, Vec3::ZERO, Vec3::X);
+        assert!((bond_angle(a, b, c) - 90.0).abs() < 1e-4);
+        assert!((bond_angle(Vec3::NEG_X, b, c) - 180.0).abs() < 1e-4);
+
+        // Looking down b-c, d is turned clockwise from a by a right angle.
+        assert!((torsion_angle(a, b, c, Vec3::new(1.0, 0.0, 1.0)) - 90.0).abs() < 1e-4);
+        assert!((torsion_angle(a, b, c, Vec3::new(1.0, 0.0, -1.0)) + 90.0).abs() < 1e-4);
+        assert!(torsion_angle(a, b, c, Vec3::new(1.0, 1.0, 0.0)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn build_places_atoms_from_earlier_entries() {
+        let coordinate =
+            |atom_name: &str, references: [&str; 3], torsion: f32| InternalCoordinate {
+                atom_name: atom_name.to_string(),
+                references: references.map(str::to_string),
+                bond_length: 1.5,
+                bond_angle: 110.0,
+                torsion,
+            };
+        let mut positions = HashMap::from([
+            ("A".to_string(), A),
+            ("B".to_string(), B),
+            ("C".to_string(), C),
+        ]);
+        build(
+            &[
+                coordinate("D", ["A", "B", "C"], 60.0),
+                coordinate("E", ["B", "C", "D"], -60.0),
+            ],
+            &mut positions,
+        )
+        .unwrap();
+        let measured = torsion_angle(B, C, positions["D"], positions["E"]);
+        assert!((measured + 60.0).abs() < 1e-2);
+
+        let missing = coordinate("F", ["A", "B", "X"], 0.0);
+        assert!(matches!(
+            build(&[missing], &mut positions),
+            Err(ChemistryError::Geometry(_))
+        ));
+    }
+}
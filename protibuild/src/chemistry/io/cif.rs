@@ -0,0 +1,268 @@
+use std::collections::BTreeMap;
+
+use crate::chemistry::error::ChemistryError;
+
+/// A lexical token of a CIF file, tagged with the line it starts on.
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Data(String),
+    Loop,
+    Tag(String),
+    /// A value; `None` for the `.` (inapplicable) and `?` (unknown) markers.
+    Value(Option<String>),
+}
+
+/// A category of a data block, such as `_atom_site`.
+///
+/// Single tag/value items are stored as a table with one row, so loops and
+/// plain items are read the same way.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CifTable {
+    pub columns: Vec<String>,
+    rows: Vec<Vec<Option<String>>>,
+    /// Line number of the first value of each row.
+    lines: Vec<usize>,
+}
+
+/// A row of a [`CifTable`].
+#[derive(Debug, Clone, Copy)]
+pub struct CifRow<'a> {
+    pub line: usize,
+    values: &'a [Option<String>],
+}
+
+impl<'a> CifRow<'a> {
+    /// Value of a column, or `None` if the column is absent or the value is
+    /// `.` or `?`.
+    pub fn get(&self, column: Option<usize>) -> Option<&'a str> {
+        column.and_then(|index| self.values.get(index)?.as_deref())
+    }
+}
+
+impl CifTable {
+    /// Index of a column by its field name, without the category prefix.
+    pub fn column(&self, field: &str) -> Option<usize> {
+        self.columns
+            .iter()
+            .position(|column| column.eq_ignore_ascii_case(field))
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = CifRow<'_>> {
+        self.rows
+            .iter()
+            .zip(&self.lines)
+            .map(|(values, &line)| CifRow { line, values })
+    }
+}
+
+/// A `data_` block with its categories keyed by name (e.g. `_atom_site`).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CifBlock {
+    pub name: String,
+    pub tables: BTreeMap<String, CifTable>,
+}
+
+impl CifBlock {
+    pub fn table(&self, category: &str) -> Option<&CifTable> {
+        self.tables.get(category)
+    }
+
+    fn table_mut(&mut self, category: &str) -> &mut CifTable {
+        self.tables.entry(category.to_string()).or_default()
+    }
+}
+
+/// Parses every data block of a CIF file.
+pub fn parse(input: &str) -> Result<Vec<CifBlock>, ChemistryError> {
+    let tokens = tokenize(input)?;
+    let mut blocks: Vec<CifBlock> = Vec::new();
+    let mut index = 0;
+
+    while index < tokens.len() {
+        let (line, token) = &tokens[index];
+        index += 1;
+
+        match token {
+            Token::Data(name) => blocks.push(CifBlock {
+                name: name.clone(),
+                ..Default::default()
+            }),
+            Token::Tag(tag) => {
+                let block = current_block(&mut blocks, *line)?;
+                let Some((_, Token::Value(value))) = tokens.get(index) else {
+                    return Err(parse_error(*line, format!("missing value for {}", tag)));
+                };
+                index += 1;
+
+                let (category, field) = split_tag(tag);
+                let table = block.table_mut(category);
+                if table.rows.is_empty() {
+                    table.rows.push(Vec::new());
+                    table.lines.push(*line);
+                }
+                table.columns.push(field.to_string());
+                table.rows[0].push(value.clone());
+            }
+            Token::Loop => {
+                let block = current_block(&mut blocks, *line)?;
+
+                let mut category = None;
+                let mut columns = Vec::new();
+                while let Some((tag_line, Token::Tag(tag))) = tokens.get(index) {
+                    let (tag_category, field) = split_tag(tag);
+                    if category.is_some_and(|category| category != tag_category) {
+                        return Err(parse_error(*tag_line, "loop mixes categories"));
+                    }
+                    category = Some(tag_category);
+                    columns.push(field.to_string());
+                    index += 1;
+                }
+                let Some(category) = category else {
+                    return Err(parse_error(*line, "loop_ without tags"));
+                };
+
+                let mut table = CifTable {
+                    columns,
+                    ..Default::default()
+                };
+                let mut row = Vec::new();
+                while let Some((value_line, Token::Value(value))) = tokens.get(index) {
+                    if row.is_empty() {
+                        table.lines.push(*value_line);
+                    }
+                    row.push(value.clone());
+                    if row.len() == table.columns.len() {
+                        table.rows.push(std::mem::take(&mut row));
+                    }
+                    index += 1;
+                }
+                if !row.is_empty() {
+                    return Err(parse_error(
+                        table.lines.last().copied().unwrap_or(*line),
+                        format!("incomplete row in {} loop", category),
+                    ));
+                }
+
+                block.tables.insert(category.to_string(), table);
+            }
+            Token::Value(_) => {
+                return Err(parse_error(*line, "value without a tag"));
+            }
+        }
+    }
+
+    Ok(blocks)
+}
+
+/// Formats a value so it reads back as a single token.
+pub fn quote(value: &str) -> String {
+    let needs_quotes = value.is_empty()
+        || value.contains(char::is_whitespace)
+        || value.starts_with(['_', '#', '$', '\'', '"', ';', '[', ']'])
+        || value == "."
+        || value == "?"
+        || value.to_ascii_lowercase().starts_with("data_")
+        || value.eq_ignore_ascii_case("loop_");
+
+    if !needs_quotes {
+        value.to_string()
+    } else if !value.contains("' ") && !value.ends_with('\'') {
+        format!("'{}'", value)
+    } else {
+        format!("\"{}\"", value)
+    }
+}
+
+fn current_block(blocks: &mut [CifBlock], line: usize) -> Result<&mut CifBlock, ChemistryError> {
+    blocks
+        .last_mut()
+        .ok_or_else(|| parse_error(line, "item outside a data_ block"))
+}
+
+/// Splits `_atom_site.Cartn_x` into `("_atom_site", "Cartn_x")`.
+fn split_tag(tag: &str) -> (&str, &str) {
+    tag.split_once('.').unwrap_or((tag, ""))
+}
+
+fn parse_error(line: usize, message: impl Into<String>) -> ChemistryError {
+    ChemistryError::Parse {
+        line,
+        message: message.into(),
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<(usize, Token)>, ChemistryError> {
+    let mut tokens = Vec::new();
+    let mut lines = input.lines().enumerate();
+
+    while let Some((index, line)) = lines.next() {
+        let line_number = index + 1;
+
+        // Semicolon text fields span lines until a line starting with ';'.
+        if let Some(rest) = line.strip_prefix(';') {
+            let mut text = rest.to_string();
+            loop {
+                match lines.next() {
+                    Some((_, text_line)) if text_line.starts_with(';') => break,
+                    Some((_, text_line)) => {
+                        text.push('\n');
+                        text.push_str(text_line);
+                    }
+                    None => return Err(parse_error(line_number, "unterminated text field")),
+                }
+            }
+            tokens.push((line_number, Token::Value(Some(text.trim().to_string()))));
+            continue;
+        }
+
+        let mut rest = line;
+        loop {
+            rest = rest.trim_start();
+            let Some(first) = rest.chars().next() else {
+                break;
+            };
+
+            match first {
+                '#' => break,
+                '\'' | '"' => {
+                    // A quote only closes when followed by whitespace or the end of line.
+                    let body = &rest[1..];
+                    let close = body
+                        .match_indices(first)
+                        .map(|(position, _)| position)
+                        .find(|&position| {
+                            body[position + 1..]
+                                .chars()
+                                .next()
+                                .is_none_or(char::is_whitespace)
+                        })
+                        .ok_or_else(|| parse_error(line_number, "unterminated quoted value"))?;
+                    tokens.push((line_number, Token::Value(Some(body[..close].to_string()))));
+                    rest = &body[close + 1..];
+                }
+                _ => {
+                    let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                    tokens.push((line_number, classify(&rest[..end])));
+                    rest = &rest[end..];
+                }
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn classify(word: &str) -> Token {
+    let lower = word.to_ascii_lowercase();
+    if let Some(name) = lower.strip_prefix("data_") {
+        Token::Data(word[word.len() - name.len()..].to_string())
+    } else if lower == "loop_" {
+        Token::Loop
+    } else if word.starts_with('_') {
+        Token::Tag(word.to_string())
+    } else if word == "." || word == "?" {
+        Token::Value(None)
+    } else {
+        Token::Value(Some(word.to_string()))
+    }
+}
@@ -0,0 +1,650 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::Path;
+
+use super::cif::{self, CifRow, CifTable};
+use super::{
+    SOLVENT_RESIDUES, Structure, StructureAtom, StructureResidue, residue_code, standard_atom,
+};
+use crate::chemistry::amino_acids::DEFAULT_CHAIN_ID;
+use crate::chemistry::atoms::Element;
+use crate::chemistry::error::ChemistryError;
+
+/// Reads an mmCIF (PDBx) file from disk.
+pub fn load(path: impl AsRef<Path>) -> Result<Structure, ChemistryError> {
+    let contents = std::fs::read_to_string(path)?;
+    parse(&contents)
+}
+
+/// Writes a structure to an mmCIF file on disk.
+pub fn save(path: impl AsRef<Path>, structure: &Structure) -> Result<(), ChemistryError> {
+    let name = path
+        .as_ref()
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("protibuild");
+    std::fs::write(&path, write(structure, name))?;
+    Ok(())
+}
+
+/// Parses the first data block of an mmCIF file.
+///
+/// Atoms come from `_atom_site`, preferring the author chain and residue
+/// numbering when present, and only the first model is read. Covalent and
+/// disulfide bonds from `_struct_conn` are added to the bonds inferred from
+/// distances. When `_entity_poly_seq` numbering is available, no peptide bond
+/// is inferred across a gap in `label_seq_id`. Water and `HETATM` groups that
+/// are not amino acids, such as ligands and ions, are skipped, and
+/// selenomethionine is read as methionine.
+pub fn parse(input: &str) -> Result<Structure, ChemistryError> {
+    let block = cif::parse(input)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| parse_error(1, "no data block"))?;
+    let atom_site = block
+        .table("_atom_site")
+        .ok_or_else(|| parse_error(1, "missing _atom_site category"))?;
+
+    let columns = AtomSiteColumns::new(atom_site);
+    let has_poly_seq = block.table("_entity_poly_seq").is_some();
+
+    let mut structure = Structure::default();
+    let mut label_seq_ids: Vec<Option<i64>> = Vec::new();
+    let mut atom_lookup: HashMap<(String, i32, String, String), u32> = HashMap::new();
+    let mut current_key: Option<(String, String, String, String)> = None;
+    let mut first_model: Option<String> = None;
+    // Whether the rows of the current residue belong to a skipped group.
+    let mut skipping = false;
+
+    for row in atom_site.rows() {
+        if let Some(model) = row.get(columns.model) {
+            match &first_model {
+                Some(first) if first != model => continue,
+                Some(_) => {}
+                None => first_model = Some(model.to_string()),
+            }
+        }
+
+        let alt_loc = row.get(columns.alt_id).unwrap_or("A");
+        if alt_loc != "A" {
+            continue;
+        }
+
+        let residue_name = required(row, columns.comp_id, "comp_id")?;
+        let hetero = row.get(columns.group) == Some("HETATM");
+        if hetero && SOLVENT_RESIDUES.contains(&residue_name) {
+            continue;
+        }
+
+        let chain_id = row
+            .get(columns.auth_asym_id)
+            .or(row.get(columns.label_asym_id))
+            .unwrap_or(DEFAULT_CHAIN_ID)
+            .to_string();
+        let residue_seq = required(row, columns.auth_seq_id.or(columns.label_seq_id), "seq_id")?;
+        let insertion_code = row.get(columns.ins_code).unwrap_or("");
+        let key = (
+            chain_id.clone(),
+            residue_seq.to_string(),
+            insertion_code.to_string(),
+            residue_name.to_string(),
+        );
+
+        if current_key.as_ref() != Some(&key) {
+            current_key = Some(key);
+            skipping = false;
+            let residue_number = residue_seq.parse::<i32>().map_err(|_| {
+                parse_error(
+                    row.line,
+                    format!("invalid residue number '{}'", residue_seq),
+                )
+            })?;
+            match residue_code(residue_name, hetero)? {
+                Some(code) => {
+                    structure.residues.push(StructureResidue {
+                        code,
+                        residue_number,
+                        chain_id: chain_id.clone(),
+                        atoms: Vec::new(),
+                    });
+                    label_seq_ids.push(
+                        row.get(columns.label_seq_id)
+                            .and_then(|value| value.parse().ok()),
+                    );
+                }
+                None => {
+                    warn!(
+                        "Skipping HETATM group {} {}{} on line {}",
+                        residue_name, chain_id, residue_seq, row.line
+                    );
+                    skipping = true;
+                }
+            }
+        }
+        if skipping {
+            continue;
+        }
+
+        let file_atom_name = required(row, columns.atom_id, "atom_id")?;
+        let (atom_name, element) = match standard_atom(residue_name, file_atom_name) {
+            Some((atom_name, element)) => (atom_name.to_string(), element),
+            None => (
+                file_atom_name.to_string(),
+                atom_element(row, &columns, file_atom_name)?,
+            ),
+        };
+        let serial = parse_number(row, columns.id, "id")?;
+
+        let Some(residue) = structure.residues.last_mut() else {
+            continue;
+        };
+        // `_struct_conn` names atoms as they are in the file.
+        atom_lookup.insert(
+            (
+                chain_id,
+                residue.residue_number,
+                insertion_code.to_string(),
+                file_atom_name.to_string(),
+            ),
+            serial,
+        );
+        residue.atoms.push(StructureAtom {
+            serial,
+            atom_name,
+            element,
+            position: Vec3::new(
+                parse_number(row, columns.x, "Cartn_x")?,
+                parse_number(row, columns.y, "Cartn_y")?,
+                parse_number(row, columns.z, "Cartn_z")?,
+            ),
+            b_factor: match row.get(columns.b_factor) {
+                Some(_) => parse_number(row, columns.b_factor, "B_iso_or_equiv")?,
+                None => 0.0,
+            },
+        });
+    }
+
+    let chain_breaks: Vec<usize> = if has_poly_seq {
+        label_seq_ids
+            .windows(2)
+            .enumerate()
+            .filter_map(|(index, pair)| match pair {
+                [Some(previous), Some(next)] if next - previous != 1 => Some(index + 1),
+                _ => None,
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    structure.infer_bonds(&chain_breaks);
+
+    if let Some(struct_conn) = block.table("_struct_conn") {
+        let conn_type = struct_conn.column("conn_type_id");
+        let partners = [1, 2].map(|partner| PartnerColumns::new(struct_conn, partner));
+
+        for row in struct_conn.rows() {
+            let is_covalent = row
+                .get(conn_type)
+                .is_some_and(|kind| kind.starts_with("covale") || kind == "disulf");
+            if !is_covalent {
+                continue;
+            }
+
+            let serials = partners.each_ref().map(|partner| {
+                partner
+                    .key(row)
+                    .and_then(|key| atom_lookup.get(&key).copied())
+            });
+            if let [Some(serial1), Some(serial2)] = serials {
+                structure.add_bond(serial1, serial2);
+            }
+        }
+    }
+
+    Ok(structure)
+}
+
+/// Formats a structure as an mmCIF data block with `_entity_poly_seq`,
+/// `_atom_site` and `_struct_conn` categories.
+///
+/// Each chain is written as its own entity. Bonds within a residue and
+/// peptide bonds between consecutive residues are left to distance-based
+/// inference on import; every other bond is written to `_struct_conn`.
+///
+/// The bonds are therefore not written losslessly: `_struct_conn` only
+/// describes connections between residues, so a bond within a residue or a
+/// peptide bond whose atoms are further apart than bonding distance is lost
+/// on import, and one whose atoms are within bonding distance but unbonded
+/// is added back. Use [`super::pdb::write`], which writes every bond as a
+/// `CONECT` record, when the exact bonds matter.
+pub fn write(structure: &Structure, name: &str) -> String {
+    let mut output = String::new();
+    let _ = writeln!(output, "data_{}", name.replace(char::is_whitespace, "_"));
+
+    // Entity and label_seq_id of every residue, numbered per chain.
+    let mut chains: Vec<&str> = Vec::new();
+    let mut labels: Vec<(usize, usize)> = Vec::new();
+    let mut residue_counts: HashMap<&str, usize> = HashMap::new();
+    for residue in &structure.residues {
+        let chain = residue.chain_id.as_str();
+        let entity = match chains.iter().position(|&known| known == chain) {
+            Some(position) => position + 1,
+            None => {
+                chains.push(chain);
+                chains.len()
+            }
+        };
+        let count = residue_counts.entry(chain).or_default();
+        *count += 1;
+        labels.push((entity, *count));
+    }
+
+    let _ = writeln!(output, "#");
+    let _ = writeln!(output, "loop_");
+    for field in ["entity_id", "num", "mon_id", "hetero"] {
+        let _ = writeln!(output, "_entity_poly_seq.{}", field);
+    }
+    for (residue, (entity, seq_id)) in structure.residues.iter().zip(&labels) {
+        let _ = writeln!(
+            output,
+            "{} {} {} n",
+            entity,
+            seq_id,
+            residue.code.three_letter()
+        );
+    }
+
+    let _ = writeln!(output, "#");
+    let _ = writeln!(output, "loop_");
+    for field in [
+        "group_PDB",
+        "id",
+        "type_symbol",
+        "label_atom_id",
+        "label_alt_id",
+        "label_comp_id",
+        "label_asym_id",
+        "label_entity_id",
+        "label_seq_id",
+        "pdbx_PDB_ins_code",
+        "Cartn_x",
+        "Cartn_y",
+        "Cartn_z",
+        "occupancy",
+        "B_iso_or_equiv",
+        "auth_seq_id",
+        "auth_asym_id",
+        "pdbx_PDB_model_num",
+    ] {
+        let _ = writeln!(output, "_atom_site.{}", field);
+    }
+
+    let mut atom_names: HashMap<u32, (usize, &str)> = HashMap::new();
+    for (index, (residue, (entity, seq_id))) in structure.residues.iter().zip(&labels).enumerate() {
+        let chain_id = cif::quote(&residue.chain_id);
+        for atom in &residue.atoms {
+            atom_names.insert(atom.serial, (index, atom.atom_name.as_str()));
+            let _ = writeln!(
+                output,
+                "ATOM {} {} {} . {} {} {} {} ? {:.3} {:.3} {:.3} 1.00 {:.2} {} {} 1",
+                atom.serial,
+                atom.element.symbol(),
+                cif::quote(&atom.atom_name),
+                residue.code.three_letter(),
+                chain_id,
+                entity,
+                seq_id,
+                atom.position.x,
+                atom.position.y,
+                atom.position.z,
+                atom.b_factor,
+                residue.residue_number,
+                chain_id,
+            );
+        }
+    }
+
+    let connections: Vec<_> = structure
+        .bonds()
+        .iter()
+        .filter_map(|(serial1, serial2)| {
+            let &(residue1, name1) = atom_names.get(serial1)?;
+            let &(residue2, name2) = atom_names.get(serial2)?;
+            if residue1 == residue2
+                || is_peptide_bond(structure, (residue1, name1), (residue2, name2))
+            {
+                return None;
+            }
+            Some(((residue1, name1), (residue2, name2)))
+        })
+        .collect();
+
+    if !connections.is_empty() {
+        let _ = writeln!(output, "#");
+        let _ = writeln!(output, "loop_");
+        for field in [
+            "id",
+            "conn_type_id",
+            "ptnr1_auth_asym_id",
+            "ptnr1_label_comp_id",
+            "ptnr1_auth_seq_id",
+            "ptnr1_label_atom_id",
+            "ptnr2_auth_asym_id",
+            "ptnr2_label_comp_id",
+            "ptnr2_auth_seq_id",
+            "ptnr2_label_atom_id",
+        ] {
+            let _ = writeln!(output, "_struct_conn.{}", field);
+        }
+
+        for (index, partners) in connections.iter().enumerate() {
+            let ((residue1, name1), (residue2, name2)) = *partners;
+            let kind = if name1 == "SG" && name2 == "SG" {
+                "disulf"
+            } else {
+                "covale"
+            };
+            let _ = write!(output, "{}{} {}", kind, index + 1, kind);
+            for (residue, name) in [(residue1, name1), (residue2, name2)] {
+                let residue = &structure.residues[residue];
+                let _ = write!(
+                    output,
+                    " {} {} {} {}",
+                    cif::quote(&residue.chain_id),
+                    residue.code.three_letter(),
+                    residue.residue_number,
+                    cif::quote(name),
+                );
+            }
+            output.push('\n');
+        }
+    }
+
+    let _ = writeln!(output, "#");
+    output
+}
+
+/// Whether a bond is the C(i)-N(i+1) peptide bond between consecutive residues
+/// of a chain, which the reader infers without a `_struct_conn` entry.
+fn is_peptide_bond(
+    structure: &Structure,
+    (residue1, name1): (usize, &str),
+    (residue2, name2): (usize, &str),
+) -> bool {
+    let same_chain = structure.residues[residue1].chain_id == structure.residues[residue2].chain_id;
+    same_chain
+        && ((residue2 == residue1 + 1 && name1 == "C" && name2 == "N")
+            || (residue1 == residue2 + 1 && name1 == "N" && name2 == "C"))
+}
+
+/// Column indices of the `_atom_site` fields used by the reader.
+struct AtomSiteColumns {
+    group: Option<usize>,
+    id: Option<usize>,
+    type_symbol: Option<usize>,
+    atom_id: Option<usize>,
+    alt_id: Option<usize>,
+    comp_id: Option<usize>,
+    label_asym_id: Option<usize>,
+    auth_asym_id: Option<usize>,
+    label_seq_id: Option<usize>,
+    auth_seq_id: Option<usize>,
+    ins_code: Option<usize>,
+    x: Option<usize>,
+    y: Option<usize>,
+    z: Option<usize>,
+    b_factor: Option<usize>,
+    model: Option<usize>,
+}
+
+impl AtomSiteColumns {
+    fn new(table: &CifTable) -> Self {
+        Self {
+            group: table.column("group_PDB"),
+            id: table.column("id"),
+            type_symbol: table.column("type_symbol"),
+            atom_id: table
+                .column("label_atom_id")
+                .or(table.column("auth_atom_id")),
+            alt_id: table.column("label_alt_id"),
+            comp_id: table
+                .column("label_comp_id")
+                .or(table.column("auth_comp_id")),
+            label_asym_id: table.column("label_asym_id"),
+            auth_asym_id: table.column("auth_asym_id"),
+            label_seq_id: table.column("label_seq_id"),
+            auth_seq_id: table.column("auth_seq_id"),
+            ins_code: table.column("pdbx_PDB_ins_code"),
+            x: table.column("Cartn_x"),
+            y: table.column("Cartn_y"),
+            z: table.column("Cartn_z"),
+            b_factor: table.column("B_iso_or_equiv"),
+            model: table.column("pdbx_PDB_model_num"),
+        }
+    }
+}
+
+/// Column indices identifying one partner atom of a `_struct_conn` row, using
+/// the same author/label preference as the `_atom_site` reader.
+struct PartnerColumns {
+    asym_id: Option<usize>,
+    seq_id: Option<usize>,
+    ins_code: Option<usize>,
+    atom_id: Option<usize>,
+}
+
+impl PartnerColumns {
+    fn new(table: &CifTable, partner: u8) -> Self {
+        let column = |auth: &str, label: &str| {
+            table
+                .column(&format!("ptnr{}_{}", partner, auth))
+                .or(table.column(&format!("ptnr{}_{}", partner, label)))
+        };
+        Self {
+            asym_id: column("auth_asym_id", "label_asym_id"),
+            seq_id: column("auth_seq_id", "label_seq_id"),
+            ins_code: table.column(&format!("pdbx_ptnr{}_PDB_ins_code", partner)),
+            atom_id: column("label_atom_id", "auth_atom_id"),
+        }
+    }
+
+    fn key(&self, row: CifRow) -> Option<(String, i32, String, String)> {
+        Some((
+            row.get(self.asym_id)?.to_string(),
+            row.get(self.seq_id)?.parse().ok()?,
+            row.get(self.ins_code).unwrap_or("").to_string(),
+            row.get(self.atom_id)?.to_string(),
+        ))
+    }
+}
+
+/// Element of an `_atom_site` row, from `type_symbol` or else the first letter
+/// of the atom name.
+fn atom_element(
+    row: CifRow,
+    columns: &AtomSiteColumns,
+    atom_name: &str,
+) -> Result<Element, ChemistryError> {
+    let symbol = row
+        .get(columns.type_symbol)
+        .unwrap_or(atom_name.get(..1).unwrap_or(""));
+    Element::from_symbol(symbol).ok_or_else(|| ChemistryError::UnknownElement(symbol.to_string()))
+}
+
+fn required<'a>(
+    row: CifRow<'a>,
+    column: Option<usize>,
+    field: &str,
+) -> Result<&'a str, ChemistryError> {
+    row.get(column)
+        .ok_or_else(|| parse_error(row.line, format!("missing _atom_site.{}", field)))
+}
+
+fn parse_number<T: std::str::FromStr>(
+    row: CifRow,
+    column: Option<usize>,
+    field: &str,
+) -> Result<T, ChemistryError> {
+    let value = required(row, column, field)?;
+    value
+        .parse()
+        .map_err(|_| parse_error(row.line, format!("invalid {} '{}'", field, value)))
+}
+
+fn parse_error(line: usize, message: impl Into<String>) -> ChemistryError {
+    ChemistryError::Parse {
+        line,
+        message: message.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chemistry::amino_acids::AminoAcidCode;
+
+    fn atom(serial: u32, name: &str, element: Element, x: f32, y: f32) -> StructureAtom {
+        StructureAtom {
+            serial,
+            atom_name: name.to_string(),
+            element,
+            position: Vec3::new(x, y, 0.0),
+            b_factor: 0.0,
+        }
+    }
+
+    fn normalized(bonds: &[(u32, u32)]) -> Vec<(u32, u32)> {
+        let mut bonds: Vec<_> = bonds.iter().map(|&(a, b)| (a.min(b), a.max(b))).collect();
+        bonds.sort_unstable();
+        bonds
+    }
+
+    #[test]
+    fn write_then_parse_round_trips_multi_character_chains() {
+        let structure = Structure::new(
+            vec![
+                StructureResidue {
+                    code: AminoAcidCode::Cys,
+                    residue_number: 7,
+                    chain_id: "AA".to_string(),
+                    atoms: vec![
+                        atom(1, "CB", Element::Carbon, 0.0, 0.0),
+                        atom(2, "SG", Element::Sulfur, 1.81, 0.0),
+                    ],
+                },
+                StructureResidue {
+                    code: AminoAcidCode::Cys,
+                    residue_number: 12,
+                    chain_id: "B1".to_string(),
+                    atoms: vec![
+                        atom(3, "SG", Element::Sulfur, 3.84, 0.0),
+                        atom(4, "CB", Element::Carbon, 5.65, 0.0),
+                    ],
+                },
+            ],
+            [(1, 2), (2, 3), (3, 4)],
+        );
+
+        let written = write(&structure, "round trip");
+        assert!(written.starts_with("data_round_trip\n"));
+        assert!(written.contains("disulf1 disulf AA CYS 7 SG B1 CYS 12 SG"));
+
+        let parsed = parse(&written).expect("written mmCIF should parse");
+        assert_eq!(parsed.residues, structure.residues);
+        assert_eq!(normalized(parsed.bonds()), normalized(structure.bonds()));
+    }
+
+    const ATOM_SITE_HEADER: &str = "data_test
+loop_
+_atom_site.group_PDB
+_atom_site.id
+_atom_site.type_symbol
+_atom_site.label_atom_id
+_atom_site.label_comp_id
+_atom_site.auth_asym_id
+_atom_site.auth_seq_id
+_atom_site.Cartn_x
+_atom_site.Cartn_y
+_atom_site.Cartn_z
+";
+
+    #[test]
+    fn negative_residue_numbers_are_read() {
+        let input = format!(
+            "{}ATOM 1 C CA GLY A -3 0.0 0.0 0.0\nATOM 2 C CA ALA A 1 3.8 0.0 0.0\n",
+            ATOM_SITE_HEADER
+        );
+        let structure = parse(&input).expect("negative residue numbers should parse");
+        assert_eq!(structure.residues[0].residue_number, -3);
+        assert_eq!(structure.residues[1].residue_number, 1);
+    }
+
+    #[test]
+    fn hetero_groups_are_skipped_and_selenomethionine_is_read_as_methionine() {
+        let input = format!(
+            "{}ATOM 1 C CA GLY A 1 0.0 0.0 0.0
+HETATM 2 C CA MSE A 2 3.8 0.0 0.0
+HETATM 3 SE SE MSE A 2 5.0 1.5 0.0
+HETATM 4 ZN ZN ZN A 101 9.0 0.0 0.0
+HETATM 5 C C1 NAG A 102 12.0 0.0 0.0
+HETATM 6 O O HOH A 201 15.0 0.0 0.0
+",
+            ATOM_SITE_HEADER
+        );
+        let structure = parse(&input).expect("hetero groups should be skipped");
+        let codes: Vec<AminoAcidCode> = structure
+            .residues
+            .iter()
+            .map(|residue| residue.code)
+            .collect();
+        assert_eq!(codes, [AminoAcidCode::Gly, AminoAcidCode::Met]);
+        let selenium = &structure.residues[1].atoms[1];
+        assert_eq!(selenium.atom_name, "SD");
+        assert_eq!(selenium.element, Element::Sulfur);
+
+        let unknown = format!("{}ATOM 1 C C1 NAG A 1 0.0 0.0 0.0\n", ATOM_SITE_HEADER);
+        assert!(matches!(
+            parse(&unknown),
+            Err(ChemistryError::UnknownResidue(name)) if name == "NAG"
+        ));
+    }
+
+    #[test]
+    fn struct_conn_partners_are_matched_by_insertion_code() {
+        let input = "data_test
+loop_
+_atom_site.group_PDB
+_atom_site.id
+_atom_site.type_symbol
+_atom_site.label_atom_id
+_atom_site.label_comp_id
+_atom_site.auth_asym_id
+_atom_site.auth_seq_id
+_atom_site.pdbx_PDB_ins_code
+_atom_site.Cartn_x
+_atom_site.Cartn_y
+_atom_site.Cartn_z
+ATOM 1 S SG CYS A 52 ? 0.0 0.0 0.0
+ATOM 2 S SG CYS A 52 A 10.0 0.0 0.0
+ATOM 3 S SG CYS A 60 ? 20.0 0.0 0.0
+#
+loop_
+_struct_conn.id
+_struct_conn.conn_type_id
+_struct_conn.ptnr1_auth_asym_id
+_struct_conn.ptnr1_auth_seq_id
+_struct_conn.pdbx_ptnr1_PDB_ins_code
+_struct_conn.ptnr1_label_atom_id
+_struct_conn.ptnr2_auth_asym_id
+_struct_conn.ptnr2_auth_seq_id
+_struct_conn.pdbx_ptnr2_PDB_ins_code
+_struct_conn.ptnr2_label_atom_id
+disulf1 disulf A 52 ? SG A 60 ? SG
+";
+        let structure = parse(input).expect("insertion codes should parse");
+        assert_eq!(structure.residues.len(), 3);
+        // Without the insertion code, 52A would shadow 52.
+        assert_eq!(normalized(structure.bonds()), [(1, 3)]);
+    }
+}
@@ -0,0 +1,364 @@
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+pub mod cif;
+pub mod mmcif;
+pub mod pdb;
+
+use crate::chemistry::amino_acids::{AminoAcid, AminoAcidBuilder, AminoAcidCode, Residue};
+use crate::chemistry::atoms::{Atom, Bond, Element};
+use crate::chemistry::error::ChemistryError;
+
+/// Bond length tolerance applied to the sum of covalent radii when inferring bonds.
+const BOND_TOLERANCE: f32 = 1.2;
+
+/// Structure file formats that can be read and written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StructureFormat {
+    Pdb,
+    Mmcif,
+}
+
+impl StructureFormat {
+    /// Picks a format from a file extension, defaulting to PDB.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension)
+                if extension.eq_ignore_ascii_case("cif")
+                    || extension.eq_ignore_ascii_case("mmcif") =>
+            {
+                StructureFormat::Mmcif
+            }
+            _ => StructureFormat::Pdb,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            StructureFormat::Pdb => "pdb",
+            StructureFormat::Mmcif => "cif",
+        }
+    }
+}
+
+/// Reads a structure file, choosing the format from its extension.
+pub fn load(path: impl AsRef<Path>) -> Result<Structure, ChemistryError> {
+    let path = path.as_ref();
+    match StructureFormat::from_path(path) {
+        StructureFormat::Pdb => pdb::load(path),
+        StructureFormat::Mmcif => mmcif::load(path),
+    }
+}
+
+/// Writes a structure file, choosing the format from its extension.
+pub fn save(path: impl AsRef<Path>, structure: &Structure) -> Result<(), ChemistryError> {
+    let path = path.as_ref();
+    match StructureFormat::from_path(path) {
+        StructureFormat::Pdb => pdb::save(path, structure),
+        StructureFormat::Mmcif => mmcif::save(path, structure),
+    }
+}
+
+/// Residue names of solvent molecules that are skipped on import.
+const SOLVENT_RESIDUES: [&str; 2] = ["HOH", "WAT"];
+
+/// Residue name of selenomethionine, which is read as methionine.
+const SELENOMETHIONINE: &str = "MSE";
+
+/// Amino acid of a residue read from a structure file.
+///
+/// Selenomethionine is read as methionine. Unknown residues of `ATOM` records
+/// are an error, while unknown `HETATM` groups such as ligands and ions give
+/// `None` so they can be skipped.
+fn residue_code(residue_name: &str, hetero: bool) -> Result<Option<AminoAcidCode>, ChemistryError> {
+    if residue_name == SELENOMETHIONINE {
+        return Ok(Some(AminoAcidCode::Met));
+    }
+    match AminoAcidCode::from_three_letter(residue_name) {
+        Some(code) => Ok(Some(code)),
+        None if hetero => Ok(None),
+        None => Err(ChemistryError::UnknownResidue(residue_name.to_string())),
+    }
+}
+
+/// Name and element an atom is read with when its residue is read as another
+/// one: the selenium of selenomethionine becomes the sulfur of methionine.
+fn standard_atom(residue_name: &str, atom_name: &str) -> Option<(&'static str, Element)> {
+    (residue_name == SELENOMETHIONINE && atom_name == "SE").then_some(("SD", Element::Sulfur))
+}
+
+/// A single atom read from a structure file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructureAtom {
+    pub serial: u32,
+    pub atom_name: String,
+    pub element: Element,
+    pub position: Vec3,
+    /// Temperature factor in Å², zero when the source has none.
+    pub b_factor: f32,
+}
+
+/// A residue read from a structure file, with its atoms in file order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructureResidue {
+    pub code: AminoAcidCode,
+    pub residue_number: i32,
+    pub chain_id: String,
+    pub atoms: Vec<StructureAtom>,
+}
+
+/// Format-independent molecular structure shared by the file readers and the
+/// peptide builder.
+///
+/// Bonds reference atoms by serial number. They are only added through
+/// [`Structure::add_bond`], which keeps a set of the bonded pairs so large
+/// files load without comparing every new bond against all earlier ones.
+#[derive(Debug, Clone, Default)]
+pub struct Structure {
+    pub residues: Vec<StructureResidue>,
+    bonds: Vec<(u32, u32)>,
+    /// Bonded pairs with the lower serial first.
+    bonded: HashSet<(u32, u32)>,
+}
+
+impl PartialEq for Structure {
+    fn eq(&self, other: &Self) -> bool {
+        self.residues == other.residues && self.bonds == other.bonds
+    }
+}
+
+impl Structure {
+    /// Creates a structure from residues and bonds, dropping duplicate bonds.
+    pub fn new(
+        residues: Vec<StructureResidue>,
+        bonds: impl IntoIterator<Item = (u32, u32)>,
+    ) -> Self {
+        let mut structure = Self {
+            residues,
+            ..default()
+        };
+        for (serial1, serial2) in bonds {
+            structure.add_bond(serial1, serial2);
+        }
+        structure
+    }
+
+    /// Bonds in the order they were added.
+    pub fn bonds(&self) -> &[(u32, u32)] {
+        &self.bonds
+    }
+
+    /// Removes and returns all bonds.
+    pub fn take_bonds(&mut self) -> Vec<(u32, u32)> {
+        self.bonded.clear();
+        std::mem::take(&mut self.bonds)
+    }
+
+    /// Collects the residues under `roots` from the world, reading each atom's
+    /// world-space position from its `GlobalTransform`.
+    ///
+    /// Roots that are not amino acids are skipped. Atoms are numbered in
+    /// traversal order starting at 1.
+    pub fn from_entities(
+        roots: &[Entity],
+        residue_query: &Query<(&AminoAcid, &Residue, &Children)>,
+        atom_query: &Query<(&Atom, &GlobalTransform)>,
+        bond_query: &Query<&Bond>,
+    ) -> Self {
+        Self::from_selected_entities(roots, residue_query, atom_query, bond_query, |_| true)
+    }
+
+    /// Like [`Structure::from_entities`], but keeps only the atoms for which
+    /// `selected` returns true. Residues without selected atoms are skipped,
+    /// as are bonds to atoms that are not selected.
+    pub fn from_selected_entities(
+        roots: &[Entity],
+        residue_query: &Query<(&AminoAcid, &Residue, &Children)>,
+        atom_query: &Query<(&Atom, &GlobalTransform)>,
+        bond_query: &Query<&Bond>,
+        selected: impl Fn(Entity) -> bool,
+    ) -> Self {
+        let mut structure = Structure::default();
+        let mut serials: HashMap<Entity, u32> = HashMap::new();
+        let mut bonds = Vec::new();
+
+        for &root in roots {
+            let Ok((amino_acid, residue, children)) = residue_query.get(root) else {
+                continue;
+            };
+
+            let mut atoms = Vec::new();
+            for child in children.iter() {
+                if let Ok((atom, transform)) = atom_query.get(child) {
+                    if !selected(child) {
+                        continue;
+                    }
+                    let serial = serials.len() as u32 + 1;
+                    serials.insert(child, serial);
+                    atoms.push(StructureAtom {
+                        serial,
+                        atom_name: atom.atom_name.clone(),
+                        element: atom.element,
+                        position: transform.translation(),
+                        b_factor: atom.b_factor,
+                    });
+                } else if let Ok(bond) = bond_query.get(child) {
+                    bonds.push((bond.atom1, bond.atom2));
+                }
+            }
+            if atoms.is_empty() {
+                continue;
+            }
+
+            structure.residues.push(StructureResidue {
+                code: amino_acid.code,
+                residue_number: residue.residue_number,
+                chain_id: residue.chain_id.clone(),
+                atoms,
+            });
+        }
+
+        for (atom1, atom2) in bonds {
+            if let (Some(&serial1), Some(&serial2)) = (serials.get(&atom1), serials.get(&atom2)) {
+                structure.add_bond(serial1, serial2);
+            }
+        }
+
+        structure
+    }
+
+    pub fn atoms(&self) -> impl Iterator<Item = &StructureAtom> {
+        self.residues
+            .iter()
+            .flat_map(|residue| residue.atoms.iter())
+    }
+
+    pub fn centroid(&self) -> Vec3 {
+        let (sum, count) = self.atoms().fold((Vec3::ZERO, 0), |(sum, count), atom| {
+            (sum + atom.position, count + 1)
+        });
+        if count == 0 {
+            Vec3::ZERO
+        } else {
+            sum / count as f32
+        }
+    }
+
+    /// Adds a bond unless the same pair is already bonded.
+    pub fn add_bond(&mut self, serial1: u32, serial2: u32) {
+        if serial1 == serial2 {
+            return;
+        }
+        if self
+            .bonded
+            .insert((serial1.min(serial2), serial1.max(serial2)))
+        {
+            self.bonds.push((serial1, serial2));
+        }
+    }
+
+    /// Infers covalent bonds within each residue and peptide bonds between
+    /// consecutive residues of the same chain from interatomic distances.
+    ///
+    /// `chain_breaks` holds residue indices that start a new chain segment.
+    pub fn infer_bonds(&mut self, chain_breaks: &[usize]) {
+        let mut inferred = Vec::new();
+
+        for (index, residue) in self.residues.iter().enumerate() {
+            for (i, atom1) in residue.atoms.iter().enumerate() {
+                for atom2 in &residue.atoms[i + 1..] {
+                    if Self::is_bonded(atom1, atom2) {
+                        inferred.push((atom1.serial, atom2.serial));
+                    }
+                }
+            }
+
+            let Some(next) = self.residues.get(index + 1) else {
+                continue;
+            };
+            if next.chain_id != residue.chain_id || chain_breaks.contains(&(index + 1)) {
+                continue;
+            }
+            let carbon = residue.atoms.iter().find(|atom| atom.atom_name == "C");
+            let nitrogen = next.atoms.iter().find(|atom| atom.atom_name == "N");
+            if let (Some(carbon), Some(nitrogen)) = (carbon, nitrogen)
+                && Self::is_bonded(carbon, nitrogen)
+            {
+                inferred.push((carbon.serial, nitrogen.serial));
+            }
+        }
+
+        for (serial1, serial2) in inferred {
+            self.add_bond(serial1, serial2);
+        }
+    }
+
+    fn is_bonded(atom1: &StructureAtom, atom2: &StructureAtom) -> bool {
+        let max_length =
+            (atom1.element.covalent_radius() + atom2.element.covalent_radius()) * BOND_TOLERANCE;
+        atom1.position.distance_squared(atom2.position) <= max_length * max_length
+    }
+
+    /// Spawns every residue as an `AminoAcid` parent with child `Atom` and `Bond`
+    /// entities, with the structure's centroid placed at `position`.
+    ///
+    /// Each bond is parented to the residue of its first atom, so bonds between
+    /// residues (peptide or disulfide bonds) are spawned as well.
+    pub fn spawn(&self, commands: &mut Commands, position: Vec3) -> Vec<Entity> {
+        let offset = position - self.centroid();
+
+        let mut roots = Vec::with_capacity(self.residues.len());
+        let mut atom_entities: HashMap<u32, (Entity, Entity)> = HashMap::new();
+
+        for residue in &self.residues {
+            let origin = residue
+                .atoms
+                .iter()
+                .find(|atom| atom.atom_name == "CA")
+                .or(residue.atoms.first())
+                .map_or(Vec3::ZERO, |atom| atom.position);
+
+            let atoms: Vec<Atom> = residue
+                .atoms
+                .iter()
+                .map(|atom| {
+                    Atom::new(atom.element, atom.position - origin, &atom.atom_name)
+                        .with_b_factor(atom.b_factor)
+                })
+                .collect();
+
+            let (parent, entities) = AminoAcidBuilder::spawn_residue(
+                commands,
+                residue.code,
+                Residue {
+                    residue_number: residue.residue_number,
+                    chain_id: residue.chain_id.clone(),
+                },
+                origin + offset,
+                &atoms,
+            );
+
+            for (atom, entity) in residue.atoms.iter().zip(entities) {
+                atom_entities.insert(atom.serial, (parent, entity));
+            }
+            roots.push(parent);
+        }
+
+        for &(serial1, serial2) in &self.bonds {
+            if let (Some(&(parent, atom1)), Some(&(_, atom2))) =
+                (atom_entities.get(&serial1), atom_entities.get(&serial2))
+            {
+                AminoAcidBuilder::spawn_bond(
+                    commands,
+                    parent,
+                    atom1,
+                    atom2,
+                    format!("Bond_{}_{}", serial1, serial2),
+                );
+            }
+        }
+
+        roots
+    }
+}
@@ -0,0 +1,494 @@
+use bevy::prelude::*;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
+use std::path::Path;
+
+use super::{
+    SOLVENT_RESIDUES, Structure, StructureAtom, StructureResidue, residue_code, standard_atom,
+};
+use crate::chemistry::amino_acids::DEFAULT_CHAIN_ID;
+use crate::chemistry::atoms::Element;
+use crate::chemistry::error::ChemistryError;
+
+/// Reads a PDB file from disk.
+pub fn load(path: impl AsRef<Path>) -> Result<Structure, ChemistryError> {
+    let contents = std::fs::read_to_string(path)?;
+    parse(&contents)
+}
+
+/// Writes a structure to a PDB file on disk.
+pub fn save(path: impl AsRef<Path>, structure: &Structure) -> Result<(), ChemistryError> {
+    std::fs::write(path, write(structure)?)?;
+    Ok(())
+}
+
+/// Formats a structure as ATOM, TER, CONECT and END records.
+///
+/// Atoms are renumbered sequentially, with a TER record closing each chain.
+/// Every bond is written as a CONECT record so the file round-trips through
+/// [`parse`] without relying on distance-based bond inference. Chain IDs
+/// longer than one character are remapped, see [`pdb_chain_ids`].
+pub fn write(structure: &Structure) -> Result<String, ChemistryError> {
+    let chain_ids = pdb_chain_ids(structure)?;
+    let mut output = String::new();
+    let mut serials: HashMap<u32, u32> = HashMap::new();
+    let mut serial = 0;
+
+    for (index, residue) in structure.residues.iter().enumerate() {
+        let residue_name = residue.code.three_letter();
+        let chain_id = chain_ids[&residue.chain_id];
+
+        for atom in &residue.atoms {
+            serial += 1;
+            serials.insert(atom.serial, serial);
+            let _ = writeln!(
+                output,
+                "ATOM  {:>5} {} {:>3} {}{:>4}    {:>8.3}{:>8.3}{:>8.3}{:>6.2}{:>6.2}          {:>2}",
+                serial,
+                format_atom_name(&atom.atom_name, atom.element),
+                residue_name,
+                chain_id,
+                residue.residue_number,
+                atom.position.x,
+                atom.position.y,
+                atom.position.z,
+                1.0,
+                atom.b_factor,
+                atom.element.symbol(),
+            );
+        }
+
+        let chain_ends = structure
+            .residues
+            .get(index + 1)
+            .is_none_or(|next| next.chain_id != residue.chain_id);
+        if chain_ends {
+            serial += 1;
+            let _ = writeln!(
+                output,
+                "TER   {:>5}      {:>3} {}{:>4}",
+                serial, residue_name, chain_id, residue.residue_number,
+            );
+        }
+    }
+
+    let mut neighbours: BTreeMap<u32, Vec<u32>> = BTreeMap::new();
+    for (serial1, serial2) in structure.bonds() {
+        if let (Some(&serial1), Some(&serial2)) = (serials.get(serial1), serials.get(serial2)) {
+            neighbours.entry(serial1).or_default().push(serial2);
+            neighbours.entry(serial2).or_default().push(serial1);
+        }
+    }
+    for (serial, bonded) in neighbours {
+        for chunk in bonded.chunks(4) {
+            let _ = write!(output, "CONECT{:>5}", serial);
+            for target in chunk {
+                let _ = write!(output, "{:>5}", target);
+            }
+            output.push('\n');
+        }
+    }
+
+    output.push_str("END\n");
+    Ok(output)
+}
+
+/// Parses the ATOM, HETATM, TER, MODEL and CONECT records of a PDB file.
+///
+/// Only the first model is read and only the first alternate location of each
+/// atom is kept. Water and `HETATM` groups that are not amino acids, such as
+/// ligands and ions, are skipped, and selenomethionine is read as methionine. Intra-residue and peptide bonds are inferred from distances,
+/// and explicit CONECT bonds are added on top.
+pub fn parse(input: &str) -> Result<Structure, ChemistryError> {
+    let mut structure = Structure::default();
+    let mut chain_breaks = Vec::new();
+    let mut current_key: Option<(String, i32, char, String)> = None;
+    let mut models_seen = 0;
+    let mut model_done = false;
+    // Whether the atoms of the current residue belong to a skipped group.
+    let mut skipping = false;
+
+    for (index, line) in input.lines().enumerate() {
+        let line_number = index + 1;
+        let record = column(line, 1, 6).trim_end();
+
+        match record {
+            "MODEL" => {
+                models_seen += 1;
+                if models_seen > 1 {
+                    model_done = true;
+                }
+            }
+            "ENDMDL" => model_done = true,
+            "TER" if !model_done => {
+                current_key = None;
+                chain_breaks.push(structure.residues.len());
+            }
+            "ATOM" | "HETATM" if !model_done => {
+                let alt_loc = char_at(line, 17);
+                if alt_loc != ' ' && alt_loc != 'A' {
+                    continue;
+                }
+
+                let residue_name = column(line, 18, 20).trim();
+                if record == "HETATM" && SOLVENT_RESIDUES.contains(&residue_name) {
+                    continue;
+                }
+
+                let chain_id = match char_at(line, 22) {
+                    ' ' => DEFAULT_CHAIN_ID.to_string(),
+                    chain_id => chain_id.to_string(),
+                };
+                let residue_seq: i32 = parse_field(line, 23, 26, line_number, "residue number")?;
+                let insertion_code = char_at(line, 27);
+                let key = (
+                    chain_id.clone(),
+                    residue_seq,
+                    insertion_code,
+                    residue_name.to_string(),
+                );
+
+                if current_key.as_ref() != Some(&key) {
+                    current_key = Some(key);
+                    skipping = false;
+                    match residue_code(residue_name, record == "HETATM")? {
+                        Some(code) => structure.residues.push(StructureResidue {
+                            code,
+                            residue_number: residue_seq,
+                            chain_id,
+                            atoms: Vec::new(),
+                        }),
+                        None => {
+                            warn!(
+                                "Skipping HETATM group {} {}{} on line {}",
+                                residue_name, chain_id, residue_seq, line_number
+                            );
+                            skipping = true;
+                        }
+                    }
+                }
+                if skipping {
+                    continue;
+                }
+
+                let atom_name = column(line, 13, 16).trim();
+                let (atom_name, element) = match standard_atom(residue_name, atom_name) {
+                    Some((atom_name, element)) => (atom_name.to_string(), element),
+                    None => (atom_name.to_string(), parse_element(line, atom_name)?),
+                };
+                let atom = StructureAtom {
+                    serial: parse_field(line, 7, 11, line_number, "atom serial")?,
+                    atom_name,
+                    element,
+                    position: Vec3::new(
+                        parse_field(line, 31, 38, line_number, "x coordinate")?,
+                        parse_field(line, 39, 46, line_number, "y coordinate")?,
+                        parse_field(line, 47, 54, line_number, "z coordinate")?,
+                    ),
+                    b_factor: parse_b_factor(line, line_number)?,
+                };
+
+                if let Some(residue) = structure.residues.last_mut() {
+                    residue.atoms.push(atom);
+                }
+            }
+            "CONECT" => {
+                let origin: u32 = parse_field(line, 7, 11, line_number, "CONECT serial")?;
+                for start in [12, 17, 22, 27] {
+                    let field = column(line, start, start + 4).trim();
+                    if field.is_empty() {
+                        continue;
+                    }
+                    let target: u32 = parse_field(line, start, start + 4, line_number, "bond")?;
+                    structure.add_bond(origin, target);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let explicit = structure.take_bonds();
+    structure.infer_bonds(&chain_breaks);
+    // CONECT records of skipped groups name atoms that were not read.
+    let serials: HashSet<u32> = structure.atoms().map(|atom| atom.serial).collect();
+    for (serial1, serial2) in explicit {
+        if serials.contains(&serial1) && serials.contains(&serial2) {
+            structure.add_bond(serial1, serial2);
+        }
+    }
+
+    Ok(structure)
+}
+
+/// Characters tried in order when a chain ID has to be remapped.
+const PDB_CHAIN_IDS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+
+/// Maps every chain ID of a structure to the single column PDB files have for
+/// it. Single-character IDs are kept, and longer mmCIF chain IDs are given the
+/// first character not used by another chain, with a warning. Fails when there
+/// are more chains than single-character IDs.
+fn pdb_chain_ids(structure: &Structure) -> Result<HashMap<String, char>, ChemistryError> {
+    let mut chain_ids: HashMap<String, char> = HashMap::new();
+    let mut remapped = Vec::new();
+    for residue in &structure.residues {
+        let chain_id = &residue.chain_id;
+        if chain_ids.contains_key(chain_id) || remapped.contains(chain_id) {
+            continue;
+        }
+        let mut chars = chain_id.chars();
+        match (chars.next(), chars.next()) {
+            (None, _) => {
+                chain_ids.insert(chain_id.clone(), ' ');
+            }
+            (Some(id), None) => {
+                chain_ids.insert(chain_id.clone(), id);
+            }
+            (Some(_), Some(_)) => remapped.push(chain_id.clone()),
+        }
+    }
+
+    let mut unused = PDB_CHAIN_IDS
+        .chars()
+        .filter(|id| !chain_ids.values().any(|used| used == id))
+        .collect::<Vec<_>>()
+        .into_iter();
+    for chain_id in remapped {
+        let Some(id) = unused.next() else {
+            return Err(ChemistryError::Export(format!(
+                "chain {} has no free single-character PDB chain ID",
+                chain_id
+            )));
+        };
+        warn!("Writing chain {} as chain {} in PDB format", chain_id, id);
+        chain_ids.insert(chain_id, id);
+    }
+    Ok(chain_ids)
+}
+
+/// Aligns an atom name in columns 13-16: names of one-letter elements start in
+/// column 14 unless they fill all four columns.
+fn format_atom_name(name: &str, element: Element) -> String {
+    if name.len() < 4 && element.symbol().len() == 1 {
+        format!(" {:<3}", name)
+    } else {
+        format!("{:<4}", name)
+    }
+}
+
+/// Returns the 1-based, inclusive column range of a fixed-width record.
+fn column(line: &str, start: usize, end: usize) -> &str {
+    let end = end.min(line.len());
+    line.get(start - 1..end).unwrap_or("")
+}
+
+fn char_at(line: &str, position: usize) -> char {
+    column(line, position, position)
+        .chars()
+        .next()
+        .unwrap_or(' ')
+}
+
+fn parse_field<T: std::str::FromStr>(
+    line: &str,
+    start: usize,
+    end: usize,
+    line_number: usize,
+    field: &str,
+) -> Result<T, ChemistryError> {
+    let value = column(line, start, end).trim();
+    value.parse().map_err(|_| ChemistryError::Parse {
+        line: line_number,
+        message: format!("invalid {} '{}'", field, value),
+    })
+}
+
+/// Reads the temperature factor from columns 61-66, which some files leave
+/// blank.
+fn parse_b_factor(line: &str, line_number: usize) -> Result<f32, ChemistryError> {
+    if column(line, 61, 66).trim().is_empty() {
+        Ok(0.0)
+    } else {
+        parse_field(line, 61, 66, line_number, "temperature factor")
+    }
+}
+
+/// Reads the element symbol from columns 77-78, falling back to the first
+/// letter of the atom name for files that omit it.
+fn parse_element(line: &str, atom_name: &str) -> Result<Element, ChemistryError> {
+    let symbol = column(line, 77, 78).trim();
+    let symbol = if symbol.is_empty() {
+        atom_name
+            .trim_start_matches(|c: char| c.is_ascii_digit())
+            .get(..1)
+            .unwrap_or("")
+    } else {
+        symbol
+    };
+    Element::from_symbol(symbol).ok_or_else(|| ChemistryError::UnknownElement(symbol.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chemistry::amino_acids::AminoAcidCode;
+
+    fn atom(serial: u32, name: &str, element: Element, x: f32, y: f32) -> StructureAtom {
+        StructureAtom {
+            serial,
+            atom_name: name.to_string(),
+            element,
+            position: Vec3::new(x, y, 0.0),
+            b_factor: 0.0,
+        }
+    }
+
+    fn normalized(bonds: &[(u32, u32)]) -> Vec<(u32, u32)> {
+        let mut bonds: Vec<_> = bonds.iter().map(|&(a, b)| (a.min(b), a.max(b))).collect();
+        bonds.sort_unstable();
+        bonds
+    }
+
+    #[test]
+    fn write_then_parse_round_trips() {
+        let mut structure = Structure::new(
+            vec![
+                StructureResidue {
+                    code: AminoAcidCode::Gly,
+                    residue_number: 1,
+                    chain_id: "B".to_string(),
+                    atoms: vec![
+                        atom(1, "N", Element::Nitrogen, 0.0, 0.0),
+                        atom(2, "CA", Element::Carbon, 1.458, 0.0),
+                        atom(3, "C", Element::Carbon, 2.009, 1.42),
+                        atom(4, "O", Element::Oxygen, 1.251, 2.39),
+                    ],
+                },
+                StructureResidue {
+                    code: AminoAcidCode::Ala,
+                    residue_number: 2,
+                    chain_id: "B".to_string(),
+                    atoms: vec![
+                        atom(5, "N", Element::Nitrogen, 3.332, 1.536),
+                        atom(6, "CA", Element::Carbon, 4.009, 2.828),
+                        atom(7, "C", Element::Carbon, 5.519, 2.664),
+                        atom(8, "O", Element::Oxygen, 6.066, 1.565),
+                    ],
+                },
+            ],
+            [(1, 2), (2, 3), (3, 4), (3, 5), (5, 6), (6, 7), (7, 8)],
+        );
+        structure.residues[1].atoms[1].b_factor = 23.5;
+
+        let written = write(&structure).expect("structure should be writable");
+        assert!(written.starts_with(
+            "ATOM      1  N   GLY B   1       0.000   0.000   0.000  1.00  0.00           N"
+        ));
+        assert!(written.contains("TER       9      ALA B   2"));
+
+        let parsed = parse(&written).expect("written PDB should parse");
+        assert_eq!(parsed.residues, structure.residues);
+        assert_eq!(normalized(parsed.bonds()), normalized(structure.bonds()));
+    }
+
+    #[test]
+    fn long_chain_ids_are_remapped_to_unused_characters() {
+        let residue = |chain_id: &str| StructureResidue {
+            code: AminoAcidCode::Gly,
+            residue_number: 1,
+            chain_id: chain_id.to_string(),
+            atoms: Vec::new(),
+        };
+        let structure = Structure::new(vec![residue("AA"), residue("A"), residue("B1")], []);
+
+        let chain_ids = pdb_chain_ids(&structure).expect("chains should fit");
+        assert_eq!(chain_ids["A"], 'A');
+        assert_eq!(chain_ids["AA"], 'B');
+        assert_eq!(chain_ids["B1"], 'C');
+
+        let residues = (0..=PDB_CHAIN_IDS.len())
+            .map(|index| residue(&format!("C{}", index)))
+            .collect();
+        assert!(write(&Structure::new(residues, [])).is_err());
+    }
+
+    /// An ATOM or HETATM record in the fixed PDB columns.
+    fn record(
+        record: &str,
+        serial: u32,
+        name: &str,
+        residue_name: &str,
+        residue_number: i32,
+        element: &str,
+    ) -> String {
+        format!(
+            "{:<6}{:>5}  {:<3} {:>3} A{:>4}    {:>8.3}{:>8.3}{:>8.3}{:>6.2}{:>6.2}          {:>2}\n",
+            record,
+            serial,
+            name,
+            residue_name,
+            residue_number,
+            serial as f32 * 1.5,
+            0.0,
+            0.0,
+            1.0,
+            0.0,
+            element
+        )
+    }
+
+    #[test]
+    fn negative_residue_numbers_are_read() {
+        let input = [
+            record("ATOM", 1, "CA", "GLY", -2, "C"),
+            record("ATOM", 2, "CA", "GLY", 0, "C"),
+            record("ATOM", 3, "CA", "ALA", 1, "C"),
+        ]
+        .concat();
+
+        let structure = parse(&input).expect("negative residue numbers should parse");
+        let numbers: Vec<i32> = structure
+            .residues
+            .iter()
+            .map(|residue| residue.residue_number)
+            .collect();
+        assert_eq!(numbers, [-2, 0, 1]);
+        assert!(write(&structure).unwrap().contains("GLY A  -2"));
+    }
+
+    #[test]
+    fn hetero_groups_are_skipped_and_selenomethionine_is_read_as_methionine() {
+        let input = [
+            record("ATOM", 1, "CA", "GLY", 1, "C"),
+            record("HETATM", 2, "CA", "MSE", 2, "C"),
+            record("HETATM", 3, "SE", "MSE", 2, "SE"),
+            record("HETATM", 4, "ZN", "ZN", 101, "ZN"),
+            record("HETATM", 5, "C1", "NAG", 102, "C"),
+            record("HETATM", 6, "O", "HOH", 201, "O"),
+            "CONECT    3    4\n".to_string(),
+        ]
+        .concat();
+
+        let structure = parse(&input).expect("hetero groups should be skipped");
+        let codes: Vec<AminoAcidCode> = structure
+            .residues
+            .iter()
+            .map(|residue| residue.code)
+            .collect();
+        assert_eq!(codes, [AminoAcidCode::Gly, AminoAcidCode::Met]);
+        let selenium = &structure.residues[1].atoms[1];
+        assert_eq!(selenium.atom_name, "SD");
+        assert_eq!(selenium.element, Element::Sulfur);
+        assert!(
+            structure
+                .bonds()
+                .iter()
+                .all(|&(serial1, serial2)| serial1 <= 3 && serial2 <= 3)
+        );
+
+        let unknown = record("ATOM", 1, "C1", "NAG", 1, "C");
+        assert!(matches!(
+            parse(&unknown),
+            Err(ChemistryError::UnknownResidue(name)) if name == "NAG"
+        ));
+    }
+}
@@ -0,0 +1,737 @@
+//! Energy minimization of atom positions under the force field.
+
+use bevy::prelude::*;
+use std::collections::{HashSet, VecDeque};
+
+use crate::chemistry::amino_acids::Residue;
+use crate::chemistry::atoms::AtomPositions;
+use crate::chemistry::force_field::{MolecularSystem, Topology};
+
+/// Sufficient decrease constant of the L-BFGS backtracking line search.
+const ARMIJO_CONSTANT: f64 = 1e-4;
+/// Halvings of the L-BFGS step before the search direction is abandoned.
+const MAX_BACKTRACKS: usize = 20;
+/// Smallest steepest descent step, in Ångström, before giving up.
+const MIN_STEP: f64 = 1e-6;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MinimizationAlgorithm {
+    /// Moves along the forces with an adaptive step. Robust for badly
+    /// strained structures.
+    SteepestDescent,
+    /// Limited-memory BFGS quasi-Newton method. Converges much faster near a
+    /// minimum.
+    #[default]
+    Lbfgs,
+}
+
+impl MinimizationAlgorithm {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MinimizationAlgorithm::SteepestDescent => "Steepest descent",
+            MinimizationAlgorithm::Lbfgs => "L-BFGS",
+        }
+    }
+}
+
+/// Minimizer settings and convergence criteria.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct MinimizationSettings {
+    pub algorithm: MinimizationAlgorithm,
+    pub max_iterations: usize,
+    /// Converged once the RMS force on the moving atoms drops below this, in
+    /// kcal/(mol·Å).
+    pub force_tolerance: f32,
+    /// Converged once an iteration lowers the energy by less than this, in
+    /// kcal/mol.
+    pub energy_tolerance: f64,
+    /// Largest displacement of any atom in one iteration, in Ångström.
+    pub max_step: f32,
+    /// Number of previous steps remembered by L-BFGS.
+    pub history: usize,
+    /// Iterations run per frame, so long minimizations do not stall the app.
+    pub iterations_per_update: usize,
+}
+
+impl Default for MinimizationSettings {
+    fn default() -> Self {
+        Self {
+            algorithm: MinimizationAlgorithm::default(),
+            max_iterations: 500,
+            force_tolerance: 0.1,
+            energy_tolerance: 1e-4,
+            max_step: 0.2,
+            history: 10,
+            iterations_per_update: 10,
+        }
+    }
+}
+
+/// Energy and RMS force after one iteration.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MinimizationStep {
+    pub iteration: usize,
+    pub energy: f64,
+    pub rms_force: f32,
+}
+
+/// Why a minimization stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MinimizationStop {
+    /// The RMS force or the energy change of an iteration dropped below its
+    /// tolerance.
+    Converged,
+    /// The steepest descent step shrank below its minimum without lowering
+    /// the energy.
+    StepTooSmall,
+    /// The L-BFGS line search found no lower energy, even along the steepest
+    /// descent direction.
+    LineSearchFailed,
+    /// `max_iterations` ran out first.
+    MaxIterations,
+}
+
+impl MinimizationStop {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MinimizationStop::Converged => "converged",
+            MinimizationStop::StepTooSmall => "step too small",
+            MinimizationStop::LineSearchFailed => "line search failed",
+            MinimizationStop::MaxIterations => "max iterations",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MinimizationResult {
+    pub iterations: usize,
+    pub initial_energy: f64,
+    pub energy: f64,
+    pub rms_force: f32,
+    pub stop: MinimizationStop,
+}
+
+/// Minimizes the energy of a topology, moving only some of its atoms.
+///
+/// The minimizer keeps its own copy of the positions and runs one iteration
+/// per [`Minimizer::step`], so a long minimization can be spread over several
+/// frames. Atoms that are not mobile stay in place but still exert forces on
+/// the mobile ones.
+pub struct Minimizer {
+    mobile: Vec<usize>,
+    settings: MinimizationSettings,
+    positions: Vec<Vec3>,
+    coordinates: Vec<f64>,
+    energy: f64,
+    gradient: Vec<f64>,
+    initial_energy: f64,
+    iterations: usize,
+    /// Steepest descent step length, in Ångström.
+    step_length: f64,
+    /// L-BFGS correction pairs `(s, y, 1 / s·y)`.
+    history: VecDeque<(Vec<f64>, Vec<f64>, f64)>,
+    stop: Option<MinimizationStop>,
+}
+
+impl Minimizer {
+    /// Starts a minimization of the `mobile` atoms from `positions`, which
+    /// holds every atom of the topology.
+    pub fn new(
+        topology: &Topology,
+        mobile: Vec<usize>,
+        settings: MinimizationSettings,
+        positions: Vec<Vec3>,
+    ) -> Self {
+        let coordinates: Vec<f64> = mobile
+            .iter()
+            .flat_map(|&atom| positions[atom].to_array().map(f64::from))
+            .collect();
+        let mut minimizer = Self {
+            mobile,
+            settings,
+            positions,
+            coordinates: coordinates.clone(),
+            energy: 0.0,
+            gradient: Vec::new(),
+            initial_energy: 0.0,
+            iterations: 0,
+            step_length: settings.max_step as f64,
+            history: VecDeque::new(),
+            stop: None,
+        };
+
+        let (energy, gradient) = minimizer.evaluate(topology, &coordinates);
+        minimizer.energy = energy;
+        minimizer.initial_energy = energy;
+        minimizer.gradient = gradient;
+        if minimizer.rms_force(&minimizer.gradient) < settings.force_tolerance {
+            minimizer.stop = Some(MinimizationStop::Converged);
+        }
+        minimizer
+    }
+
+    /// Runs one iteration, or returns `None` once the minimization has
+    /// stopped.
+    pub fn step(&mut self, topology: &Topology) -> Option<MinimizationStep> {
+        if self.stop.is_some() {
+            return None;
+        }
+
+        self.iterations += 1;
+        match self.settings.algorithm {
+            MinimizationAlgorithm::SteepestDescent => self.steepest_descent(topology),
+            MinimizationAlgorithm::Lbfgs => self.lbfgs(topology),
+        }
+        if self.stop.is_none() && self.iterations >= self.settings.max_iterations {
+            self.stop = Some(MinimizationStop::MaxIterations);
+        }
+
+        Some(MinimizationStep {
+            iteration: self.iterations,
+            energy: self.energy,
+            rms_force: self.rms_force(&self.gradient),
+        })
+    }
+
+    /// The outcome, once the minimization has stopped.
+    pub fn result(&self) -> Option<MinimizationResult> {
+        Some(MinimizationResult {
+            iterations: self.iterations,
+            initial_energy: self.initial_energy,
+            energy: self.energy,
+            rms_force: self.rms_force(&self.gradient),
+            stop: self.stop?,
+        })
+    }
+
+    /// Topology index and current position of every mobile atom.
+    pub fn mobile_positions(&self) -> impl Iterator<Item = (usize, Vec3)> + '_ {
+        self.mobile
+            .iter()
+            .zip(self.coordinates.chunks_exact(3))
+            .map(|(&atom, coordinate)| {
+                let position = Vec3::new(
+                    coordinate[0] as f32,
+                    coordinate[1] as f32,
+                    coordinate[2] as f32,
+                );
+                (atom, position)
+            })
+    }
+
+    fn steepest_descent(&mut self, topology: &Topology) {
+        let largest = self.largest_displacement(&self.gradient);
+        if largest == 0.0 {
+            self.stop = Some(MinimizationStop::Converged);
+            return;
+        }
+
+        let trial: Vec<f64> = self
+            .coordinates
+            .iter()
+            .zip(&self.gradient)
+            .map(|(x, g)| x - self.step_length * g / largest)
+            .collect();
+        let (trial_energy, trial_gradient) = self.evaluate(topology, &trial);
+
+        if trial_energy < self.energy {
+            self.accept(trial, trial_energy, trial_gradient);
+            self.step_length = (self.step_length * 1.2).min(self.settings.max_step as f64);
+        } else {
+            self.step_length *= 0.2;
+            if self.step_length < MIN_STEP {
+                self.stop = Some(MinimizationStop::StepTooSmall);
+            }
+        }
+    }
+
+    fn lbfgs(&mut self, topology: &Topology) {
+        let mut direction = Self::two_loop(&self.history, &self.gradient);
+        let mut slope = dot(&direction, &self.gradient);
+        if slope >= 0.0 {
+            self.history.clear();
+            direction = self.gradient.iter().map(|g| -g).collect();
+            slope = dot(&direction, &self.gradient);
+        }
+
+        let largest = self.largest_displacement(&direction);
+        if largest > self.settings.max_step as f64 {
+            let scale = self.settings.max_step as f64 / largest;
+            direction.iter_mut().for_each(|d| *d *= scale);
+            slope *= scale;
+        }
+
+        let mut alpha = 1.0;
+        let mut accepted = None;
+        for _ in 0..MAX_BACKTRACKS {
+            let trial: Vec<f64> = self
+                .coordinates
+                .iter()
+                .zip(&direction)
+                .map(|(x, d)| x + alpha * d)
+                .collect();
+            let (trial_energy, trial_gradient) = self.evaluate(topology, &trial);
+            if trial_energy <= self.energy + ARMIJO_CONSTANT * alpha * slope {
+                accepted = Some((trial, trial_energy, trial_gradient));
+                break;
+            }
+            alpha *= 0.5;
+        }
+
+        match accepted {
+            Some((trial, trial_energy, trial_gradient)) => {
+                let s: Vec<f64> = trial
+                    .iter()
+                    .zip(&self.coordinates)
+                    .map(|(a, b)| a - b)
+                    .collect();
+                let y: Vec<f64> = trial_gradient
+                    .iter()
+                    .zip(&self.gradient)
+                    .map(|(a, b)| a - b)
+                    .collect();
+                let curvature = dot(&s, &y);
+                if curvature > f64::EPSILON {
+                    self.history.push_back((s, y, 1.0 / curvature));
+                    if self.history.len() > self.settings.history {
+                        self.history.pop_front();
+                    }
+                }
+                self.accept(trial, trial_energy, trial_gradient);
+            }
+            // Retry along the steepest descent direction before giving up.
+            None if !self.history.is_empty() => self.history.clear(),
+            None => self.stop = Some(MinimizationStop::LineSearchFailed),
+        }
+    }
+
+    /// Moves to a trial point that lowered the energy, stopping if the
+    /// improvement or the remaining force is below its tolerance.
+    fn accept(&mut self, coordinates: Vec<f64>, energy: f64, gradient: Vec<f64>) {
+        if self.energy - energy < self.settings.energy_tolerance
+            || self.rms_force(&gradient) < self.settings.force_tolerance
+        {
+            self.stop = Some(MinimizationStop::Converged);
+        }
+        self.coordinates = coordinates;
+        self.energy = energy;
+        self.gradient = gradient;
+    }
+
+    /// L-BFGS two-loop recursion giving the quasi-Newton step `-H g`.
+    fn two_loop(history: &VecDeque<(Vec<f64>, Vec<f64>, f64)>, gradient: &[f64]) -> Vec<f64> {
+        let mut q = gradient.to_vec();
+        let mut alphas = Vec::with_capacity(history.len());
+        for (s, y, rho) in history.iter().rev() {
+            let alpha = rho * dot(s, &q);
+            q.iter_mut().zip(y).for_each(|(q, y)| *q -= alpha * y);
+            alphas.push(alpha);
+        }
+
+        if let Some((s, y, _)) = history.back() {
+            let gamma = dot(s, y) / dot(y, y);
+            q.iter_mut().for_each(|q| *q *= gamma);
+        }
+
+        for ((s, y, rho), alpha) in history.iter().zip(alphas.into_iter().rev()) {
+            let beta = rho * dot(y, &q);
+            q.iter_mut()
+                .zip(s)
+                .for_each(|(q, s)| *q += (alpha - beta) * s);
+        }
+
+        q.iter_mut().for_each(|q| *q = -*q);
+        q
+    }
+
+    /// Energy and gradient with respect to the mobile coordinates.
+    fn evaluate(&mut self, topology: &Topology, coordinates: &[f64]) -> (f64, Vec<f64>) {
+        for (&atom, coordinate) in self.mobile.iter().zip(coordinates.chunks_exact(3)) {
+            self.positions[atom] = Vec3::new(
+                coordinate[0] as f32,
+                coordinate[1] as f32,
+                coordinate[2] as f32,
+            );
+        }
+        let evaluation = topology.evaluate(&self.positions);
+        let gradient = self
+            .mobile
+            .iter()
+            .flat_map(|&atom| (-evaluation.forces[atom]).to_array().map(f64::from))
+            .collect();
+        (evaluation.energy.total(), gradient)
+    }
+
+    fn rms_force(&self, gradient: &[f64]) -> f32 {
+        if self.mobile.is_empty() {
+            return 0.0;
+        }
+        (dot(gradient, gradient) / self.mobile.len() as f64).sqrt() as f32
+    }
+
+    /// Length of the largest per-atom vector in a coordinate-sized vector.
+    fn largest_displacement(&self, vector: &[f64]) -> f64 {
+        vector
+            .chunks_exact(3)
+            .map(|v| (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt())
+            .fold(0.0, f64::max)
+    }
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+/// Atoms moved by a minimization.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MinimizationScope {
+    /// The atoms of one `AminoAcid` entity.
+    Residue(Entity),
+    /// The atoms of every residue with this chain ID.
+    Chain(String),
+    /// These atom entities, e.g. the current selection.
+    Atoms(Vec<Entity>),
+    /// Every atom in the scene.
+    Project,
+}
+
+/// Message to minimize the energy of part of the scene with the current
+/// [`MinimizationSettings`].
+#[derive(Message, Debug, Clone)]
+pub struct MinimizeMessage {
+    pub scope: MinimizationScope,
+}
+
+/// Progress of the running or last minimization.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MinimizationReport {
+    pub algorithm: MinimizationAlgorithm,
+    /// Whether a minimization is advancing, a few iterations every frame.
+    pub running: bool,
+    /// Energy after each iteration, in kcal/mol.
+    pub energies: Vec<f64>,
+    pub result: Option<MinimizationResult>,
+}
+
+/// The running minimization with the topology atoms it was started on.
+#[derive(Resource, Default)]
+struct ActiveMinimization {
+    minimizer: Option<Minimizer>,
+    atoms: Vec<Entity>,
+}
+
+pub struct MinimizationPlugin;
+
+impl Plugin for MinimizationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MinimizationSettings>()
+            .init_resource::<MinimizationReport>()
+            .init_resource::<ActiveMinimization>()
+            .add_message::<MinimizeMessage>()
+            .add_systems(Update, (handle_minimize, advance_minimization).chain());
+    }
+}
+
+/// Starts a minimization, replacing the running one.
+fn handle_minimize(
+    mut messages: MessageReader<MinimizeMessage>,
+    settings: Res<MinimizationSettings>,
+    mut report: ResMut<MinimizationReport>,
+    mut active: ResMut<ActiveMinimization>,
+    system: Res<MolecularSystem>,
+    atom_positions: AtomPositions,
+    residue_query: Query<&Residue>,
+) {
+    for message in messages.read() {
+        let Some(topology) = &system.topology else {
+            warn!("No force field topology to minimize");
+            continue;
+        };
+
+        // The topology is rebuilt once despawned atoms are gone.
+        let Some(positions) = system
+            .atoms
+            .iter()
+            .map(|&entity| atom_positions.get(entity))
+            .collect::<Option<Vec<Vec3>>>()
+        else {
+            continue;
+        };
+
+        let selected: HashSet<Entity> = match &message.scope {
+            MinimizationScope::Atoms(atoms) => atoms.iter().copied().collect(),
+            _ => HashSet::new(),
+        };
+        let mobile: Vec<usize> = system
+            .atoms
+            .iter()
+            .enumerate()
+            .filter(|&(_, &entity)| {
+                let residue = atom_positions.residue(entity);
+                match &message.scope {
+                    MinimizationScope::Residue(selected) => residue == Some(*selected),
+                    MinimizationScope::Chain(chain_id) => residue
+                        .and_then(|residue| residue_query.get(residue).ok())
+                        .is_some_and(|residue| residue.chain_id == *chain_id),
+                    MinimizationScope::Atoms(_) => selected.contains(&entity),
+                    MinimizationScope::Project => true,
+                }
+            })
+            .map(|(index, _)| index)
+            .collect();
+        if mobile.is_empty() {
+            warn!("No atoms to minimize in {:?}", message.scope);
+            continue;
+        }
+
+        report.algorithm = settings.algorithm;
+        report.running = true;
+        report.energies.clear();
+        report.result = None;
+        active.minimizer = Some(Minimizer::new(topology, mobile, *settings, positions));
+        active.atoms = system.atoms.clone();
+    }
+}
+
+/// Runs up to `iterations_per_update` iterations of the active minimization
+/// and moves its atoms to the new positions.
+fn advance_minimization(
+    settings: Res<MinimizationSettings>,
+    mut report: ResMut<MinimizationReport>,
+    mut active: ResMut<ActiveMinimization>,
+    system: Res<MolecularSystem>,
+    mut atom_positions: AtomPositions,
+) {
+    let active = &mut *active;
+    let Some(minimizer) = &mut active.minimizer else {
+        return;
+    };
+
+    let Some(topology) = system
+        .topology
+        .as_ref()
+        .filter(|_| system.atoms == active.atoms)
+    else {
+        warn!("Atoms were added or removed, stopping the minimization");
+        active.minimizer = None;
+        report.running = false;
+        return;
+    };
+
+    for _ in 0..settings.iterations_per_update.max(1) {
+        let Some(step) = minimizer.step(topology) else {
+            break;
+        };
+        debug!(
+            "{} iteration {}: {:.3} kcal/mol, RMS force {:.3} kcal/(mol·Å)",
+            report.algorithm.name(),
+            step.iteration,
+            step.energy,
+            step.rms_force
+        );
+        report.energies.push(step.energy);
+    }
+
+    atom_positions.set_all(
+        minimizer
+            .mobile_positions()
+            .map(|(index, position)| (active.atoms[index], position)),
+    );
+
+    if let Some(result) = minimizer.result() {
+        info!(
+            "{} stopped ({}) after {} iterations: {:.3} -> {:.3} kcal/mol",
+            report.algorithm.name(),
+            result.stop.name(),
+            result.iterations,
+            result.initial_energy,
+            result.energy
+        );
+        report.result = Some(result);
+        report.running = false;
+        active.minimizer = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chemistry::force_field::{
+        AngleBend, AngleParameters, BondParameters, BondStretch, NonbondedParameters, TopologyAtom,
+    };
+
+    const BOND_LENGTH: f32 = 1.5;
+
+    /// Uncharged atoms joined in a chain by harmonic bonds, with an angle
+    /// bend at every inner atom. Nonbonded terms vanish.
+    fn chain_topology(count: usize) -> Topology {
+        let mut topology = Topology::new(NonbondedParameters {
+            cutoff: 0.0,
+            switch_distance: 0.0,
+            dielectric: 1.0,
+            scale_14_electrostatic: 1.0,
+            scale_14_van_der_waals: 1.0,
+        });
+        topology.atoms = vec![
+            TopologyAtom {
+                atom_type: "C3".to_string(),
+                charge: 0.0,
+                mass: 12.011,
+                rmin_half: 0.0,
+                epsilon: 0.0,
+            };
+            count
+        ];
+        topology.neighbours = vec![Vec::new(); count];
+        topology.exclusions = vec![HashSet::new(); count];
+        for atom in 1..count {
+            topology.bonds.push(BondStretch {
+                atoms: [atom - 1, atom],
+                parameters: BondParameters {
+                    k: 300.0,
+                    length: BOND_LENGTH,
+                },
+            });
+        }
+        for atom in 2..count {
+            topology.angles.push(AngleBend {
+                atoms: [atom - 2, atom - 1, atom],
+                parameters: AngleParameters {
+                    k: 50.0,
+                    angle: 109.5,
+                },
+            });
+        }
+        topology
+    }
+
+    /// Runs a minimization to the end, returning its result and the energy
+    /// after every iteration.
+    fn minimize(
+        topology: &Topology,
+        settings: MinimizationSettings,
+        positions: Vec<Vec3>,
+    ) -> (Minimizer, MinimizationResult, Vec<f64>) {
+        let mobile = (0..positions.len()).collect();
+        let mut minimizer = Minimizer::new(topology, mobile, settings, positions);
+        let mut energies = Vec::new();
+        while let Some(step) = minimizer.step(topology) {
+            energies.push(step.energy);
+        }
+        let result = minimizer.result().unwrap();
+        (minimizer, result, energies)
+    }
+
+    #[test]
+    fn two_atom_bond_converges_to_rest_length() {
+        for algorithm in [
+            MinimizationAlgorithm::SteepestDescent,
+            MinimizationAlgorithm::Lbfgs,
+        ] {
+            let settings = MinimizationSettings {
+                algorithm,
+                max_iterations: 1000,
+                force_tolerance: 0.01,
+                energy_tolerance: 1e-12,
+                ..default()
+            };
+            let positions = vec![Vec3::ZERO, Vec3::new(2.1, 0.3, -0.2)];
+            let (minimizer, result, _) = minimize(&chain_topology(2), settings, positions);
+
+            assert_eq!(result.stop, MinimizationStop::Converged, "{:?}", algorithm);
+            let positions: Vec<Vec3> = minimizer.mobile_positions().map(|(_, p)| p).collect();
+            let length = positions[0].distance(positions[1]);
+            assert!(
+                (length - BOND_LENGTH).abs() < 1e-3,
+                "{:?}: bond length {}",
+                algorithm,
+                length
+            );
+            assert!(
+                result.energy < 1e-4,
+                "{:?}: energy {}",
+                algorithm,
+                result.energy
+            );
+        }
+    }
+
+    #[test]
+    fn energy_never_increases() {
+        let positions = vec![
+            Vec3::ZERO,
+            Vec3::new(1.9, 0.2, 0.0),
+            Vec3::new(2.1, 1.0, 0.4),
+            Vec3::new(3.5, 1.2, 1.3),
+        ];
+        for algorithm in [
+            MinimizationAlgorithm::SteepestDescent,
+            MinimizationAlgorithm::Lbfgs,
+        ] {
+            let settings = MinimizationSettings {
+                algorithm,
+                ..default()
+            };
+            let (_, result, energies) = minimize(&chain_topology(4), settings, positions.clone());
+
+            let mut previous = result.initial_energy;
+            for (iteration, &energy) in energies.iter().enumerate() {
+                assert!(
+                    energy <= previous,
+                    "{:?}: energy rose from {} to {} at iteration {}",
+                    algorithm,
+                    previous,
+                    energy,
+                    iteration + 1
+                );
+                previous = energy;
+            }
+            assert!(result.energy < result.initial_energy, "{:?}", algorithm);
+        }
+    }
+
+    #[test]
+    fn iteration_limit_stops_the_run() {
+        let settings = MinimizationSettings {
+            algorithm: MinimizationAlgorithm::SteepestDescent,
+            max_iterations: 3,
+            force_tolerance: 0.0,
+            energy_tolerance: 0.0,
+            max_step: 0.01,
+            ..default()
+        };
+        let positions = vec![Vec3::ZERO, Vec3::new(2.5, 0.0, 0.0)];
+        let (_, result, energies) = minimize(&chain_topology(2), settings, positions);
+
+        assert_eq!(result.stop, MinimizationStop::MaxIterations);
+        assert_eq!(result.iterations, 3);
+        assert_eq!(energies.len(), 3);
+    }
+
+    #[test]
+    fn tolerances_stop_the_run() {
+        let positions = vec![Vec3::ZERO, Vec3::new(2.5, 0.0, 0.0)];
+
+        // The initial RMS force is already below the force tolerance.
+        let settings = MinimizationSettings {
+            force_tolerance: 1e4,
+            ..default()
+        };
+        let (_, result, energies) = minimize(&chain_topology(2), settings, positions.clone());
+        assert_eq!(result.stop, MinimizationStop::Converged);
+        assert_eq!(result.iterations, 0);
+        assert!(energies.is_empty());
+
+        // The first iteration lowers the energy by less than the energy
+        // tolerance.
+        let settings = MinimizationSettings {
+            force_tolerance: 0.0,
+            energy_tolerance: 1e6,
+            ..default()
+        };
+        let (_, result, _) = minimize(&chain_topology(2), settings, positions);
+        assert_eq!(result.stop, MinimizationStop::Converged);
+        assert_eq!(result.iterations, 1);
+        assert!(result.energy < result.initial_energy);
+    }
+}
@@ -2,14 +2,31 @@ use bevy::prelude::*;
 
 pub mod amino_acids;
 pub mod atoms;
+pub mod dynamics;
+pub mod error;
+pub mod force_field;
+pub mod internal_coordinates;
+pub mod io;
+pub mod minimization;
 pub mod rendering;
+pub mod surface;
 
+use amino_acids::rotamers::RotamerPlugin;
+use dynamics::DynamicsPlugin;
+use force_field::ForceFieldPlugin;
+use minimization::MinimizationPlugin;
 use rendering::RenderingPlugin;
+use surface::SurfacePlugin;
 
 pub struct ChemistryPlugin;
 
 impl Plugin for ChemistryPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(RenderingPlugin);
+        app.add_plugins(RenderingPlugin)
+            .add_plugins(RotamerPlugin)
+            .add_plugins(ForceFieldPlugin)
+            .add_plugins(DynamicsPlugin)
+            .add_plugins(MinimizationPlugin)
+            .add_plugins(SurfacePlugin);
     }
 }
@@ -0,0 +1,264 @@
+//! Batched drawing of atoms and bonds.
+//!
+//! Atoms and bonds stay separate entities for picking, measurements and the
+//! simulations, but are not drawn one mesh each. Instead every residue gets a
+//! single mesh holding the spheres of its atoms and the cylinders of its
+//! bonds, with the atom colors stored per vertex. The mesh lives on the
+//! residue entity in its local space, so dragging a residue moves the mesh
+//! without rebuilding it. A residue's mesh is rebuilt when its atoms move
+//! within it, change color, are added or removed, when its representation
+//! changes, or when an atom of a bond to another residue moves.
+
+use std::collections::HashSet;
+
+use bevy::asset::RenderAssetUsages;
+use bevy::ecs::system::SystemParam;
+use bevy::mesh::{Indices, PrimitiveTopology};
+use bevy::prelude::*;
+
+use super::{AtomColor, RenderingMaterials, Representation, atom_color};
+use crate::chemistry::amino_acids::AminoAcid;
+use crate::chemistry::atoms::{Atom, AtomBonds, Bond};
+
+/// Subdivisions of the icosphere drawn for each atom.
+const SPHERE_SUBDIVISIONS: u32 = 3;
+
+/// Sides of the prism drawn for each bond half.
+const CYLINDER_SIDES: usize = 8;
+
+/// Unit sphere copied into residue meshes for every atom.
+#[derive(Resource)]
+pub(super) struct SphereTemplate {
+    positions: Vec<Vec3>,
+    indices: Vec<u32>,
+}
+
+impl Default for SphereTemplate {
+    fn default() -> Self {
+        let mesh = Sphere::new(1.0)
+            .mesh()
+            .ico(SPHERE_SUBDIVISIONS)
+            .expect("Failed to create icosphere mesh");
+        let positions = mesh
+            .attribute(Mesh::ATTRIBUTE_POSITION)
+            .and_then(|positions| positions.as_float3())
+            .map(|positions| positions.iter().map(|&p| Vec3::from_array(p)).collect())
+            .unwrap_or_default();
+        let indices = mesh
+            .indices()
+            .map(|indices| indices.iter().map(|index| index as u32).collect())
+            .unwrap_or_default();
+        Self { positions, indices }
+    }
+}
+
+/// Vertices of the spheres and cylinders of one residue.
+#[derive(Default)]
+struct ResidueMesh {
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    colors: Vec<[f32; 4]>,
+    indices: Vec<u32>,
+}
+
+impl ResidueMesh {
+    fn add_sphere(&mut self, template: &SphereTemplate, center: Vec3, radius: f32, color: Color) {
+        let first = self.positions.len() as u32;
+        let color = color.to_linear().to_f32_array();
+        for &point in &template.positions {
+            self.positions.push((center + point * radius).to_array());
+            self.normals.push(point.to_array());
+            self.colors.push(color);
+        }
+        self.indices
+            .extend(template.indices.iter().map(|&index| first + index));
+    }
+
+    /// Adds an open cylinder from `start` to `end`. Its ends are hidden in
+    /// the atom spheres or in the other half of the bond.
+    fn add_cylinder(&mut self, start: Vec3, end: Vec3, radius: f32, color: Color) {
+        let axis = (end - start).normalize_or_zero();
+        if axis == Vec3::ZERO {
+            return;
+        }
+        let (side, up) = axis.any_orthonormal_pair();
+        let first = self.positions.len() as u32;
+        let color = color.to_linear().to_f32_array();
+        for point in 0..CYLINDER_SIDES {
+            let angle = point as f32 / CYLINDER_SIDES as f32 * std::f32::consts::TAU;
+            let (sin, cos) = angle.sin_cos();
+            let normal = side * cos + up * sin;
+            for center in [start, end] {
+                self.positions.push((center + normal * radius).to_array());
+                self.normals.push(normal.to_array());
+                self.colors.push(color);
+            }
+        }
+        for point in 0..CYLINDER_SIDES as u32 {
+            let next = (point + 1) % CYLINDER_SIDES as u32;
+            let (a, b) = (first + point * 2, first + point * 2 + 1);
+            let (c, d) = (first + next * 2, first + next * 2 + 1);
+            self.indices.extend([a, c, b, b, c, d]);
+        }
+    }
+
+    fn into_mesh(self) -> Option<Mesh> {
+        if self.indices.is_empty() {
+            return None;
+        }
+        Some(
+            Mesh::new(
+                PrimitiveTopology::TriangleList,
+                RenderAssetUsages::default(),
+            )
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.positions)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, self.colors)
+            .with_inserted_indices(Indices::U32(self.indices)),
+        )
+    }
+}
+
+/// Changes that make residue meshes out of date.
+#[allow(clippy::type_complexity)]
+#[derive(SystemParam)]
+pub(super) struct ResidueMeshChanges<'w, 's> {
+    moved_atoms: Query<'w, 's, &'static ChildOf, (With<Atom>, Changed<Transform>)>,
+    recolored_atoms: Query<'w, 's, &'static ChildOf, (With<Atom>, Changed<AtomColor>)>,
+    changed_representations: Query<'w, 's, Entity, Changed<Representation>>,
+    changed_children: Query<'w, 's, Entity, (With<AminoAcid>, Changed<Children>)>,
+    world_moved_atoms: Query<'w, 's, &'static AtomBonds, Changed<GlobalTransform>>,
+}
+
+impl ResidueMeshChanges<'_, '_> {
+    /// Residues whose meshes need to be rebuilt.
+    fn residues(
+        &self,
+        bond_query: &Query<(&Bond, &ChildOf)>,
+        atom_query: &AtomQuery,
+    ) -> HashSet<Entity> {
+        let mut residues: HashSet<Entity> = self
+            .moved_atoms
+            .iter()
+            .chain(&self.recolored_atoms)
+            .map(ChildOf::parent)
+            .chain(&self.changed_representations)
+            .chain(&self.changed_children)
+            .collect();
+
+        // Bonds to another residue are drawn in the local space of the
+        // residue holding the bond, so they go stale when either residue
+        // moves as a whole.
+        let residue = |atom| {
+            atom_query
+                .get(atom)
+                .ok()
+                .map(|(.., child_of)| child_of.parent())
+        };
+        for bond_entity in self.world_moved_atoms.iter().flat_map(AtomBonds::iter) {
+            if let Ok((bond, child_of)) = bond_query.get(bond_entity)
+                && residue(bond.atom1) != residue(bond.atom2)
+            {
+                residues.insert(child_of.parent());
+            }
+        }
+        residues
+    }
+}
+
+type AtomQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Atom,
+        Option<&'static AtomColor>,
+        &'static Transform,
+        &'static GlobalTransform,
+        &'static ChildOf,
+    ),
+>;
+
+/// Asset storage used to build residue meshes.
+#[derive(SystemParam)]
+pub(super) struct ResidueMeshAssets<'w> {
+    meshes: ResMut<'w, Assets<Mesh>>,
+    rendering_materials: Res<'w, RenderingMaterials>,
+    sphere: Res<'w, SphereTemplate>,
+}
+
+/// Rebuilds the meshes of residues whose atoms or bonds changed.
+pub(super) fn update_residue_meshes(
+    mut commands: Commands,
+    changes: ResidueMeshChanges,
+    mut assets: ResidueMeshAssets,
+    residue_query: Query<(
+        &Children,
+        &GlobalTransform,
+        Option<&Representation>,
+        Option<&Mesh3d>,
+    )>,
+    atom_query: AtomQuery,
+    bond_query: Query<(&Bond, &ChildOf)>,
+) {
+    for residue in changes.residues(&bond_query, &atom_query) {
+        let Ok((children, residue_transform, representation, current)) = residue_query.get(residue)
+        else {
+            continue;
+        };
+        let representation = representation.copied().unwrap_or_default();
+        let inverse = residue_transform.affine().inverse();
+
+        // Atoms of this residue are placed by their local translation, and
+        // atoms of neighbouring residues are brought into its local space.
+        let local_position = |entity: Entity| {
+            let (atom, color, transform, global_transform, child_of) =
+                atom_query.get(entity).ok()?;
+            let position = if child_of.parent() == residue {
+                transform.translation
+            } else {
+                inverse.transform_point3(global_transform.translation())
+            };
+            Some((position, atom_color(atom, color)))
+        };
+
+        let mut mesh = ResidueMesh::default();
+        for child in children.iter() {
+            if let Ok((atom, ..)) = atom_query.get(child)
+                && let Some(radius) = representation.atom_radius(atom.element)
+                && let Some((position, color)) = local_position(child)
+            {
+                mesh.add_sphere(&assets.sphere, position, radius, color);
+            }
+
+            if let Ok((bond, _)) = bond_query.get(child)
+                && let Some(radius) = representation.bond_radius()
+                && let (Some((pos1, color1)), Some((pos2, color2))) =
+                    (local_position(bond.atom1), local_position(bond.atom2))
+            {
+                let middle = (pos1 + pos2) / 2.0;
+                mesh.add_cylinder(pos1, middle, radius, color1);
+                mesh.add_cylinder(middle, pos2, radius, color2);
+            }
+        }
+
+        match (mesh.into_mesh(), current) {
+            (Some(mesh), Some(current)) if assets.meshes.contains(&current.0) => {
+                if let Err(e) = assets.meshes.insert(&current.0, mesh) {
+                    error!("Failed to update residue mesh: {}", e);
+                }
+            }
+            (Some(mesh), _) => {
+                commands.entity(residue).insert((
+                    Mesh3d(assets.meshes.add(mesh)),
+                    MeshMaterial3d(assets.rendering_materials.atom_material.clone()),
+                ));
+            }
+            (None, Some(_)) => {
+                commands
+                    .entity(residue)
+                    .remove::<(Mesh3d, MeshMaterial3d<StandardMaterial>)>();
+            }
+            (None, None) => {}
+        }
+    }
+}
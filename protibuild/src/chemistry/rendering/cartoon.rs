@@ -0,0 +1,639 @@
+//! Cartoon representation of protein backbones.
+//!
+//! Residues drawn as cartoons are grouped into chain segments by
+//! `Residue::chain_id` and `residue_number`, split wherever consecutive
+//! residues are not joined by a peptide bond. Each segment gets one mesh swept
+//! along a Catmull-Rom spline through its CA atoms: helices as wide coils,
+//! strands as flat arrows and loops as tubes. A segment's mesh is regenerated
+//! whenever its backbone atoms move.
+
+use std::collections::{HashMap, HashSet};
+
+use bevy::asset::RenderAssetUsages;
+use bevy::ecs::system::SystemParam;
+use bevy::mesh::{Indices, PrimitiveTopology};
+use bevy::prelude::*;
+
+use super::{RenderingMaterials, Representation};
+use crate::chemistry::amino_acids::{AminoAcid, Residue};
+use crate::chemistry::atoms::{Atom, Bond};
+use crate::chemistry::internal_coordinates::torsion_angle;
+
+/// Spline samples between two consecutive CA atoms.
+const SEGMENTS_PER_RESIDUE: usize = 8;
+
+/// Points around the cross-section of the swept mesh.
+const PROFILE_POINTS: usize = 12;
+
+/// Distance between consecutive CA atoms of a trans peptide in Å. Cartoon
+/// dimensions are scaled by the actual spacing so they fit structures built
+/// at any scale.
+const CA_SPACING: f32 = 3.8;
+
+const TUBE_RADIUS: f32 = 0.3;
+const HELIX_HALF_WIDTH: f32 = 1.0;
+const STRAND_HALF_WIDTH: f32 = 0.8;
+const ARROW_HALF_WIDTH: f32 = 1.3;
+const SHEET_HALF_THICKNESS: f32 = 0.2;
+
+/// Shortest runs kept as helices and strands; shorter ones are drawn as loops.
+const MIN_HELIX_LENGTH: usize = 4;
+const MIN_STRAND_LENGTH: usize = 3;
+
+/// Secondary structure of a residue, as shown by the cartoon.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SecondaryStructure {
+    Helix,
+    Strand,
+    Loop,
+}
+
+impl SecondaryStructure {
+    /// Classifies a residue by the region of the Ramachandran plot its
+    /// backbone dihedrals (in degrees) fall in.
+    pub fn from_dihedrals(phi: f32, psi: f32) -> Self {
+        if (-160.0..=-20.0).contains(&phi) && (-120.0..=50.0).contains(&psi) {
+            SecondaryStructure::Helix
+        } else if (-180.0..=-90.0).contains(&phi) && (psi >= 90.0 || psi <= -150.0) {
+            SecondaryStructure::Strand
+        } else {
+            SecondaryStructure::Loop
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            SecondaryStructure::Helix => Color::srgb(0.9, 0.25, 0.35),
+            SecondaryStructure::Strand => Color::srgb(0.95, 0.8, 0.2),
+            SecondaryStructure::Loop => Color::srgb(0.85, 0.85, 0.85),
+        }
+    }
+
+    fn min_length(&self) -> usize {
+        match self {
+            SecondaryStructure::Helix => MIN_HELIX_LENGTH,
+            SecondaryStructure::Strand => MIN_STRAND_LENGTH,
+            SecondaryStructure::Loop => 1,
+        }
+    }
+}
+
+/// Assigns the secondary structure of consecutive residues from their
+/// `(phi, psi)` dihedrals, `None` where a dihedral is undefined. Helix and
+/// strand runs too short to be drawn as such become loops.
+pub fn assign_secondary_structure(dihedrals: &[Option<(f32, f32)>]) -> Vec<SecondaryStructure> {
+    let mut assigned: Vec<SecondaryStructure> = dihedrals
+        .iter()
+        .map(|dihedrals| match dihedrals {
+            Some((phi, psi)) => SecondaryStructure::from_dihedrals(*phi, *psi),
+            None => SecondaryStructure::Loop,
+        })
+        .collect();
+
+    let mut start = 0;
+    while start < assigned.len() {
+        let kind = assigned[start];
+        let end = run_end(&assigned, start);
+        if end - start + 1 < kind.min_length() {
+            assigned[start..=end].fill(SecondaryStructure::Loop);
+        }
+        start = end + 1;
+    }
+    assigned
+}
+
+/// Index of the last residue in the run of equal structure containing
+/// `index`.
+fn run_end(structure: &[SecondaryStructure], index: usize) -> usize {
+    let kind = structure[index];
+    structure[index..]
+        .iter()
+        .position(|&s| s != kind)
+        .map_or(structure.len() - 1, |offset| index + offset - 1)
+}
+
+/// Backbone atom positions of a residue in world space.
+#[derive(Clone, Copy, Debug)]
+pub struct BackboneAtoms {
+    pub n: Vec3,
+    pub ca: Vec3,
+    pub c: Vec3,
+    pub o: Vec3,
+}
+
+/// Assigns the secondary structure of each residue of a chain segment from
+/// its backbone dihedrals. The end residues, which lack one of them, are
+/// loops.
+pub fn secondary_structure(backbone: &[BackboneAtoms]) -> Vec<SecondaryStructure> {
+    let dihedrals: Vec<Option<(f32, f32)>> = (0..backbone.len())
+        .map(|i| {
+            let previous = backbone.get(i.checked_sub(1)?)?;
+            let next = backbone.get(i + 1)?;
+            let residue = &backbone[i];
+            Some((
+                torsion_angle(previous.c, residue.n, residue.ca, residue.c),
+                torsion_angle(residue.n, residue.ca, residue.c, next.n),
+            ))
+        })
+        .collect();
+    assign_secondary_structure(&dihedrals)
+}
+
+/// Builds the cartoon mesh of a chain segment in world space, or `None` when
+/// the segment has fewer than two residues.
+pub fn cartoon_mesh(backbone: &[BackboneAtoms]) -> Option<Mesh> {
+    if backbone.len() < 2 {
+        return None;
+    }
+
+    let structure = secondary_structure(backbone);
+
+    let spacing = backbone
+        .windows(2)
+        .map(|pair| pair[0].ca.distance(pair[1].ca))
+        .sum::<f32>()
+        / (backbone.len() - 1) as f32;
+    let scale = spacing / CA_SPACING;
+
+    // The ribbon's width follows the carbonyl direction, flipped where needed
+    // so it does not twist half a turn between residues.
+    let mut guides: Vec<Vec3> = backbone
+        .iter()
+        .map(|residue| (residue.o - residue.c).normalize_or_zero())
+        .collect();
+    for i in 1..guides.len() {
+        if guides[i].dot(guides[i - 1]) < 0.0 {
+            guides[i] = -guides[i];
+        }
+    }
+
+    let mut sweep = Sweep::default();
+    let samples = (backbone.len() - 1) * SEGMENTS_PER_RESIDUE;
+    for sample in 0..=samples {
+        let u = sample as f32 / SEGMENTS_PER_RESIDUE as f32;
+        let (position, tangent) = spline(backbone, u);
+        let residue = (u.round() as usize).min(backbone.len() - 1);
+
+        let segment = (u.floor() as usize).min(backbone.len() - 2);
+        let t = u - segment as f32;
+        let guide = guides[segment].lerp(guides[segment + 1], t);
+        let side = guide
+            .reject_from_normalized(tangent)
+            .try_normalize()
+            .unwrap_or_else(|| tangent.any_orthonormal_vector());
+        let up = tangent.cross(side);
+
+        let (half_width, half_thickness) = profile(&structure, residue, u);
+        sweep.add_ring(
+            position,
+            side * half_width * scale,
+            up * half_thickness * scale,
+            structure[residue].color(),
+        );
+    }
+
+    Some(sweep.into_mesh())
+}
+
+/// Position and unit tangent of the Catmull-Rom spline through the CA atoms at
+/// `u`, where integer values are at the atoms.
+fn spline(backbone: &[BackboneAtoms], u: f32) -> (Vec3, Vec3) {
+    let last = backbone.len() - 1;
+    let segment = (u.floor() as usize).min(last - 1);
+    let t = u - segment as f32;
+
+    let p1 = backbone[segment].ca;
+    let p2 = backbone[segment + 1].ca;
+    // Mirror the neighbours past the ends so the spline reaches them.
+    let p0 = match segment.checked_sub(1) {
+        Some(previous) => backbone[previous].ca,
+        None => 2.0 * p1 - p2,
+    };
+    let p3 = match backbone.get(segment + 2) {
+        Some(next) => next.ca,
+        None => 2.0 * p2 - p1,
+    };
+
+    let t2 = t * t;
+    let t3 = t2 * t;
+    let position = 0.5
+        * (2.0 * p1
+            + (p2 - p0) * t
+            + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+            + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3);
+    let derivative = 0.5
+        * ((p2 - p0)
+            + 2.0 * (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t
+            + 3.0 * (3.0 * p1 - p0 - 3.0 * p2 + p3) * t2);
+    let tangent = derivative
+        .try_normalize()
+        .unwrap_or_else(|| (p2 - p1).normalize_or(Vec3::X));
+    (position, tangent)
+}
+
+/// Half width and half thickness of the cross-section at `u`, before scaling.
+/// The last residue of a strand narrows from an arrow head down to a tube.
+fn profile(structure: &[SecondaryStructure], residue: usize, u: f32) -> (f32, f32) {
+    match structure[residue] {
+        SecondaryStructure::Helix => (HELIX_HALF_WIDTH, SHEET_HALF_THICKNESS),
+        SecondaryStructure::Strand => {
+            let end = run_end(structure, residue);
+            let head_start = end.saturating_sub(1) as f32;
+            if u >= head_start {
+                let t = (u - head_start).clamp(0.0, 1.0);
+                let half_width = ARROW_HALF_WIDTH + (TUBE_RADIUS - ARROW_HALF_WIDTH) * t;
+                (half_width, SHEET_HALF_THICKNESS)
+            } else {
+                (STRAND_HALF_WIDTH, SHEET_HALF_THICKNESS)
+            }
+        }
+        SecondaryStructure::Loop => (TUBE_RADIUS, TUBE_RADIUS),
+    }
+}
+
+/// Vertices of elliptical rings swept along a path, joined into a closed tube.
+#[derive(Default)]
+struct Sweep {
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    colors: Vec<[f32; 4]>,
+    centers: Vec<Vec3>,
+    directions: Vec<Vec3>,
+}
+
+impl Sweep {
+    /// Adds a ring around `center` with semi-axes `side` and `up`.
+    fn add_ring(&mut self, center: Vec3, side: Vec3, up: Vec3, color: Color) {
+        let color = color.to_linear().to_f32_array();
+        let (side_normal, up_normal) = (
+            side / side.length_squared().max(f32::EPSILON),
+            up / up.length_squared().max(f32::EPSILON),
+        );
+        for point in 0..PROFILE_POINTS {
+            let angle = point as f32 / PROFILE_POINTS as f32 * std::f32::consts::TAU;
+            let (sin, cos) = angle.sin_cos();
+            self.positions
+                .push((center + side * cos + up * sin).to_array());
+            self.normals.push(
+                (side_normal * cos + up_normal * sin)
+                    .normalize_or_zero()
+                    .to_array(),
+            );
+            self.colors.push(color);
+        }
+        if let Some(&previous) = self.centers.last() {
+            self.directions
+                .push((center - previous).normalize_or_zero());
+        }
+        self.centers.push(center);
+    }
+
+    fn into_mesh(mut self) -> Mesh {
+        let rings = self.centers.len();
+        let ring_start = |ring: usize| (ring * PROFILE_POINTS) as u32;
+        let mut indices = Vec::with_capacity(rings * PROFILE_POINTS * 6);
+        for ring in 0..rings.saturating_sub(1) {
+            for point in 0..PROFILE_POINTS {
+                let next = (point + 1) % PROFILE_POINTS;
+                let a = ring_start(ring) + point as u32;
+                let b = ring_start(ring) + next as u32;
+                let c = ring_start(ring + 1) + point as u32;
+                let d = ring_start(ring + 1) + next as u32;
+                indices.extend([a, b, c, b, d, c]);
+            }
+        }
+
+        // Cap both ends with a fan around the ring's center.
+        let caps = [
+            (0, -self.directions.first().copied().unwrap_or(Vec3::NEG_Z)),
+            (
+                rings - 1,
+                self.directions.last().copied().unwrap_or(Vec3::Z),
+            ),
+        ];
+        for (ring, normal) in caps {
+            let center = self.positions.len() as u32;
+            self.positions.push(self.centers[ring].to_array());
+            self.normals.push(normal.to_array());
+            self.colors.push(self.colors[ring * PROFILE_POINTS]);
+            let first = self.positions.len() as u32;
+            for point in 0..PROFILE_POINTS {
+                let source = ring * PROFILE_POINTS + point;
+                self.positions.push(self.positions[source]);
+                self.normals.push(normal.to_array());
+                self.colors.push(self.colors[source]);
+            }
+            for point in 0..PROFILE_POINTS as u32 {
+                let next = (point + 1) % PROFILE_POINTS as u32;
+                if ring == 0 {
+                    indices.extend([center, first + next, first + point]);
+                } else {
+                    indices.extend([center, first + point, first + next]);
+                }
+            }
+        }
+
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, self.colors)
+        .with_inserted_indices(Indices::U32(indices))
+    }
+}
+
+/// Cartoon mesh entity of one chain segment, placed in world space.
+#[derive(Component)]
+pub struct Cartoon {
+    /// Residues of the segment in chain order.
+    pub residues: Vec<Entity>,
+}
+
+/// Changes that make the cartoons out of date.
+#[derive(SystemParam)]
+pub(super) struct CartoonChanges<'w, 's> {
+    moved_atoms: Query<'w, 's, (&'static Atom, &'static ChildOf), Changed<GlobalTransform>>,
+    changed_representations: Query<'w, 's, (), Changed<Representation>>,
+    removed_representations: RemovedComponents<'w, 's, Representation>,
+    representation_query: Query<'w, 's, &'static Representation>,
+}
+
+impl CartoonChanges<'_, '_> {
+    /// Cartoon residues whose backbone atoms moved, or `None` when nothing
+    /// changed. Residues that switched representation or were despawned
+    /// change the segments, so the set is returned for them even when empty.
+    fn moved_residues(&mut self) -> Option<HashSet<Entity>> {
+        let moved: HashSet<Entity> = self
+            .moved_atoms
+            .iter()
+            .filter(|(atom, _)| matches!(atom.atom_name.as_str(), "N" | "CA" | "C" | "O"))
+            .map(|(_, child_of)| child_of.parent())
+            .filter(|&residue| {
+                self.representation_query
+                    .get(residue)
+                    .is_ok_and(|representation| *representation == Representation::Cartoon)
+            })
+            .collect();
+        let any_removed = self.removed_representations.read().count() > 0;
+        (any_removed || !self.changed_representations.is_empty() || !moved.is_empty())
+            .then_some(moved)
+    }
+}
+
+/// Asset storage used to spawn cartoon meshes.
+#[derive(SystemParam)]
+pub(super) struct CartoonAssets<'w> {
+    meshes: ResMut<'w, Assets<Mesh>>,
+    rendering_materials: Res<'w, RenderingMaterials>,
+}
+
+/// Regenerates the cartoons of chain segments whose residues changed.
+///
+/// A cartoon is kept as long as its segment has the same residues, and its
+/// mesh is rewritten in place when their backbone atoms move. Segments that
+/// gained or lost residues get a new cartoon.
+pub(super) fn update_cartoons(
+    mut commands: Commands,
+    mut changes: CartoonChanges,
+    mut assets: CartoonAssets,
+    residue_query: Query<(Entity, &Residue, &Representation, &Children), With<AminoAcid>>,
+    atom_query: Query<(&Atom, &GlobalTransform, &ChildOf)>,
+    bond_query: Query<&Bond>,
+    cartoon_query: Query<(Entity, &Cartoon, &Mesh3d)>,
+) {
+    let Some(moved) = changes.moved_residues() else {
+        return;
+    };
+
+    let mut cartoons: HashMap<&[Entity], (Entity, &Mesh3d)> = cartoon_query
+        .iter()
+        .map(|(entity, cartoon, mesh)| (cartoon.residues.as_slice(), (entity, mesh)))
+        .collect();
+
+    let residues: Vec<(&Residue, Entity, BackboneAtoms)> = residue_query
+        .iter()
+        .filter(|(_, _, representation, _)| **representation == Representation::Cartoon)
+        .filter_map(|(entity, residue, _, children)| {
+            Some((residue, entity, backbone_atoms(children, &atom_query)?))
+        })
+        .collect();
+
+    for segment in chain_segments(residues, &bond_query, &atom_query) {
+        let (residues, backbone): (Vec<Entity>, Vec<BackboneAtoms>) = segment.into_iter().unzip();
+        match cartoons.remove(residues.as_slice()) {
+            Some(_) if !residues.iter().any(|residue| moved.contains(residue)) => {}
+            Some((entity, current)) => match cartoon_mesh(&backbone) {
+                Some(mesh) => {
+                    if let Err(e) = assets.meshes.insert(&current.0, mesh) {
+                        error!("Failed to update cartoon mesh: {}", e);
+                    }
+                }
+                None => commands.entity(entity).despawn(),
+            },
+            None => spawn_cartoon(&mut commands, &mut assets, residues, &backbone),
+        }
+    }
+
+    for (entity, _) in cartoons.into_values() {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// World-space backbone atoms of a residue, or `None` when one is missing.
+pub(super) fn backbone_atoms(
+    children: &Children,
+    atom_query: &Query<(&Atom, &GlobalTransform, &ChildOf)>,
+) -> Option<BackboneAtoms> {
+    let position = |name: &str| {
+        children.iter().find_map(|child| {
+            atom_query
+                .get(child)
+                .ok()
+                .filter(|(atom, ..)| atom.atom_name == name)
+                .map(|(_, transform, _)| transform.translation())
+        })
+    };
+    Some(BackboneAtoms {
+        n: position("N")?,
+        ca: position("CA")?,
+        c: position("C")?,
+        o: position("O")?,
+    })
+}
+
+/// Splits residues into chain segments: runs of residues joined by bonds
+/// between them, such as peptide bonds. Residues are grouped by the bonds
+/// first, so separate objects sharing a chain ID stay apart, and ordered by
+/// chain ID and residue number within each group.
+pub(super) fn chain_segments<T: Copy>(
+    residues: Vec<(&Residue, Entity, T)>,
+    bond_query: &Query<&Bond>,
+    atom_query: &Query<(&Atom, &GlobalTransform, &ChildOf)>,
+) -> Vec<Vec<(Entity, T)>> {
+    let mut neighbors: HashMap<Entity, Vec<Entity>> = HashMap::new();
+    for bond in bond_query {
+        let (Ok((_, _, parent1)), Ok((_, _, parent2))) =
+            (atom_query.get(bond.atom1), atom_query.get(bond.atom2))
+        else {
+            continue;
+        };
+        let (residue1, residue2) = (parent1.parent(), parent2.parent());
+        if residue1 != residue2 {
+            neighbors.entry(residue1).or_default().push(residue2);
+            neighbors.entry(residue2).or_default().push(residue1);
+        }
+    }
+    let joined = |a: Entity, b: Entity| neighbors.get(&a).is_some_and(|n| n.contains(&b));
+
+    let by_entity: HashMap<Entity, (&Residue, T)> = residues
+        .iter()
+        .map(|&(residue, entity, data)| (entity, (residue, data)))
+        .collect();
+    let mut visited: HashSet<Entity> = HashSet::new();
+    let mut segments = Vec::new();
+    for &(_, start, _) in &residues {
+        if !visited.insert(start) {
+            continue;
+        }
+
+        let mut component = vec![start];
+        let mut index = 0;
+        while let Some(&entity) = component.get(index) {
+            index += 1;
+            for &neighbor in neighbors.get(&entity).into_iter().flatten() {
+                if by_entity.contains_key(&neighbor) && visited.insert(neighbor) {
+                    component.push(neighbor);
+                }
+            }
+        }
+        component.sort_by(|a, b| {
+            let (residue_a, residue_b) = (by_entity[a].0, by_entity[b].0);
+            (&residue_a.chain_id, residue_a.residue_number, a).cmp(&(
+                &residue_b.chain_id,
+                residue_b.residue_number,
+                b,
+            ))
+        });
+
+        let mut segment: Vec<(Entity, T)> = Vec::new();
+        for entity in component {
+            if let Some(&(previous, _)) = segment.last()
+                && !joined(previous, entity)
+            {
+                segments.push(std::mem::take(&mut segment));
+            }
+            segment.push((entity, by_entity[&entity].1));
+        }
+        segments.push(segment);
+    }
+    segments
+}
+
+fn spawn_cartoon(
+    commands: &mut Commands,
+    assets: &mut CartoonAssets,
+    residues: Vec<Entity>,
+    backbone: &[BackboneAtoms],
+) {
+    let Some(mesh) = cartoon_mesh(backbone) else {
+        return;
+    };
+    commands.spawn((
+        Name::new("Cartoon"),
+        Cartoon { residues },
+        Mesh3d(assets.meshes.add(mesh)),
+        MeshMaterial3d(assets.rendering_materials.cartoon_material.clone()),
+        Transform::default(),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chemistry::amino_acids::{Conformation, PeptideBuilder};
+
+    fn built_backbone(conformation: Conformation) -> Vec<BackboneAtoms> {
+        let structure = PeptideBuilder::build("AAAAAAAAAA", "A", &conformation).unwrap();
+        structure
+            .residues
+            .iter()
+            .map(|residue| {
+                let position = |name: &str| {
+                    residue
+                        .atoms
+                        .iter()
+                        .find(|atom| atom.atom_name == name)
+                        .unwrap()
+                        .position
+                };
+                BackboneAtoms {
+                    n: position("N"),
+                    ca: position("CA"),
+                    c: position("C"),
+                    o: position("O"),
+                }
+            })
+            .collect()
+    }
+
+    fn interior(structure: &[SecondaryStructure]) -> &[SecondaryStructure] {
+        &structure[1..structure.len() - 1]
+    }
+
+    #[test]
+    fn ideal_helix_is_assigned_helix() {
+        let structure = secondary_structure(&built_backbone(Conformation::AlphaHelix));
+        assert_eq!(structure.first(), Some(&SecondaryStructure::Loop));
+        assert_eq!(structure.last(), Some(&SecondaryStructure::Loop));
+        assert!(
+            interior(&structure)
+                .iter()
+                .all(|&s| s == SecondaryStructure::Helix)
+        );
+    }
+
+    #[test]
+    fn ideal_strand_is_assigned_strand() {
+        let structure = secondary_structure(&built_backbone(Conformation::BetaStrand));
+        assert!(
+            interior(&structure)
+                .iter()
+                .all(|&s| s == SecondaryStructure::Strand)
+        );
+    }
+
+    #[test]
+    fn polyproline_is_assigned_loop() {
+        let structure = secondary_structure(&built_backbone(Conformation::PolyprolineII));
+        assert!(structure.iter().all(|&s| s == SecondaryStructure::Loop));
+    }
+
+    #[test]
+    fn short_runs_become_loops() {
+        let helix = Some((-57.0, -47.0));
+        let strand = Some((-120.0, 130.0));
+        let assigned = assign_secondary_structure(&[helix, helix, helix, None, strand, strand]);
+        assert!(assigned.iter().all(|&s| s == SecondaryStructure::Loop));
+
+        let assigned = assign_secondary_structure(&[strand, strand, strand, helix]);
+        assert_eq!(
+            assigned,
+            [
+                SecondaryStructure::Strand,
+                SecondaryStructure::Strand,
+                SecondaryStructure::Strand,
+                SecondaryStructure::Loop,
+            ]
+        );
+    }
+
+    #[test]
+    fn cartoon_mesh_needs_two_residues() {
+        let backbone = built_backbone(Conformation::AlphaHelix);
+        assert!(cartoon_mesh(&backbone[..1]).is_none());
+        let mesh = cartoon_mesh(&backbone).unwrap();
+        assert!(mesh.count_vertices() > 0);
+    }
+}
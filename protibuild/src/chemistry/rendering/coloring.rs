@@ -0,0 +1,320 @@
+//! Color schemes of atoms and bonds.
+//!
+//! Every atom of an object gets an [`AtomColor`] from the object's
+//! [`ColorScheme`], recomputed for the whole scene when a scheme changes,
+//! atoms are added, or backbone atoms of secondary structure colored residues
+//! move. Atoms and bonds whose color changed are redrawn, each bond half in
+//! the color of its atom.
+
+use std::collections::{BTreeSet, HashMap};
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use super::cartoon::{
+    BackboneAtoms, SecondaryStructure, backbone_atoms, chain_segments, secondary_structure,
+};
+use crate::chemistry::amino_acids::{AminoAcid, Residue, types::AminoAcidCode};
+use crate::chemistry::atoms::{Atom, Bond};
+
+/// Colors of successive chains, repeating after the last.
+const CHAIN_COLORS: [Color; 8] = [
+    Color::srgb(0.35, 0.6, 0.95),
+    Color::srgb(0.95, 0.55, 0.25),
+    Color::srgb(0.4, 0.8, 0.4),
+    Color::srgb(0.9, 0.35, 0.45),
+    Color::srgb(0.7, 0.5, 0.9),
+    Color::srgb(0.95, 0.85, 0.3),
+    Color::srgb(0.35, 0.85, 0.85),
+    Color::srgb(0.9, 0.55, 0.75),
+];
+
+const LOW_COLOR: Color = Color::srgb(0.2, 0.35, 0.95);
+const MIDDLE_COLOR: Color = Color::WHITE;
+const HIGH_COLOR: Color = Color::srgb(0.95, 0.2, 0.2);
+const HYDROPHOBIC_COLOR: Color = Color::srgb(0.95, 0.55, 0.15);
+
+/// Largest magnitude of the Kyte-Doolittle hydropathy index.
+const MAX_HYDROPATHY: f32 = 4.5;
+
+/// How the atoms of a project object are colored. It is stored on the
+/// object's root entities next to its [`Representation`](super::Representation).
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorScheme {
+    /// CPK color of the atom's element.
+    #[default]
+    Element,
+    /// One color per chain ID.
+    Chain,
+    /// One color per amino acid, after the RasMol "amino" scheme.
+    ResidueType,
+    /// Kyte-Doolittle hydropathy of the residue, blue for hydrophilic through
+    /// white to orange for hydrophobic.
+    Hydrophobicity,
+    /// Position of the residue along its chain segment, the residues joined
+    /// by peptide bonds, from blue at the N-terminus to red at the C-terminus.
+    Rainbow,
+    /// Helix, strand or loop, as drawn by the cartoon.
+    SecondaryStructure,
+    /// The atom's [`Atom::b_factor`], blue for the lowest value in the scene
+    /// through white to red for the highest.
+    BFactor,
+}
+
+impl ColorScheme {
+    pub const ALL: [ColorScheme; 7] = [
+        ColorScheme::Element,
+        ColorScheme::Chain,
+        ColorScheme::ResidueType,
+        ColorScheme::Hydrophobicity,
+        ColorScheme::Rainbow,
+        ColorScheme::SecondaryStructure,
+        ColorScheme::BFactor,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorScheme::Element => "Element",
+            ColorScheme::Chain => "Chain",
+            ColorScheme::ResidueType => "Residue Type",
+            ColorScheme::Hydrophobicity => "Hydrophobicity",
+            ColorScheme::Rainbow => "Rainbow",
+            ColorScheme::SecondaryStructure => "Secondary Structure",
+            ColorScheme::BFactor => "B-factor",
+        }
+    }
+
+    /// The scheme after this one, cycling back to element colors.
+    pub fn next(&self) -> Self {
+        let index = Self::ALL.iter().position(|s| s == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+/// Color an atom and its half of each bond are drawn in. Atoms without one
+/// use their element color.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct AtomColor(pub Color);
+
+/// Color of each amino acid in the RasMol "amino" scheme.
+pub fn residue_type_color(code: AminoAcidCode) -> Color {
+    match code {
+        AminoAcidCode::Asp | AminoAcidCode::Glu => Color::srgb(0.9, 0.04, 0.04),
+        AminoAcidCode::Cys | AminoAcidCode::Met => Color::srgb(0.9, 0.9, 0.0),
+        AminoAcidCode::Lys | AminoAcidCode::Arg => Color::srgb(0.08, 0.35, 1.0),
+        AminoAcidCode::Ser | AminoAcidCode::Thr => Color::srgb(0.98, 0.59, 0.0),
+        AminoAcidCode::Phe | AminoAcidCode::Tyr => Color::srgb(0.2, 0.2, 0.67),
+        AminoAcidCode::Asn | AminoAcidCode::Gln => Color::srgb(0.0, 0.86, 0.86),
+        AminoAcidCode::Gly => Color::srgb(0.92, 0.92, 0.92),
+        AminoAcidCode::Leu | AminoAcidCode::Val | AminoAcidCode::Ile => {
+            Color::srgb(0.06, 0.51, 0.06)
+        }
+        AminoAcidCode::Ala => Color::srgb(0.78, 0.78, 0.78),
+        AminoAcidCode::Trp => Color::srgb(0.71, 0.35, 0.71),
+        AminoAcidCode::His => Color::srgb(0.51, 0.51, 0.82),
+        AminoAcidCode::Pro => Color::srgb(0.86, 0.59, 0.51),
+    }
+}
+
+/// Blue through white to orange for hydropathy from -4.5 to 4.5.
+pub fn hydropathy_color(hydropathy: f32) -> Color {
+    let t = (hydropathy / MAX_HYDROPATHY).clamp(-1.0, 1.0);
+    if t < 0.0 {
+        MIDDLE_COLOR.mix(&LOW_COLOR, -t)
+    } else {
+        MIDDLE_COLOR.mix(&HYDROPHOBIC_COLOR, t)
+    }
+}
+
+/// Hue from blue at 0 to red at 1.
+pub fn rainbow_color(t: f32) -> Color {
+    Color::hsl(240.0 * (1.0 - t.clamp(0.0, 1.0)), 0.85, 0.55)
+}
+
+/// Blue through white to red for `t` from 0 to 1.
+pub fn gradient_color(t: f32) -> Color {
+    let t = t.clamp(0.0, 1.0) * 2.0 - 1.0;
+    if t < 0.0 {
+        MIDDLE_COLOR.mix(&LOW_COLOR, -t)
+    } else {
+        MIDDLE_COLOR.mix(&HIGH_COLOR, t)
+    }
+}
+
+/// Backbone atoms whose positions define the secondary structure.
+const BACKBONE_ATOM_NAMES: [&str; 3] = ["N", "CA", "C"];
+
+/// Changes that make the atom colors out of date.
+#[derive(SystemParam)]
+pub(super) struct ColorChanges<'w, 's> {
+    changed_schemes: Query<'w, 's, (), Changed<ColorScheme>>,
+    removed_schemes: RemovedComponents<'w, 's, ColorScheme>,
+    added_atoms: Query<'w, 's, (), Added<Atom>>,
+    moved_atoms: Query<'w, 's, (&'static Atom, &'static ChildOf), Changed<GlobalTransform>>,
+    schemes: Query<'w, 's, &'static ColorScheme>,
+}
+
+impl ColorChanges<'_, '_> {
+    fn any(&mut self) -> bool {
+        let any_removed = self.removed_schemes.read().count() > 0;
+        any_removed
+            || !self.changed_schemes.is_empty()
+            || !self.added_atoms.is_empty()
+            || self.backbone_moved()
+    }
+
+    /// Whether a backbone atom of a residue colored by secondary structure
+    /// moved, which changes the dihedrals the structure is assigned from.
+    fn backbone_moved(&self) -> bool {
+        self.moved_atoms.iter().any(|(atom, child_of)| {
+            BACKBONE_ATOM_NAMES.contains(&atom.atom_name.as_str())
+                && self
+                    .schemes
+                    .get(child_of.parent())
+                    .is_ok_and(|scheme| *scheme == ColorScheme::SecondaryStructure)
+        })
+    }
+}
+
+/// What the residue-level schemes need to know about the scene.
+struct SchemeContext {
+    chain_indices: HashMap<String, usize>,
+    /// Position of each residue along its chain, from 0 to 1.
+    chain_positions: HashMap<Entity, f32>,
+    secondary_structure: HashMap<Entity, SecondaryStructure>,
+    b_factor_range: (f32, f32),
+}
+
+/// Assigns every atom its color from its object's scheme. Only atoms whose
+/// color changes are touched, so only their residues are redrawn.
+pub(super) fn assign_atom_colors(
+    mut commands: Commands,
+    mut changes: ColorChanges,
+    residue_query: Query<(
+        Entity,
+        &AminoAcid,
+        &Residue,
+        Option<&ColorScheme>,
+        &Children,
+    )>,
+    atom_query: Query<(&Atom, &GlobalTransform, &ChildOf)>,
+    bond_query: Query<&Bond>,
+    color_query: Query<Option<&AtomColor>, With<Atom>>,
+) {
+    if !changes.any() {
+        return;
+    }
+
+    let context = scheme_context(&residue_query, &atom_query, &bond_query);
+
+    for (entity, amino_acid, residue, scheme, children) in &residue_query {
+        let scheme = scheme_of(scheme);
+        for child in children.iter() {
+            let (Ok((atom, ..)), Ok(current)) = (atom_query.get(child), color_query.get(child))
+            else {
+                continue;
+            };
+
+            let color = match scheme {
+                ColorScheme::Element => atom.element.color(),
+                ColorScheme::Chain => {
+                    let index = context.chain_indices[&residue.chain_id];
+                    CHAIN_COLORS[index % CHAIN_COLORS.len()]
+                }
+                ColorScheme::ResidueType => residue_type_color(amino_acid.code),
+                ColorScheme::Hydrophobicity => hydropathy_color(amino_acid.code.hydropathy()),
+                ColorScheme::Rainbow => rainbow_color(context.chain_positions[&entity]),
+                ColorScheme::SecondaryStructure => context
+                    .secondary_structure
+                    .get(&entity)
+                    .unwrap_or(&SecondaryStructure::Loop)
+                    .color(),
+                ColorScheme::BFactor => {
+                    let (min, max) = context.b_factor_range;
+                    let t = if max > min {
+                        (atom.b_factor - min) / (max - min)
+                    } else {
+                        0.5
+                    };
+                    gradient_color(t)
+                }
+            };
+
+            if current.map_or(atom.element.color(), |current| current.0) == color {
+                continue;
+            }
+            commands.entity(child).insert(AtomColor(color));
+        }
+    }
+}
+
+/// Scheme of a residue; residues without one use element colors.
+fn scheme_of(scheme: Option<&ColorScheme>) -> ColorScheme {
+    scheme.copied().unwrap_or_default()
+}
+
+fn scheme_context(
+    residue_query: &Query<(
+        Entity,
+        &AminoAcid,
+        &Residue,
+        Option<&ColorScheme>,
+        &Children,
+    )>,
+    atom_query: &Query<(&Atom, &GlobalTransform, &ChildOf)>,
+    bond_query: &Query<&Bond>,
+) -> SchemeContext {
+    let chain_ids: BTreeSet<&str> = residue_query
+        .iter()
+        .map(|(_, _, residue, ..)| residue.chain_id.as_str())
+        .collect();
+    let chain_indices = chain_ids
+        .into_iter()
+        .enumerate()
+        .map(|(index, chain_id)| (chain_id.to_string(), index))
+        .collect();
+
+    let residues: Vec<(&Residue, Entity, ())> = residue_query
+        .iter()
+        .map(|(entity, _, residue, ..)| (residue, entity, ()))
+        .collect();
+    let mut chain_positions = HashMap::new();
+    for segment in chain_segments(residues, bond_query, atom_query) {
+        let last = (segment.len() - 1).max(1) as f32;
+        for (index, &(entity, _)) in segment.iter().enumerate() {
+            chain_positions.insert(entity, index as f32 / last);
+        }
+    }
+
+    let backbones: Vec<(&Residue, Entity, BackboneAtoms)> = residue_query
+        .iter()
+        .filter(|(.., scheme, _)| scheme_of(*scheme) == ColorScheme::SecondaryStructure)
+        .filter_map(|(entity, _, residue, _, children)| {
+            Some((residue, entity, backbone_atoms(children, atom_query)?))
+        })
+        .collect();
+    let mut structures = HashMap::new();
+    for segment in chain_segments(backbones, bond_query, atom_query) {
+        let backbone: Vec<BackboneAtoms> = segment.iter().map(|&(_, backbone)| backbone).collect();
+        let assigned = secondary_structure(&backbone);
+        for (&(entity, _), structure) in segment.iter().zip(assigned) {
+            structures.insert(entity, structure);
+        }
+    }
+
+    let b_factor_range = residue_query
+        .iter()
+        .filter(|(.., scheme, _)| scheme_of(*scheme) == ColorScheme::BFactor)
+        .flat_map(|(.., children)| children.iter())
+        .filter_map(|child| atom_query.get(child).ok())
+        .fold((f32::MAX, f32::MIN), |(min, max), (atom, ..)| {
+            (min.min(atom.b_factor), max.max(atom.b_factor))
+        });
+
+    SchemeContext {
+        chain_indices,
+        chain_positions,
+        secondary_structure: structures,
+        b_factor_range,
+    }
+}
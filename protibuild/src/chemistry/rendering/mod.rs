@@ -1,125 +1,194 @@
 use bevy::prelude::*;
+use bevy::transform::TransformSystems;
 
 use crate::chemistry::atoms::{Atom, Bond, Element};
 
+mod batching;
+pub mod cartoon;
+pub mod coloring;
+
+pub use coloring::{AtomColor, ColorScheme};
+
+/// Atom radius of the ball-and-stick model as a fraction of the covalent radius.
+const BALL_AND_STICK_ATOM_SCALE: f32 = 0.3;
+
+/// Bond radius of the ball-and-stick model.
+const BALL_AND_STICK_BOND_RADIUS: f32 = 0.025;
+
+/// Radius of licorice sticks and of the atoms joining them.
+const LICORICE_RADIUS: f32 = 0.08;
+
+/// How the atoms and bonds of a project object are drawn. It is stored on the
+/// object's root entities, and their atoms and bonds are redrawn when it
+/// changes.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Representation {
+    /// Spheres at a fraction of the covalent radius joined by thin sticks.
+    #[default]
+    BallAndStick,
+    /// CPK space-filling spheres at the van der Waals radius, without bonds.
+    SpaceFilling,
+    /// Sticks of equal radius joined by spheres of the same radius.
+    Licorice,
+    /// Bonds drawn as lines colored by their atoms, without spheres.
+    Wireframe,
+    /// A ribbon through the backbone with helices, strands and loops; see
+    /// [`cartoon`]. Atoms and bonds are hidden.
+    Cartoon,
+}
+
+impl Representation {
+    pub const ALL: [Representation; 5] = [
+        Representation::BallAndStick,
+        Representation::SpaceFilling,
+        Representation::Licorice,
+        Representation::Wireframe,
+        Representation::Cartoon,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Representation::BallAndStick => "Ball and Stick",
+            Representation::SpaceFilling => "Space Filling",
+            Representation::Licorice => "Licorice",
+            Representation::Wireframe => "Wireframe",
+            Representation::Cartoon => "Cartoon",
+        }
+    }
+
+    /// The representation after this one, cycling back to ball-and-stick.
+    pub fn next(&self) -> Self {
+        let index = Self::ALL.iter().position(|r| r == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Radius of an atom's sphere, or `None` when atoms are not drawn.
+    pub fn atom_radius(&self, element: Element) -> Option<f32> {
+        match self {
+            Representation::BallAndStick => {
+                Some(element.covalent_radius() * BALL_AND_STICK_ATOM_SCALE)
+            }
+            Representation::SpaceFilling => Some(element.van_der_waals_radius()),
+            Representation::Licorice => Some(LICORICE_RADIUS),
+            Representation::Wireframe | Representation::Cartoon => None,
+        }
+    }
+
+    /// Radius atoms are picked with under the cursor: their sphere, or the
+    /// ball-and-stick sphere where atoms are not drawn as spheres.
+    pub fn pick_radius(&self, element: Element) -> f32 {
+        self.atom_radius(element)
+            .unwrap_or(element.covalent_radius() * BALL_AND_STICK_ATOM_SCALE)
+    }
+
+    /// Radius of bond cylinders, or `None` when bonds are not drawn as
+    /// cylinders.
+    pub fn bond_radius(&self) -> Option<f32> {
+        match self {
+            Representation::BallAndStick => Some(BALL_AND_STICK_BOND_RADIUS),
+            Representation::Licorice => Some(LICORICE_RADIUS),
+            Representation::SpaceFilling | Representation::Wireframe | Representation::Cartoon => {
+                None
+            }
+        }
+    }
+}
+
 pub struct RenderingPlugin;
 
 impl Plugin for RenderingPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, setup_rendering_resources)
-            .add_systems(Update, render_atoms)
-            .add_systems(PostUpdate, render_bonds);
+        app.init_resource::<batching::SphereTemplate>()
+            .add_systems(Startup, setup_rendering_resources)
+            .add_systems(
+                PostUpdate,
+                (
+                    (
+                        coloring::assign_atom_colors,
+                        batching::update_residue_meshes,
+                    )
+                        .chain(),
+                    cartoon::update_cartoons,
+                )
+                    .after(TransformSystems::Propagate),
+            )
+            .add_systems(Last, draw_wireframes);
     }
 }
 
 #[derive(Resource)]
 pub struct RenderingMaterials {
-    pub materials: Vec<Handle<StandardMaterial>>,
-    pub sphere_mesh: Handle<Mesh>,
-    pub cylinder_mesh: Handle<Mesh>,
+    /// Material of residue meshes, which carry the atom colors per vertex.
+    pub atom_material: Handle<StandardMaterial>,
+    /// Material of cartoon meshes, which carry their colors per vertex.
+    pub cartoon_material: Handle<StandardMaterial>,
 }
 
 fn setup_rendering_resources(
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
-    let elements = [
-        Element::Hydrogen,
-        Element::Carbon,
-        Element::Nitrogen,
-        Element::Oxygen,
-        Element::Sulfur,
-    ];
+    let atom_material = materials.add(StandardMaterial {
+        base_color: Color::WHITE,
+        metallic: 0.0,
+        perceptual_roughness: 0.3,
+        reflectance: 0.5,
+        ..default()
+    });
 
-    let element_materials: Vec<Handle<StandardMaterial>> = elements
-        .iter()
-        .map(|e| {
-            materials.add(StandardMaterial {
-                base_color: e.color(),
-                metallic: 0.0,
-                perceptual_roughness: 0.3,
-                reflectance: 0.5,
-                ..default()
-            })
-        })
-        .collect();
-
-    let sphere_mesh = meshes.add(
-        Sphere::new(1.0)
-            .mesh()
-            .ico(16)
-            .expect("Failed to create icosphere mesh"),
-    );
-    let cylinder_mesh = meshes.add(Cylinder::new(0.025, 1.0));
+    let cartoon_material = materials.add(StandardMaterial {
+        base_color: Color::WHITE,
+        metallic: 0.0,
+        perceptual_roughness: 0.5,
+        reflectance: 0.4,
+        double_sided: true,
+        cull_mode: None,
+        ..default()
+    });
 
     commands.insert_resource(RenderingMaterials {
-        materials: element_materials,
-        sphere_mesh,
-        cylinder_mesh,
+        atom_material,
+        cartoon_material,
     });
 }
 
-fn render_atoms(
-    mut commands: Commands,
-    query: Query<(Entity, &Atom, &Transform), Without<Mesh3d>>,
-    rendering_materials: Res<RenderingMaterials>,
-) {
-    for (entity, atom, transform) in &query {
-        let material_idx = match atom.element {
-            Element::Hydrogen => 0,
-            Element::Carbon => 1,
-            Element::Nitrogen => 2,
-            Element::Oxygen => 3,
-            Element::Sulfur => 4,
-        };
-
-        let radius = atom.element.covalent_radius() * 0.3;
+/// Color an atom is drawn in: its assigned color, or its element color.
+fn atom_color(atom: &Atom, color: Option<&AtomColor>) -> Color {
+    color.map_or(atom.element.color(), |color| color.0)
+}
 
-        commands.entity(entity).insert((
-            Mesh3d(rendering_materials.sphere_mesh.clone()),
-            MeshMaterial3d(rendering_materials.materials[material_idx].clone()),
-            Transform::from_translation(transform.translation).with_scale(Vec3::splat(radius)),
-        ));
-    }
+/// Representation of the object an atom or bond belongs to.
+fn representation_of(
+    child_of: Option<&ChildOf>,
+    representation_query: &Query<&Representation>,
+) -> Representation {
+    child_of
+        .and_then(|child_of| representation_query.get(child_of.parent()).ok())
+        .copied()
+        .unwrap_or_default()
 }
 
-fn render_bonds(
-    mut commands: Commands,
-    bond_query: Query<(Entity, &Bond, &ChildOf), Without<Mesh3d>>,
-    atom_query: Query<&GlobalTransform>,
-    parent_query: Query<&GlobalTransform>,
-    rendering_materials: Res<RenderingMaterials>,
+/// Draws the bonds of wireframe objects as lines, each half in the color of
+/// its atom.
+fn draw_wireframes(
+    mut gizmos: Gizmos,
+    bond_query: Query<(&Bond, &ChildOf)>,
+    atom_query: Query<(&Atom, Option<&AtomColor>, &GlobalTransform)>,
+    representation_query: Query<&Representation>,
 ) {
-    for (entity, bond, child_of) in &bond_query {
-        if let (Ok(atom_transform1), Ok(atom_transform2), Ok(parent_transform)) = (
-            atom_query.get(bond.atom1),
-            atom_query.get(bond.atom2),
-            parent_query.get(child_of.parent()),
-        ) {
-            let pos1 = atom_transform1.translation();
-            let pos2 = atom_transform2.translation();
-            let mid_global = (pos1 + pos2) / 2.0;
-            let direction = pos2 - pos1;
-            let length = direction.length();
-
-            if length > 0.0 {
-                // Convert global position to local position relative to parent
-                let parent_inv = parent_transform.to_matrix().inverse();
-                let mid_local = parent_inv.transform_point3(mid_global);
-
-                // Convert global direction to local direction
-                let direction_local = parent_inv.transform_vector3(direction);
-                let rotation = Quat::from_rotation_arc(Vec3::Y, direction_local.normalize());
-
-                commands.entity(entity).insert((
-                    Mesh3d(rendering_materials.cylinder_mesh.clone()),
-                    MeshMaterial3d(rendering_materials.materials[1].clone()), // Carbon color for bonds
-                    Transform::from_translation(mid_local)
-                        .with_rotation(rotation)
-                        .with_scale(Vec3::new(1.0, length, 1.0)),
-                ));
-            }
+    for (bond, child_of) in &bond_query {
+        if representation_of(Some(child_of), &representation_query) != Representation::Wireframe {
+            continue;
         }
+        let (Ok((atom1, color1, transform1)), Ok((atom2, color2, transform2))) =
+            (atom_query.get(bond.atom1), atom_query.get(bond.atom2))
+        else {
+            continue;
+        };
+        let pos1 = transform1.translation();
+        let pos2 = transform2.translation();
+        let middle = (pos1 + pos2) / 2.0;
+        gizmos.line(pos1, middle, atom_color(atom1, color1));
+        gizmos.line(middle, pos2, atom_color(atom2, color2));
     }
 }
@@ -0,0 +1,463 @@
+//! Surface extraction on a regular grid.
+//!
+//! The surface is the zero level of a scalar field sampled on a grid, positive
+//! inside the molecule:
+//!
+//! - Solvent accessible: `max(r + probe - |p - a|)` over the atoms, so the
+//!   surface is traced by the center of the probe rolling over the atoms.
+//! - Solvent excluded: the distance to the nearest probe center outside the
+//!   accessible surface minus the probe radius, so the surface is the part of
+//!   space the probe cannot reach.
+//!
+//! The field is triangulated with marching tetrahedra, splitting every grid
+//! cube into six tetrahedra along its main diagonal.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use super::SurfaceKind;
+
+/// Grid spacing in Å, coarsened for large systems to at most
+/// [`MAX_GRID_POINTS`].
+const GRID_SPACING: f32 = 0.5;
+const MAX_GRID_POINTS: f32 = 4_000_000.0;
+
+/// The six tetrahedra of a cube around its diagonal from corner 0 to corner 7.
+/// Corner `i` is offset by its bits `(x, y, z) = (i & 1, i >> 1 & 1, i >> 2 & 1)`.
+const TETRAHEDRA: [[usize; 4]; 6] = [
+    [0, 1, 3, 7],
+    [0, 1, 5, 7],
+    [0, 2, 3, 7],
+    [0, 2, 6, 7],
+    [0, 4, 5, 7],
+    [0, 4, 6, 7],
+];
+
+/// An atom as seen by the surface: its position and van der Waals radius.
+#[derive(Clone, Copy, Debug)]
+pub struct SurfaceAtom {
+    pub position: Vec3,
+    pub radius: f32,
+}
+
+/// Triangulated surface with the area it assigns to each atom.
+#[derive(Debug, Default)]
+pub struct SurfaceMesh {
+    pub positions: Vec<Vec3>,
+    /// Outward unit normals.
+    pub normals: Vec<Vec3>,
+    /// Triangles wound counter-clockwise seen from outside.
+    pub indices: Vec<u32>,
+    /// Index of the atom each vertex lies over.
+    pub vertex_atoms: Vec<usize>,
+    /// Surface area over each atom in Å².
+    pub atom_areas: Vec<f32>,
+}
+
+impl SurfaceMesh {
+    pub fn total_area(&self) -> f32 {
+        self.atom_areas.iter().sum()
+    }
+}
+
+/// Points bucketed into cubic cells for neighbour searches.
+pub(super) struct CellList {
+    cell_size: f32,
+    cells: HashMap<IVec3, Vec<usize>>,
+}
+
+impl CellList {
+    pub(super) fn new(points: impl IntoIterator<Item = Vec3>, cell_size: f32) -> Self {
+        let mut cells: HashMap<IVec3, Vec<usize>> = HashMap::new();
+        for (index, point) in points.into_iter().enumerate() {
+            cells
+                .entry((point / cell_size).floor().as_ivec3())
+                .or_default()
+                .push(index);
+        }
+        Self { cell_size, cells }
+    }
+
+    /// Indices of the points in the cells around `point`, which include every
+    /// point closer than the cell size.
+    pub(super) fn near(&self, point: Vec3) -> impl Iterator<Item = usize> + '_ {
+        let cell = (point / self.cell_size).floor().as_ivec3();
+        (-1..=1)
+            .flat_map(move |x| {
+                (-1..=1).flat_map(move |y| (-1..=1).map(move |z| IVec3::new(x, y, z)))
+            })
+            .filter_map(move |offset| self.cells.get(&(cell + offset)))
+            .flatten()
+            .copied()
+    }
+}
+
+/// Scalar field sampled on a regular grid.
+struct Grid {
+    origin: Vec3,
+    spacing: f32,
+    size: UVec3,
+    values: Vec<f32>,
+}
+
+impl Grid {
+    /// Grid covering `min..max` with every value set to zero.
+    fn new(min: Vec3, max: Vec3) -> Self {
+        let extent = max - min;
+        let volume = extent.x * extent.y * extent.z;
+        let spacing = GRID_SPACING.max((volume / MAX_GRID_POINTS).cbrt());
+        let size = (extent / spacing).ceil().as_uvec3() + UVec3::ONE;
+        Self {
+            origin: min,
+            spacing,
+            size,
+            values: vec![0.0; (size.x * size.y * size.z) as usize],
+        }
+    }
+
+    fn index(&self, cell: UVec3) -> usize {
+        (cell.x + self.size.x * (cell.y + self.size.y * cell.z)) as usize
+    }
+
+    fn cell(&self, index: usize) -> UVec3 {
+        let index = index as u32;
+        UVec3::new(
+            index % self.size.x,
+            index / self.size.x % self.size.y,
+            index / (self.size.x * self.size.y),
+        )
+    }
+
+    fn point(&self, cell: UVec3) -> Vec3 {
+        self.origin + cell.as_vec3() * self.spacing
+    }
+
+    /// Grid cells inside the box around `center` with half extent `radius`.
+    fn cells_around(&self, center: Vec3, radius: f32) -> impl Iterator<Item = UVec3> + use<> {
+        let min = ((center - radius - self.origin) / self.spacing)
+            .floor()
+            .max(Vec3::ZERO)
+            .as_uvec3();
+        let max = ((center + radius - self.origin) / self.spacing)
+            .ceil()
+            .as_uvec3()
+            .min(self.size - UVec3::ONE);
+        (min.z..=max.z).flat_map(move |z| {
+            (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| UVec3::new(x, y, z)))
+        })
+    }
+}
+
+/// Generates the solvent-accessible or solvent-excluded surface of `atoms`
+/// for a probe of radius `probe_radius`.
+///
+/// The mesh is a snapshot of the given positions; nothing updates it when the
+/// atoms move afterwards, so callers compute it again to follow them.
+pub fn generate(atoms: &[SurfaceAtom], kind: SurfaceKind, probe_radius: f32) -> SurfaceMesh {
+    if atoms.is_empty() {
+        return SurfaceMesh::default();
+    }
+
+    let max_radius = atoms.iter().map(|atom| atom.radius).fold(0.0, f32::max);
+    let margin = max_radius + probe_radius + 2.0 * GRID_SPACING;
+    let (min, max) = atoms.iter().fold(
+        (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+        |(min, max), atom| (min.min(atom.position), max.max(atom.position)),
+    );
+
+    // Only the sign and the values next to the surface matter, so the field
+    // is clamped a couple of cells away from it.
+    let mut grid = Grid::new(min - margin, max + margin);
+    let floor = -2.0 * grid.spacing;
+    grid.values.fill(floor);
+    for atom in atoms {
+        let reach = atom.radius + probe_radius;
+        for cell in grid.cells_around(atom.position, reach + 2.0 * grid.spacing) {
+            let index = grid.index(cell);
+            let value = reach - grid.point(cell).distance(atom.position);
+            if value > grid.values[index] {
+                grid.values[index] = value;
+            }
+        }
+    }
+
+    if kind == SurfaceKind::SolventExcluded {
+        exclude_probe(&mut grid, atoms, probe_radius);
+    }
+
+    let mut mesh = triangulate(&grid);
+    assign_atoms(&mut mesh, atoms, probe_radius + 2.0 * grid.spacing);
+    mesh
+}
+
+/// Turns an accessible surface field into the excluded surface field. Probe
+/// centers are the grid points just outside the accessible surface, moved
+/// onto it; points inside it become the distance to the nearest center minus
+/// the probe radius.
+fn exclude_probe(grid: &mut Grid, atoms: &[SurfaceAtom], probe_radius: f32) {
+    let neighbours = [
+        IVec3::X,
+        IVec3::NEG_X,
+        IVec3::Y,
+        IVec3::NEG_Y,
+        IVec3::Z,
+        IVec3::NEG_Z,
+    ];
+    let centers: Vec<Vec3> = (0..grid.values.len())
+        .filter(|&index| grid.values[index] <= 0.0)
+        .filter(|&index| {
+            let cell = grid.cell(index).as_ivec3();
+            neighbours.iter().any(|&offset| {
+                let neighbour = cell + offset;
+                neighbour.cmpge(IVec3::ZERO).all()
+                    && neighbour.cmplt(grid.size.as_ivec3()).all()
+                    && grid.values[grid.index(neighbour.as_uvec3())] > 0.0
+            })
+        })
+        .map(|index| grid.point(grid.cell(index)))
+        .collect();
+
+    // Without this the surface would grow by up to a grid cell.
+    let max_radius = atoms.iter().map(|atom| atom.radius).fold(0.0, f32::max);
+    let atom_cells = CellList::new(
+        atoms.iter().map(|atom| atom.position),
+        max_radius + probe_radius + 2.0 * grid.spacing,
+    );
+    let centers: Vec<Vec3> = centers
+        .into_iter()
+        .map(|center| {
+            let reach = |atom: &SurfaceAtom| atom.radius + probe_radius;
+            atom_cells
+                .near(center)
+                .map(|atom| &atoms[atom])
+                .max_by(|a, b| {
+                    let depth = |atom: &SurfaceAtom| reach(atom) - atom.position.distance(center);
+                    depth(a).total_cmp(&depth(b))
+                })
+                .map_or(center, |atom| {
+                    atom.position + (center - atom.position).normalize_or_zero() * reach(atom)
+                })
+        })
+        .collect();
+
+    let ceiling = 2.0 * grid.spacing;
+    let search = probe_radius + ceiling;
+    let cells = CellList::new(centers.iter().copied(), search);
+    let values = (0..grid.values.len())
+        .map(|index| {
+            if grid.values[index] <= 0.0 {
+                return -probe_radius;
+            }
+            let point = grid.point(grid.cell(index));
+            let nearest = cells
+                .near(point)
+                .map(|center| centers[center].distance(point))
+                .fold(search, f32::min);
+            (nearest - probe_radius).min(ceiling)
+        })
+        .collect();
+    grid.values = values;
+}
+
+/// Extracts the zero level of the field with marching tetrahedra.
+fn triangulate(grid: &Grid) -> SurfaceMesh {
+    let mut mesh = SurfaceMesh::default();
+    // Vertices are shared between the tetrahedra meeting at a grid edge.
+    let mut edge_vertices: HashMap<(usize, usize), u32> = HashMap::new();
+
+    for z in 0..grid.size.z.saturating_sub(1) {
+        for y in 0..grid.size.y.saturating_sub(1) {
+            for x in 0..grid.size.x.saturating_sub(1) {
+                let corners: [usize; 8] = std::array::from_fn(|corner| {
+                    let offset = UVec3::new(
+                        corner as u32 & 1,
+                        corner as u32 >> 1 & 1,
+                        corner as u32 >> 2 & 1,
+                    );
+                    grid.index(UVec3::new(x, y, z) + offset)
+                });
+                let inside = corners.map(|index| grid.values[index] > 0.0);
+                if inside.iter().all(|&inside| inside) || inside.iter().all(|&inside| !inside) {
+                    continue;
+                }
+
+                for tetrahedron in TETRAHEDRA {
+                    let indices = tetrahedron.map(|corner| corners[corner]);
+                    triangulate_tetrahedron(grid, indices, &mut edge_vertices, &mut mesh);
+                }
+            }
+        }
+    }
+
+    let mut normals = vec![Vec3::ZERO; mesh.positions.len()];
+    for triangle in mesh.indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| mesh.positions[triangle[i] as usize]);
+        let normal = (b - a).cross(c - a);
+        for &vertex in triangle {
+            normals[vertex as usize] += normal;
+        }
+    }
+    mesh.normals = normals.into_iter().map(Vec3::normalize_or_zero).collect();
+    mesh
+}
+
+fn triangulate_tetrahedron(
+    grid: &Grid,
+    corners: [usize; 4],
+    edge_vertices: &mut HashMap<(usize, usize), u32>,
+    mesh: &mut SurfaceMesh,
+) {
+    let (inside, outside): (Vec<usize>, Vec<usize>) = corners
+        .into_iter()
+        .partition(|&index| grid.values[index] > 0.0);
+
+    let mut vertex = |from: usize, to: usize| -> (u32, Vec3) {
+        let (a, b) = (grid.point(grid.cell(from)), grid.point(grid.cell(to)));
+        let key = (from.min(to), from.max(to));
+        let index = *edge_vertices.entry(key).or_insert_with(|| {
+            let (value_a, value_b) = (grid.values[from], grid.values[to]);
+            let t = value_a / (value_a - value_b);
+            mesh.positions.push(a.lerp(b, t));
+            (mesh.positions.len() - 1) as u32
+        });
+        // Points from inside to outside, i.e. along the outward normal.
+        (index, b - a)
+    };
+
+    let triangles: Vec<[(u32, Vec3); 3]> = match (inside.as_slice(), outside.as_slice()) {
+        (&[a], &[b, c, d]) => vec![[vertex(a, b), vertex(a, c), vertex(a, d)]],
+        (&[a, b, c], &[d]) => vec![[vertex(a, d), vertex(b, d), vertex(c, d)]],
+        (&[a, b], &[c, d]) => {
+            let quad = [vertex(a, c), vertex(a, d), vertex(b, d), vertex(b, c)];
+            vec![[quad[0], quad[1], quad[2]], [quad[0], quad[2], quad[3]]]
+        }
+        _ => Vec::new(),
+    };
+
+    for triangle in triangles {
+        let [a, b, c] = triangle.map(|(index, _)| index);
+        let outward: Vec3 = triangle.iter().map(|(_, direction)| *direction).sum();
+        let [pa, pb, pc] = [a, b, c].map(|index| mesh.positions[index as usize]);
+        if (pb - pa).cross(pc - pa).dot(outward) >= 0.0 {
+            mesh.indices.extend([a, b, c]);
+        } else {
+            mesh.indices.extend([a, c, b]);
+        }
+    }
+}
+
+/// Assigns every vertex to the atom whose van der Waals surface is closest,
+/// and splits the area of each triangle between the atoms of its vertices.
+fn assign_atoms(mesh: &mut SurfaceMesh, atoms: &[SurfaceAtom], reach: f32) {
+    let max_radius = atoms.iter().map(|atom| atom.radius).fold(0.0, f32::max);
+    let cells = CellList::new(atoms.iter().map(|atom| atom.position), max_radius + reach);
+
+    mesh.vertex_atoms = mesh
+        .positions
+        .iter()
+        .map(|&position| {
+            cells
+                .near(position)
+                .min_by(|&a, &b| {
+                    let gap =
+                        |atom: usize| atoms[atom].position.distance(position) - atoms[atom].radius;
+                    gap(a).total_cmp(&gap(b))
+                })
+                .unwrap_or(0)
+        })
+        .collect();
+
+    mesh.atom_areas = vec![0.0; atoms.len()];
+    for triangle in mesh.indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| mesh.positions[triangle[i] as usize]);
+        let area = (b - a).cross(c - a).length() / 2.0;
+        for &vertex in triangle {
+            mesh.atom_areas[mesh.vertex_atoms[vertex as usize]] += area / 3.0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CARBON_RADIUS: f32 = 1.7;
+    const PROBE_RADIUS: f32 = 1.4;
+
+    fn atom(x: f32) -> SurfaceAtom {
+        SurfaceAtom {
+            position: Vec3::new(x, 0.0, 0.0),
+            radius: CARBON_RADIUS,
+        }
+    }
+
+    #[test]
+    fn single_atom_accessible_area_matches_sphere() {
+        let mesh = generate(&[atom(0.0)], SurfaceKind::SolventAccessible, PROBE_RADIUS);
+
+        let radius = CARBON_RADIUS + PROBE_RADIUS;
+        let expected = 4.0 * std::f32::consts::PI * radius * radius;
+        let area = mesh.total_area();
+        assert!(
+            (area - expected).abs() < 0.05 * expected,
+            "area {} Å², expected {} Å²",
+            area,
+            expected
+        );
+        for position in &mesh.positions {
+            assert!((position.length() - radius).abs() < GRID_SPACING);
+        }
+    }
+
+    #[test]
+    fn excluded_surface_fills_the_crevice_between_two_atoms() {
+        let distance = 3.0;
+        let atoms = [atom(0.0), atom(distance)];
+        let excluded = generate(&atoms, SurfaceKind::SolventExcluded, PROBE_RADIUS);
+        let accessible = generate(&atoms, SurfaceKind::SolventAccessible, PROBE_RADIUS);
+        assert!(!excluded.indices.is_empty());
+        assert!(excluded.total_area() < accessible.total_area());
+
+        // Every vertex lies between the van der Waals and accessible surfaces.
+        for &position in &excluded.positions {
+            let nearest = atoms
+                .iter()
+                .map(|atom| atom.position.distance(position))
+                .fold(f32::MAX, f32::min);
+            assert!(
+                nearest > CARBON_RADIUS - GRID_SPACING,
+                "vertex {}",
+                position
+            );
+            assert!(
+                nearest < CARBON_RADIUS + PROBE_RADIUS,
+                "vertex {}",
+                position
+            );
+        }
+
+        // Between the atoms the surface follows a probe touching both of them
+        // rather than the narrower neck of the van der Waals spheres.
+        let half = distance / 2.0;
+        let reach = CARBON_RADIUS + PROBE_RADIUS;
+        let neck = (reach * reach - half * half).sqrt() - PROBE_RADIUS;
+        let van_der_waals_neck = (CARBON_RADIUS * CARBON_RADIUS - half * half).sqrt();
+        let middle: Vec<f32> = excluded
+            .positions
+            .iter()
+            .filter(|position| (position.x - half).abs() < GRID_SPACING / 2.0)
+            .map(|position| position.yz().length())
+            .collect();
+        assert!(!middle.is_empty());
+        for radius in middle {
+            assert!(
+                (radius - neck).abs() < GRID_SPACING,
+                "neck radius {} Å, expected {} Å rather than {} Å",
+                radius,
+                neck,
+                van_der_waals_neck
+            );
+        }
+    }
+}
@@ -0,0 +1,383 @@
+//! Solvent-accessible and solvent-excluded molecular surfaces.
+//!
+//! A surface is computed from every atom in the scene on request and drawn as
+//! a translucent world-space mesh. It is a snapshot: moving atoms leaves it in
+//! place until it is computed again, and despawning any of its atoms removes
+//! it.
+
+use std::collections::{HashMap, HashSet};
+
+use bevy::asset::RenderAssetUsages;
+use bevy::ecs::system::SystemParam;
+use bevy::mesh::{Indices, PrimitiveTopology};
+use bevy::prelude::*;
+
+use crate::chemistry::amino_acids::{AminoAcid, types::AminoAcidCode};
+use crate::chemistry::atoms::Atom;
+use crate::chemistry::force_field::{AtomParameters, COULOMB_CONSTANT};
+use crate::chemistry::rendering::coloring::residue_type_color;
+
+pub mod grid;
+
+use grid::{CellList, SurfaceAtom};
+
+/// Radius of the water probe rolled over the atoms, in Ångström.
+pub const PROBE_RADIUS: f32 = 1.4;
+/// Alpha of the surface material.
+const SURFACE_OPACITY: f32 = 0.55;
+/// Electrostatic potential, in kcal/(mol·e), mapped to full red or blue.
+const POTENTIAL_RANGE: f32 = 10.0;
+/// Atoms farther than this from a vertex do not contribute to its potential.
+const ELECTROSTATIC_CUTOFF: f32 = 12.0;
+/// Residue type color of atoms outside amino acids.
+const NON_RESIDUE_COLOR: Color = Color::srgb(0.6, 0.6, 0.6);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SurfaceKind {
+    /// Surface traced by the center of the probe.
+    SolventAccessible,
+    /// Surface of the volume the probe cannot enter, i.e. the van der Waals
+    /// surface with the crevices too narrow for the probe filled in.
+    #[default]
+    SolventExcluded,
+}
+
+impl SurfaceKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SurfaceKind::SolventAccessible => "SAS",
+            SurfaceKind::SolventExcluded => "SES",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SurfaceColoring {
+    /// Color of the closest atom's element.
+    #[default]
+    Element,
+    /// Residue type color of the closest atom's residue, as in the
+    /// residue type atom color scheme.
+    ResidueType,
+    /// Coulomb potential of the force field partial charges, red negative
+    /// and blue positive. Atoms without force field parameters use the
+    /// formal charge of their residue's charged groups.
+    Electrostatic,
+}
+
+impl SurfaceColoring {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SurfaceColoring::Element => "Element",
+            SurfaceColoring::ResidueType => "Residue",
+            SurfaceColoring::Electrostatic => "Charge",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            SurfaceColoring::Element => SurfaceColoring::ResidueType,
+            SurfaceColoring::ResidueType => SurfaceColoring::Electrostatic,
+            SurfaceColoring::Electrostatic => SurfaceColoring::Element,
+        }
+    }
+}
+
+/// How new surfaces are colored.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SurfaceSettings {
+    pub coloring: SurfaceColoring,
+}
+
+/// Molecular surface mesh entity, with the area it assigns to each residue.
+#[derive(Component, Debug)]
+pub struct MolecularSurface {
+    pub kind: SurfaceKind,
+    pub coloring: SurfaceColoring,
+    /// Atoms the surface was computed from.
+    pub atoms: Vec<Entity>,
+    /// Total surface area in Å².
+    pub total_area: f32,
+    /// Surface area over the atoms of each residue in Å².
+    pub residue_areas: HashMap<Entity, f32>,
+}
+
+impl MolecularSurface {
+    /// Area of the surface over a residue, zero for buried residues.
+    pub fn residue_area(&self, residue: Entity) -> f32 {
+        self.residue_areas.get(&residue).copied().unwrap_or(0.0)
+    }
+}
+
+/// Message to replace the current surface with a new one of all atoms.
+#[derive(Message, Debug, Clone, Copy)]
+pub struct ComputeSurfaceMessage {
+    pub kind: SurfaceKind,
+}
+
+/// Message to remove the current surface.
+#[derive(Message, Debug, Clone, Copy)]
+pub struct ClearSurfaceMessage;
+
+pub struct SurfacePlugin;
+
+impl Plugin for SurfacePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SurfaceSettings>()
+            .add_message::<ComputeSurfaceMessage>()
+            .add_message::<ClearSurfaceMessage>()
+            .add_systems(
+                Update,
+                (
+                    handle_clear_surface,
+                    handle_compute_surface,
+                    remove_stale_surfaces,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Atoms the surface is computed from, with what the colorings need.
+#[allow(clippy::type_complexity)]
+#[derive(SystemParam)]
+struct SurfaceAtoms<'w, 's> {
+    atom_query: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Atom,
+            &'static GlobalTransform,
+            Option<&'static ChildOf>,
+            Option<&'static AtomParameters>,
+        ),
+    >,
+    amino_acid_query: Query<'w, 's, &'static AminoAcid>,
+}
+
+/// Asset storage used to spawn surface meshes.
+#[derive(SystemParam)]
+struct SurfaceAssets<'w> {
+    meshes: ResMut<'w, Assets<Mesh>>,
+    materials: ResMut<'w, Assets<StandardMaterial>>,
+}
+
+fn handle_clear_surface(
+    mut commands: Commands,
+    mut messages: MessageReader<ClearSurfaceMessage>,
+    surface_query: Query<Entity, With<MolecularSurface>>,
+) {
+    if messages.read().count() == 0 {
+        return;
+    }
+
+    for surface in &surface_query {
+        commands.entity(surface).despawn();
+    }
+}
+
+fn handle_compute_surface(
+    mut commands: Commands,
+    mut messages: MessageReader<ComputeSurfaceMessage>,
+    settings: Res<SurfaceSettings>,
+    atoms: SurfaceAtoms,
+    mut assets: SurfaceAssets,
+    surface_query: Query<Entity, With<MolecularSurface>>,
+) {
+    let Some(message) = messages.read().last().copied() else {
+        return;
+    };
+
+    for surface in &surface_query {
+        commands.entity(surface).despawn();
+    }
+
+    let entities: Vec<Entity> = atoms.atom_query.iter().map(|(entity, ..)| entity).collect();
+    let surface_atoms: Vec<SurfaceAtom> = atoms
+        .atom_query
+        .iter()
+        .map(|(_, atom, transform, ..)| SurfaceAtom {
+            position: transform.translation(),
+            radius: atom.element.van_der_waals_radius(),
+        })
+        .collect();
+    if surface_atoms.is_empty() {
+        warn!("No atoms to compute a surface of");
+        return;
+    }
+
+    let mesh = grid::generate(&surface_atoms, message.kind, PROBE_RADIUS);
+    if mesh.indices.is_empty() {
+        warn!("Failed to generate a {} surface", message.kind.name());
+        return;
+    }
+
+    let mut residue_areas: HashMap<Entity, f32> = HashMap::new();
+    for ((.., child_of, _), &area) in atoms.atom_query.iter().zip(&mesh.atom_areas) {
+        if let Some(child_of) = child_of
+            && atoms.amino_acid_query.contains(child_of.parent())
+        {
+            *residue_areas.entry(child_of.parent()).or_default() += area;
+        }
+    }
+
+    let colors: Vec<[f32; 4]> = match settings.coloring {
+        SurfaceColoring::Electrostatic => {
+            let charges = atom_charges(&atoms);
+            electrostatic_colors(&mesh.positions, &surface_atoms, &charges)
+        }
+        SurfaceColoring::Element | SurfaceColoring::ResidueType => {
+            let atom_colors = atom_colors(&atoms, settings.coloring);
+            mesh.vertex_atoms
+                .iter()
+                .map(|&atom| atom_colors[atom])
+                .collect()
+        }
+    };
+
+    let total_area = mesh.total_area();
+    info!(
+        "{} surface of {} atoms: {:.0} Å²",
+        message.kind.name(),
+        surface_atoms.len(),
+        total_area
+    );
+
+    let render_mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, mesh.positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, mesh.normals)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+    .with_inserted_indices(Indices::U32(mesh.indices));
+
+    commands.spawn((
+        Name::new(format!("{} surface", message.kind.name())),
+        MolecularSurface {
+            kind: message.kind,
+            coloring: settings.coloring,
+            atoms: entities,
+            total_area,
+            residue_areas,
+        },
+        Mesh3d(assets.meshes.add(render_mesh)),
+        MeshMaterial3d(assets.materials.add(StandardMaterial {
+            base_color: Color::WHITE.with_alpha(SURFACE_OPACITY),
+            alpha_mode: AlphaMode::Blend,
+            perceptual_roughness: 0.6,
+            ..default()
+        })),
+        Transform::default(),
+    ));
+}
+
+/// Color of each atom by its element or the type of its residue.
+fn atom_colors(atoms: &SurfaceAtoms, coloring: SurfaceColoring) -> Vec<[f32; 4]> {
+    atoms
+        .atom_query
+        .iter()
+        .map(|(_, atom, _, child_of, _)| {
+            let color = if coloring == SurfaceColoring::ResidueType {
+                child_of
+                    .and_then(|child_of| atoms.amino_acid_query.get(child_of.parent()).ok())
+                    .map_or(NON_RESIDUE_COLOR, |amino_acid| {
+                        residue_type_color(amino_acid.code)
+                    })
+            } else {
+                atom.element.color()
+            };
+            color.to_linear().to_f32_array()
+        })
+        .collect()
+}
+
+/// Partial charge of each atom from its force field parameters, or its
+/// formal charge when the force field could not type it.
+fn atom_charges(atoms: &SurfaceAtoms) -> Vec<f32> {
+    let mut formal = 0;
+    let charges = atoms
+        .atom_query
+        .iter()
+        .map(|(_, atom, _, child_of, parameters)| match parameters {
+            Some(parameters) => parameters.charge,
+            None => {
+                formal += 1;
+                let code = child_of
+                    .and_then(|child_of| atoms.amino_acid_query.get(child_of.parent()).ok())
+                    .map(|amino_acid| amino_acid.code);
+                formal_charge(code, &atom.atom_name)
+            }
+        })
+        .collect();
+    if formal > 0 {
+        info!(
+            "{} atoms have no force field charges; using formal charges for them",
+            formal
+        );
+    }
+    charges
+}
+
+/// Formal charge of an atom in the charged side chains at neutral pH, spread
+/// evenly over the atoms sharing it.
+fn formal_charge(code: Option<AminoAcidCode>, atom_name: &str) -> f32 {
+    match (code, atom_name) {
+        (Some(AminoAcidCode::Asp), "OD1" | "OD2") => -0.5,
+        (Some(AminoAcidCode::Glu), "OE1" | "OE2") => -0.5,
+        (Some(AminoAcidCode::Lys), "NZ") => 1.0,
+        (Some(AminoAcidCode::Arg), "NH1" | "NH2") => 0.5,
+        _ => 0.0,
+    }
+}
+
+/// Colors each vertex by the Coulomb potential of the atom charges with a
+/// distance-dependent dielectric of `4r`, which roughly accounts for solvent
+/// screening without solving the Poisson-Boltzmann equation.
+fn electrostatic_colors(
+    positions: &[Vec3],
+    atoms: &[SurfaceAtom],
+    charges: &[f32],
+) -> Vec<[f32; 4]> {
+    let cells = CellList::new(atoms.iter().map(|atom| atom.position), ELECTROSTATIC_CUTOFF);
+    positions
+        .iter()
+        .map(|&position| {
+            let potential: f32 = cells
+                .near(position)
+                .filter_map(|atom| {
+                    let distance = atoms[atom].position.distance(position).max(1.0);
+                    (distance <= ELECTROSTATIC_CUTOFF)
+                        .then(|| COULOMB_CONSTANT * charges[atom] / (4.0 * distance * distance))
+                })
+                .sum();
+            let t = (potential / POTENTIAL_RANGE).clamp(-1.0, 1.0);
+            let color = if t < 0.0 {
+                Color::WHITE.mix(&Color::srgb(0.9, 0.15, 0.15), -t)
+            } else {
+                Color::WHITE.mix(&Color::srgb(0.15, 0.3, 0.95), t)
+            };
+            color.to_linear().to_f32_array()
+        })
+        .collect()
+}
+
+/// Removes surfaces whose atoms were despawned, e.g. when the project
+/// changes.
+fn remove_stale_surfaces(
+    mut commands: Commands,
+    mut removed_atoms: RemovedComponents<Atom>,
+    surface_query: Query<(Entity, &MolecularSurface)>,
+) {
+    let removed: HashSet<Entity> = removed_atoms.read().collect();
+    if removed.is_empty() {
+        return;
+    }
+
+    for (entity, surface) in &surface_query {
+        if surface.atoms.iter().any(|atom| removed.contains(atom)) {
+            commands.entity(entity).despawn();
+        }
+    }
+}
@@ -4,6 +4,8 @@ use std::collections::HashMap;
 
 use crate::chemistry::amino_acids::types::AminoAcidCode;
 use crate::chemistry::atoms::Element;
+use crate::chemistry::error::ChemistryError;
+use crate::chemistry::internal_coordinates::InternalCoordinate;
 
 /// Configuration for a single atom parsed from JSON.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,44 +38,228 @@ impl AminoAcidsConfig {
     }
 
     pub fn get(&self, code: &AminoAcidCode) -> Option<&AminoAcidConfig> {
-        let code_str = match code {
-            AminoAcidCode::Gly => "Gly",
-            AminoAcidCode::Ala => "Ala",
-            AminoAcidCode::Ser => "Ser",
-            AminoAcidCode::Cys => "Cys",
-            AminoAcidCode::Pro => "Pro",
-            AminoAcidCode::Val => "Val",
-            AminoAcidCode::Ile => "Ile",
-            AminoAcidCode::Leu => "Leu",
-            AminoAcidCode::Met => "Met",
-            AminoAcidCode::Phe => "Phe",
-            AminoAcidCode::Tyr => "Tyr",
-            AminoAcidCode::Trp => "Trp",
-            AminoAcidCode::Asn => "Asn",
-            AminoAcidCode::Gln => "Gln",
-            AminoAcidCode::Thr => "Thr",
-            AminoAcidCode::Asp => "Asp",
-            AminoAcidCode::Glu => "Glu",
-            AminoAcidCode::Lys => "Lys",
-            AminoAcidCode::Arg => "Arg",
-            AminoAcidCode::His => "His",
-        };
-        self.amino_acids.get(code_str)
+        self.amino_acids.get(config_key(code))
     }
 }
 
-impl AtomConfig {
-    pub fn to_element(&self) -> Result<Element, String> {
-        match self.element.as_str() {
-            "H" => Ok(Element::Hydrogen),
-            "C" => Ok(Element::Carbon),
-            "N" => Ok(Element::Nitrogen),
-            "O" => Ok(Element::Oxygen),
-            "S" => Ok(Element::Sulfur),
-            _ => Err(format!("Unknown element: {}", self.element)),
+/// Internal coordinates of a single side-chain atom parsed from JSON.
+///
+/// `references` names three atoms placed earlier; the atom is bonded to the
+/// last one. Angles are in degrees.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InternalCoordinateConfig {
+    pub name: String,
+    pub references: [String; 3],
+    pub bond_length: f32,
+    pub bond_angle: f32,
+    pub torsion: f32,
+}
+
+/// Side-chain geometry of a single amino acid parsed from JSON.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResidueGeometryConfig {
+    pub code: String,
+    pub atoms: Vec<InternalCoordinateConfig>,
+}
+
+/// Top-level configuration containing the side-chain geometry of all amino acids.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResidueGeometriesConfig {
+    pub residues: HashMap<String, ResidueGeometryConfig>,
+}
+
+impl ResidueGeometriesConfig {
+    pub fn load() -> Result<Self, serde_json::Error> {
+        const JSON_DATA: &str = include_str!("residue_geometry.json");
+        serde_json::from_str(JSON_DATA)
+    }
+
+    pub fn get(&self, code: &AminoAcidCode) -> Option<&ResidueGeometryConfig> {
+        self.residues.get(config_key(code))
+    }
+}
+
+/// A named side-chain rotamer parsed from JSON.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RotamerConfig {
+    pub name: String,
+    pub chi: Vec<f32>,
+}
+
+/// Chi angle definitions and rotamers of a single amino acid parsed from JSON.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResidueRotamersConfig {
+    pub code: String,
+    pub chi_angles: Vec<[String; 4]>,
+    pub rotamers: Vec<RotamerConfig>,
+}
+
+/// Top-level configuration containing the rotamer library.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RotamerLibraryConfig {
+    pub residues: HashMap<String, ResidueRotamersConfig>,
+}
+
+impl RotamerLibraryConfig {
+    pub fn load() -> Result<Self, serde_json::Error> {
+        const JSON_DATA: &str = include_str!("rotamers.json");
+        serde_json::from_str(JSON_DATA)
+    }
+
+    pub fn get(&self, code: &AminoAcidCode) -> Option<&ResidueRotamersConfig> {
+        self.residues.get(config_key(code))
+    }
+}
+
+/// A force field atom type parsed from JSON.
+///
+/// `rmin_half` is half the Lennard-Jones minimum distance in Ångström and
+/// `epsilon` the well depth in kcal/mol.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AtomTypeConfig {
+    pub name: String,
+    pub element: String,
+    pub mass: f32,
+    pub rmin_half: f32,
+    pub epsilon: f32,
+    pub description: String,
+}
+
+/// Bond stretch parameters for a pair of atom types parsed from JSON.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BondParameterConfig {
+    pub types: [String; 2],
+    pub k: f32,
+    pub length: f32,
+}
+
+/// Angle bend parameters for a triple of atom types parsed from JSON.
+///
+/// `X` matches any type at either end; the angle is in degrees.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AngleParameterConfig {
+    pub types: [String; 3],
+    pub k: f32,
+    pub angle: f32,
+}
+
+/// A single cosine term of a torsion parsed from JSON.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TorsionTermConfig {
+    pub k: f32,
+    pub periodicity: u32,
+    pub phase: f32,
+}
+
+/// Proper torsion parameters for four atom types parsed from JSON.
+///
+/// `X` matches any type at either end.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TorsionParameterConfig {
+    pub types: [String; 4],
+    pub terms: Vec<TorsionTermConfig>,
+}
+
+/// Improper torsion parameters for a planar centre type parsed from JSON.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImproperParameterConfig {
+    pub center: String,
+    pub k: f32,
+    pub periodicity: u32,
+    pub phase: f32,
+}
+
+/// Partial charge moved across a bond parsed from JSON.
+///
+/// The first type gains `increment` and the second loses it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChargeIncrementConfig {
+    pub types: [String; 2],
+    pub increment: f32,
+}
+
+/// Nonbonded interaction settings parsed from JSON.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NonbondedConfig {
+    pub cutoff: f32,
+    pub switch_distance: f32,
+    pub dielectric: f32,
+    pub scale_14_electrostatic: f32,
+    pub scale_14_van_der_waals: f32,
+}
+
+/// Top-level configuration containing the force field parameters.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForceFieldConfig {
+    pub atom_types: Vec<AtomTypeConfig>,
+    pub bonds: Vec<BondParameterConfig>,
+    pub angles: Vec<AngleParameterConfig>,
+    pub torsions: Vec<TorsionParameterConfig>,
+    pub impropers: Vec<ImproperParameterConfig>,
+    pub bond_charge_increments: Vec<ChargeIncrementConfig>,
+    pub nonbonded: NonbondedConfig,
+}
+
+impl ForceFieldConfig {
+    pub fn load() -> Result<Self, serde_json::Error> {
+        const JSON_DATA: &str = include_str!("force_field.json");
+        serde_json::from_str(JSON_DATA)
+    }
+}
+
+/// Key of an amino acid in the JSON configuration files.
+fn config_key(code: &AminoAcidCode) -> &'static str {
+    match code {
+        AminoAcidCode::Gly => "Gly",
+        AminoAcidCode::Ala => "Ala",
+        AminoAcidCode::Ser => "Ser",
+        AminoAcidCode::Cys => "Cys",
+        AminoAcidCode::Pro => "Pro",
+        AminoAcidCode::Val => "Val",
+        AminoAcidCode::Ile => "Ile",
+        AminoAcidCode::Leu => "Leu",
+        AminoAcidCode::Met => "Met",
+        AminoAcidCode::Phe => "Phe",
+        AminoAcidCode::Tyr => "Tyr",
+        AminoAcidCode::Trp => "Trp",
+        AminoAcidCode::Asn => "Asn",
+        AminoAcidCode::Gln => "Gln",
+        AminoAcidCode::Thr => "Thr",
+        AminoAcidCode::Asp => "Asp",
+        AminoAcidCode::Glu => "Glu",
+        AminoAcidCode::Lys => "Lys",
+        AminoAcidCode::Arg => "Arg",
+        AminoAcidCode::His => "His",
+    }
+}
+
+impl InternalCoordinateConfig {
+    pub fn to_internal_coordinate(&self) -> InternalCoordinate {
+        InternalCoordinate {
+            atom_name: self.name.clone(),
+            references: self.references.clone(),
+            bond_length: self.bond_length,
+            bond_angle: self.bond_angle,
+            torsion: self.torsion,
         }
     }
 }
 
+impl AtomConfig {
+    pub fn to_element(&self) -> Result<Element, ChemistryError> {
+        Element::from_symbol(&self.element)
+            .ok_or_else(|| ChemistryError::UnknownElement(self.element.clone()))
+    }
+}
+
 pub static AMINO_ACIDS_CONFIG: Lazy<Result<AminoAcidsConfig, serde_json::Error>> =
     Lazy::new(AminoAcidsConfig::load);
+
+pub static RESIDUE_GEOMETRY_CONFIG: Lazy<Result<ResidueGeometriesConfig, serde_json::Error>> =
+    Lazy::new(ResidueGeometriesConfig::load);
+
+pub static ROTAMER_LIBRARY_CONFIG: Lazy<Result<RotamerLibraryConfig, serde_json::Error>> =
+    Lazy::new(RotamerLibraryConfig::load);
+
+pub static FORCE_FIELD_CONFIG: Lazy<Result<ForceFieldConfig, serde_json::Error>> =
+    Lazy::new(ForceFieldConfig::load);
@@ -1,8 +1,11 @@
 pub mod chemistry;
 pub mod config;
+pub mod measurements;
 pub(crate) mod objects;
+pub mod physics;
 pub mod prelude;
 pub mod projects;
+pub mod selection;
 pub(crate) mod ui;
 pub(crate) mod user;
 pub mod world;
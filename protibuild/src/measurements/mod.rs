@@ -0,0 +1,441 @@
+//! Distance, angle and dihedral measurements between atoms.
+//!
+//! A measurement is an entity holding the two, three or four atoms it
+//! measures. Its value follows the atoms as they move, and it is despawned
+//! together with them when the project is switched. Measurements are saved
+//! with the project by referencing their atoms through the project objects.
+
+use bevy::prelude::*;
+
+use crate::chemistry::atoms::Atom;
+use crate::chemistry::internal_coordinates::{bond_angle, torsion_angle};
+use crate::projects::{ProjectDocument, ProjectEntities};
+
+/// Color of measurement lines, arcs and labels.
+pub const MEASUREMENT_COLOR: Color = Color::srgb(0.3, 1.0, 0.6);
+
+/// Color of atoms picked for a measurement that is not complete yet.
+const PICKED_ATOM_COLOR: Color = Color::srgb(1.0, 0.6, 0.2);
+
+/// What a measurement measures, given by its number of atoms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeasurementKind {
+    /// Distance between two atoms in Å.
+    Distance,
+    /// Angle `a-b-c` at the middle atom in degrees.
+    Angle,
+    /// Dihedral angle `a-b-c-d` about the `b-c` axis in degrees.
+    Dihedral,
+}
+
+impl MeasurementKind {
+    pub fn from_atom_count(count: usize) -> Option<Self> {
+        match count {
+            2 => Some(MeasurementKind::Distance),
+            3 => Some(MeasurementKind::Angle),
+            4 => Some(MeasurementKind::Dihedral),
+            _ => None,
+        }
+    }
+
+    pub fn atom_count(&self) -> usize {
+        match self {
+            MeasurementKind::Distance => 2,
+            MeasurementKind::Angle => 3,
+            MeasurementKind::Dihedral => 4,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MeasurementKind::Distance => "Distance",
+            MeasurementKind::Angle => "Angle",
+            MeasurementKind::Dihedral => "Dihedral",
+        }
+    }
+
+    pub fn unit(&self) -> &'static str {
+        match self {
+            MeasurementKind::Distance => " Å",
+            MeasurementKind::Angle | MeasurementKind::Dihedral => "°",
+        }
+    }
+
+    /// The kind after this one, cycling back to no tool after dihedrals.
+    pub fn next(kind: Option<Self>) -> Option<Self> {
+        match kind {
+            None => Some(MeasurementKind::Distance),
+            Some(MeasurementKind::Distance) => Some(MeasurementKind::Angle),
+            Some(MeasurementKind::Angle) => Some(MeasurementKind::Dihedral),
+            Some(MeasurementKind::Dihedral) => None,
+        }
+    }
+}
+
+/// A measurement between atom entities, with its current value.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct Measurement {
+    pub atoms: Vec<Entity>,
+    /// Distance in Å or angle in degrees, updated as the atoms move.
+    pub value: f32,
+}
+
+impl Measurement {
+    pub fn kind(&self) -> MeasurementKind {
+        MeasurementKind::from_atom_count(self.atoms.len()).unwrap_or(MeasurementKind::Distance)
+    }
+
+    /// The value measured between the atom positions, in order.
+    pub fn measure(positions: &[Vec3]) -> f32 {
+        match *positions {
+            [a, b] => a.distance(b),
+            [a, b, c] => bond_angle(a, b, c),
+            [a, b, c, d] => torsion_angle(a, b, c, d),
+            _ => 0.0,
+        }
+    }
+
+    /// Value with its unit, e.g. `1.53 Å` or `109.5°`.
+    pub fn display_value(&self) -> String {
+        match self.kind() {
+            MeasurementKind::Distance => format!("{:.2}{}", self.value, self.kind().unit()),
+            kind => format!("{:.1}{}", self.value, kind.unit()),
+        }
+    }
+}
+
+/// An atom addressed by the index of its project object, the index of its
+/// residue among the object's residues and its name.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AtomReference {
+    pub object: usize,
+    pub residue: usize,
+    pub atom_name: String,
+}
+
+/// State of the measurement tool. While a kind is selected, clicking atoms
+/// picks them instead of grabbing objects.
+#[derive(Resource, Debug, Default)]
+pub struct MeasurementTool {
+    pub kind: Option<MeasurementKind>,
+    /// Atoms picked so far for the next measurement.
+    pub picked: Vec<Entity>,
+}
+
+impl MeasurementTool {
+    pub fn is_active(&self) -> bool {
+        self.kind.is_some()
+    }
+}
+
+/// Measurements of a newly spawned project, created once its atoms exist.
+#[derive(Resource, Debug, Default)]
+pub struct PendingMeasurements {
+    pub measurements: Vec<Vec<AtomReference>>,
+}
+
+/// Message to measure between two, three or four atoms.
+#[derive(Message, Debug, Clone)]
+pub struct AddMeasurementMessage {
+    pub atoms: Vec<Entity>,
+}
+
+/// Message to delete a measurement entity.
+#[derive(Message, Debug, Clone)]
+pub struct RemoveMeasurementMessage {
+    pub measurement: Entity,
+}
+
+/// Gizmo group drawing measurements with dashed lines on top of the scene.
+#[derive(Default, Reflect, GizmoConfigGroup)]
+pub struct MeasurementGizmos;
+
+/// Plugin for measuring distances, angles and dihedrals.
+pub struct MeasurementPlugin;
+
+impl Plugin for MeasurementPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MeasurementTool>()
+            .init_resource::<PendingMeasurements>()
+            .add_message::<AddMeasurementMessage>()
+            .add_message::<RemoveMeasurementMessage>()
+            .insert_gizmo_config(
+                MeasurementGizmos,
+                GizmoConfig {
+                    line: GizmoLineConfig {
+                        style: GizmoLineStyle::Dashed {
+                            gap_scale: 3.0,
+                            line_scale: 6.0,
+                        },
+                        ..default()
+                    },
+                    depth_bias: -1.0,
+                    ..default()
+                },
+            )
+            .add_systems(
+                Update,
+                (
+                    resolve_pending_measurements,
+                    handle_add_measurement,
+                    handle_remove_measurement,
+                    update_measurements,
+                )
+                    .chain(),
+            )
+            .add_systems(Last, draw_measurements);
+    }
+}
+
+fn spawn_measurement(commands: &mut Commands, atoms: Vec<Entity>) -> Entity {
+    let kind = MeasurementKind::from_atom_count(atoms.len()).unwrap_or(MeasurementKind::Distance);
+    commands
+        .spawn((Name::new(kind.name()), Measurement { atoms, value: 0.0 }))
+        .id()
+}
+
+/// Creates the measurements of a newly spawned project from their atom
+/// references.
+fn resolve_pending_measurements(
+    mut commands: Commands,
+    mut pending: ResMut<PendingMeasurements>,
+    project_entities: Res<ProjectEntities>,
+    children_query: Query<&Children>,
+    atom_query: Query<&Atom>,
+) {
+    if pending.measurements.is_empty() {
+        return;
+    }
+
+    for references in std::mem::take(&mut pending.measurements) {
+        let atoms: Option<Vec<Entity>> = references
+            .iter()
+            .map(|reference| {
+                let residue = *project_entities
+                    .objects
+                    .get(reference.object)?
+                    .get(reference.residue)?;
+                children_query.get(residue).ok()?.iter().find(|&child| {
+                    atom_query
+                        .get(child)
+                        .is_ok_and(|atom| atom.atom_name == reference.atom_name)
+                })
+            })
+            .collect();
+
+        match atoms {
+            Some(atoms) if MeasurementKind::from_atom_count(atoms.len()).is_some() => {
+                spawn_measurement(&mut commands, atoms);
+            }
+            _ => warn!("Skipping measurement with missing atoms: {:?}", references),
+        }
+    }
+}
+
+fn handle_add_measurement(
+    mut commands: Commands,
+    mut messages: MessageReader<AddMeasurementMessage>,
+    mut document: ResMut<ProjectDocument>,
+) {
+    for message in messages.read() {
+        let Some(kind) = MeasurementKind::from_atom_count(message.atoms.len()) else {
+            warn!(
+                "Measurements need 2 to 4 atoms, got {}",
+                message.atoms.len()
+            );
+            continue;
+        };
+        spawn_measurement(&mut commands, message.atoms.clone());
+        document.dirty = true;
+        info!("Added {} measurement", kind.name().to_lowercase());
+    }
+}
+
+fn handle_remove_measurement(
+    mut commands: Commands,
+    mut messages: MessageReader<RemoveMeasurementMessage>,
+    mut document: ResMut<ProjectDocument>,
+    measurement_query: Query<(), With<Measurement>>,
+) {
+    for message in messages.read() {
+        if measurement_query.contains(message.measurement) {
+            commands.entity(message.measurement).despawn();
+            document.dirty = true;
+        }
+    }
+}
+
+/// Recomputes measurement values from the current atom positions, and
+/// despawns measurements whose atoms are gone.
+fn update_measurements(
+    mut commands: Commands,
+    mut tool: ResMut<MeasurementTool>,
+    mut measurement_query: Query<(Entity, &mut Measurement)>,
+    atom_query: Query<&GlobalTransform, With<Atom>>,
+) {
+    for (entity, mut measurement) in &mut measurement_query {
+        let positions: Option<Vec<Vec3>> = measurement
+            .atoms
+            .iter()
+            .map(|&atom| atom_query.get(atom).ok().map(GlobalTransform::translation))
+            .collect();
+
+        match positions {
+            Some(positions) => {
+                let value = Measurement::measure(&positions);
+                if measurement.value != value {
+                    measurement.value = value;
+                }
+            }
+            None => commands.entity(entity).despawn(),
+        }
+    }
+
+    if tool.picked.iter().any(|&atom| !atom_query.contains(atom)) {
+        tool.picked.clear();
+    }
+}
+
+/// Draws dashed lines between the measured atoms, arcs for angles and
+/// dihedrals, and marks the atoms picked for the next measurement.
+fn draw_measurements(
+    mut gizmos: Gizmos<MeasurementGizmos>,
+    tool: Res<MeasurementTool>,
+    measurement_query: Query<&Measurement>,
+    atom_query: Query<(&Atom, &GlobalTransform)>,
+) {
+    for measurement in &measurement_query {
+        let Some(positions) = measurement
+            .atoms
+            .iter()
+            .map(|&atom| {
+                atom_query
+                    .get(atom)
+                    .ok()
+                    .map(|(_, transform)| transform.translation())
+            })
+            .collect::<Option<Vec<Vec3>>>()
+        else {
+            continue;
+        };
+
+        for pair in positions.windows(2) {
+            gizmos.line(pair[0], pair[1], MEASUREMENT_COLOR);
+        }
+
+        match *positions {
+            [a, b, c] => {
+                let radius = 0.3 * a.distance(b).min(c.distance(b));
+                gizmos.short_arc_3d_between(
+                    b,
+                    b + (a - b).normalize_or_zero() * radius,
+                    b + (c - b).normalize_or_zero() * radius,
+                    MEASUREMENT_COLOR,
+                );
+            }
+            [a, b, c, d] => {
+                let axis = (c - b).normalize_or_zero();
+                let center = (b + c) / 2.0;
+                let radius = 0.4 * b.distance(c);
+                let from = (a - b).reject_from_normalized(axis).normalize_or_zero();
+                let to = (d - c).reject_from_normalized(axis).normalize_or_zero();
+                gizmos.short_arc_3d_between(
+                    center,
+                    center + from * radius,
+                    center + to * radius,
+                    MEASUREMENT_COLOR,
+                );
+            }
+            _ => {}
+        }
+    }
+
+    for &atom in &tool.picked {
+        if let Ok((atom, transform)) = atom_query.get(atom) {
+            gizmos.sphere(
+                Isometry3d::from_translation(transform.translation()),
+                atom.element.covalent_radius(),
+                PICKED_ATOM_COLOR,
+            );
+        }
+    }
+}
+
+/// World position a measurement's label is placed at: the middle of a
+/// distance, the vertex of an angle or the middle of a dihedral's axis.
+pub fn label_anchor(positions: &[Vec3]) -> Option<Vec3> {
+    match *positions {
+        [a, b] => Some((a + b) / 2.0),
+        [_, b, _] => Some(b),
+        [_, b, c, _] => Some((b + c) / 2.0),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two bonds of 1.5 Å at a right angle.
+    const A: Vec3 = Vec3::new(0.0, 1.5, 0.0);
+    const B: Vec3 = Vec3::ZERO;
+    const C: Vec3 = Vec3::new(1.5, 0.0, 0.0);
+
+    #[test]
+    fn distances_are_measured_between_two_atoms() {
+        assert_eq!(Measurement::measure(&[B, C]), 1.5);
+        let diagonal = Measurement::measure(&[Vec3::ZERO, Vec3::new(1.0, 2.0, 2.0)]);
+        assert!((diagonal - 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn angles_are_measured_at_the_middle_atom() {
+        assert!((Measurement::measure(&[A, B, C]) - 90.0).abs() < 1e-4);
+        assert!((Measurement::measure(&[Vec3::NEG_X, B, C]) - 180.0).abs() < 1e-4);
+        let tetrahedral =
+            Measurement::measure(&[Vec3::new(1.0, 1.0, 1.0), B, Vec3::new(1.0, -1.0, -1.0)]);
+        assert!((tetrahedral - 109.471).abs() < 1e-2);
+    }
+
+    #[test]
+    fn dihedrals_are_signed_about_the_middle_bond() {
+        let cis = Vec3::new(1.5, 1.5, 0.0);
+        let trans = Vec3::new(1.5, -1.5, 0.0);
+        let gauche = Vec3::new(1.5, 0.0, 1.5);
+
+        assert!(Measurement::measure(&[A, B, C, cis]).abs() < 1e-4);
+        assert!((Measurement::measure(&[A, B, C, trans]).abs() - 180.0).abs() < 1e-4);
+        let plus = Measurement::measure(&[A, B, C, gauche]);
+        let minus = Measurement::measure(&[A, B, C, gauche * Vec3::new(1.0, 1.0, -1.0)]);
+        assert!((plus.abs() - 90.0).abs() < 1e-4);
+        assert!((plus + minus).abs() < 1e-4);
+    }
+
+    #[test]
+    fn other_atom_counts_measure_nothing() {
+        assert_eq!(Measurement::measure(&[]), 0.0);
+        assert_eq!(Measurement::measure(&[A]), 0.0);
+        assert_eq!(Measurement::measure(&[A, B, C, A, B]), 0.0);
+    }
+
+    #[test]
+    fn values_are_shown_with_their_unit() {
+        let measurement = |atoms: usize, value: f32| Measurement {
+            atoms: vec![Entity::PLACEHOLDER; atoms],
+            value,
+        };
+        assert_eq!(measurement(2, 1.5329).display_value(), "1.53 Å");
+        assert_eq!(measurement(3, 109.47).display_value(), "109.5°");
+        assert_eq!(measurement(4, -60.04).display_value(), "-60.0°");
+    }
+
+    #[test]
+    fn labels_are_anchored_on_the_measured_atoms() {
+        let d = Vec3::new(1.5, 1.5, 0.0);
+        assert_eq!(label_anchor(&[B, C]), Some(Vec3::new(0.75, 0.0, 0.0)));
+        assert_eq!(label_anchor(&[A, B, C]), Some(B));
+        assert_eq!(label_anchor(&[A, B, C, d]), Some(Vec3::new(0.75, 0.0, 0.0)));
+        assert_eq!(label_anchor(&[A]), None);
+        assert_eq!(label_anchor(&[A, B, C, d, A]), None);
+    }
+}
@@ -1,8 +1,10 @@
 use bevy::prelude::*;
 
-use crate::user::interaction::Movable;
+use crate::physics::{BoxCollider, PhysicsBody};
+use crate::user::interaction::{Movable, PickBounds};
 
-/// Marker component for entities that should rotate over time.
+/// Marker component for entities that should rotate over time, unless they
+/// are simulated as rigid bodies.
 #[derive(Component)]
 pub struct Rotatable;
 
@@ -20,7 +22,7 @@ pub fn spawn_dev_cube(
     meshes: &mut ResMut<Assets<Mesh>>,
     materials: &mut ResMut<Assets<StandardMaterial>>,
     position: Vec3,
-    _rotation: Quat,
+    rotation: Quat,
     scale: Vec3,
 ) -> Entity {
     commands
@@ -28,14 +30,21 @@ pub fn spawn_dev_cube(
             Name::new("DevCube"),
             Mesh3d(meshes.add(Cuboid::new(1.0 * scale.x, 1.0 * scale.y, 1.0 * scale.z))),
             MeshMaterial3d(materials.add(Color::srgb(0.5, 0.5, 0.5))),
-            Transform::from_translation(position),
+            Transform::from_translation(position).with_rotation(rotation),
             Rotatable,
             Movable,
+            PickBounds::cuboid(0.5 * scale),
+            BoxCollider {
+                half_extents: 0.5 * scale,
+            },
         ))
         .id()
 }
 
-fn update_rotatable(time: Res<Time>, mut query: Query<&mut Transform, With<Rotatable>>) {
+fn update_rotatable(
+    time: Res<Time>,
+    mut query: Query<&mut Transform, (With<Rotatable>, Without<PhysicsBody>)>,
+) {
     for mut transform in &mut query {
         transform.rotate_y(time.delta_secs() * 1.0);
     }
@@ -2,212 +2,229 @@ use bevy::prelude::*;
 
 use crate::user::camera::CameraTransformData;
 
-pub struct GridPlugin;
+/// Length of the axis lines through the origin.
+const AXIS_LENGTH: f32 = 10000.0;
+
+/// Length of the dashes marking lattice points.
+const DASH_LENGTH: f32 = 1.0;
+
+/// Opacity of grid lines next to the camera.
+const GRID_ALPHA: f32 = 0.6;
+
+const GRID_COLOR: Color = Color::WHITE;
+const X_AXIS_COLOR: Color = Color::srgb(1.0, 0.0, 0.0);
+const Y_AXIS_COLOR: Color = Color::srgb(0.0, 1.0, 0.0);
+const Z_AXIS_COLOR: Color = Color::srgb(0.0, 0.0, 1.0);
+
+/// Grid spacings offered by the grid panel, in Å.
+pub(crate) const GRID_SPACINGS: [f32; 5] = [1.0, 2.0, 5.0, 10.0, 20.0];
+
+/// Grid extents offered by the grid panel, in cells.
+pub(crate) const GRID_EXTENTS: [i32; 3] = [5, 10, 20];
+
+/// Largest extent the lattice is drawn with. A lattice fills a sphere of
+/// `extent` cells around the camera, so its points grow with the cube of the
+/// extent: 12 cells keep it at about 7,000 crosses (22,000 lines) a frame,
+/// where 20 cells would draw over 100,000 lines.
+const MAX_LATTICE_EXTENT: i32 = 12;
+
+/// Layout of the reference grid.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum GridMode {
+    /// Lines on the XZ plane through the origin.
+    #[default]
+    Floor,
+    /// Dashes along all three axes at every lattice point.
+    Lattice,
+}
 
-#[derive(Component)]
-pub(crate) struct GridLine;
+impl GridMode {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            GridMode::Floor => "Floor",
+            GridMode::Lattice => "Lattice",
+        }
+    }
 
-#[derive(Component)]
-pub(crate) struct AxisRay;
+    pub(crate) fn next(&self) -> Self {
+        match self {
+            GridMode::Floor => GridMode::Lattice,
+            GridMode::Lattice => GridMode::Floor,
+        }
+    }
+}
 
+/// Reference grid drawn around the camera, following it in whole cells so it
+/// appears endless.
 #[derive(Resource)]
 pub(crate) struct GridConfig {
-    spacing: f32,
-    range: i32,
-    grid_center: Vec3,
+    pub visible: bool,
+    /// Distance between grid lines in Å.
+    pub spacing: f32,
+    /// Cells drawn on each side of the cell nearest the camera.
+    pub extent: i32,
+    pub mode: GridMode,
+    /// Whether grid lines on the axes are labeled with their coordinate.
+    pub labels: bool,
 }
 
-impl Plugin for GridPlugin {
-    fn build(&self, app: &mut App) {
-        app.add_systems(Startup, Self::setup)
-            .add_systems(Update, Self::update);
+impl Default for GridConfig {
+    fn default() -> Self {
+        Self {
+            visible: true,
+            spacing: 5.0,
+            extent: 10,
+            mode: GridMode::Floor,
+            labels: true,
+        }
     }
 }
 
-impl GridPlugin {
-    fn setup(
-        mut commands: Commands,
-        mut meshes: ResMut<Assets<Mesh>>,
-        mut materials: ResMut<Assets<StandardMaterial>>,
-    ) {
-        let grid_material = materials.add(StandardMaterial {
-            base_color: Color::WHITE,
-            emissive: LinearRgba::WHITE,
-            ..default()
-        });
-
-        let x_axis_material = materials.add(StandardMaterial {
-            base_color: Color::srgb(1.0, 0.0, 0.0),
-            emissive: LinearRgba::new(1.0, 0.0, 0.0, 1.0),
-            ..default()
-        });
-        let y_axis_material = materials.add(StandardMaterial {
-            base_color: Color::srgb(0.0, 1.0, 0.0),
-            emissive: LinearRgba::new(0.0, 1.0, 0.0, 1.0),
-            ..default()
-        });
-        let z_axis_material = materials.add(StandardMaterial {
-            base_color: Color::srgb(0.0, 0.0, 1.0),
-            emissive: LinearRgba::new(0.0, 0.0, 1.0, 1.0),
-            ..default()
-        });
-
-        let thickness = 0.04;
-        let dash_length = 1.0;
-        let spacing = 20.0;
-        let range = 5;
-
-        commands.insert_resource(GridConfig {
-            spacing,
-            range,
-            grid_center: Vec3::ZERO,
-        });
-
-        let ray_length = 10000.0;
-        let axis_thickness = 0.04 + 0.01;
-
-        commands.spawn((
-            Mesh3d(meshes.add(Cuboid::new(ray_length, axis_thickness, axis_thickness))),
-            MeshMaterial3d(x_axis_material),
-            Transform::from_xyz(0.0, 0.0, 0.0),
-            AxisRay,
-        ));
-
-        commands.spawn((
-            Mesh3d(meshes.add(Cuboid::new(axis_thickness, ray_length, axis_thickness))),
-            MeshMaterial3d(y_axis_material),
-            Transform::from_xyz(0.0, 0.0, 0.0),
-            AxisRay,
-        ));
-
-        commands.spawn((
-            Mesh3d(meshes.add(Cuboid::new(axis_thickness, axis_thickness, ray_length))),
-            MeshMaterial3d(z_axis_material),
-            Transform::from_xyz(0.0, 0.0, 0.0),
-            AxisRay,
-        ));
-
-        let mesh_x = meshes.add(Cuboid::new(dash_length, thickness, thickness));
-        let mesh_y = meshes.add(Cuboid::new(thickness, dash_length, thickness));
-        let mesh_z = meshes.add(Cuboid::new(thickness, thickness, dash_length));
-
-        for j in -range..=range {
-            for k in -range..=range {
-                for i in -range..=range {
-                    commands.spawn((
-                        Mesh3d(mesh_x.clone()),
-                        MeshMaterial3d(grid_material.clone()),
-                        Transform::from_xyz(
-                            i as f32 * spacing,
-                            j as f32 * spacing,
-                            k as f32 * spacing,
-                        ),
-                        GridLine,
-                    ));
-                }
-            }
+impl GridConfig {
+    /// Grid point nearest to `position`, around which the grid is drawn.
+    pub(crate) fn center(&self, position: Vec3) -> Vec3 {
+        let center = (position / self.spacing).round() * self.spacing;
+        match self.mode {
+            GridMode::Floor => Vec3::new(center.x, 0.0, center.z),
+            GridMode::Lattice => center,
         }
+    }
 
-        for i in -range..=range {
-            for k in -range..=range {
-                for j in -range..=range {
-                    commands.spawn((
-                        MeshMaterial3d(grid_material.clone()),
-                        Mesh3d(mesh_y.clone()),
-                        Transform::from_xyz(
-                            i as f32 * spacing,
-                            j as f32 * spacing,
-                            k as f32 * spacing,
-                        ),
-                        GridLine,
-                    ));
-                }
-            }
+    /// Cells drawn on each side of the camera: `extent`, capped for the
+    /// lattice.
+    pub(crate) fn drawn_extent(&self) -> i32 {
+        match self.mode {
+            GridMode::Floor => self.extent,
+            GridMode::Lattice => self.extent.min(MAX_LATTICE_EXTENT),
         }
+    }
 
-        for i in -range..=range {
-            for j in -range..=range {
-                for k in -range..=range {
-                    commands.spawn((
-                        MeshMaterial3d(grid_material.clone()),
-                        Mesh3d(mesh_z.clone()),
-                        Transform::from_xyz(
-                            i as f32 * spacing,
-                            j as f32 * spacing,
-                            k as f32 * spacing,
-                        ),
-                        GridLine,
-                    ));
-                }
-            }
-        }
+    /// Distance from the camera at which grid lines have faded out, in Å:
+    /// the edge of the drawn grid, so it ends without a visible border.
+    pub(crate) fn fade_distance(&self) -> f32 {
+        self.drawn_extent() as f32 * self.spacing
     }
 
-    fn update(
-        mut grid_config: ResMut<GridConfig>,
-        camera_data: Res<CameraTransformData>,
-        mut grid_lines: Query<&mut Transform, (With<GridLine>, Without<AxisRay>)>,
-    ) {
-        let camera_pos = camera_data.transform.translation;
-        let spacing = grid_config.spacing;
-
-        let new_center_x = (camera_pos.x / spacing).round() * spacing;
-        let new_center_y = (camera_pos.y / spacing).round() * spacing;
-        let new_center_z = (camera_pos.z / spacing).round() * spacing;
-        let new_center = Vec3::new(new_center_x, new_center_y, new_center_z);
-
-        let threshold = spacing / 2.0;
-        if (new_center - grid_config.grid_center).length() > threshold {
-            grid_config.grid_center = new_center;
-
-            let range = grid_config.range;
-            let mut transforms: Vec<_> = grid_lines.iter_mut().collect();
-            let mut index = 0;
-
-            for j in -range..=range {
-                for k in -range..=range {
-                    for i in -range..=range {
-                        if index < transforms.len() {
-                            transforms[index].translation = Vec3::new(
-                                new_center.x + i as f32 * spacing,
-                                new_center.y + j as f32 * spacing,
-                                new_center.z + k as f32 * spacing,
-                            );
-                        }
-                        index += 1;
+    /// Opacity of the grid at `distance` from the camera.
+    pub(crate) fn fade(&self, distance: f32) -> f32 {
+        (1.0 - distance / self.fade_distance()).clamp(0.0, 1.0)
+    }
+
+    /// Coordinates of the grid lines along one axis, centered on `center`.
+    pub(crate) fn ticks(&self, center: f32) -> impl Iterator<Item = f32> + '_ {
+        let extent = self.drawn_extent();
+        (-extent..=extent).map(move |i| center + i as f32 * self.spacing)
+    }
+
+    /// Lattice points that have not faded out as seen from `camera`.
+    fn lattice_points(&self, camera: Vec3) -> impl Iterator<Item = Vec3> + '_ {
+        let center = self.center(camera);
+        self.ticks(center.x)
+            .flat_map(move |x| self.ticks(center.y).map(move |y| (x, y)))
+            .flat_map(move |(x, y)| self.ticks(center.z).map(move |z| Vec3::new(x, y, z)))
+            .filter(move |point| point.distance(camera) < self.fade_distance())
+    }
+}
+
+pub struct GridPlugin;
+
+impl Plugin for GridPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GridConfig>()
+            .add_systems(Last, Self::draw);
+    }
+}
+
+impl GridPlugin {
+    fn draw(mut gizmos: Gizmos, config: Res<GridConfig>, camera_data: Res<CameraTransformData>) {
+        if !config.visible {
+            return;
+        }
+
+        gizmos.line(
+            Vec3::NEG_X * AXIS_LENGTH,
+            Vec3::X * AXIS_LENGTH,
+            X_AXIS_COLOR,
+        );
+        gizmos.line(
+            Vec3::NEG_Y * AXIS_LENGTH,
+            Vec3::Y * AXIS_LENGTH,
+            Y_AXIS_COLOR,
+        );
+        gizmos.line(
+            Vec3::NEG_Z * AXIS_LENGTH,
+            Vec3::Z * AXIS_LENGTH,
+            Z_AXIS_COLOR,
+        );
+
+        let camera = camera_data.transform.translation;
+        let center = config.center(camera);
+        let color =
+            |point: Vec3| GRID_COLOR.with_alpha(GRID_ALPHA * config.fade(point.distance(camera)));
+
+        match config.mode {
+            GridMode::Floor => {
+                // Each line is drawn cell by cell so it fades along its length.
+                let xs: Vec<f32> = config.ticks(center.x).collect();
+                let zs: Vec<f32> = config.ticks(center.z).collect();
+                for &x in &xs {
+                    for pair in zs.windows(2) {
+                        let (start, end) = (Vec3::new(x, 0.0, pair[0]), Vec3::new(x, 0.0, pair[1]));
+                        gizmos.line_gradient(start, end, color(start), color(end));
                     }
                 }
-            }
-
-            for i in -range..=range {
-                for k in -range..=range {
-                    for j in -range..=range {
-                        if index < transforms.len() {
-                            transforms[index].translation = Vec3::new(
-                                new_center.x + i as f32 * spacing,
-                                new_center.y + j as f32 * spacing,
-                                new_center.z + k as f32 * spacing,
-                            );
-                        }
-                        index += 1;
+                for &z in &zs {
+                    for pair in xs.windows(2) {
+                        let (start, end) = (Vec3::new(pair[0], 0.0, z), Vec3::new(pair[1], 0.0, z));
+                        gizmos.line_gradient(start, end, color(start), color(end));
                     }
                 }
             }
-
-            for i in -range..=range {
-                for j in -range..=range {
-                    for k in -range..=range {
-                        if index < transforms.len() {
-                            transforms[index].translation = Vec3::new(
-                                new_center.x + i as f32 * spacing,
-                                new_center.y + j as f32 * spacing,
-                                new_center.z + k as f32 * spacing,
-                            );
-                        }
-                        index += 1;
+            GridMode::Lattice => {
+                let half_dash = DASH_LENGTH / 2.0;
+                for point in config.lattice_points(camera) {
+                    let color = color(point);
+                    for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+                        gizmos.line(point - axis * half_dash, point + axis * half_dash, color);
                     }
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lattice_extent_is_capped() {
+        let camera = Vec3::new(0.3, -1.2, 2.6);
+        let lattice = |extent: i32| GridConfig {
+            extent,
+            mode: GridMode::Lattice,
+            ..default()
+        };
+
+        let small = lattice(5);
+        assert_eq!(small.drawn_extent(), 5);
+        assert_eq!(small.fade_distance(), 25.0);
+
+        let largest = lattice(GRID_EXTENTS[GRID_EXTENTS.len() - 1]);
+        assert_eq!(largest.drawn_extent(), MAX_LATTICE_EXTENT);
+        let points = largest.lattice_points(camera).count();
+        assert!(3 * points < 25_000, "{} lattice points", points);
+        assert!(
+            largest
+                .lattice_points(camera)
+                .all(|point| point.distance(camera) < largest.fade_distance())
+        );
+
+        // The floor only grows with the square of the extent and is not capped.
+        let floor = GridConfig {
+            mode: GridMode::Floor,
+            ..largest
+        };
+        assert_eq!(floor.drawn_extent(), 20);
+    }
+}
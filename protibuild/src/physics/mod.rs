@@ -0,0 +1,423 @@
+//! Rigid-body physics of residues and scene objects.
+//!
+//! When the active project has physics enabled, every `AminoAcid` parent and
+//! every `Movable` object becomes a rapier rigid body. Residues collide through
+//! one ball per atom with the atom's covalent radius. Residues joined by a bond
+//! are held together by a ball joint at the bond's midpoint and do not collide
+//! with each other, since bonded atoms are closer than their radii. A held
+//! object is moved kinematically so it pushes the others around, and keeps its
+//! momentum when it is dropped.
+
+use std::collections::{HashMap, HashSet};
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use rapier3d::prelude::{
+    CCDSolver, ColliderBuilder, ColliderSet, DefaultBroadPhase, ImpulseJointHandle,
+    ImpulseJointSet, IntegrationParameters, IslandManager, MultibodyJointSet, NarrowPhase,
+    PhysicsPipeline, Pose, RigidBodyBuilder, RigidBodyHandle, RigidBodySet, RigidBodyType,
+    SphericalJointBuilder,
+};
+
+use crate::chemistry::amino_acids::AminoAcid;
+use crate::chemistry::atoms::{Atom, Bond};
+use crate::chemistry::dynamics::DynamicsState;
+use crate::chemistry::minimization::MinimizationReport;
+use crate::projects::{ProjectDocument, ProjectResource};
+use crate::user::interaction::{HeldMovable, Movable};
+
+/// Simulation parameters shared by every body.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct PhysicsSettings {
+    /// Zero by default, so residues float in place instead of falling.
+    pub gravity: Vec3,
+    /// Damping slowing down thrown objects, per second.
+    pub linear_damping: f32,
+    pub angular_damping: f32,
+    pub density: f32,
+    pub friction: f32,
+    pub restitution: f32,
+}
+
+impl Default for PhysicsSettings {
+    fn default() -> Self {
+        Self {
+            gravity: Vec3::ZERO,
+            linear_damping: 0.5,
+            angular_damping: 1.0,
+            density: 1.0,
+            friction: 0.5,
+            restitution: 0.2,
+        }
+    }
+}
+
+/// Marker for entities simulated as rigid bodies.
+#[derive(Component)]
+pub struct PhysicsBody;
+
+/// Box-shaped collider for objects without atoms, with its half extents.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct BoxCollider {
+    pub half_extents: Vec3,
+}
+
+/// Message to switch physics on or off for the active project.
+#[derive(Message, Debug, Clone)]
+pub struct TogglePhysicsMessage;
+
+/// The rapier simulation state.
+#[derive(Resource, Default)]
+struct PhysicsWorld {
+    pipeline: PhysicsPipeline,
+    integration_parameters: IntegrationParameters,
+    islands: IslandManager,
+    broad_phase: DefaultBroadPhase,
+    narrow_phase: NarrowPhase,
+    bodies: RigidBodySet,
+    colliders: ColliderSet,
+    impulse_joints: ImpulseJointSet,
+    multibody_joints: MultibodyJointSet,
+    ccd_solver: CCDSolver,
+    entities: HashMap<Entity, RigidBodyHandle>,
+    /// Joints between bodies, by the bond entity they stand for.
+    joints: HashMap<Entity, ImpulseJointHandle>,
+}
+
+impl PhysicsWorld {
+    fn remove(&mut self, entity: Entity) {
+        if let Some(handle) = self.entities.remove(&entity) {
+            self.bodies.remove(
+                handle,
+                &mut self.islands,
+                &mut self.colliders,
+                &mut self.impulse_joints,
+                &mut self.multibody_joints,
+                true,
+            );
+            // Removing a body removes its joints too.
+            let impulse_joints = &self.impulse_joints;
+            self.joints
+                .retain(|_, joint| impulse_joints.get(*joint).is_some());
+        }
+    }
+
+    /// Replaces the colliders attached to a body.
+    fn set_colliders(
+        &mut self,
+        handle: RigidBodyHandle,
+        shape: ColliderShape,
+        settings: &PhysicsSettings,
+    ) {
+        let attached = self
+            .bodies
+            .get(handle)
+            .map(|body| body.colliders().to_vec())
+            .unwrap_or_default();
+        for collider in attached {
+            self.colliders
+                .remove(collider, &mut self.islands, &mut self.bodies, true);
+        }
+
+        let builders = match shape {
+            ColliderShape::Box(half_extents) => {
+                vec![ColliderBuilder::cuboid(
+                    half_extents.x,
+                    half_extents.y,
+                    half_extents.z,
+                )]
+            }
+            ColliderShape::Atoms(atoms) => atoms
+                .into_iter()
+                .map(|(center, radius)| ColliderBuilder::ball(radius).translation(center))
+                .collect(),
+        };
+        for builder in builders {
+            let collider = builder
+                .density(settings.density)
+                .friction(settings.friction)
+                .restitution(settings.restitution);
+            self.colliders
+                .insert_with_parent(collider, handle, &mut self.bodies);
+        }
+    }
+}
+
+enum ColliderShape {
+    Box(Vec3),
+    /// Local centre and radius of every atom.
+    Atoms(Vec<(Vec3, f32)>),
+}
+
+pub struct PhysicsPlugin;
+
+impl Plugin for PhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PhysicsSettings>()
+            .init_resource::<PhysicsWorld>()
+            .add_message::<TogglePhysicsMessage>()
+            .add_systems(
+                Update,
+                (
+                    handle_toggle_physics,
+                    remove_bodies,
+                    (update_colliders, create_bodies, create_joints).run_if(physics_enabled),
+                )
+                    .chain(),
+            )
+            .add_systems(FixedUpdate, step_physics.run_if(physics_enabled));
+    }
+}
+
+fn physics_enabled(project_res: Res<ProjectResource>) -> bool {
+    project_res.project.physics
+}
+
+fn handle_toggle_physics(
+    mut messages: MessageReader<TogglePhysicsMessage>,
+    mut project_res: ResMut<ProjectResource>,
+    mut document: ResMut<ProjectDocument>,
+) {
+    for _ in messages.read() {
+        project_res.project.physics = !project_res.project.physics;
+        document.dirty = true;
+        info!(
+            "Physics {}",
+            if project_res.project.physics {
+                "enabled"
+            } else {
+                "disabled"
+            }
+        );
+    }
+}
+
+/// Removes the bodies of despawned objects and the joints of despawned bonds,
+/// or every body once physics is switched off.
+fn remove_bodies(
+    mut commands: Commands,
+    mut world: ResMut<PhysicsWorld>,
+    project_res: Res<ProjectResource>,
+    mut removed: RemovedComponents<PhysicsBody>,
+    mut removed_bonds: RemovedComponents<Bond>,
+    body_query: Query<Entity, With<PhysicsBody>>,
+) {
+    for entity in removed.read() {
+        world.remove(entity);
+    }
+    for entity in removed_bonds.read() {
+        if let Some(joint) = world.joints.remove(&entity) {
+            world.impulse_joints.remove(joint, true);
+        }
+    }
+
+    if !project_res.project.physics {
+        for entity in &body_query {
+            world.remove(entity);
+            commands.entity(entity).remove::<PhysicsBody>();
+        }
+    }
+}
+
+/// Simulations that move atoms on every frame.
+#[derive(SystemParam)]
+struct AtomSimulations<'w> {
+    dynamics: Res<'w, DynamicsState>,
+    minimization: Res<'w, MinimizationReport>,
+}
+
+impl AtomSimulations<'_> {
+    fn running(&self) -> bool {
+        self.dynamics.running || self.minimization.running
+    }
+}
+
+/// Rebuilds the colliders of residues whose atoms moved. Running simulations
+/// and minimizations move atoms on every frame, so their residues are only
+/// rebuilt once they are paused or finished.
+fn update_colliders(
+    mut moved: Local<HashSet<Entity>>,
+    mut world: ResMut<PhysicsWorld>,
+    settings: Res<PhysicsSettings>,
+    simulations: AtomSimulations,
+    changed_atoms: Query<&ChildOf, Changed<Atom>>,
+    children_query: Query<&Children>,
+    atom_query: Query<&Atom>,
+) {
+    moved.extend(changed_atoms.iter().map(ChildOf::parent));
+    if simulations.running() {
+        return;
+    }
+
+    for parent in moved.drain() {
+        if let Some(&handle) = world.entities.get(&parent)
+            && let Ok(children) = children_query.get(parent)
+        {
+            world.set_colliders(handle, atom_shape(children, &atom_query), &settings);
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn create_bodies(
+    mut commands: Commands,
+    mut world: ResMut<PhysicsWorld>,
+    settings: Res<PhysicsSettings>,
+    new_query: Query<
+        (Entity, &Transform, Option<&Children>, Option<&BoxCollider>),
+        (Or<(With<AminoAcid>, With<Movable>)>, Without<PhysicsBody>),
+    >,
+    atom_query: Query<&Atom>,
+) {
+    for (entity, transform, children, box_collider) in &new_query {
+        let shape = match (box_collider, children) {
+            (Some(box_collider), _) => ColliderShape::Box(box_collider.half_extents),
+            (None, Some(children)) => atom_shape(children, &atom_query),
+            (None, None) => continue,
+        };
+
+        let body = RigidBodyBuilder::dynamic()
+            .pose(Pose::from_parts(transform.translation, transform.rotation))
+            .linear_damping(settings.linear_damping)
+            .angular_damping(settings.angular_damping)
+            .build();
+        let handle = world.bodies.insert(body);
+        world.set_colliders(handle, shape, &settings);
+        world.entities.insert(entity, handle);
+        commands.entity(entity).insert(PhysicsBody);
+    }
+}
+
+/// Joins the bodies of bonded residues once new bodies exist, with a ball
+/// joint at the bond's midpoint that also disables contacts between them.
+fn create_joints(
+    mut world: ResMut<PhysicsWorld>,
+    new_bodies: Query<(), Added<PhysicsBody>>,
+    bond_query: Query<(Entity, &Bond)>,
+    atom_query: Query<(&Transform, &ChildOf), With<Atom>>,
+    body_query: Query<&Transform, With<PhysicsBody>>,
+) {
+    if new_bodies.is_empty() {
+        return;
+    }
+
+    let world = &mut *world;
+    for (entity, bond) in &bond_query {
+        if world.joints.contains_key(&entity) {
+            continue;
+        }
+        let (Ok((transform1, child_of1)), Ok((transform2, child_of2))) =
+            (atom_query.get(bond.atom1), atom_query.get(bond.atom2))
+        else {
+            continue;
+        };
+        let (parent1, parent2) = (child_of1.parent(), child_of2.parent());
+        if parent1 == parent2 {
+            continue;
+        }
+        let (Some(&body1), Some(&body2), Ok(residue1), Ok(residue2)) = (
+            world.entities.get(&parent1),
+            world.entities.get(&parent2),
+            body_query.get(parent1),
+            body_query.get(parent2),
+        ) else {
+            continue;
+        };
+
+        let midpoint = (residue1.transform_point(transform1.translation)
+            + residue2.transform_point(transform2.translation))
+            / 2.0;
+        let local = |residue: &Transform| {
+            residue
+                .compute_affine()
+                .inverse()
+                .transform_point3(midpoint)
+        };
+        let joint = SphericalJointBuilder::new()
+            .local_anchor1(local(residue1))
+            .local_anchor2(local(residue2))
+            .contacts_enabled(false);
+        let handle = world.impulse_joints.insert(body1, body2, joint, true);
+        world.joints.insert(entity, handle);
+    }
+}
+
+fn atom_shape(children: &Children, atom_query: &Query<&Atom>) -> ColliderShape {
+    ColliderShape::Atoms(
+        children
+            .iter()
+            .filter_map(|child| atom_query.get(child).ok())
+            .map(|atom| (atom.position, atom.element.covalent_radius()))
+            .collect(),
+    )
+}
+
+/// Moves held bodies to their target and free bodies whose transform was
+/// changed from outside, e.g. by undo, steps the simulation and writes the
+/// poses of the free bodies back to their transforms.
+fn step_physics(
+    time: Res<Time>,
+    settings: Res<PhysicsSettings>,
+    mut world: ResMut<PhysicsWorld>,
+    mut transform_query: Query<(&mut Transform, Has<HeldMovable>), With<PhysicsBody>>,
+) {
+    let world = &mut *world;
+
+    for (&entity, &handle) in &world.entities {
+        let Ok((transform, held)) = transform_query.get_mut(entity) else {
+            continue;
+        };
+        let Some(body) = world.bodies.get_mut(handle) else {
+            continue;
+        };
+
+        if held {
+            body.set_body_type(RigidBodyType::KinematicPositionBased, true);
+            body.set_next_kinematic_position(Pose::from_parts(
+                transform.translation,
+                transform.rotation,
+            ));
+        } else {
+            if body.body_type() != RigidBodyType::Dynamic {
+                // The velocity of the kinematic motion is kept, so dropped
+                // objects fly on.
+                body.set_body_type(RigidBodyType::Dynamic, true);
+            }
+            // Poses written back below are not seen as changed on the next
+            // run, so this only picks up other systems' edits.
+            if transform.is_changed() {
+                body.set_position(
+                    Pose::from_parts(transform.translation, transform.rotation),
+                    true,
+                );
+            }
+        }
+    }
+
+    world.integration_parameters.dt = time.delta_secs();
+    world.pipeline.step(
+        settings.gravity,
+        &world.integration_parameters,
+        &mut world.islands,
+        &mut world.broad_phase,
+        &mut world.narrow_phase,
+        &mut world.bodies,
+        &mut world.colliders,
+        &mut world.impulse_joints,
+        &mut world.multibody_joints,
+        &mut world.ccd_solver,
+        &(),
+        &(),
+    );
+
+    for (&entity, &handle) in &world.entities {
+        if let Ok((mut transform, false)) = transform_query.get_mut(entity)
+            && let Some(body) = world.bodies.get(handle)
+            && !body.is_sleeping()
+        {
+            let pose = body.position();
+            transform.translation = pose.translation;
+            transform.rotation = pose.rotation;
+        }
+    }
+}
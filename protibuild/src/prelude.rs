@@ -1,7 +1,16 @@
-pub use crate::chemistry::amino_acids::{AminoAcid, AminoAcidBuilder, AminoAcidCode};
+pub use crate::chemistry::amino_acids::{
+    AminoAcid, AminoAcidBuilder, AminoAcidCode, BackboneDihedrals, Conformation, PeptideBuilder,
+};
 pub use crate::chemistry::atoms::{Atom, Bond, Element};
-pub use crate::projects::templates::ProjectTemplates;
-pub use crate::projects::{Project, ProjectEntities, ProjectResource, SwitchProjectMessage};
+pub use crate::chemistry::error::ChemistryError;
+pub use crate::chemistry::force_field::{ComputeEnergyMessage, EnergyTerms, ForceField, Topology};
+pub use crate::chemistry::rendering::{ColorScheme, Representation};
+pub use crate::projects::templates::{ProjectTemplates, Template, TemplateRegistry};
+pub use crate::projects::{
+    ExportProjectMessage, LoadProjectMessage, Project, ProjectEntities, ProjectResource,
+    SaveProjectMessage, SetColorSchemeMessage, SetRepresentationMessage, SwitchProjectMessage,
+};
+pub use crate::selection::{SelectMessage, Selection, SelectionMode};
 pub use crate::ui::tab_bar::{ActiveTab, TabBarPlugin, TabType};
 
 pub use bevy::prelude::*;
@@ -0,0 +1,49 @@
+use std::fmt;
+
+use crate::chemistry::error::ChemistryError;
+
+/// Errors produced while saving or loading project files.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProjectError {
+    /// The project file could not be read or written.
+    Io(String),
+    /// The project file is not valid JSON or does not match the format.
+    Format(String),
+    /// The project file was written by a newer version of Protibuild.
+    UnsupportedVersion(u32),
+    /// An object in the project file references an unknown residue or element.
+    Chemistry(ChemistryError),
+}
+
+impl fmt::Display for ProjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProjectError::Io(message) => write!(f, "I/O error: {}", message),
+            ProjectError::Format(message) => write!(f, "Invalid project file: {}", message),
+            ProjectError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported project file version {}", version)
+            }
+            ProjectError::Chemistry(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for ProjectError {}
+
+impl From<std::io::Error> for ProjectError {
+    fn from(error: std::io::Error) -> Self {
+        ProjectError::Io(error.to_string())
+    }
+}
+
+impl From<serde_json::Error> for ProjectError {
+    fn from(error: serde_json::Error) -> Self {
+        ProjectError::Format(error.to_string())
+    }
+}
+
+impl From<ChemistryError> for ProjectError {
+    fn from(error: ChemistryError) -> Self {
+        ProjectError::Chemistry(error)
+    }
+}
@@ -0,0 +1,862 @@
+//! Versioned JSON project files.
+//!
+//! The file format is kept separate from [`Project`] so the in-memory types can
+//! change without breaking saved projects. Every file carries a `version`;
+//! files written by older versions are upgraded step by step by [`MIGRATIONS`]
+//! before they are deserialized.
+//!
+//! To change the format, bump [`PROJECT_FILE_VERSION`] and append a migration
+//! that rewrites the JSON of the previous version. Adding a new object type
+//! needs no migration, since older files simply do not contain it.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::error::ProjectError;
+use super::history::{Change, RootReference, SceneEdit};
+use super::{Project, objects::ProjectObject};
+use crate::chemistry::amino_acids::{AminoAcidCode, BackboneDihedrals, Conformation};
+use crate::chemistry::atoms::Element;
+use crate::chemistry::error::ChemistryError;
+use crate::chemistry::io::{Structure, StructureAtom, StructureResidue};
+use crate::chemistry::rendering::{ColorScheme, Representation};
+use crate::measurements::AtomReference;
+
+/// Version written to new project files.
+pub const PROJECT_FILE_VERSION: u32 = 1;
+
+/// File extension of project files.
+pub const PROJECT_FILE_EXTENSION: &str = "json";
+
+/// `MIGRATIONS[n - 1]` upgrades the JSON of a version `n` file to version
+/// `n + 1`.
+const MIGRATIONS: [fn(&mut Value); PROJECT_FILE_VERSION as usize - 1] = [];
+
+/// Reads a project file from disk, upgrading older versions.
+pub fn load(path: impl AsRef<Path>) -> Result<Project, ProjectError> {
+    let contents = std::fs::read_to_string(path)?;
+    parse(&contents)
+}
+
+/// Writes a project file to disk in the current version.
+pub fn save(path: impl AsRef<Path>, project: &Project) -> Result<(), ProjectError> {
+    std::fs::write(path, write(project)?)?;
+    Ok(())
+}
+
+/// Formats a project as pretty-printed JSON.
+pub fn write(project: &Project) -> Result<String, ProjectError> {
+    Ok(serde_json::to_string_pretty(&SavedProject::from(project))?)
+}
+
+/// Parses a project file of any supported version.
+pub fn parse(contents: &str) -> Result<Project, ProjectError> {
+    let mut value: Value = serde_json::from_str(contents)?;
+    migrate(&mut value)?;
+    serde_json::from_value::<SavedProject>(value)?.try_into()
+}
+
+fn migrate(value: &mut Value) -> Result<(), ProjectError> {
+    let version = value
+        .get("version")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| ProjectError::Format("missing version".to_string()))?;
+    let version = u32::try_from(version).map_err(|_| ProjectError::UnsupportedVersion(u32::MAX))?;
+    if version == 0 || version > PROJECT_FILE_VERSION {
+        return Err(ProjectError::UnsupportedVersion(version));
+    }
+
+    for migration in &MIGRATIONS[version as usize - 1..] {
+        migration(value);
+    }
+    value["version"] = PROJECT_FILE_VERSION.into();
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedProject {
+    version: u32,
+    name: String,
+    #[serde(default)]
+    description: String,
+    camera_position: [f32; 3],
+    camera_look_at: [f32; 3],
+    #[serde(default)]
+    physics: bool,
+    objects: Vec<SavedObject>,
+    #[serde(default)]
+    measurements: Vec<Vec<SavedAtomReference>>,
+    #[serde(default)]
+    save_history: bool,
+    #[serde(default)]
+    history: Vec<SavedSceneEdit>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+enum SavedObject {
+    DevCube {
+        position: [f32; 3],
+        rotation: [f32; 4],
+        scale: [f32; 3],
+    },
+    AminoAcid {
+        code: String,
+        position: [f32; 3],
+        residue_number: i32,
+        #[serde(default)]
+        representation: SavedRepresentation,
+        #[serde(default)]
+        color_scheme: SavedColorScheme,
+    },
+    Peptide {
+        sequence: String,
+        position: [f32; 3],
+        chain_id: String,
+        conformation: SavedConformation,
+        #[serde(default)]
+        representation: SavedRepresentation,
+        #[serde(default)]
+        color_scheme: SavedColorScheme,
+    },
+    StructureFile {
+        path: PathBuf,
+        position: [f32; 3],
+        #[serde(default)]
+        representation: SavedRepresentation,
+        #[serde(default)]
+        color_scheme: SavedColorScheme,
+    },
+    Structure {
+        residues: Vec<SavedResidue>,
+        bonds: Vec<[u32; 2]>,
+        #[serde(default)]
+        representation: SavedRepresentation,
+        #[serde(default)]
+        color_scheme: SavedColorScheme,
+    },
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy)]
+enum SavedRepresentation {
+    #[default]
+    BallAndStick,
+    SpaceFilling,
+    Licorice,
+    Wireframe,
+    Cartoon,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy)]
+enum SavedColorScheme {
+    #[default]
+    Element,
+    Chain,
+    ResidueType,
+    Hydrophobicity,
+    Rainbow,
+    SecondaryStructure,
+    BFactor,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+enum SavedConformation {
+    Extended,
+    AlphaHelix,
+    BetaStrand,
+    PolyprolineII,
+    Custom { dihedrals: Vec<SavedDihedrals> },
+}
+
+/// Backbone dihedrals in degrees.
+#[derive(Serialize, Deserialize)]
+struct SavedDihedrals {
+    phi: f32,
+    psi: f32,
+    omega: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedResidue {
+    code: String,
+    residue_number: i32,
+    chain_id: String,
+    atoms: Vec<SavedAtom>,
+}
+
+/// Atom of a measurement, addressed by object index, residue index within
+/// the object and atom name.
+#[derive(Serialize, Deserialize)]
+struct SavedAtomReference {
+    object: usize,
+    residue: usize,
+    atom: String,
+}
+
+/// Undo step of the saved history. Changes are sorted so saving the same
+/// history twice writes the same file.
+#[derive(Serialize, Deserialize)]
+struct SavedSceneEdit {
+    #[serde(default)]
+    transforms: Vec<SavedResidueChange<SavedTransform>>,
+    /// World positions of atoms.
+    #[serde(default)]
+    atoms: Vec<SavedAtomChange>,
+    #[serde(default)]
+    representations: Vec<SavedResidueChange<SavedRepresentation>>,
+    #[serde(default)]
+    color_schemes: Vec<SavedResidueChange<SavedColorScheme>>,
+    #[serde(default)]
+    measurements: Option<SavedMeasurementChange>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedResidueChange<T> {
+    object: usize,
+    residue: usize,
+    before: T,
+    after: T,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedAtomChange {
+    #[serde(flatten)]
+    atom: SavedAtomReference,
+    before: [f32; 3],
+    after: [f32; 3],
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedMeasurementChange {
+    before: Vec<Vec<SavedAtomReference>>,
+    after: Vec<Vec<SavedAtomReference>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+struct SavedTransform {
+    position: [f32; 3],
+    rotation: [f32; 4],
+    scale: [f32; 3],
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedAtom {
+    serial: u32,
+    name: String,
+    element: String,
+    position: [f32; 3],
+    #[serde(default)]
+    b_factor: f32,
+}
+
+impl From<&Project> for SavedProject {
+    fn from(project: &Project) -> Self {
+        Self {
+            version: PROJECT_FILE_VERSION,
+            name: project.name.clone(),
+            description: project.description.clone(),
+            camera_position: project.camera_position.to_array(),
+            camera_look_at: project.camera_look_at.to_array(),
+            physics: project.physics,
+            objects: project.objects.iter().map(SavedObject::from).collect(),
+            measurements: save_measurements(&project.measurements),
+            save_history: project.save_history,
+            history: project.history.iter().map(SavedSceneEdit::from).collect(),
+        }
+    }
+}
+
+impl TryFrom<SavedProject> for Project {
+    type Error = ProjectError;
+
+    fn try_from(saved: SavedProject) -> Result<Self, Self::Error> {
+        let objects = saved
+            .objects
+            .into_iter()
+            .map(ProjectObject::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut project = Project::new(saved.name, saved.description)
+            .with_objects(objects)
+            .with_camera(
+                Vec3::from_array(saved.camera_position),
+                Vec3::from_array(saved.camera_look_at),
+            )
+            .with_physics(saved.physics)
+            .with_measurements(load_measurements(saved.measurements));
+        if saved.save_history {
+            project = project.with_history(saved.history.into_iter().map(SceneEdit::from));
+        }
+        Ok(project)
+    }
+}
+
+fn save_measurements(measurements: &[Vec<AtomReference>]) -> Vec<Vec<SavedAtomReference>> {
+    measurements
+        .iter()
+        .map(|atoms| atoms.iter().map(SavedAtomReference::from).collect())
+        .collect()
+}
+
+fn load_measurements(measurements: Vec<Vec<SavedAtomReference>>) -> Vec<Vec<AtomReference>> {
+    measurements
+        .into_iter()
+        .map(|atoms| atoms.into_iter().map(AtomReference::from).collect())
+        .collect()
+}
+
+impl From<&AtomReference> for SavedAtomReference {
+    fn from(reference: &AtomReference) -> Self {
+        Self {
+            object: reference.object,
+            residue: reference.residue,
+            atom: reference.atom_name.clone(),
+        }
+    }
+}
+
+impl From<SavedAtomReference> for AtomReference {
+    fn from(saved: SavedAtomReference) -> Self {
+        Self {
+            object: saved.object,
+            residue: saved.residue,
+            atom_name: saved.atom,
+        }
+    }
+}
+
+impl From<&SceneEdit> for SavedSceneEdit {
+    fn from(edit: &SceneEdit) -> Self {
+        let mut atoms: Vec<SavedAtomChange> = edit
+            .atoms
+            .iter()
+            .map(|(reference, change)| SavedAtomChange {
+                atom: reference.into(),
+                before: change.before.to_array(),
+                after: change.after.to_array(),
+            })
+            .collect();
+        atoms.sort_by(|a, b| {
+            (a.atom.object, a.atom.residue, &a.atom.atom).cmp(&(
+                b.atom.object,
+                b.atom.residue,
+                &b.atom.atom,
+            ))
+        });
+
+        Self {
+            transforms: save_residue_changes(&edit.transforms, |&transform| {
+                SavedTransform::from(transform)
+            }),
+            atoms,
+            representations: save_residue_changes(&edit.representations, |&representation| {
+                SavedRepresentation::from(representation)
+            }),
+            color_schemes: save_residue_changes(&edit.color_schemes, |&color_scheme| {
+                SavedColorScheme::from(color_scheme)
+            }),
+            measurements: edit
+                .measurements
+                .as_ref()
+                .map(|change| SavedMeasurementChange {
+                    before: save_measurements(&change.before),
+                    after: save_measurements(&change.after),
+                }),
+        }
+    }
+}
+
+impl From<SavedSceneEdit> for SceneEdit {
+    fn from(saved: SavedSceneEdit) -> Self {
+        Self {
+            transforms: load_residue_changes(saved.transforms, Transform::from),
+            atoms: saved
+                .atoms
+                .into_iter()
+                .map(|change| {
+                    (
+                        AtomReference::from(change.atom),
+                        Change {
+                            before: Vec3::from_array(change.before),
+                            after: Vec3::from_array(change.after),
+                        },
+                    )
+                })
+                .collect(),
+            representations: load_residue_changes(saved.representations, Representation::from),
+            color_schemes: load_residue_changes(saved.color_schemes, ColorScheme::from),
+            measurements: saved.measurements.map(|change| Change {
+                before: load_measurements(change.before),
+                after: load_measurements(change.after),
+            }),
+        }
+    }
+}
+
+fn save_residue_changes<T, S>(
+    changes: &HashMap<RootReference, Change<T>>,
+    save: impl Fn(&T) -> S,
+) -> Vec<SavedResidueChange<S>> {
+    let mut saved: Vec<(&RootReference, &Change<T>)> = changes.iter().collect();
+    saved.sort_by_key(|&(root, _)| *root);
+    saved
+        .into_iter()
+        .map(|(root, change)| SavedResidueChange {
+            object: root.object,
+            residue: root.residue,
+            before: save(&change.before),
+            after: save(&change.after),
+        })
+        .collect()
+}
+
+fn load_residue_changes<S, T>(
+    saved: Vec<SavedResidueChange<S>>,
+    load: impl Fn(S) -> T,
+) -> HashMap<RootReference, Change<T>> {
+    saved
+        .into_iter()
+        .map(|change| {
+            (
+                RootReference {
+                    object: change.object,
+                    residue: change.residue,
+                },
+                Change {
+                    before: load(change.before),
+                    after: load(change.after),
+                },
+            )
+        })
+        .collect()
+}
+
+impl From<Transform> for SavedTransform {
+    fn from(transform: Transform) -> Self {
+        Self {
+            position: transform.translation.to_array(),
+            rotation: transform.rotation.to_array(),
+            scale: transform.scale.to_array(),
+        }
+    }
+}
+
+impl From<SavedTransform> for Transform {
+    fn from(saved: SavedTransform) -> Self {
+        Transform {
+            translation: Vec3::from_array(saved.position),
+            rotation: Quat::from_array(saved.rotation),
+            scale: Vec3::from_array(saved.scale),
+        }
+    }
+}
+
+impl From<&ProjectObject> for SavedObject {
+    fn from(object: &ProjectObject) -> Self {
+        match object {
+            ProjectObject::DevCube {
+                position,
+                rotation,
+                scale,
+            } => SavedObject::DevCube {
+                position: position.to_array(),
+                rotation: rotation.to_array(),
+                scale: scale.to_array(),
+            },
+            ProjectObject::AminoAcid {
+                code,
+                position,
+                residue_number,
+                representation,
+                color_scheme,
+            } => SavedObject::AminoAcid {
+                code: code.three_letter().to_string(),
+                position: position.to_array(),
+                residue_number: *residue_number,
+                representation: (*representation).into(),
+                color_scheme: (*color_scheme).into(),
+            },
+            ProjectObject::Peptide {
+                sequence,
+                position,
+                chain_id,
+                conformation,
+                representation,
+                color_scheme,
+            } => SavedObject::Peptide {
+                sequence: sequence.clone(),
+                position: position.to_array(),
+                chain_id: chain_id.clone(),
+                conformation: SavedConformation::from(conformation),
+                representation: (*representation).into(),
+                color_scheme: (*color_scheme).into(),
+            },
+            ProjectObject::StructureFile {
+                path,
+                position,
+                representation,
+                color_scheme,
+            } => SavedObject::StructureFile {
+                path: path.clone(),
+                position: position.to_array(),
+                representation: (*representation).into(),
+                color_scheme: (*color_scheme).into(),
+            },
+            ProjectObject::Structure {
+                structure,
+                representation,
+                color_scheme,
+            } => SavedObject::Structure {
+                residues: structure.residues.iter().map(SavedResidue::from).collect(),
+                bonds: structure.bonds().iter().map(|&(a, b)| [a, b]).collect(),
+                representation: (*representation).into(),
+                color_scheme: (*color_scheme).into(),
+            },
+        }
+    }
+}
+
+impl TryFrom<SavedObject> for ProjectObject {
+    type Error = ProjectError;
+
+    fn try_from(saved: SavedObject) -> Result<Self, Self::Error> {
+        Ok(match saved {
+            SavedObject::DevCube {
+                position,
+                rotation,
+                scale,
+            } => ProjectObject::DevCube {
+                position: Vec3::from_array(position),
+                rotation: Quat::from_array(rotation).normalize(),
+                scale: Vec3::from_array(scale),
+            },
+            SavedObject::AminoAcid {
+                code,
+                position,
+                residue_number,
+                representation,
+                color_scheme,
+            } => ProjectObject::AminoAcid {
+                code: residue_code(&code)?,
+                position: Vec3::from_array(position),
+                residue_number,
+                representation: representation.into(),
+                color_scheme: color_scheme.into(),
+            },
+            SavedObject::Peptide {
+                sequence,
+                position,
+                chain_id,
+                conformation,
+                representation,
+                color_scheme,
+            } => ProjectObject::Peptide {
+                sequence,
+                position: Vec3::from_array(position),
+                chain_id,
+                conformation: conformation.into(),
+                representation: representation.into(),
+                color_scheme: color_scheme.into(),
+            },
+            SavedObject::StructureFile {
+                path,
+                position,
+                representation,
+                color_scheme,
+            } => ProjectObject::StructureFile {
+                path,
+                position: Vec3::from_array(position),
+                representation: representation.into(),
+                color_scheme: color_scheme.into(),
+            },
+            SavedObject::Structure {
+                residues,
+                bonds,
+                representation,
+                color_scheme,
+            } => ProjectObject::Structure {
+                structure: Structure::new(
+                    residues
+                        .into_iter()
+                        .map(StructureResidue::try_from)
+                        .collect::<Result<_, _>>()?,
+                    bonds.into_iter().map(|[a, b]| (a, b)),
+                ),
+                representation: representation.into(),
+                color_scheme: color_scheme.into(),
+            },
+        })
+    }
+}
+
+impl From<Representation> for SavedRepresentation {
+    fn from(representation: Representation) -> Self {
+        match representation {
+            Representation::BallAndStick => SavedRepresentation::BallAndStick,
+            Representation::SpaceFilling => SavedRepresentation::SpaceFilling,
+            Representation::Licorice => SavedRepresentation::Licorice,
+            Representation::Wireframe => SavedRepresentation::Wireframe,
+            Representation::Cartoon => SavedRepresentation::Cartoon,
+        }
+    }
+}
+
+impl From<SavedRepresentation> for Representation {
+    fn from(saved: SavedRepresentation) -> Self {
+        match saved {
+            SavedRepresentation::BallAndStick => Representation::BallAndStick,
+            SavedRepresentation::SpaceFilling => Representation::SpaceFilling,
+            SavedRepresentation::Licorice => Representation::Licorice,
+            SavedRepresentation::Wireframe => Representation::Wireframe,
+            SavedRepresentation::Cartoon => Representation::Cartoon,
+        }
+    }
+}
+
+impl From<ColorScheme> for SavedColorScheme {
+    fn from(color_scheme: ColorScheme) -> Self {
+        match color_scheme {
+            ColorScheme::Element => SavedColorScheme::Element,
+            ColorScheme::Chain => SavedColorScheme::Chain,
+            ColorScheme::ResidueType => SavedColorScheme::ResidueType,
+            ColorScheme::Hydrophobicity => SavedColorScheme::Hydrophobicity,
+            ColorScheme::Rainbow => SavedColorScheme::Rainbow,
+            ColorScheme::SecondaryStructure => SavedColorScheme::SecondaryStructure,
+            ColorScheme::BFactor => SavedColorScheme::BFactor,
+        }
+    }
+}
+
+impl From<SavedColorScheme> for ColorScheme {
+    fn from(saved: SavedColorScheme) -> Self {
+        match saved {
+            SavedColorScheme::Element => ColorScheme::Element,
+            SavedColorScheme::Chain => ColorScheme::Chain,
+            SavedColorScheme::ResidueType => ColorScheme::ResidueType,
+            SavedColorScheme::Hydrophobicity => ColorScheme::Hydrophobicity,
+            SavedColorScheme::Rainbow => ColorScheme::Rainbow,
+            SavedColorScheme::SecondaryStructure => ColorScheme::SecondaryStructure,
+            SavedColorScheme::BFactor => ColorScheme::BFactor,
+        }
+    }
+}
+
+impl From<&Conformation> for SavedConformation {
+    fn from(conformation: &Conformation) -> Self {
+        match conformation {
+            Conformation::Extended => SavedConformation::Extended,
+            Conformation::AlphaHelix => SavedConformation::AlphaHelix,
+            Conformation::BetaStrand => SavedConformation::BetaStrand,
+            Conformation::PolyprolineII => SavedConformation::PolyprolineII,
+            Conformation::Custom(dihedrals) => SavedConformation::Custom {
+                dihedrals: dihedrals
+                    .iter()
+                    .map(|dihedrals| SavedDihedrals {
+                        phi: dihedrals.phi,
+                        psi: dihedrals.psi,
+                        omega: dihedrals.omega,
+                    })
+                    .collect(),
+            },
+        }
+    }
+}
+
+impl From<SavedConformation> for Conformation {
+    fn from(saved: SavedConformation) -> Self {
+        match saved {
+            SavedConformation::Extended => Conformation::Extended,
+            SavedConformation::AlphaHelix => Conformation::AlphaHelix,
+            SavedConformation::BetaStrand => Conformation::BetaStrand,
+            SavedConformation::PolyprolineII => Conformation::PolyprolineII,
+            SavedConformation::Custom { dihedrals } => Conformation::Custom(
+                dihedrals
+                    .into_iter()
+                    .map(|dihedrals| {
+                        BackboneDihedrals::new(dihedrals.phi, dihedrals.psi, dihedrals.omega)
+                    })
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<&StructureResidue> for SavedResidue {
+    fn from(residue: &StructureResidue) -> Self {
+        Self {
+            code: residue.code.three_letter().to_string(),
+            residue_number: residue.residue_number,
+            chain_id: residue.chain_id.clone(),
+            atoms: residue
+                .atoms
+                .iter()
+                .map(|atom| SavedAtom {
+                    serial: atom.serial,
+                    name: atom.atom_name.clone(),
+                    element: atom.element.symbol().to_string(),
+                    position: atom.position.to_array(),
+                    b_factor: atom.b_factor,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<SavedResidue> for StructureResidue {
+    type Error = ProjectError;
+
+    fn try_from(saved: SavedResidue) -> Result<Self, Self::Error> {
+        let atoms = saved
+            .atoms
+            .into_iter()
+            .map(|atom| {
+                Ok(StructureAtom {
+                    serial: atom.serial,
+                    element: Element::from_symbol(&atom.element)
+                        .ok_or(ChemistryError::UnknownElement(atom.element))?,
+                    atom_name: atom.name,
+                    position: Vec3::from_array(atom.position),
+                    b_factor: atom.b_factor,
+                })
+            })
+            .collect::<Result<_, ProjectError>>()?;
+
+        Ok(Self {
+            code: residue_code(&saved.code)?,
+            residue_number: saved.residue_number,
+            chain_id: saved.chain_id,
+            atoms,
+        })
+    }
+}
+
+fn residue_code(name: &str) -> Result<AminoAcidCode, ChemistryError> {
+    AminoAcidCode::from_three_letter(name)
+        .ok_or_else(|| ChemistryError::UnknownResidue(name.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_then_parse_round_trips() {
+        let structure = Structure::new(
+            vec![StructureResidue {
+                code: AminoAcidCode::Gly,
+                residue_number: 7,
+                chain_id: "B".to_string(),
+                atoms: vec![
+                    StructureAtom {
+                        serial: 1,
+                        atom_name: "N".to_string(),
+                        element: Element::Nitrogen,
+                        position: Vec3::new(0.0, 0.0, 0.0),
+                        b_factor: 0.0,
+                    },
+                    StructureAtom {
+                        serial: 2,
+                        atom_name: "CA".to_string(),
+                        element: Element::Carbon,
+                        position: Vec3::new(1.458, 0.0, 0.0),
+                        b_factor: 12.5,
+                    },
+                ],
+            }],
+            [(1, 2)],
+        );
+        let measurements = vec![vec![
+            AtomReference {
+                object: 1,
+                residue: 0,
+                atom_name: "CA".to_string(),
+            },
+            AtomReference {
+                object: 4,
+                residue: 0,
+                atom_name: "N".to_string(),
+            },
+        ]];
+        let project = Project::new("Saved", "Round trip")
+            .with_objects([
+                ProjectObject::dev_cube_with_transform(
+                    Vec3::new(1.0, 2.0, 3.0),
+                    Quat::from_rotation_y(0.5),
+                    Vec3::splat(2.0),
+                ),
+                ProjectObject::amino_acid(AminoAcidCode::Trp, Vec3::X, 3)
+                    .with_representation(Representation::SpaceFilling),
+                ProjectObject::peptide_with_conformation(
+                    "ACDE",
+                    Vec3::Y,
+                    Conformation::Custom(vec![BackboneDihedrals::ALPHA_HELIX; 4]),
+                )
+                .with_color_scheme(ColorScheme::Rainbow),
+                ProjectObject::structure_file("1abc.pdb", Vec3::Z)
+                    .with_representation(Representation::Wireframe),
+                ProjectObject::Structure {
+                    structure,
+                    representation: Representation::Licorice,
+                    color_scheme: ColorScheme::BFactor,
+                },
+            ])
+            .with_camera(Vec3::new(0.0, 5.0, 10.0), Vec3::ZERO)
+            .with_physics(true)
+            .with_measurements(measurements.clone())
+            .with_history([SceneEdit {
+                transforms: HashMap::from([(
+                    RootReference {
+                        object: 0,
+                        residue: 0,
+                    },
+                    Change {
+                        before: Transform::from_xyz(1.0, 2.0, 3.0),
+                        after: Transform::from_xyz(4.0, 5.0, 6.0)
+                            .with_rotation(Quat::from_rotation_x(0.25)),
+                    },
+                )]),
+                atoms: HashMap::from([(
+                    AtomReference {
+                        object: 4,
+                        residue: 0,
+                        atom_name: "CA".to_string(),
+                    },
+                    Change {
+                        before: Vec3::new(1.458, 0.0, 0.0),
+                        after: Vec3::new(1.5, 0.1, 0.0),
+                    },
+                )]),
+                color_schemes: HashMap::from([(
+                    RootReference {
+                        object: 2,
+                        residue: 1,
+                    },
+                    Change {
+                        before: ColorScheme::Element,
+                        after: ColorScheme::Rainbow,
+                    },
+                )]),
+                measurements: Some(Change {
+                    before: Vec::new(),
+                    after: measurements.clone(),
+                }),
+                ..default()
+            }]);
+
+        let parsed = parse(&write(&project).unwrap()).unwrap();
+
+        assert_eq!(parsed, project);
+    }
+
+    #[test]
+    fn newer_versions_are_rejected() {
+        let contents = r#"{"version": 99, "name": "Future", "camera_position": [0, 0, 0],
+            "camera_look_at": [0, 0, 0], "objects": []}"#;
+
+        assert_eq!(parse(contents), Err(ProjectError::UnsupportedVersion(99)));
+    }
+}
@@ -0,0 +1,911 @@
+//! Undo and redo of scene edits.
+//!
+//! Edits are not recorded by the systems making them. Instead the history
+//! keeps the last known state of the project objects and compares it with the
+//! scene at the end of every frame, so moving, rotating, rotamer changes,
+//! minimization, dynamics, representation and color scheme changes and
+//! measurements are all undoable without knowing about it. Changes on
+//! consecutive frames, like a drag, a simulation run or the physics settling,
+//! are grouped into one step, which is closed on the first frame without
+//! changes once nothing is held.
+//!
+//! Objects are referenced by their index in the project, like measurements,
+//! so steps stay valid when the project is respawned. Atom positions are
+//! stored in world space, which keeps saved steps valid although a project
+//! file may spawn residues with other origins than the scene they were
+//! recorded in.
+//!
+//! Switching projects is a step too. It starts a new history, whose first
+//! step switches back to the previous project.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::path::PathBuf;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use super::{
+    Project, ProjectDocument, ProjectEntities, ProjectResource, ProjectScene, SwitchProjectMessage,
+};
+use crate::chemistry::atoms::Atom;
+use crate::chemistry::rendering::{ColorScheme, Representation};
+use crate::measurements::{AtomReference, Measurement, PendingMeasurements};
+use crate::user::interaction::HeldMovable;
+
+/// Steps kept for undo. Older steps are dropped.
+const MAX_STEPS: usize = 100;
+
+/// Changed values kept for undo across all steps, which bounds the memory of
+/// long simulation runs on large structures. Older steps are dropped.
+const MAX_CHANGES: usize = 200_000;
+
+/// A root entity of a project object, addressed by the index of the object
+/// and the index of the residue among the object's roots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RootReference {
+    pub object: usize,
+    pub residue: usize,
+}
+
+impl From<&AtomReference> for RootReference {
+    fn from(reference: &AtomReference) -> Self {
+        Self {
+            object: reference.object,
+            residue: reference.residue,
+        }
+    }
+}
+
+/// A value before and after a step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Change<T> {
+    pub before: T,
+    pub after: T,
+}
+
+impl<T> Change<T> {
+    /// The value a step starts from and the value it ends at, when undoing
+    /// or redoing it.
+    fn direction(&self, action: HistoryAction) -> (&T, &T) {
+        match action {
+            HistoryAction::Undo => (&self.after, &self.before),
+            HistoryAction::Redo => (&self.before, &self.after),
+        }
+    }
+}
+
+/// Changes to the spawned project objects, recorded as one undo step.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SceneEdit {
+    /// Moved and rotated objects and residues.
+    pub transforms: HashMap<RootReference, Change<Transform>>,
+    /// Atoms moved within their residue, in world space.
+    pub atoms: HashMap<AtomReference, Change<Vec3>>,
+    pub representations: HashMap<RootReference, Change<Representation>>,
+    pub color_schemes: HashMap<RootReference, Change<ColorScheme>>,
+    /// Atoms of every measurement, sorted.
+    pub measurements: Option<Change<Vec<Vec<AtomReference>>>>,
+}
+
+impl SceneEdit {
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of changed values.
+    pub fn len(&self) -> usize {
+        self.transforms.len()
+            + self.atoms.len()
+            + self.representations.len()
+            + self.color_schemes.len()
+            + usize::from(self.measurements.is_some())
+    }
+
+    /// Adds the changes of a later edit, keeping the earlier `before` values.
+    fn merge(&mut self, later: SceneEdit) {
+        merge_changes(&mut self.transforms, later.transforms);
+        merge_changes(&mut self.atoms, later.atoms);
+        merge_changes(&mut self.representations, later.representations);
+        merge_changes(&mut self.color_schemes, later.color_schemes);
+        if let Some(later) = later.measurements {
+            match &mut self.measurements {
+                Some(change) => change.after = later.after,
+                None => self.measurements = Some(later),
+            }
+        }
+    }
+
+    /// Drops changes that ended where they started.
+    fn retain_changed(&mut self) {
+        self.transforms
+            .retain(|_, change| change.before != change.after);
+        self.atoms.retain(|_, change| change.before != change.after);
+        self.representations
+            .retain(|_, change| change.before != change.after);
+        self.color_schemes
+            .retain(|_, change| change.before != change.after);
+        self.measurements
+            .take_if(|change| change.before == change.after);
+    }
+}
+
+fn merge_changes<K: Eq + Hash, T>(
+    changes: &mut HashMap<K, Change<T>>,
+    later: HashMap<K, Change<T>>,
+) {
+    for (key, change) in later {
+        match changes.get_mut(&key) {
+            Some(existing) => existing.after = change.after,
+            None => {
+                changes.insert(key, change);
+            }
+        }
+    }
+}
+
+/// A project as it was shown, to switch back to it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectSnapshot {
+    pub project: Project,
+    pub path: Option<PathBuf>,
+    /// Whether the project had changes not saved to `path`.
+    pub dirty: bool,
+}
+
+/// One undoable step.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HistoryStep {
+    Edit(SceneEdit),
+    /// Switch to another project, which respawns every object.
+    Switch(Change<ProjectSnapshot>),
+}
+
+impl HistoryStep {
+    fn len(&self) -> usize {
+        match self {
+            HistoryStep::Edit(edit) => edit.len(),
+            HistoryStep::Switch(_) => 1,
+        }
+    }
+}
+
+/// Undo and redo stacks of the active project.
+#[derive(Resource, Debug, Default)]
+pub struct History {
+    undo: Vec<HistoryStep>,
+    redo: Vec<HistoryStep>,
+    /// Step collecting the changes of an edit that is still going on.
+    open: SceneEdit,
+    /// Atom changes of the open step in the local space of their residue.
+    /// They are brought into world space once the step is closed and the
+    /// final residue transforms are known.
+    open_atoms: HashMap<AtomReference, Change<Vec3>>,
+    /// Switch step popped by undo or redo, whose project is captured before
+    /// the scene is replaced.
+    replaying: Option<(Change<ProjectSnapshot>, HistoryAction)>,
+    /// New project switch, which starts a new history once the project is
+    /// spawned.
+    recorded_switch: Option<Change<ProjectSnapshot>>,
+    /// Whether the project being spawned comes from an undone or redone
+    /// switch, which keeps the history.
+    replayed_switch: bool,
+}
+
+impl History {
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty() || !self.open.is_empty() || !self.open_atoms.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Scene edits since the last project switch, oldest first, as saved
+    /// with the project.
+    pub fn edits(&self) -> Vec<SceneEdit> {
+        let mut edits: Vec<SceneEdit> = self
+            .undo
+            .iter()
+            .rev()
+            .map_while(|step| match step {
+                HistoryStep::Edit(edit) => Some(edit.clone()),
+                HistoryStep::Switch(_) => None,
+            })
+            .collect();
+        edits.reverse();
+        edits
+    }
+
+    fn push(&mut self, step: HistoryStep) {
+        self.redo.clear();
+        self.undo.push(step);
+
+        let mut changes: usize = self.undo.iter().map(HistoryStep::len).sum();
+        while self.undo.len() > MAX_STEPS || (changes > MAX_CHANGES && self.undo.len() > 1) {
+            changes -= self.undo.remove(0).len();
+        }
+    }
+
+    /// Ends the open step and pushes it, unless nothing changed.
+    fn close(&mut self, baseline: &SceneBaseline) {
+        let mut edit = std::mem::take(&mut self.open);
+        for (reference, change) in self.open_atoms.drain() {
+            let root = RootReference::from(&reference);
+            let after = baseline.transform(root);
+            let before = edit
+                .transforms
+                .get(&root)
+                .map_or(after, |change| change.before);
+            edit.atoms.insert(
+                reference,
+                Change {
+                    before: before.transform_point(change.before),
+                    after: after.transform_point(change.after),
+                },
+            );
+        }
+
+        edit.retain_changed();
+        if !edit.is_empty() {
+            self.push(HistoryStep::Edit(edit));
+        }
+    }
+
+    /// Replaces both stacks with the edits saved with a project.
+    fn restore(&mut self, edits: &[SceneEdit]) {
+        self.undo = edits.iter().cloned().map(HistoryStep::Edit).collect();
+        self.redo.clear();
+    }
+}
+
+/// Whether a [`HistoryMessage`] undoes or redoes a step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryAction {
+    Undo,
+    Redo,
+}
+
+/// Message to undo or redo the last step.
+#[derive(Message, Debug, Clone)]
+pub struct HistoryMessage {
+    pub action: HistoryAction,
+}
+
+/// Last known state of the project objects, which the scene is compared
+/// with to find edits.
+#[derive(Resource, Default)]
+pub(super) struct SceneBaseline {
+    roots: HashMap<Entity, RootReference>,
+    entities: HashMap<RootReference, Entity>,
+    transforms: HashMap<Entity, Transform>,
+    /// Local atom positions.
+    atoms: HashMap<Entity, Vec3>,
+    representations: HashMap<Entity, Representation>,
+    color_schemes: HashMap<Entity, ColorScheme>,
+    measurements: Vec<Vec<AtomReference>>,
+}
+
+impl SceneBaseline {
+    fn reset(
+        &mut self,
+        objects: &[Vec<Entity>],
+        scene: &ProjectScene,
+        measurements: Vec<Vec<AtomReference>>,
+    ) {
+        *self = SceneBaseline {
+            measurements: sorted(measurements),
+            ..default()
+        };
+
+        for (object, roots) in objects.iter().enumerate() {
+            for (residue, &root) in roots.iter().enumerate() {
+                let reference = RootReference { object, residue };
+                self.roots.insert(root, reference);
+                self.entities.insert(reference, root);
+                if let Ok(transform) = scene.transforms.get(root) {
+                    self.transforms.insert(root, *transform);
+                }
+                if let Ok(&representation) = scene.representation_query.get(root) {
+                    self.representations.insert(root, representation);
+                }
+                if let Ok(&color_scheme) = scene.color_scheme_query.get(root) {
+                    self.color_schemes.insert(root, color_scheme);
+                }
+                let Ok((.., children)) = scene.residue_query.get(root) else {
+                    continue;
+                };
+                for child in children.iter() {
+                    if scene.atoms.contains(child)
+                        && let Ok(transform) = scene.transforms.get(child)
+                    {
+                        self.atoms.insert(child, transform.translation);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Current transform of a root.
+    fn transform(&self, root: RootReference) -> Transform {
+        self.entities
+            .get(&root)
+            .and_then(|entity| self.transforms.get(entity))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+fn sorted(mut measurements: Vec<Vec<AtomReference>>) -> Vec<Vec<AtomReference>> {
+    measurements.sort();
+    measurements
+}
+
+/// Components of the project objects that changed since the last frame.
+#[allow(clippy::type_complexity)]
+#[derive(SystemParam)]
+pub(super) struct SceneChanges<'w, 's> {
+    moved_atoms: Query<
+        'w,
+        's,
+        (Entity, &'static Atom, &'static Transform, &'static ChildOf),
+        Changed<Transform>,
+    >,
+    representations: Query<'w, 's, (Entity, &'static Representation), Changed<Representation>>,
+    color_schemes: Query<'w, 's, (Entity, &'static ColorScheme), Changed<ColorScheme>>,
+    added_measurements: Query<'w, 's, (), Added<Measurement>>,
+    removed_measurements: RemovedComponents<'w, 's, Measurement>,
+    pending_measurements: Res<'w, PendingMeasurements>,
+    held_query: Query<'w, 's, (), With<HeldMovable>>,
+}
+
+/// Compares the scene with the baseline and adds what changed to the open
+/// step, or closes the step once the edit is over. A newly spawned project
+/// resets the baseline instead.
+pub(super) fn record_scene_changes(
+    mut history: ResMut<History>,
+    mut baseline: ResMut<SceneBaseline>,
+    project_res: Res<ProjectResource>,
+    project_entities: Res<ProjectEntities>,
+    mut changes: SceneChanges,
+    scene: ProjectScene,
+) {
+    if project_entities.is_changed() {
+        history.open = SceneEdit::default();
+        history.open_atoms.clear();
+        if !std::mem::take(&mut history.replayed_switch) {
+            history.restore(&project_res.project.history);
+        }
+        if let Some(switch) = history.recorded_switch.take() {
+            history.undo.insert(0, HistoryStep::Switch(switch));
+        }
+        changes.removed_measurements.clear();
+        baseline.reset(
+            &project_entities.objects,
+            &scene,
+            changes.pending_measurements.measurements.clone(),
+        );
+        return;
+    }
+
+    let mut edit = SceneEdit::default();
+    let mut atoms = HashMap::new();
+
+    for (entity, atom, transform, child_of) in &changes.moved_atoms {
+        let Some(&root) = baseline.roots.get(&child_of.parent()) else {
+            continue;
+        };
+        if let Some(before) = baseline.atoms.insert(entity, transform.translation)
+            && before != transform.translation
+        {
+            let reference = AtomReference {
+                object: root.object,
+                residue: root.residue,
+                atom_name: atom.atom_name.clone(),
+            };
+            atoms.insert(
+                reference,
+                Change {
+                    before,
+                    after: transform.translation,
+                },
+            );
+        }
+    }
+
+    let SceneBaseline {
+        roots,
+        transforms,
+        representations,
+        color_schemes,
+        ..
+    } = &mut *baseline;
+    for (&entity, &root) in roots.iter() {
+        if let Ok(transform) = scene.transforms.get(entity)
+            && transform.is_changed()
+            && let Some(before) = transforms.insert(entity, *transform)
+            && before != *transform
+        {
+            edit.transforms.insert(
+                root,
+                Change {
+                    before,
+                    after: *transform,
+                },
+            );
+        }
+    }
+    for (entity, &representation) in &changes.representations {
+        if let Some(&root) = roots.get(&entity)
+            && let Some(before) = representations.insert(entity, representation)
+            && before != representation
+        {
+            edit.representations.insert(
+                root,
+                Change {
+                    before,
+                    after: representation,
+                },
+            );
+        }
+    }
+    for (entity, &color_scheme) in &changes.color_schemes {
+        if let Some(&root) = roots.get(&entity)
+            && let Some(before) = color_schemes.insert(entity, color_scheme)
+            && before != color_scheme
+        {
+            edit.color_schemes.insert(
+                root,
+                Change {
+                    before,
+                    after: color_scheme,
+                },
+            );
+        }
+    }
+
+    // Measurements of a respawned project or an undone step are compared
+    // once their atoms are resolved.
+    let removed = changes.removed_measurements.read().count() > 0;
+    if (removed || !changes.added_measurements.is_empty())
+        && changes.pending_measurements.measurements.is_empty()
+    {
+        let measurements = sorted(scene.capture_measurements(&project_entities.objects));
+        if measurements != baseline.measurements {
+            edit.measurements = Some(Change {
+                before: std::mem::replace(&mut baseline.measurements, measurements.clone()),
+                after: measurements,
+            });
+        }
+    }
+
+    if !edit.is_empty() || !atoms.is_empty() {
+        history.open.merge(edit);
+        merge_changes(&mut history.open_atoms, atoms);
+    } else if changes.held_query.is_empty() {
+        history.close(&baseline);
+    }
+}
+
+/// Captures the project being switched away from. Runs right before
+/// [`handle_project_switch`](super::handle_project_switch), while the old
+/// scene still exists.
+pub(super) fn record_project_switch(
+    mut history: ResMut<History>,
+    document: Res<ProjectDocument>,
+    project_res: Res<ProjectResource>,
+    project_entities: Res<ProjectEntities>,
+    mut switch_messages: MessageReader<SwitchProjectMessage>,
+    scene: ProjectScene,
+) {
+    for message in switch_messages.read() {
+        // Postponed switches are read again once they go ahead.
+        if document.dirty {
+            continue;
+        }
+
+        let current = ProjectSnapshot {
+            project: Project {
+                history: Vec::new(),
+                ..scene.capture_project(&project_res.project, &project_entities.objects)
+            },
+            path: document.path.clone(),
+            dirty: document.discarded,
+        };
+        match history.replaying.take() {
+            Some((mut switch, HistoryAction::Undo)) => {
+                switch.after = current;
+                history.redo.push(HistoryStep::Switch(switch));
+                history.replayed_switch = true;
+            }
+            Some((mut switch, HistoryAction::Redo)) => {
+                switch.before = current;
+                history.undo.push(HistoryStep::Switch(switch));
+                history.replayed_switch = true;
+            }
+            None => {
+                let next = ProjectSnapshot {
+                    project: Project {
+                        history: Vec::new(),
+                        ..message.project.clone()
+                    },
+                    path: message.path.clone(),
+                    dirty: message.dirty,
+                };
+                history.recorded_switch = Some(Change {
+                    before: current,
+                    after: next,
+                });
+            }
+        }
+    }
+}
+
+/// Entities that undo and redo write to.
+#[allow(clippy::type_complexity)]
+#[derive(SystemParam)]
+pub(super) struct SceneTargets<'w, 's> {
+    commands: Commands<'w, 's>,
+    transforms: Query<'w, 's, (&'static mut Transform, Option<&'static mut Atom>)>,
+    children_query: Query<'w, 's, &'static Children>,
+    representations: Query<'w, 's, &'static mut Representation>,
+    color_schemes: Query<'w, 's, &'static mut ColorScheme>,
+    measurements: Query<'w, 's, Entity, With<Measurement>>,
+    pending_measurements: ResMut<'w, PendingMeasurements>,
+}
+
+impl SceneTargets<'_, '_> {
+    /// Writes the values an edit starts or ends with to the scene and the
+    /// baseline, so they are not recorded again.
+    fn apply(&mut self, edit: &SceneEdit, action: HistoryAction, baseline: &mut SceneBaseline) {
+        for (root, change) in &edit.transforms {
+            let Some(&entity) = baseline.entities.get(root) else {
+                continue;
+            };
+            let Ok((mut transform, _)) = self.transforms.get_mut(entity) else {
+                continue;
+            };
+            let (from, to) = change.direction(action);
+            *transform = replay_transform(*transform, *from, *to);
+            baseline.transforms.insert(entity, *transform);
+        }
+
+        for (reference, change) in &edit.atoms {
+            let Some(&root) = baseline.entities.get(&RootReference::from(reference)) else {
+                continue;
+            };
+            let Some(root_transform) = baseline.transforms.get(&root).copied() else {
+                continue;
+            };
+            let Some(entity) = self.find_atom(root, &reference.atom_name) else {
+                continue;
+            };
+            let (_, &to) = change.direction(action);
+            let local = root_transform
+                .compute_affine()
+                .inverse()
+                .transform_point3(to);
+            if let Ok((mut transform, Some(mut atom))) = self.transforms.get_mut(entity) {
+                atom.position = local;
+                transform.translation = local;
+                baseline.atoms.insert(entity, local);
+            }
+        }
+
+        for (root, change) in &edit.representations {
+            if let Some(&entity) = baseline.entities.get(root)
+                && let Ok(mut representation) = self.representations.get_mut(entity)
+            {
+                let (_, &to) = change.direction(action);
+                *representation = to;
+                baseline.representations.insert(entity, to);
+            }
+        }
+        for (root, change) in &edit.color_schemes {
+            if let Some(&entity) = baseline.entities.get(root)
+                && let Ok(mut color_scheme) = self.color_schemes.get_mut(entity)
+            {
+                let (_, &to) = change.direction(action);
+                *color_scheme = to;
+                baseline.color_schemes.insert(entity, to);
+            }
+        }
+
+        // Measurements are respawned from their references, like those of a
+        // newly spawned project.
+        if let Some(change) = &edit.measurements {
+            let (_, to) = change.direction(action);
+            for entity in &self.measurements {
+                self.commands.entity(entity).despawn();
+            }
+            self.pending_measurements.measurements = to.clone();
+            baseline.measurements = to.clone();
+        }
+    }
+
+    fn find_atom(&self, root: Entity, name: &str) -> Option<Entity> {
+        self.children_query.get(root).ok()?.iter().find(|&child| {
+            self.transforms
+                .get(child)
+                .is_ok_and(|(_, atom)| atom.is_some_and(|atom| atom.atom_name == name))
+        })
+    }
+}
+
+/// The transform a root moves to when a step takes it from `from` to `to`.
+/// Roots respawned from a saved project can have other origins than the
+/// ones the step was recorded with, so the motion is applied to the current
+/// transform instead of jumping to `to`.
+fn replay_transform(current: Transform, from: Transform, to: Transform) -> Transform {
+    if current == from {
+        return to;
+    }
+    let motion = to.compute_affine() * from.compute_affine().inverse();
+    Transform::from_matrix(Mat4::from(motion * current.compute_affine()))
+}
+
+/// Undoes and redoes steps. Switch steps are handed to
+/// [`record_project_switch`] and [`handle_project_switch`](super::handle_project_switch)
+/// through a [`SwitchProjectMessage`].
+pub(super) fn handle_history_messages(
+    mut history: ResMut<History>,
+    mut baseline: ResMut<SceneBaseline>,
+    mut document: ResMut<ProjectDocument>,
+    mut messages: MessageReader<HistoryMessage>,
+    mut switch_messages: MessageWriter<SwitchProjectMessage>,
+    mut targets: SceneTargets,
+) {
+    for message in messages.read() {
+        if history.replaying.is_some() {
+            break;
+        }
+        history.close(&baseline);
+
+        let action = message.action;
+        let step = match action {
+            HistoryAction::Undo => history.undo.pop(),
+            HistoryAction::Redo => history.redo.pop(),
+        };
+        let Some(step) = step else {
+            continue;
+        };
+
+        match step {
+            HistoryStep::Edit(edit) => {
+                targets.apply(&edit, action, &mut baseline);
+                document.dirty = true;
+                match action {
+                    HistoryAction::Undo => history.redo.push(HistoryStep::Edit(edit)),
+                    HistoryAction::Redo => history.undo.push(HistoryStep::Edit(edit)),
+                }
+            }
+            HistoryStep::Switch(switch) => {
+                let (_, to) = switch.direction(action);
+                info!("Switching back to {}", to.project.name);
+                switch_messages.write(SwitchProjectMessage {
+                    project: to.project.clone(),
+                    path: to.path.clone(),
+                    dirty: to.dirty,
+                });
+                // The project being left is captured with its current state,
+                // unsaved changes included, so nothing needs to be saved
+                // first.
+                document.discarded |= document.dirty;
+                document.dirty = false;
+                document.pending_switch = None;
+                history.replaying = Some((switch, action));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chemistry::atoms::Element;
+
+    const ROOT: RootReference = RootReference {
+        object: 0,
+        residue: 0,
+    };
+
+    fn atom_reference() -> AtomReference {
+        AtomReference {
+            object: 0,
+            residue: 0,
+            atom_name: "CA".to_string(),
+        }
+    }
+
+    fn color_edit(after: ColorScheme) -> SceneEdit {
+        SceneEdit {
+            color_schemes: HashMap::from([(
+                ROOT,
+                Change {
+                    before: ColorScheme::default(),
+                    after,
+                },
+            )]),
+            ..default()
+        }
+    }
+
+    fn color_step(after: ColorScheme) -> HistoryStep {
+        HistoryStep::Edit(color_edit(after))
+    }
+
+    /// App running only the undo and redo system on a residue at
+    /// `transform` with one atom at `local`.
+    fn history_app(transform: Transform, local: Vec3) -> (App, Entity, Entity) {
+        let mut app = App::new();
+        app.init_resource::<History>()
+            .init_resource::<SceneBaseline>()
+            .init_resource::<ProjectDocument>()
+            .init_resource::<PendingMeasurements>()
+            .add_message::<HistoryMessage>()
+            .add_message::<SwitchProjectMessage>()
+            .add_systems(Update, handle_history_messages);
+
+        let atom = app
+            .world_mut()
+            .spawn((
+                Atom::new(Element::Carbon, local, "CA"),
+                Transform::from_translation(local),
+            ))
+            .id();
+        let root = app.world_mut().spawn(transform).add_child(atom).id();
+
+        let mut baseline = app.world_mut().resource_mut::<SceneBaseline>();
+        baseline.roots.insert(root, ROOT);
+        baseline.entities.insert(ROOT, root);
+        baseline.transforms.insert(root, transform);
+        baseline.atoms.insert(atom, local);
+        (app, root, atom)
+    }
+
+    fn send(app: &mut App, action: HistoryAction) {
+        app.world_mut().write_message(HistoryMessage { action });
+        app.update();
+    }
+
+    fn state(app: &App, root: Entity, atom: Entity) -> (Transform, Vec3, Vec3) {
+        let world = app.world();
+        let atom_component = world.get::<Atom>(atom).expect("atom should exist");
+        (
+            *world.get::<Transform>(root).expect("root should exist"),
+            world
+                .get::<Transform>(atom)
+                .expect("atom should exist")
+                .translation,
+            atom_component.position,
+        )
+    }
+
+    #[test]
+    fn undo_and_redo_restore_transforms_and_atom_positions() {
+        let before = Transform::from_xyz(1.0, 2.0, 3.0);
+        let after = Transform::from_xyz(-4.0, 0.5, 8.0);
+        let (local_before, local_after) = (Vec3::new(1.5, 0.0, 0.0), Vec3::new(0.0, 1.25, 0.0));
+        let (mut app, root, atom) = history_app(after, local_after);
+        app.world_mut()
+            .resource_mut::<History>()
+            .push(HistoryStep::Edit(SceneEdit {
+                transforms: HashMap::from([(ROOT, Change { before, after })]),
+                atoms: HashMap::from([(
+                    atom_reference(),
+                    Change {
+                        before: before.transform_point(local_before),
+                        after: after.transform_point(local_after),
+                    },
+                )]),
+                ..default()
+            }));
+
+        send(&mut app, HistoryAction::Undo);
+        assert_eq!(
+            state(&app, root, atom),
+            (before, local_before, local_before)
+        );
+        let history = app.world().resource::<History>();
+        assert!(!history.can_undo());
+        assert!(history.can_redo());
+        assert!(app.world().resource::<ProjectDocument>().dirty);
+
+        send(&mut app, HistoryAction::Redo);
+        assert_eq!(state(&app, root, atom), (after, local_after, local_after));
+        let history = app.world().resource::<History>();
+        assert!(history.can_undo());
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn new_step_clears_redo_stack() {
+        let mut history = History::default();
+        history.push(color_step(ColorScheme::Chain));
+        let undone = history.undo.pop().expect("step should be pushed");
+        history.redo.push(undone);
+        assert!(history.can_redo());
+
+        history.push(color_step(ColorScheme::ResidueType));
+        assert!(!history.can_redo());
+        assert_eq!(history.undo, vec![color_step(ColorScheme::ResidueType)]);
+    }
+
+    #[test]
+    fn history_is_capped() {
+        let mut history = History::default();
+        for step in 0..MAX_STEPS + 10 {
+            let scheme = if step % 2 == 0 {
+                ColorScheme::Chain
+            } else {
+                ColorScheme::ResidueType
+            };
+            history.push(color_step(scheme));
+        }
+        assert_eq!(history.undo.len(), MAX_STEPS);
+
+        // A step larger than the change budget is kept on its own.
+        let atoms = (0..MAX_CHANGES + 1)
+            .map(|index| {
+                let reference = AtomReference {
+                    atom_name: index.to_string(),
+                    ..atom_reference()
+                };
+                let change = Change {
+                    before: Vec3::ZERO,
+                    after: Vec3::X,
+                };
+                (reference, change)
+            })
+            .collect();
+        history.push(HistoryStep::Edit(SceneEdit { atoms, ..default() }));
+        assert_eq!(history.undo.len(), 1);
+    }
+
+    #[test]
+    fn merged_edits_keep_the_first_before_and_drop_round_trips() {
+        let start = Transform::from_xyz(1.0, 0.0, 0.0);
+        let middle = Transform::from_xyz(2.0, 0.0, 0.0);
+        let mut edit = SceneEdit {
+            transforms: HashMap::from([(
+                ROOT,
+                Change {
+                    before: start,
+                    after: middle,
+                },
+            )]),
+            ..default()
+        };
+        edit.merge(color_edit(ColorScheme::Chain));
+        edit.merge(SceneEdit {
+            transforms: HashMap::from([(
+                ROOT,
+                Change {
+                    before: middle,
+                    after: start,
+                },
+            )]),
+            ..default()
+        });
+        assert_eq!(edit.transforms[&ROOT].before, start);
+        assert_eq!(edit.len(), 2);
+
+        edit.retain_changed();
+        assert!(edit.transforms.is_empty());
+        assert_eq!(edit.color_schemes[&ROOT].after, ColorScheme::Chain);
+    }
+
+    #[test]
+    fn replayed_transforms_apply_the_recorded_motion() {
+        let from = Transform::from_xyz(1.0, 0.0, 0.0);
+        let to = Transform::from_xyz(1.0, 2.0, 0.0).with_rotation(Quat::from_rotation_z(1.0));
+        assert_eq!(replay_transform(from, from, to), to);
+
+        // A root respawned elsewhere makes the same motion from where it is.
+        let current = Transform::from_xyz(5.0, 0.0, 0.0);
+        let replayed = replay_transform(current, from, to);
+        let expected = Quat::from_rotation_z(1.0) * Vec3::new(4.0, 0.0, 0.0) + to.translation;
+        assert!(replayed.translation.distance(expected) < 1e-5);
+        assert!(replayed.rotation.angle_between(to.rotation) < 1e-5);
+    }
+}
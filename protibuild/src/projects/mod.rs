@@ -1,18 +1,48 @@
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 
+pub mod error;
+pub mod file;
+pub mod history;
 pub mod objects;
+pub mod recent;
 pub mod templates;
 
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use crate::chemistry::amino_acids::{AminoAcid, Residue};
+use crate::chemistry::atoms::{Atom, Bond};
+use crate::chemistry::error::ChemistryError;
+use crate::chemistry::io::{self, Structure};
+use crate::chemistry::rendering::{ColorScheme, Representation};
+use crate::measurements::{AtomReference, Measurement, PendingMeasurements};
+use crate::physics::PhysicsBody;
+use crate::user::interaction::HeldMovable;
+use history::{History, HistoryMessage, SceneBaseline, SceneEdit};
 use objects::ProjectObject;
+use recent::RecentProjects;
+use templates::{TEMPLATES_DIRECTORY, TemplateRegistry};
+
+/// Distance of the saved camera look-at point when the project has none.
+const DEFAULT_LOOK_DISTANCE: f32 = 10.0;
 
 /// A project containing a collection of 3D objects to display.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Project {
     pub name: String,
     pub description: String,
     pub objects: Vec<ProjectObject>,
     pub camera_position: Vec3,
     pub camera_look_at: Vec3,
+    /// Whether residues and objects are simulated as colliding rigid bodies.
+    pub physics: bool,
+    /// Distance, angle and dihedral measurements, two to four atoms each.
+    pub measurements: Vec<Vec<AtomReference>>,
+    /// Whether the undo history is saved with the project.
+    pub save_history: bool,
+    /// Undo history saved with the project, oldest edit first.
+    pub history: Vec<SceneEdit>,
 }
 
 impl Project {
@@ -23,6 +53,10 @@ impl Project {
             objects: Vec::new(),
             camera_position: Vec3::new(-2.5, 4.5, 9.0),
             camera_look_at: Vec3::ZERO,
+            physics: false,
+            measurements: Vec::new(),
+            save_history: false,
+            history: Vec::new(),
         }
     }
 
@@ -41,6 +75,26 @@ impl Project {
         self.camera_look_at = look_at;
         self
     }
+
+    pub fn with_physics(mut self, physics: bool) -> Self {
+        self.physics = physics;
+        self
+    }
+
+    pub fn with_measurements(
+        mut self,
+        measurements: impl IntoIterator<Item = Vec<AtomReference>>,
+    ) -> Self {
+        self.measurements.extend(measurements);
+        self
+    }
+
+    /// Saves the undo history with the project, starting with `history`.
+    pub fn with_history(mut self, history: impl IntoIterator<Item = SceneEdit>) -> Self {
+        self.save_history = true;
+        self.history.extend(history);
+        self
+    }
 }
 
 /// Resource holding the currently active project.
@@ -59,13 +113,73 @@ impl ProjectResource {
 #[derive(Resource, Default)]
 pub struct ProjectEntities {
     pub roots: Vec<Entity>,
+    /// Roots spawned for each object of the project, in project order.
+    pub objects: Vec<Vec<Entity>>,
     pub initialized: bool,
 }
 
+/// File and unsaved state of the active project.
+#[derive(Resource, Default, Debug)]
+pub struct ProjectDocument {
+    /// File the project was loaded from or last saved to.
+    pub path: Option<PathBuf>,
+    /// Whether the scene changed since it was loaded or saved.
+    pub dirty: bool,
+    /// Whether `dirty` was cleared to let a switch go ahead although the
+    /// scene still has the unsaved changes. The history then records the
+    /// project being left as unsaved.
+    pub discarded: bool,
+    /// Switch postponed because of unsaved changes. It goes ahead once the
+    /// changes are saved or `dirty` is cleared to discard them.
+    pub pending_switch: Option<SwitchProjectMessage>,
+}
+
 /// Message to trigger project switching
 #[derive(Message, Clone, Debug)]
 pub struct SwitchProjectMessage {
     pub project: Project,
+    /// File the project was loaded from, if any.
+    pub path: Option<PathBuf>,
+    /// Whether the project has changes not saved to `path`, e.g. when undo
+    /// switches back to a project that was left unsaved.
+    pub dirty: bool,
+}
+
+/// Message to write the current scene to a structure file; the format is
+/// chosen from the file extension
+#[derive(Message, Clone, Debug)]
+pub struct ExportProjectMessage {
+    pub path: PathBuf,
+    /// Atoms to write, e.g. the selection, or every atom when `None`.
+    pub atoms: Option<Vec<Entity>>,
+}
+
+/// Message to save the current scene to a project file
+#[derive(Message, Clone, Debug)]
+pub struct SaveProjectMessage {
+    pub path: PathBuf,
+}
+
+/// Message to load a project file and switch to it
+#[derive(Message, Clone, Debug)]
+pub struct LoadProjectMessage {
+    pub path: PathBuf,
+}
+
+/// Message to change how the atoms and bonds of project objects are drawn
+#[derive(Message, Clone, Debug)]
+pub struct SetRepresentationMessage {
+    /// Index of the object in the project, or `None` for every object.
+    pub object: Option<usize>,
+    pub representation: Representation,
+}
+
+/// Message to change how the atoms of project objects are colored
+#[derive(Message, Clone, Debug)]
+pub struct SetColorSchemeMessage {
+    /// Index of the object in the project, or `None` for every object.
+    pub object: Option<usize>,
+    pub color_scheme: ColorScheme,
 }
 
 /// Plugin for managing projects and project switching.
@@ -74,17 +188,65 @@ pub struct ProjectPlugin;
 impl Plugin for ProjectPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<ProjectEntities>()
+            .init_resource::<ProjectDocument>()
+            .init_resource::<History>()
+            .init_resource::<SceneBaseline>()
+            .insert_resource(RecentProjects::load())
+            .insert_resource(load_templates())
             .add_message::<SwitchProjectMessage>()
-            .add_systems(Update, (handle_project_spawn, handle_project_switch));
+            .add_message::<ExportProjectMessage>()
+            .add_message::<SaveProjectMessage>()
+            .add_message::<LoadProjectMessage>()
+            .add_message::<SetRepresentationMessage>()
+            .add_message::<SetColorSchemeMessage>()
+            .add_message::<HistoryMessage>()
+            .add_systems(
+                Update,
+                (
+                    handle_project_spawn,
+                    (
+                        handle_project_load,
+                        handle_project_save,
+                        resume_pending_switch,
+                        history::handle_history_messages,
+                        history::record_project_switch,
+                        handle_project_switch,
+                    )
+                        .chain(),
+                    handle_project_export,
+                    handle_set_representation,
+                    handle_set_color_scheme,
+                    track_unsaved_changes,
+                ),
+            )
+            .add_systems(Last, history::record_scene_changes);
+    }
+}
+
+/// Built-in templates plus the project files in [`TEMPLATES_DIRECTORY`].
+fn load_templates() -> TemplateRegistry {
+    let mut registry = TemplateRegistry::builtin();
+    let directory = std::path::Path::new(TEMPLATES_DIRECTORY);
+    if directory.is_dir()
+        && let Err(e) = registry.register_directory(directory)
+    {
+        error!("Failed to read templates: {}", e);
     }
+    registry
+}
+
+/// Asset storage used to spawn project objects.
+#[derive(SystemParam)]
+struct ObjectAssets<'w> {
+    meshes: ResMut<'w, Assets<Mesh>>,
+    materials: ResMut<'w, Assets<StandardMaterial>>,
 }
 
 fn handle_project_spawn(
     mut commands: Commands,
     project_res: Res<ProjectResource>,
     mut project_entities: ResMut<ProjectEntities>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut assets: ObjectAssets,
     mut camera_query: Query<&mut Transform, With<Camera3d>>,
 ) {
     if !project_entities.initialized {
@@ -94,8 +256,7 @@ fn handle_project_spawn(
             &mut commands,
             &project_res.project,
             &mut project_entities,
-            &mut meshes,
-            &mut materials,
+            &mut assets,
         ) {
             error!("Failed to spawn project objects: {}", e);
             return;
@@ -112,15 +273,31 @@ fn handle_project_switch(
     mut commands: Commands,
     mut project_res: ResMut<ProjectResource>,
     mut project_entities: ResMut<ProjectEntities>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut document: ResMut<ProjectDocument>,
+    mut assets: ObjectAssets,
     mut switch_messages: MessageReader<SwitchProjectMessage>,
     mut camera_query: Query<&mut Transform, With<Camera3d>>,
 ) {
     for message in switch_messages.read() {
+        if document.dirty {
+            warn!(
+                "Unsaved changes in {}, switch to {} postponed",
+                project_res.project.name, message.project.name
+            );
+            document.pending_switch = Some(message.clone());
+            continue;
+        }
+
+        *document = ProjectDocument {
+            path: message.path.clone(),
+            dirty: message.dirty,
+            ..default()
+        };
+
         for entity in project_entities.roots.drain(..) {
             commands.entity(entity).despawn();
         }
+        project_entities.objects.clear();
 
         *project_res = ProjectResource::new(message.project.clone());
 
@@ -128,8 +305,7 @@ fn handle_project_switch(
             &mut commands,
             &message.project,
             &mut project_entities,
-            &mut meshes,
-            &mut materials,
+            &mut assets,
         ) {
             error!("Failed to spawn project objects: {}", e);
             return;
@@ -142,29 +318,387 @@ fn handle_project_switch(
     }
 }
 
+fn handle_project_export(
+    project_entities: Res<ProjectEntities>,
+    mut export_messages: MessageReader<ExportProjectMessage>,
+    residue_query: Query<(&AminoAcid, &Residue, &Children)>,
+    atom_query: Query<(&Atom, &GlobalTransform)>,
+    bond_query: Query<&Bond>,
+) {
+    for message in export_messages.read() {
+        let structure = match &message.atoms {
+            Some(atoms) => {
+                let atoms: HashSet<Entity> = atoms.iter().copied().collect();
+                Structure::from_selected_entities(
+                    &project_entities.roots,
+                    &residue_query,
+                    &atom_query,
+                    &bond_query,
+                    |atom| atoms.contains(&atom),
+                )
+            }
+            None => Structure::from_entities(
+                &project_entities.roots,
+                &residue_query,
+                &atom_query,
+                &bond_query,
+            ),
+        };
+        if message.atoms.is_some() && structure.residues.is_empty() {
+            warn!("No selected atoms to export to {}", message.path.display());
+            continue;
+        }
+
+        match io::save(&message.path, &structure) {
+            Ok(()) => info!("Exported project to {}", message.path.display()),
+            Err(e) => error!("Failed to export project: {}", e),
+        }
+    }
+}
+
+/// Replays a postponed switch once the unsaved changes were saved or
+/// discarded.
+fn resume_pending_switch(
+    mut document: ResMut<ProjectDocument>,
+    mut switch_messages: MessageWriter<SwitchProjectMessage>,
+) {
+    if !document.dirty
+        && let Some(message) = document.pending_switch.take()
+    {
+        switch_messages.write(message);
+    }
+}
+
+/// Switches the representation of one or all project objects. Objects without
+/// atoms are left alone.
+fn handle_set_representation(
+    project_entities: Res<ProjectEntities>,
+    mut document: ResMut<ProjectDocument>,
+    mut messages: MessageReader<SetRepresentationMessage>,
+    mut representation_query: Query<&mut Representation>,
+) {
+    for message in messages.read() {
+        let objects = match message.object {
+            Some(index) => project_entities
+                .objects
+                .get(index..=index)
+                .unwrap_or_default(),
+            None => &project_entities.objects[..],
+        };
+
+        for &root in objects.iter().flatten() {
+            if let Ok(mut representation) = representation_query.get_mut(root)
+                && *representation != message.representation
+            {
+                *representation = message.representation;
+                document.dirty = true;
+            }
+        }
+    }
+}
+
+/// Switches the color scheme of one or all project objects. Objects without
+/// atoms are left alone.
+fn handle_set_color_scheme(
+    project_entities: Res<ProjectEntities>,
+    mut document: ResMut<ProjectDocument>,
+    mut messages: MessageReader<SetColorSchemeMessage>,
+    mut color_scheme_query: Query<&mut ColorScheme>,
+) {
+    for message in messages.read() {
+        let objects = match message.object {
+            Some(index) => project_entities
+                .objects
+                .get(index..=index)
+                .unwrap_or_default(),
+            None => &project_entities.objects[..],
+        };
+
+        for &root in objects.iter().flatten() {
+            if let Ok(mut color_scheme) = color_scheme_query.get_mut(root)
+                && *color_scheme != message.color_scheme
+            {
+                *color_scheme = message.color_scheme;
+                document.dirty = true;
+            }
+        }
+    }
+}
+
+/// Marks the project as changed when objects are grabbed, physics moves
+/// them or atoms move.
+fn track_unsaved_changes(
+    mut document: ResMut<ProjectDocument>,
+    held_query: Query<(), Added<HeldMovable>>,
+    atom_query: Query<Ref<Atom>, Changed<Atom>>,
+    body_query: Query<Ref<Transform>, (With<PhysicsBody>, Changed<Transform>)>,
+) {
+    if document.dirty {
+        return;
+    }
+    if !held_query.is_empty()
+        || atom_query.iter().any(|atom| !atom.is_added())
+        || body_query.iter().any(|transform| !transform.is_added())
+    {
+        document.dirty = true;
+    }
+}
+
+fn handle_project_save(
+    project_res: Res<ProjectResource>,
+    project_entities: Res<ProjectEntities>,
+    mut document: ResMut<ProjectDocument>,
+    mut recent: ResMut<RecentProjects>,
+    mut save_messages: MessageReader<SaveProjectMessage>,
+    history: Res<History>,
+    scene: ProjectScene,
+) {
+    for message in save_messages.read() {
+        let mut project = scene.capture_project(&project_res.project, &project_entities.objects);
+        project.history = if project.save_history {
+            history.edits()
+        } else {
+            Vec::new()
+        };
+
+        match file::save(&message.path, &project) {
+            Ok(()) => {
+                info!("Saved project to {}", message.path.display());
+                document.path = Some(message.path.clone());
+                document.dirty = false;
+                recent.add(message.path.clone());
+            }
+            Err(e) => error!("Failed to save project: {}", e),
+        }
+    }
+}
+
+fn handle_project_load(
+    mut load_messages: MessageReader<LoadProjectMessage>,
+    mut switch_messages: MessageWriter<SwitchProjectMessage>,
+    mut recent: ResMut<RecentProjects>,
+) {
+    for message in load_messages.read() {
+        match file::load(&message.path) {
+            Ok(project) => {
+                info!("Loaded project from {}", message.path.display());
+                recent.add(message.path.clone());
+                switch_messages.write(SwitchProjectMessage {
+                    project,
+                    path: Some(message.path.clone()),
+                    dirty: false,
+                });
+            }
+            Err(e) => error!("Failed to load project: {}", e),
+        }
+    }
+}
+
+/// Scene state of the spawned project objects.
+#[derive(SystemParam)]
+struct ProjectScene<'w, 's> {
+    transforms: Query<'w, 's, Ref<'static, Transform>>,
+    atoms: Query<'w, 's, Ref<'static, Atom>>,
+    residue_query: Query<'w, 's, (&'static AminoAcid, &'static Residue, &'static Children)>,
+    atom_query: Query<'w, 's, (&'static Atom, &'static GlobalTransform)>,
+    bond_query: Query<'w, 's, &'static Bond>,
+    parent_query: Query<'w, 's, &'static ChildOf>,
+    measurement_query: Query<'w, 's, &'static Measurement>,
+    representation_query: Query<'w, 's, &'static Representation>,
+    color_scheme_query: Query<'w, 's, &'static ColorScheme>,
+    camera_query: Query<'w, 's, &'static Transform, With<Camera3d>>,
+}
+
+impl ProjectScene<'_, '_> {
+    /// The project with its objects, measurements and camera updated from the
+    /// scene.
+    fn capture_project(&self, project: &Project, objects: &[Vec<Entity>]) -> Project {
+        let mut project = project.clone();
+        project.objects = project
+            .objects
+            .iter()
+            .zip(objects)
+            .map(|(object, roots)| self.capture(object, roots))
+            .collect();
+        project.measurements = self.capture_measurements(objects);
+
+        if let Ok(camera_transform) = self.camera_query.single() {
+            let distance = project.camera_position.distance(project.camera_look_at);
+            let distance = if distance > 0.0 {
+                distance
+            } else {
+                DEFAULT_LOOK_DISTANCE
+            };
+            project.camera_position = camera_transform.translation;
+            project.camera_look_at =
+                camera_transform.translation + camera_transform.forward() * distance;
+        }
+        project
+    }
+
+    /// Updates an object from the entities spawned for it, including its
+    /// current representation and color scheme.
+    fn capture(&self, object: &ProjectObject, roots: &[Entity]) -> ProjectObject {
+        let mut captured = self.capture_placement(object, roots);
+        let Some(&root) = roots.first() else {
+            return captured;
+        };
+        if let Ok(&representation) = self.representation_query.get(root) {
+            captured = captured.with_representation(representation);
+        }
+        if let Ok(&color_scheme) = self.color_scheme_query.get(root) {
+            captured = captured.with_color_scheme(color_scheme);
+        }
+        captured
+    }
+
+    /// Moved dev cubes and amino acids keep their type with the new
+    /// transform. Residues that were rotated or edited are captured with their
+    /// current atom positions.
+    fn capture_placement(&self, object: &ProjectObject, roots: &[Entity]) -> ProjectObject {
+        match object {
+            ProjectObject::DevCube { scale, .. } => {
+                let Some(transform) = roots
+                    .first()
+                    .and_then(|&root| self.transforms.get(root).ok())
+                else {
+                    return object.clone();
+                };
+                ProjectObject::DevCube {
+                    position: transform.translation,
+                    rotation: transform.rotation,
+                    scale: *scale,
+                }
+            }
+            ProjectObject::AminoAcid {
+                code,
+                residue_number,
+                representation,
+                color_scheme,
+                ..
+            } if !self.atoms_modified(roots) => {
+                match roots
+                    .first()
+                    .and_then(|&root| self.transforms.get(root).ok())
+                {
+                    Some(transform) if transform.rotation == Quat::IDENTITY => {
+                        ProjectObject::AminoAcid {
+                            code: *code,
+                            position: transform.translation,
+                            residue_number: *residue_number,
+                            representation: *representation,
+                            color_scheme: *color_scheme,
+                        }
+                    }
+                    _ => self.capture_structure(object, roots),
+                }
+            }
+            _ => {
+                let moved = roots.iter().any(|&root| {
+                    self.transforms
+                        .get(root)
+                        .is_ok_and(|transform| transform.last_changed() != transform.added())
+                });
+                if moved || self.atoms_modified(roots) {
+                    self.capture_structure(object, roots)
+                } else {
+                    object.clone()
+                }
+            }
+        }
+    }
+
+    fn capture_structure(&self, object: &ProjectObject, roots: &[Entity]) -> ProjectObject {
+        let structure = Structure::from_entities(
+            roots,
+            &self.residue_query,
+            &self.atom_query,
+            &self.bond_query,
+        );
+        if structure.residues.is_empty() {
+            return object.clone();
+        }
+        ProjectObject::Structure {
+            structure,
+            representation: object.representation().unwrap_or_default(),
+            color_scheme: object.color_scheme().unwrap_or_default(),
+        }
+    }
+
+    /// References the atoms of every measurement through the object and
+    /// residue they belong to. Measurements on atoms outside the project
+    /// objects are skipped.
+    fn capture_measurements(&self, objects: &[Vec<Entity>]) -> Vec<Vec<AtomReference>> {
+        let residues: HashMap<Entity, (usize, usize)> = objects
+            .iter()
+            .enumerate()
+            .flat_map(|(object, roots)| {
+                roots
+                    .iter()
+                    .enumerate()
+                    .map(move |(residue, &root)| (root, (object, residue)))
+            })
+            .collect();
+
+        self.measurement_query
+            .iter()
+            .filter_map(|measurement| {
+                measurement
+                    .atoms
+                    .iter()
+                    .map(|&atom| {
+                        let parent = self.parent_query.get(atom).ok()?.parent();
+                        let &(object, residue) = residues.get(&parent)?;
+                        Some(AtomReference {
+                            object,
+                            residue,
+                            atom_name: self.atoms.get(atom).ok()?.atom_name.clone(),
+                        })
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Whether any atom under `roots` was changed after it was spawned.
+    fn atoms_modified(&self, roots: &[Entity]) -> bool {
+        roots
+            .iter()
+            .filter_map(|&root| self.residue_query.get(root).ok())
+            .flat_map(|(_, _, children)| children.iter())
+            .filter_map(|child| self.atoms.get(child).ok())
+            .any(|atom| atom.last_changed() != atom.added())
+    }
+}
+
 fn spawn_project_objects(
     commands: &mut Commands,
     project: &Project,
     project_entities: &mut ProjectEntities,
-    meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<StandardMaterial>>,
-) -> Result<(), String> {
+    assets: &mut ObjectAssets,
+) -> Result<(), ChemistryError> {
     for object in &project.objects {
-        match object {
+        let entities = match object {
             ProjectObject::DevCube {
                 position,
                 rotation,
                 scale,
             } => {
                 let entity = crate::objects::dev_cube::spawn_dev_cube(
-                    commands, meshes, materials, *position, *rotation, *scale,
+                    commands,
+                    &mut assets.meshes,
+                    &mut assets.materials,
+                    *position,
+                    *rotation,
+                    *scale,
                 );
-                project_entities.roots.push(entity);
+                vec![entity]
             }
             ProjectObject::AminoAcid {
                 code,
                 position,
                 residue_number,
+                ..
             } => {
                 let entity = crate::chemistry::amino_acids::AminoAcidBuilder::spawn(
                     commands,
@@ -172,9 +706,46 @@ fn spawn_project_objects(
                     *position,
                     *residue_number,
                 )?;
-                project_entities.roots.push(entity);
+                vec![entity]
+            }
+            ProjectObject::Peptide {
+                sequence,
+                position,
+                chain_id,
+                conformation,
+                ..
+            } => crate::chemistry::amino_acids::PeptideBuilder::spawn(
+                commands,
+                sequence,
+                *position,
+                chain_id,
+                conformation,
+            )?,
+            ProjectObject::StructureFile { path, position, .. } => {
+                let structure = io::load(path)?;
+                structure.spawn(commands, *position)
+            }
+            ProjectObject::Structure { structure, .. } => {
+                structure.spawn(commands, structure.centroid())
+            }
+        };
+        if let (Some(representation), Some(color_scheme)) =
+            (object.representation(), object.color_scheme())
+        {
+            for &entity in &entities {
+                commands
+                    .entity(entity)
+                    .insert((representation, color_scheme));
             }
         }
+        project_entities.roots.extend(&entities);
+        project_entities.objects.push(entities);
     }
+
+    // Measurements reference atoms that only exist once the commands above
+    // are applied.
+    commands.insert_resource(PendingMeasurements {
+        measurements: project.measurements.clone(),
+    });
     Ok(())
 }
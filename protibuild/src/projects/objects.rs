@@ -1,7 +1,10 @@
-use crate::chemistry::amino_acids::types::AminoAcidCode;
+use crate::chemistry::amino_acids::{Conformation, DEFAULT_CHAIN_ID, types::AminoAcidCode};
+use crate::chemistry::io::Structure;
+use crate::chemistry::rendering::{ColorScheme, Representation};
 use bevy::prelude::*;
+use std::path::PathBuf;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ProjectObject {
     DevCube {
         position: Vec3,
@@ -11,7 +14,31 @@ pub enum ProjectObject {
     AminoAcid {
         code: AminoAcidCode,
         position: Vec3,
-        residue_number: u32,
+        residue_number: i32,
+        representation: Representation,
+        color_scheme: ColorScheme,
+    },
+    /// A polypeptide chain built from a one-letter sequence, centered on `position`.
+    Peptide {
+        sequence: String,
+        position: Vec3,
+        chain_id: String,
+        conformation: Conformation,
+        representation: Representation,
+        color_scheme: ColorScheme,
+    },
+    /// A structure loaded from a PDB or mmCIF file, centered on `position`.
+    StructureFile {
+        path: PathBuf,
+        position: Vec3,
+        representation: Representation,
+        color_scheme: ColorScheme,
+    },
+    /// Residues captured from the scene, spawned at their world coordinates.
+    Structure {
+        structure: Structure,
+        representation: Representation,
+        color_scheme: ColorScheme,
     },
 }
 
@@ -40,11 +67,91 @@ impl ProjectObject {
         }
     }
 
-    pub fn amino_acid(code: AminoAcidCode, position: Vec3, residue_number: u32) -> Self {
+    pub fn amino_acid(code: AminoAcidCode, position: Vec3, residue_number: i32) -> Self {
         Self::AminoAcid {
             code,
             position,
             residue_number,
+            representation: Representation::default(),
+            color_scheme: ColorScheme::default(),
+        }
+    }
+
+    pub fn peptide(sequence: impl Into<String>, position: Vec3) -> Self {
+        Self::peptide_with_conformation(sequence, position, Conformation::Extended)
+    }
+
+    pub fn peptide_with_conformation(
+        sequence: impl Into<String>,
+        position: Vec3,
+        conformation: Conformation,
+    ) -> Self {
+        Self::Peptide {
+            sequence: sequence.into(),
+            position,
+            chain_id: DEFAULT_CHAIN_ID.to_string(),
+            conformation,
+            representation: Representation::default(),
+            color_scheme: ColorScheme::default(),
+        }
+    }
+
+    pub fn structure_file(path: impl Into<PathBuf>, position: Vec3) -> Self {
+        Self::StructureFile {
+            path: path.into(),
+            position,
+            representation: Representation::default(),
+            color_scheme: ColorScheme::default(),
+        }
+    }
+
+    /// How the object's atoms and bonds are drawn, or `None` for objects
+    /// without atoms.
+    pub fn representation(&self) -> Option<Representation> {
+        match self {
+            Self::DevCube { .. } => None,
+            Self::AminoAcid { representation, .. }
+            | Self::Peptide { representation, .. }
+            | Self::StructureFile { representation, .. }
+            | Self::Structure { representation, .. } => Some(*representation),
+        }
+    }
+
+    /// Sets the representation of objects with atoms; dev cubes are returned
+    /// unchanged.
+    pub fn with_representation(mut self, new_representation: Representation) -> Self {
+        match &mut self {
+            Self::DevCube { .. } => {}
+            Self::AminoAcid { representation, .. }
+            | Self::Peptide { representation, .. }
+            | Self::StructureFile { representation, .. }
+            | Self::Structure { representation, .. } => *representation = new_representation,
+        }
+        self
+    }
+
+    /// How the object's atoms are colored, or `None` for objects without
+    /// atoms.
+    pub fn color_scheme(&self) -> Option<ColorScheme> {
+        match self {
+            Self::DevCube { .. } => None,
+            Self::AminoAcid { color_scheme, .. }
+            | Self::Peptide { color_scheme, .. }
+            | Self::StructureFile { color_scheme, .. }
+            | Self::Structure { color_scheme, .. } => Some(*color_scheme),
+        }
+    }
+
+    /// Sets the color scheme of objects with atoms; dev cubes are returned
+    /// unchanged.
+    pub fn with_color_scheme(mut self, new_color_scheme: ColorScheme) -> Self {
+        match &mut self {
+            Self::DevCube { .. } => {}
+            Self::AminoAcid { color_scheme, .. }
+            | Self::Peptide { color_scheme, .. }
+            | Self::StructureFile { color_scheme, .. }
+            | Self::Structure { color_scheme, .. } => *color_scheme = new_color_scheme,
         }
+        self
     }
 }
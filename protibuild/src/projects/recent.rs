@@ -0,0 +1,67 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use super::error::ProjectError;
+
+/// Number of project files remembered.
+pub const MAX_RECENT_PROJECTS: usize = 8;
+
+/// File in the configuration directory holding the recent projects.
+const RECENT_PROJECTS_FILE: &str = "recent_projects.json";
+
+/// Recently opened or saved project files, most recent first.
+///
+/// The list is stored in the user's configuration directory so it survives
+/// restarts.
+#[derive(Resource, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RecentProjects {
+    pub paths: Vec<PathBuf>,
+}
+
+impl RecentProjects {
+    /// Reads the stored list, starting empty if there is none.
+    pub fn load() -> Self {
+        let path = Self::file_path();
+        let Ok(contents) = std::fs::read_to_string(&path) else {
+            return Self::default();
+        };
+        match serde_json::from_str(&contents) {
+            Ok(recent) => recent,
+            Err(e) => {
+                error!("Failed to read recent projects: {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<(), ProjectError> {
+        let path = Self::file_path();
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Moves `path` to the front of the list and stores it.
+    pub fn add(&mut self, path: PathBuf) {
+        let path = std::fs::canonicalize(&path).unwrap_or(path);
+        self.paths.retain(|recent| *recent != path);
+        self.paths.insert(0, path);
+        self.paths.truncate(MAX_RECENT_PROJECTS);
+
+        if let Err(e) = self.save() {
+            error!("Failed to save recent projects: {}", e);
+        }
+    }
+
+    fn file_path() -> PathBuf {
+        let directory = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+            .unwrap_or_default();
+        directory.join("protibuild").join(RECENT_PROJECTS_FILE)
+    }
+}
@@ -1,7 +1,140 @@
 use bevy::prelude::*;
+use std::path::{Path, PathBuf};
 
-use super::{Project, objects::ProjectObject};
-use crate::chemistry::amino_acids::types::AminoAcidCode;
+use super::{Project, error::ProjectError, file, objects::ProjectObject};
+use crate::chemistry::amino_acids::{Conformation, types::AminoAcidCode};
+
+/// Directory searched for project files to offer as templates.
+pub const TEMPLATES_DIRECTORY: &str = "templates";
+
+/// Category of the templates read from project files.
+const FILE_TEMPLATE_CATEGORY: &str = "Project Files";
+
+/// Where a template gets its project from.
+#[derive(Debug, Clone)]
+pub enum TemplateSource {
+    /// A function building the project in code.
+    Builder(fn() -> Project),
+    /// A project file, read each time the template is used.
+    File(PathBuf),
+}
+
+/// A project template offered in the File tab.
+#[derive(Debug, Clone)]
+pub struct Template {
+    pub name: String,
+    pub description: String,
+    /// Heading the template is listed under.
+    pub category: String,
+    pub source: TemplateSource,
+}
+
+impl Template {
+    /// Template built by `builder`, named and described after the project it
+    /// builds.
+    pub fn from_builder(category: impl Into<String>, builder: fn() -> Project) -> Self {
+        let project = builder();
+        Self {
+            name: project.name,
+            description: project.description,
+            category: category.into(),
+            source: TemplateSource::Builder(builder),
+        }
+    }
+
+    /// Template read from a project file.
+    pub fn from_file(category: impl Into<String>, path: &Path) -> Result<Self, ProjectError> {
+        let project = file::load(path)?;
+        Ok(Self {
+            name: project.name,
+            description: project.description,
+            category: category.into(),
+            source: TemplateSource::File(path.to_path_buf()),
+        })
+    }
+
+    pub fn build(&self) -> Result<Project, ProjectError> {
+        match &self.source {
+            TemplateSource::Builder(builder) => Ok(builder()),
+            TemplateSource::File(path) => file::load(path),
+        }
+    }
+}
+
+/// Templates listed in the File tab, in registration order.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct TemplateRegistry {
+    templates: Vec<Template>,
+}
+
+impl TemplateRegistry {
+    /// Registry with the templates defined in [`ProjectTemplates`].
+    pub fn builtin() -> Self {
+        let mut registry = Self::default();
+        registry
+            .register(Template::from_builder(
+                "Development",
+                ProjectTemplates::dev_cube,
+            ))
+            .register(Template::from_builder(
+                "Proteins",
+                ProjectTemplates::amino_acids,
+            ))
+            .register(Template::from_builder(
+                "Proteins",
+                ProjectTemplates::secondary_structure,
+            ));
+        registry
+    }
+
+    pub fn register(&mut self, template: Template) -> &mut Self {
+        self.templates.push(template);
+        self
+    }
+
+    /// Registers every project file in `directory`, sorted by file name.
+    /// Files that fail to load are skipped with an error.
+    pub fn register_directory(&mut self, directory: &Path) -> Result<(), ProjectError> {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(directory)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|ext| ext == file::PROJECT_FILE_EXTENSION)
+            })
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            match Template::from_file(FILE_TEMPLATE_CATEGORY, &path) {
+                Ok(template) => {
+                    self.register(template);
+                }
+                Err(e) => error!("Failed to load template {}: {}", path.display(), e),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn templates(&self) -> &[Template] {
+        &self.templates
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Template> {
+        self.templates.iter().find(|template| template.name == name)
+    }
+
+    /// Categories in the order their first template was registered.
+    pub fn categories(&self) -> Vec<&str> {
+        let mut categories: Vec<&str> = Vec::new();
+        for template in &self.templates {
+            if !categories.contains(&template.category.as_str()) {
+                categories.push(&template.category);
+            }
+        }
+        categories
+    }
+}
 
 pub struct ProjectTemplates;
 
@@ -139,4 +272,29 @@ impl ProjectTemplates {
             ),
         ])
     }
+
+    pub fn secondary_structure() -> Project {
+        Project::new(
+            "Secondary Structure",
+            "The same sequence built as an alpha helix, a beta strand and a polyproline II helix",
+        )
+        .with_camera(Vec3::new(0.0, 25.0, 30.0), Vec3::ZERO)
+        .with_objects([
+            ProjectObject::peptide_with_conformation(
+                "AEAAAKEAAAKA",
+                Vec3::new(0.0, 1.0, -10.0),
+                Conformation::AlphaHelix,
+            ),
+            ProjectObject::peptide_with_conformation(
+                "AEAAAKEAAAKA",
+                Vec3::new(0.0, 1.0, 0.0),
+                Conformation::BetaStrand,
+            ),
+            ProjectObject::peptide_with_conformation(
+                "AEAAAKEAAAKA",
+                Vec3::new(0.0, 1.0, 10.0),
+                Conformation::PolyprolineII,
+            ),
+        ])
+    }
 }
@@ -0,0 +1,586 @@
+//! Text selection language.
+//!
+//! A selection is an expression over the atoms of the scene. Terms are
+//! combined with `and`, `or`, `not` and parentheses, where `and` binds
+//! tighter than `or`:
+//!
+//! - `all`, `none`
+//! - `chain A B` - atoms of residues with one of these chain IDs
+//! - `resid 10 12-20` - residue numbers and inclusive ranges
+//! - `resname CYS HIS` - three-letter residue codes
+//! - `name CA CB` - atom names
+//! - `element C N` - element symbols
+//! - `backbone`, `sidechain`, `hydrogen`
+//! - `within 5 of resid 42` - atoms within 5 Å of any atom of a selection
+//!
+//! Like `not`, `within` applies to the term right after it, so
+//! `within 5 of resid 42 and chain A` selects the atoms of chain A near
+//! residue 42. Keywords, residue codes, atom names and elements are
+//! case-insensitive; chain IDs are not.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use bevy::prelude::*;
+
+use crate::chemistry::amino_acids::AminoAcidCode;
+use crate::chemistry::atoms::Element;
+
+/// Atom names of the peptide backbone, including its hydrogens.
+const BACKBONE_ATOMS: [&str; 12] = [
+    "N", "CA", "C", "O", "OXT", "H", "H1", "H2", "H3", "HA", "HA2", "HA3",
+];
+
+/// Words that end the value list of a keyword.
+const RESERVED: [&str; 16] = [
+    "and",
+    "or",
+    "not",
+    "within",
+    "of",
+    "all",
+    "none",
+    "backbone",
+    "sidechain",
+    "hydrogen",
+    "chain",
+    "resid",
+    "resname",
+    "name",
+    "element",
+    ")",
+];
+
+/// Errors produced while parsing a selection.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectionError {
+    /// The selection ended where a term or value was expected.
+    UnexpectedEnd,
+    /// A word that does not fit where it appears.
+    UnexpectedToken(String),
+    /// A keyword without values, like `chain` on its own.
+    MissingValue(String),
+    /// A residue number, range or distance that is not a number.
+    InvalidNumber(String),
+    /// A `within` distance that is negative or not finite.
+    InvalidDistance(String),
+    /// An element symbol that the simulator does not support.
+    UnknownElement(String),
+    /// A residue name that does not map to a standard amino acid.
+    UnknownResidue(String),
+}
+
+impl fmt::Display for SelectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SelectionError::UnexpectedEnd => write!(f, "Unexpected end of selection"),
+            SelectionError::UnexpectedToken(token) => write!(f, "Unexpected '{}'", token),
+            SelectionError::MissingValue(keyword) => write!(f, "Missing value after '{}'", keyword),
+            SelectionError::InvalidNumber(value) => write!(f, "Invalid number '{}'", value),
+            SelectionError::InvalidDistance(value) => {
+                write!(f, "Distance '{}' must be zero or positive", value)
+            }
+            SelectionError::UnknownElement(symbol) => write!(f, "Unknown element '{}'", symbol),
+            SelectionError::UnknownResidue(name) => write!(f, "Unknown residue '{}'", name),
+        }
+    }
+}
+
+impl std::error::Error for SelectionError {}
+
+/// A parsed selection.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectionExpr {
+    All,
+    None,
+    Chain(Vec<String>),
+    ResId(Vec<RangeInclusive<i32>>),
+    ResName(Vec<AminoAcidCode>),
+    /// Atom names in upper case.
+    Name(Vec<String>),
+    Element(Vec<Element>),
+    Backbone,
+    Sidechain,
+    Hydrogen,
+    Not(Box<SelectionExpr>),
+    And(Box<SelectionExpr>, Box<SelectionExpr>),
+    Or(Box<SelectionExpr>, Box<SelectionExpr>),
+    /// Atoms within a distance in Å of any atom of the selection.
+    Within(f32, Box<SelectionExpr>),
+}
+
+/// The properties of one atom that selections are evaluated over.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectionAtom {
+    pub entity: Entity,
+    /// The `AminoAcid` entity the atom belongs to.
+    pub residue: Entity,
+    pub name: String,
+    pub element: Element,
+    /// World-space position.
+    pub position: Vec3,
+    pub code: AminoAcidCode,
+    pub residue_number: i32,
+    pub chain_id: String,
+}
+
+impl SelectionExpr {
+    /// Parses a selection like `chain A and resid 10-20 and name CA`.
+    pub fn parse(text: &str) -> Result<Self, SelectionError> {
+        let spaced = text.replace('(', " ( ").replace(')', " ) ");
+        let mut parser = Parser {
+            tokens: spaced.split_whitespace().collect(),
+            position: 0,
+        };
+        let expr = parser.or()?;
+        match parser.peek() {
+            Some(token) => Err(SelectionError::UnexpectedToken(token.to_string())),
+            None => Ok(expr),
+        }
+    }
+
+    /// Whether each of `atoms` is selected, in order.
+    pub fn evaluate(&self, atoms: &[SelectionAtom]) -> Vec<bool> {
+        match self {
+            SelectionExpr::Not(expr) => expr.evaluate(atoms).into_iter().map(|x| !x).collect(),
+            SelectionExpr::And(a, b) => {
+                zip_with(a.evaluate(atoms), b.evaluate(atoms), |a, b| a && b)
+            }
+            SelectionExpr::Or(a, b) => {
+                zip_with(a.evaluate(atoms), b.evaluate(atoms), |a, b| a || b)
+            }
+            SelectionExpr::Within(distance, expr) => {
+                let grid = PositionGrid::new(
+                    *distance,
+                    atoms
+                        .iter()
+                        .zip(expr.evaluate(atoms))
+                        .filter(|&(_, selected)| selected)
+                        .map(|(atom, _)| atom.position),
+                );
+                atoms
+                    .iter()
+                    .map(|atom| grid.any_within(atom.position))
+                    .collect()
+            }
+            _ => atoms.iter().map(|atom| self.matches(atom)).collect(),
+        }
+    }
+
+    /// Whether a term without sub-selections matches an atom.
+    fn matches(&self, atom: &SelectionAtom) -> bool {
+        match self {
+            SelectionExpr::All => true,
+            SelectionExpr::None => false,
+            SelectionExpr::Chain(chains) => chains.contains(&atom.chain_id),
+            SelectionExpr::ResId(ranges) => ranges
+                .iter()
+                .any(|range| range.contains(&atom.residue_number)),
+            SelectionExpr::ResName(codes) => codes.contains(&atom.code),
+            SelectionExpr::Name(names) => names
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(&atom.name)),
+            SelectionExpr::Element(elements) => elements.contains(&atom.element),
+            SelectionExpr::Backbone => is_backbone(&atom.name),
+            SelectionExpr::Sidechain => !is_backbone(&atom.name),
+            SelectionExpr::Hydrogen => atom.element == Element::Hydrogen,
+            SelectionExpr::Not(_)
+            | SelectionExpr::And(..)
+            | SelectionExpr::Or(..)
+            | SelectionExpr::Within(..) => false,
+        }
+    }
+}
+
+fn is_backbone(name: &str) -> bool {
+    BACKBONE_ATOMS
+        .iter()
+        .any(|backbone| backbone.eq_ignore_ascii_case(name))
+}
+
+fn zip_with(a: Vec<bool>, b: Vec<bool>, f: impl Fn(bool, bool) -> bool) -> Vec<bool> {
+    a.into_iter().zip(b).map(|(a, b)| f(a, b)).collect()
+}
+
+/// Recursive descent parser over whitespace separated tokens.
+struct Parser<'a> {
+    tokens: Vec<&'a str>,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Result<&'a str, SelectionError> {
+        let token = self.peek().ok_or(SelectionError::UnexpectedEnd)?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    /// Consumes the next token if it is the keyword `keyword`.
+    fn eat(&mut self, keyword: &str) -> bool {
+        let matches = self
+            .peek()
+            .is_some_and(|token| token.eq_ignore_ascii_case(keyword));
+        if matches {
+            self.position += 1;
+        }
+        matches
+    }
+
+    fn or(&mut self) -> Result<SelectionExpr, SelectionError> {
+        let mut expr = self.and()?;
+        while self.eat("or") {
+            expr = SelectionExpr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<SelectionExpr, SelectionError> {
+        let mut expr = self.unary()?;
+        while self.eat("and") {
+            expr = SelectionExpr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<SelectionExpr, SelectionError> {
+        if self.eat("not") {
+            return Ok(SelectionExpr::Not(Box::new(self.unary()?)));
+        }
+        if self.eat("within") {
+            let token = self.next()?;
+            let distance: f32 = token
+                .parse()
+                .map_err(|_| SelectionError::InvalidNumber(token.to_string()))?;
+            if !distance.is_finite() || distance < 0.0 {
+                return Err(SelectionError::InvalidDistance(token.to_string()));
+            }
+            if !self.eat("of") {
+                return match self.peek() {
+                    Some(token) => Err(SelectionError::UnexpectedToken(token.to_string())),
+                    None => Err(SelectionError::UnexpectedEnd),
+                };
+            }
+            return Ok(SelectionExpr::Within(distance, Box::new(self.unary()?)));
+        }
+        self.term()
+    }
+
+    fn term(&mut self) -> Result<SelectionExpr, SelectionError> {
+        let token = self.next()?;
+        match token.to_ascii_lowercase().as_str() {
+            "(" => {
+                let expr = self.or()?;
+                match self.next()? {
+                    ")" => Ok(expr),
+                    token => Err(SelectionError::UnexpectedToken(token.to_string())),
+                }
+            }
+            "all" => Ok(SelectionExpr::All),
+            "none" => Ok(SelectionExpr::None),
+            "backbone" => Ok(SelectionExpr::Backbone),
+            "sidechain" => Ok(SelectionExpr::Sidechain),
+            "hydrogen" => Ok(SelectionExpr::Hydrogen),
+            "chain" => Ok(SelectionExpr::Chain(
+                self.values(token, |value| Ok(value.to_string()))?,
+            )),
+            "resid" => Ok(SelectionExpr::ResId(self.values(token, parse_range)?)),
+            "resname" => Ok(SelectionExpr::ResName(self.values(token, |value| {
+                AminoAcidCode::from_three_letter(value)
+                    .ok_or_else(|| SelectionError::UnknownResidue(value.to_string()))
+            })?)),
+            "name" => Ok(SelectionExpr::Name(
+                self.values(token, |value| Ok(value.to_ascii_uppercase()))?,
+            )),
+            "element" => Ok(SelectionExpr::Element(self.values(token, |value| {
+                Element::from_symbol(value)
+                    .ok_or_else(|| SelectionError::UnknownElement(value.to_string()))
+            })?)),
+            _ => Err(SelectionError::UnexpectedToken(token.to_string())),
+        }
+    }
+
+    /// Values following `keyword`, up to the next keyword or parenthesis.
+    fn values<T>(
+        &mut self,
+        keyword: &str,
+        parse: impl Fn(&str) -> Result<T, SelectionError>,
+    ) -> Result<Vec<T>, SelectionError> {
+        let mut values = Vec::new();
+        while let Some(token) = self.peek()
+            && token != "("
+            && !RESERVED
+                .iter()
+                .any(|reserved| reserved.eq_ignore_ascii_case(token))
+        {
+            values.push(parse(token)?);
+            self.position += 1;
+        }
+        if values.is_empty() {
+            return Err(SelectionError::MissingValue(keyword.to_string()));
+        }
+        Ok(values)
+    }
+}
+
+/// Parses a residue number like `42` or `-3`, or an inclusive range like
+/// `10-20` or `-5:-1`.
+fn parse_range(value: &str) -> Result<RangeInclusive<i32>, SelectionError> {
+    let number = |text: &str| {
+        text.parse::<i32>()
+            .map_err(|_| SelectionError::InvalidNumber(value.to_string()))
+    };
+    // A leading minus sign belongs to the first number.
+    let separator = value
+        .char_indices()
+        .skip(1)
+        .find(|&(_, c)| c == '-' || c == ':');
+    match separator {
+        Some((index, _)) => Ok(number(&value[..index])?..=number(&value[index + 1..])?),
+        None => number(value).map(|number| number..=number),
+    }
+}
+
+/// Positions binned into cubic cells as wide as the search distance, so only
+/// the 27 cells around a point need to be searched.
+struct PositionGrid {
+    distance: f32,
+    cells: HashMap<IVec3, Vec<Vec3>>,
+}
+
+impl PositionGrid {
+    fn new(distance: f32, positions: impl IntoIterator<Item = Vec3>) -> Self {
+        let mut grid = Self {
+            distance: distance.max(f32::EPSILON),
+            cells: HashMap::new(),
+        };
+        for position in positions {
+            grid.cells
+                .entry(grid.cell(position))
+                .or_default()
+                .push(position);
+        }
+        grid
+    }
+
+    fn cell(&self, position: Vec3) -> IVec3 {
+        (position / self.distance).floor().as_ivec3()
+    }
+
+    fn any_within(&self, position: Vec3) -> bool {
+        let center = self.cell(position);
+        let distance_squared = self.distance * self.distance;
+        (-1..=1).any(|x| {
+            (-1..=1).any(|y| {
+                (-1..=1).any(|z| {
+                    self.cells
+                        .get(&(center + IVec3::new(x, y, z)))
+                        .is_some_and(|cell| {
+                            cell.iter()
+                                .any(|other| other.distance_squared(position) <= distance_squared)
+                        })
+                })
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn atom(
+        index: u32,
+        name: &str,
+        element: Element,
+        code: AminoAcidCode,
+        residue: i32,
+        chain_id: &str,
+    ) -> SelectionAtom {
+        SelectionAtom {
+            entity: Entity::from_raw_u32(index).unwrap(),
+            residue: Entity::from_raw_u32(1000 + residue.unsigned_abs()).unwrap(),
+            name: name.to_string(),
+            element,
+            position: Vec3::new(residue as f32 * 3.8, 0.0, 0.0),
+            code,
+            residue_number: residue,
+            chain_id: chain_id.to_string(),
+        }
+    }
+
+    /// Backbone and side-chain atoms of a few residues over two chains.
+    fn atoms() -> Vec<SelectionAtom> {
+        vec![
+            atom(1, "N", Element::Nitrogen, AminoAcidCode::Gly, 10, "A"),
+            atom(2, "CA", Element::Carbon, AminoAcidCode::Gly, 10, "A"),
+            atom(3, "CA", Element::Carbon, AminoAcidCode::Cys, 11, "A"),
+            atom(4, "SG", Element::Sulfur, AminoAcidCode::Cys, 11, "A"),
+            atom(5, "CA", Element::Carbon, AminoAcidCode::Ala, 20, "B"),
+            atom(6, "HB1", Element::Hydrogen, AminoAcidCode::Ala, 20, "B"),
+            atom(7, "CA", Element::Carbon, AminoAcidCode::Ser, -2, "B"),
+        ]
+    }
+
+    fn select(text: &str) -> Vec<bool> {
+        SelectionExpr::parse(text).unwrap().evaluate(&atoms())
+    }
+
+    #[test]
+    fn terms_match_their_atoms() {
+        assert_eq!(select("all"), [true; 7]);
+        assert_eq!(select("none"), [false; 7]);
+        assert_eq!(
+            select("chain B"),
+            [false, false, false, false, true, true, true]
+        );
+        assert_eq!(
+            select("resname CYS ser"),
+            [false, false, true, true, false, false, true]
+        );
+        assert_eq!(
+            select("name ca"),
+            [false, true, true, false, true, false, true]
+        );
+        assert_eq!(
+            select("element S H"),
+            [false, false, false, true, false, true, false]
+        );
+        assert_eq!(
+            select("sidechain"),
+            [false, false, false, true, false, true, false]
+        );
+        assert_eq!(
+            select("hydrogen"),
+            [false, false, false, false, false, true, false]
+        );
+    }
+
+    #[test]
+    fn residue_ranges_include_both_ends() {
+        assert_eq!(
+            select("resid 10"),
+            [true, true, false, false, false, false, false]
+        );
+        assert_eq!(
+            select("resid 11-20"),
+            [false, false, true, true, true, true, false]
+        );
+        assert_eq!(
+            select("resid 10:10 20"),
+            [true, true, false, false, true, true, false]
+        );
+        assert_eq!(
+            select("resid -2"),
+            [false, false, false, false, false, false, true]
+        );
+        assert_eq!(
+            select("resid -5-10"),
+            [true, true, false, false, false, false, true]
+        );
+        assert_eq!(
+            select("resid 12-15"),
+            [false, false, false, false, false, false, false]
+        );
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(
+            select("chain A and name CA"),
+            [false, true, true, false, false, false, false]
+        );
+        assert_eq!(
+            select("element S or chain B and name CA"),
+            [false, false, false, true, true, false, true]
+        );
+        assert_eq!(
+            select("(element S or chain B) and name CA"),
+            [false, false, false, false, true, false, true]
+        );
+    }
+
+    #[test]
+    fn not_applies_to_the_next_term() {
+        assert_eq!(
+            select("not chain A"),
+            [false, false, false, false, true, true, true]
+        );
+        assert_eq!(
+            select("not chain A and name CA"),
+            [false, false, false, false, true, false, true]
+        );
+        assert_eq!(select("not (backbone or element S H)"), [false; 7]);
+        assert_eq!(select("not not resid 10"), select("resid 10"));
+    }
+
+    #[test]
+    fn within_selects_atoms_near_the_selection() {
+        // Residues are 3.8 Å apart along x.
+        assert_eq!(
+            select("within 4 of resid 11"),
+            [true, true, true, true, false, false, false]
+        );
+        assert_eq!(
+            select("within 0 of resid 11"),
+            [false, false, true, true, false, false, false]
+        );
+        assert_eq!(
+            select("within 4 of resid 11 and chain A and name CA"),
+            [false, true, true, false, false, false, false]
+        );
+        assert_eq!(select("within 100 of none"), [false; 7]);
+    }
+
+    #[test]
+    fn malformed_selections_are_rejected() {
+        let error = |text: &str| SelectionExpr::parse(text).unwrap_err();
+
+        assert_eq!(
+            error("chain and name CA"),
+            SelectionError::MissingValue("chain".to_string())
+        );
+        assert_eq!(error("name CA and"), SelectionError::UnexpectedEnd);
+        assert_eq!(error("(name CA"), SelectionError::UnexpectedEnd);
+        assert_eq!(
+            error("name CA )"),
+            SelectionError::UnexpectedToken(")".to_string())
+        );
+        assert_eq!(
+            error("resid 1-x"),
+            SelectionError::InvalidNumber("1-x".to_string())
+        );
+        assert_eq!(
+            error("resname XYZ"),
+            SelectionError::UnknownResidue("XYZ".to_string())
+        );
+        assert_eq!(
+            error("element Xx"),
+            SelectionError::UnknownElement("Xx".to_string())
+        );
+        assert_eq!(
+            error("within 5 resid 3"),
+            SelectionError::UnexpectedToken("resid".to_string())
+        );
+        assert_eq!(
+            error("within far of resid 3"),
+            SelectionError::InvalidNumber("far".to_string())
+        );
+        assert_eq!(
+            error("within -1 of resid 3"),
+            SelectionError::InvalidDistance("-1".to_string())
+        );
+        assert_eq!(
+            error("within inf of resid 3"),
+            SelectionError::InvalidDistance("inf".to_string())
+        );
+        assert_eq!(
+            error("banana"),
+            SelectionError::UnexpectedToken("banana".to_string())
+        );
+    }
+}
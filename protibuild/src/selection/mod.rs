@@ -0,0 +1,354 @@
+//! Multi-atom selection.
+//!
+//! The [`Selection`] holds the atoms picked by clicking residues, dragging a
+//! box or lasso on the screen or evaluating a text query (see [`language`]).
+//! Tools that act on part of the scene, like measurements, export and
+//! minimization, read it instead of a single hovered or selected entity.
+
+pub mod language;
+
+use std::collections::HashMap;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use crate::chemistry::amino_acids::{AminoAcid, Residue};
+use crate::chemistry::atoms::Atom;
+use crate::chemistry::rendering::Representation;
+use language::{SelectionAtom, SelectionExpr};
+
+/// Gizmo color outlining selected atoms and residues.
+pub const SELECTION_COLOR: Color = Color::srgb(1.0, 0.85, 0.2);
+
+/// How new atoms are combined with the current selection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SelectionMode {
+    /// Replace the selection.
+    #[default]
+    Replace,
+    /// Add the atoms to the selection.
+    Add,
+    /// Remove the atoms from the selection.
+    Remove,
+    /// Remove the atoms if all of them are selected, otherwise add them.
+    Toggle,
+}
+
+/// Selected atoms in the order they were selected, with the `AminoAcid`
+/// entity each belongs to.
+#[derive(Resource, Debug, Default)]
+pub struct Selection {
+    atoms: Vec<Entity>,
+    residues: HashMap<Entity, Entity>,
+}
+
+impl Selection {
+    pub fn atoms(&self) -> &[Entity] {
+        &self.atoms
+    }
+
+    pub fn len(&self) -> usize {
+        self.atoms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.atoms.is_empty()
+    }
+
+    pub fn contains(&self, atom: Entity) -> bool {
+        self.residues.contains_key(&atom)
+    }
+
+    /// Residues with at least one selected atom, in selection order.
+    pub fn residues(&self) -> Vec<Entity> {
+        let mut residues: Vec<Entity> = Vec::new();
+        for atom in &self.atoms {
+            let residue = self.residues[atom];
+            if !residues.contains(&residue) {
+                residues.push(residue);
+            }
+        }
+        residues
+    }
+
+    pub fn clear(&mut self) {
+        self.atoms.clear();
+        self.residues.clear();
+    }
+
+    /// Combines atoms, given with their residue, with the selection.
+    pub fn apply(
+        &mut self,
+        atoms: impl IntoIterator<Item = (Entity, Entity)>,
+        mode: SelectionMode,
+    ) {
+        let atoms: Vec<(Entity, Entity)> = atoms.into_iter().collect();
+        let mode = match mode {
+            SelectionMode::Toggle if atoms.iter().all(|&(atom, _)| self.contains(atom)) => {
+                SelectionMode::Remove
+            }
+            SelectionMode::Toggle => SelectionMode::Add,
+            mode => mode,
+        };
+
+        match mode {
+            SelectionMode::Replace | SelectionMode::Add | SelectionMode::Toggle => {
+                if mode == SelectionMode::Replace {
+                    self.clear();
+                }
+                for (atom, residue) in atoms {
+                    if self.residues.insert(atom, residue).is_none() {
+                        self.atoms.push(atom);
+                    }
+                }
+            }
+            SelectionMode::Remove => {
+                self.remove(atoms.into_iter().map(|(atom, _)| atom));
+            }
+        }
+    }
+
+    pub fn remove(&mut self, atoms: impl IntoIterator<Item = Entity>) {
+        for atom in atoms {
+            self.residues.remove(&atom);
+        }
+        self.atoms.retain(|atom| self.residues.contains_key(atom));
+    }
+}
+
+/// Text of the selection query field and the error of the last query.
+#[derive(Resource, Debug, Default)]
+pub struct SelectionQuery {
+    pub text: String,
+    /// Whether the query field has keyboard focus, so typing does not
+    /// trigger shortcuts.
+    pub editing: bool,
+    pub error: Option<String>,
+}
+
+/// Message to select the atoms matching a selection query, e.g.
+/// `chain A and resid 10-20 and name CA`.
+#[derive(Message, Debug, Clone)]
+pub struct SelectMessage {
+    pub query: String,
+    pub mode: SelectionMode,
+}
+
+/// The atoms of amino acid residues, with the properties selections are
+/// evaluated over.
+#[derive(SystemParam)]
+pub struct SelectableAtoms<'w, 's> {
+    atom_query: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Atom,
+            &'static GlobalTransform,
+            &'static ChildOf,
+        ),
+    >,
+    residue_query: Query<'w, 's, (&'static AminoAcid, &'static Residue)>,
+    representation_query: Query<'w, 's, &'static Representation>,
+}
+
+impl SelectableAtoms<'_, '_> {
+    pub fn collect(&self) -> Vec<SelectionAtom> {
+        self.atom_query
+            .iter()
+            .filter_map(|(entity, atom, transform, child_of)| {
+                let (amino_acid, residue) = self.residue_query.get(child_of.parent()).ok()?;
+                Some(SelectionAtom {
+                    entity,
+                    residue: child_of.parent(),
+                    name: atom.atom_name.clone(),
+                    element: atom.element,
+                    position: transform.translation(),
+                    code: amino_acid.code,
+                    residue_number: residue.residue_number,
+                    chain_id: residue.chain_id.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Atoms of residues with the residue they belong to.
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, Entity, &Atom, &GlobalTransform)> + '_ {
+        self.atom_query
+            .iter()
+            .filter(|(.., child_of)| self.residue_query.contains(child_of.parent()))
+            .map(|(entity, atom, transform, child_of)| (entity, child_of.parent(), atom, transform))
+    }
+
+    /// The residue an atom belongs to, if it is part of one.
+    pub fn residue(&self, atom: Entity) -> Option<Entity> {
+        let (.., child_of) = self.atom_query.get(atom).ok()?;
+        let residue = child_of.parent();
+        self.residue_query.contains(residue).then_some(residue)
+    }
+
+    /// Radius an atom of `residue` is picked with, matching how it is drawn.
+    pub fn pick_radius(&self, residue: Entity, atom: &Atom) -> f32 {
+        self.representation_query
+            .get(residue)
+            .copied()
+            .unwrap_or_default()
+            .pick_radius(atom.element)
+    }
+
+    /// The atoms of `residue`, each with the residue.
+    pub fn residue_atoms(&self, residue: Entity) -> Vec<(Entity, Entity)> {
+        self.iter()
+            .filter(|&(_, parent, ..)| parent == residue)
+            .map(|(atom, residue, ..)| (atom, residue))
+            .collect()
+    }
+}
+
+/// Plugin for the atom selection and its query language.
+pub struct SelectionPlugin;
+
+impl Plugin for SelectionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Selection>()
+            .init_resource::<SelectionQuery>()
+            .add_message::<SelectMessage>()
+            .add_systems(Update, (remove_despawned_atoms, handle_select).chain())
+            .add_systems(Last, draw_selection);
+    }
+}
+
+/// Drops atoms from the selection once they are despawned, e.g. by a project
+/// switch or a mutation.
+fn remove_despawned_atoms(mut removed: RemovedComponents<Atom>, mut selection: ResMut<Selection>) {
+    let despawned: Vec<Entity> = removed
+        .read()
+        .filter(|&atom| selection.contains(atom))
+        .collect();
+    if !despawned.is_empty() {
+        selection.remove(despawned);
+    }
+}
+
+fn handle_select(
+    mut messages: MessageReader<SelectMessage>,
+    mut selection: ResMut<Selection>,
+    mut query: ResMut<SelectionQuery>,
+    atoms: SelectableAtoms,
+) {
+    for message in messages.read() {
+        let expr = match SelectionExpr::parse(&message.query) {
+            Ok(expr) => expr,
+            Err(e) => {
+                warn!("Failed to parse selection '{}': {}", message.query, e);
+                query.error = Some(e.to_string());
+                continue;
+            }
+        };
+        query.error = None;
+
+        let atoms = atoms.collect();
+        let selected = expr
+            .evaluate(&atoms)
+            .into_iter()
+            .zip(&atoms)
+            .filter(|&(selected, _)| selected)
+            .map(|(_, atom)| (atom.entity, atom.residue));
+        selection.apply(selected, message.mode);
+        info!("Selected {} atoms", selection.len());
+    }
+}
+
+/// Outlines each selected atom with a sphere slightly larger than the atom.
+fn draw_selection(
+    mut gizmos: Gizmos,
+    selection: Res<Selection>,
+    atom_query: Query<(&Atom, &GlobalTransform)>,
+) {
+    for &entity in selection.atoms() {
+        if let Ok((atom, transform)) = atom_query.get(entity) {
+            gizmos.sphere(
+                Isometry3d::from_translation(transform.translation()),
+                atom.element.covalent_radius() + 0.15,
+                SELECTION_COLOR,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity(index: u32) -> Entity {
+        Entity::from_raw_u32(index).unwrap()
+    }
+
+    /// Atoms 1 to 3 of residue 10 and atoms 4 and 5 of residue 20.
+    fn atoms(indices: &[u32]) -> Vec<(Entity, Entity)> {
+        indices
+            .iter()
+            .map(|&index| (entity(index), entity(if index <= 3 { 10 } else { 20 })))
+            .collect()
+    }
+
+    fn selection(indices: &[u32]) -> Selection {
+        let mut selection = Selection::default();
+        selection.apply(atoms(indices), SelectionMode::Replace);
+        selection
+    }
+
+    fn selected(selection: &Selection) -> Vec<Entity> {
+        selection.atoms().to_vec()
+    }
+
+    #[test]
+    fn replace_discards_the_previous_selection() {
+        let mut selection = selection(&[1, 2, 4]);
+        selection.apply(atoms(&[5, 3, 5]), SelectionMode::Replace);
+
+        assert_eq!(selected(&selection), [entity(5), entity(3)]);
+        assert!(!selection.contains(entity(1)));
+        assert_eq!(selection.residues(), [entity(20), entity(10)]);
+    }
+
+    #[test]
+    fn add_keeps_the_selection_order_without_duplicates() {
+        let mut selection = selection(&[4, 1]);
+        selection.apply(atoms(&[2, 4, 5]), SelectionMode::Add);
+
+        assert_eq!(
+            selected(&selection),
+            [entity(4), entity(1), entity(2), entity(5)]
+        );
+        assert_eq!(selection.residues(), [entity(20), entity(10)]);
+    }
+
+    #[test]
+    fn remove_drops_only_the_given_atoms() {
+        let mut selection = selection(&[1, 2, 4, 5]);
+        selection.apply(atoms(&[2, 3, 4]), SelectionMode::Remove);
+
+        assert_eq!(selected(&selection), [entity(1), entity(5)]);
+        assert!(!selection.contains(entity(2)));
+
+        selection.apply(atoms(&[1, 5]), SelectionMode::Remove);
+        assert!(selection.is_empty());
+        assert!(selection.residues().is_empty());
+    }
+
+    #[test]
+    fn toggle_removes_atoms_only_when_all_are_selected() {
+        let mut selection = selection(&[1, 2]);
+
+        // Atom 3 is not selected yet, so the whole residue is added.
+        selection.apply(atoms(&[1, 2, 3]), SelectionMode::Toggle);
+        assert_eq!(selected(&selection), [entity(1), entity(2), entity(3)]);
+
+        selection.apply(atoms(&[1, 2, 3]), SelectionMode::Toggle);
+        assert!(selection.is_empty());
+
+        selection.apply(atoms(&[4]), SelectionMode::Toggle);
+        assert_eq!(selected(&selection), [entity(4)]);
+    }
+}
@@ -0,0 +1,172 @@
+use bevy::prelude::*;
+
+use super::tab_bar::{SECONDARY_TEXT_COLOR, TEXT_COLOR};
+use crate::chemistry::amino_acids::{AminoAcid, Residue};
+use crate::chemistry::atoms::{Atom, AtomBonds, Bond};
+use crate::user::interaction::HoveredAtom;
+
+#[derive(Component)]
+struct AtomInspector;
+
+#[derive(Component)]
+struct AtomTitleLabel;
+
+#[derive(Component)]
+struct AtomDetailsLabel;
+
+/// Tooltip describing the atom under the crosshair.
+pub struct AtomInspectorPlugin;
+
+impl Plugin for AtomInspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_atom_inspector)
+            .add_systems(Update, update_atom_inspector);
+    }
+}
+
+fn setup_atom_inspector(mut commands: Commands) {
+    commands
+        .spawn((
+            Name::new("AtomInspector"),
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Percent(50.0),
+                top: Val::Percent(50.0),
+                margin: UiRect {
+                    left: Val::Px(16.0),
+                    top: Val::Px(16.0),
+                    ..default()
+                },
+                flex_direction: FlexDirection::Column,
+                padding: UiRect::all(Val::Px(8.0)),
+                border_radius: BorderRadius::all(Val::Px(6.0)),
+                display: Display::None,
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.12, 0.12, 0.14, 0.9)),
+            AtomInspector,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(TEXT_COLOR),
+                Node {
+                    margin: UiRect::bottom(Val::Px(4.0)),
+                    ..default()
+                },
+                AtomTitleLabel,
+            ));
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font_size: 12.0,
+                    ..default()
+                },
+                TextColor(SECONDARY_TEXT_COLOR),
+                AtomDetailsLabel,
+            ));
+        });
+}
+
+/// Shows the hovered atom's name, element, residue, world position and bonded
+/// neighbours. The text is only rebuilt when another atom is hovered or the
+/// hovered atom moves or gains or loses bonds.
+#[allow(clippy::type_complexity)]
+fn update_atom_inspector(
+    hovered: Res<HoveredAtom>,
+    atom_query: Query<(&Atom, Ref<GlobalTransform>, &ChildOf, Ref<AtomBonds>)>,
+    residue_query: Query<(&AminoAcid, &Residue)>,
+    bond_query: Query<&Bond>,
+    mut panel_query: Query<&mut Node, With<AtomInspector>>,
+    mut title_label: Query<&mut Text, (With<AtomTitleLabel>, Without<AtomDetailsLabel>)>,
+    mut details_label: Query<&mut Text, (With<AtomDetailsLabel>, Without<AtomTitleLabel>)>,
+) {
+    let Ok(mut panel) = panel_query.single_mut() else {
+        return;
+    };
+
+    let Some((atom, transform, child_of, atom_bonds)) = hovered
+        .entity
+        .and_then(|entity| atom_query.get(entity).ok())
+    else {
+        if panel.display != Display::None {
+            panel.display = Display::None;
+        }
+        return;
+    };
+    if panel.display != Display::Flex {
+        panel.display = Display::Flex;
+    }
+    if !hovered.is_changed() && !transform.is_changed() && !atom_bonds.is_changed() {
+        return;
+    }
+
+    let residue = child_of.parent();
+    let residue_line = match residue_query.get(residue) {
+        Ok((amino_acid, info)) => format!(
+            "{} {}, chain {}",
+            amino_acid.code.name(),
+            info.residue_number,
+            info.chain_id
+        ),
+        Err(_) => "No residue".to_string(),
+    };
+
+    // Neighbours in other residues (peptide and disulfide bonds) are labeled
+    // with their residue.
+    let neighbours: Vec<String> = atom_bonds
+        .iter()
+        .filter_map(|bond| bond_query.get(bond).ok())
+        .filter_map(|bond| {
+            let neighbour = if hovered.entity == Some(bond.atom1) {
+                bond.atom2
+            } else {
+                bond.atom1
+            };
+            atom_query.get(neighbour).ok()
+        })
+        .map(|(neighbour, _, neighbour_parent, _)| {
+            match residue_query.get(neighbour_parent.parent()) {
+                Ok((amino_acid, info)) if neighbour_parent.parent() != residue => format!(
+                    "{} ({} {})",
+                    neighbour.atom_name,
+                    amino_acid.code.three_letter(),
+                    info.residue_number
+                ),
+                _ => neighbour.atom_name.clone(),
+            }
+        })
+        .collect();
+
+    let title = format!(
+        "{}  {} ({})",
+        atom.atom_name,
+        atom.element.name(),
+        atom.element.symbol()
+    );
+    if let Ok(mut text) = title_label.single_mut()
+        && text.0 != title
+    {
+        text.0 = title;
+    }
+
+    let position = transform.translation();
+    let bonded = if neighbours.is_empty() {
+        "none".to_string()
+    } else {
+        neighbours.join(", ")
+    };
+    let details = format!(
+        "{}\n({:.2}, {:.2}, {:.2}) Å\nBonded to: {}",
+        residue_line, position.x, position.y, position.z, bonded
+    );
+    if let Ok(mut text) = details_label.single_mut()
+        && text.0 != details
+    {
+        text.0 = details;
+    }
+}
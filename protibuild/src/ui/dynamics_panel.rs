@@ -0,0 +1,424 @@
+use bevy::prelude::*;
+
+use super::tab_bar::{
+    BUTTON_COLOR, BUTTON_HOVER_COLOR, BUTTON_TEXT_COLOR, SECONDARY_TEXT_COLOR, TEXT_COLOR,
+};
+use crate::chemistry::amino_acids::Residue;
+use crate::chemistry::dynamics::{
+    DynamicsControl, DynamicsControlMessage, DynamicsSettings, DynamicsState,
+};
+use crate::chemistry::minimization::{
+    MinimizationAlgorithm, MinimizationReport, MinimizationScope, MinimizationSettings,
+    MinimizeMessage,
+};
+use crate::physics::TogglePhysicsMessage;
+use crate::projects::ProjectResource;
+use crate::user::interaction::SelectedResidue;
+
+#[derive(Component)]
+struct DynamicsStatusLabel;
+
+#[derive(Component)]
+struct DynamicsButton {
+    control: DynamicsControl,
+}
+
+/// Label of the start/pause button.
+#[derive(Component)]
+struct ToggleLabel;
+
+#[derive(Component)]
+struct MinimizationStatusLabel;
+
+/// Label of the button switching the minimization algorithm.
+#[derive(Component)]
+struct AlgorithmLabel;
+
+#[derive(Component)]
+struct PhysicsButton;
+
+/// Label of the rigid-body physics toggle.
+#[derive(Component)]
+struct PhysicsLabel;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum MinimizeButton {
+    Residue,
+    Chain,
+    Project,
+    Algorithm,
+}
+
+/// Panel with the simulation, minimization and physics controls and their
+/// state.
+pub struct DynamicsPanelPlugin;
+
+impl Plugin for DynamicsPanelPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_dynamics_panel).add_systems(
+            Update,
+            (
+                update_dynamics_panel,
+                update_minimization_panel,
+                update_physics_label,
+                handle_dynamics_button_click,
+                handle_minimize_button_click,
+                handle_physics_button_click,
+            ),
+        );
+    }
+}
+
+fn setup_dynamics_panel(mut commands: Commands) {
+    commands
+        .spawn((
+            Name::new("DynamicsPanel"),
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(44.0),
+                right: Val::Px(12.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Start,
+                padding: UiRect::all(Val::Px(10.0)),
+                border_radius: BorderRadius::all(Val::Px(8.0)),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.12, 0.12, 0.14, 0.98)),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("Dynamics"),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(TEXT_COLOR),
+                Node {
+                    margin: UiRect::bottom(Val::Px(6.0)),
+                    ..default()
+                },
+            ));
+
+            parent
+                .spawn(Node {
+                    flex_direction: FlexDirection::Row,
+                    column_gap: Val::Px(6.0),
+                    margin: UiRect::bottom(Val::Px(6.0)),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    spawn_dynamics_button(parent, DynamicsControl::Toggle, "Start");
+                    spawn_dynamics_button(parent, DynamicsControl::Step, "Step");
+                });
+
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font_size: 12.0,
+                    ..default()
+                },
+                TextColor(SECONDARY_TEXT_COLOR),
+                DynamicsStatusLabel,
+            ));
+
+            parent.spawn((
+                Text::new("Minimize"),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(TEXT_COLOR),
+                Node {
+                    margin: UiRect::vertical(Val::Px(6.0)),
+                    ..default()
+                },
+            ));
+
+            parent
+                .spawn(Node {
+                    flex_direction: FlexDirection::Row,
+                    column_gap: Val::Px(6.0),
+                    margin: UiRect::bottom(Val::Px(6.0)),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    spawn_minimize_button(parent, MinimizeButton::Residue, "Residue");
+                    spawn_minimize_button(parent, MinimizeButton::Chain, "Chain");
+                    spawn_minimize_button(parent, MinimizeButton::Project, "All");
+                    spawn_minimize_button(parent, MinimizeButton::Algorithm, "");
+                });
+
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font_size: 12.0,
+                    ..default()
+                },
+                TextColor(SECONDARY_TEXT_COLOR),
+                MinimizationStatusLabel,
+            ));
+
+            parent
+                .spawn((
+                    Node {
+                        width: Val::Px(120.0),
+                        height: Val::Px(28.0),
+                        margin: UiRect::top(Val::Px(8.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        border_radius: BorderRadius::all(Val::Px(6.0)),
+                        ..default()
+                    },
+                    BackgroundColor(BUTTON_COLOR),
+                    PhysicsButton,
+                    Interaction::None,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        Text::new(""),
+                        TextFont {
+                            font_size: 13.0,
+                            ..default()
+                        },
+                        TextColor(BUTTON_TEXT_COLOR),
+                        PhysicsLabel,
+                    ));
+                });
+        });
+}
+
+fn spawn_minimize_button(parent: &mut ChildSpawnerCommands, button: MinimizeButton, label: &str) {
+    parent
+        .spawn((
+            Node {
+                width: Val::Px(64.0),
+                height: Val::Px(28.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                border_radius: BorderRadius::all(Val::Px(6.0)),
+                ..default()
+            },
+            BackgroundColor(BUTTON_COLOR),
+            button,
+            Interaction::None,
+        ))
+        .with_children(|parent| {
+            let mut text = parent.spawn((
+                Text::new(label),
+                TextFont {
+                    font_size: 13.0,
+                    ..default()
+                },
+                TextColor(BUTTON_TEXT_COLOR),
+            ));
+            if button == MinimizeButton::Algorithm {
+                text.insert(AlgorithmLabel);
+            }
+        });
+}
+
+fn spawn_dynamics_button(parent: &mut ChildSpawnerCommands, control: DynamicsControl, label: &str) {
+    parent
+        .spawn((
+            Node {
+                width: Val::Px(64.0),
+                height: Val::Px(28.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                border_radius: BorderRadius::all(Val::Px(6.0)),
+                ..default()
+            },
+            BackgroundColor(BUTTON_COLOR),
+            DynamicsButton { control },
+            Interaction::None,
+        ))
+        .with_children(|parent| {
+            let mut text = parent.spawn((
+                Text::new(label),
+                TextFont {
+                    font_size: 13.0,
+                    ..default()
+                },
+                TextColor(BUTTON_TEXT_COLOR),
+            ));
+            if control == DynamicsControl::Toggle {
+                text.insert(ToggleLabel);
+            }
+        });
+}
+
+#[allow(clippy::type_complexity)]
+fn update_dynamics_panel(
+    state: Res<DynamicsState>,
+    settings: Res<DynamicsSettings>,
+    mut status_label: Query<&mut Text, (With<DynamicsStatusLabel>, Without<ToggleLabel>)>,
+    mut toggle_label: Query<&mut Text, (With<ToggleLabel>, Without<DynamicsStatusLabel>)>,
+) {
+    if !state.is_changed() && !settings.is_changed() {
+        return;
+    }
+
+    if let Ok(mut text) = toggle_label.single_mut() {
+        text.0 = if state.running { "Pause" } else { "Start" }.to_string();
+    }
+
+    if let Ok(mut text) = status_label.single_mut() {
+        text.0 = format!(
+            "Step {} ({:.2} ps, dt {} fs)\nT {:.0} K\nKinetic {:.1} kcal/mol\nPotential {:.1} kcal/mol",
+            state.step,
+            state.time,
+            settings.timestep * 1000.0,
+            state.temperature,
+            state.kinetic_energy,
+            state.potential_energy
+        );
+    }
+}
+
+fn handle_dynamics_button_click(
+    mut messages: MessageWriter<DynamicsControlMessage>,
+    mut interaction_query: Query<
+        (&Interaction, &DynamicsButton, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+) {
+    for (interaction, button, mut bg_color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                messages.write(DynamicsControlMessage {
+                    control: button.control,
+                });
+            }
+            Interaction::Hovered => {
+                *bg_color = BackgroundColor(BUTTON_HOVER_COLOR);
+            }
+            Interaction::None => {
+                *bg_color = BackgroundColor(BUTTON_COLOR);
+            }
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_minimization_panel(
+    settings: Res<MinimizationSettings>,
+    report: Res<MinimizationReport>,
+    mut status_label: Query<&mut Text, (With<MinimizationStatusLabel>, Without<AlgorithmLabel>)>,
+    mut algorithm_label: Query<&mut Text, (With<AlgorithmLabel>, Without<MinimizationStatusLabel>)>,
+) {
+    if !settings.is_changed() && !report.is_changed() {
+        return;
+    }
+
+    if let Ok(mut text) = algorithm_label.single_mut() {
+        text.0 = match settings.algorithm {
+            MinimizationAlgorithm::SteepestDescent => "SD",
+            MinimizationAlgorithm::Lbfgs => "L-BFGS",
+        }
+        .to_string();
+    }
+
+    if let Ok(mut text) = status_label.single_mut() {
+        text.0 = match report.result {
+            Some(result) => format!(
+                "{}: {} iterations ({})\n{:.1} -> {:.1} kcal/mol\nRMS force {:.3} kcal/(mol·Å)",
+                report.algorithm.name(),
+                result.iterations,
+                result.stop.name(),
+                result.initial_energy,
+                result.energy,
+                result.rms_force
+            ),
+            None if report.running => format!(
+                "{}: {} iterations\n{:.1} kcal/mol",
+                report.algorithm.name(),
+                report.energies.len(),
+                report.energies.last().copied().unwrap_or_default()
+            ),
+            None => String::new(),
+        };
+    }
+}
+
+fn handle_minimize_button_click(
+    mut messages: MessageWriter<MinimizeMessage>,
+    mut settings: ResMut<MinimizationSettings>,
+    selected: Res<SelectedResidue>,
+    residue_query: Query<&Residue>,
+    mut interaction_query: Query<
+        (&Interaction, &MinimizeButton, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+) {
+    for (interaction, button, mut bg_color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                let scope = match button {
+                    MinimizeButton::Residue => selected.entity.map(MinimizationScope::Residue),
+                    MinimizeButton::Chain => selected
+                        .entity
+                        .and_then(|entity| residue_query.get(entity).ok())
+                        .map(|residue| MinimizationScope::Chain(residue.chain_id.clone())),
+                    MinimizeButton::Project => Some(MinimizationScope::Project),
+                    MinimizeButton::Algorithm => {
+                        settings.algorithm = match settings.algorithm {
+                            MinimizationAlgorithm::SteepestDescent => MinimizationAlgorithm::Lbfgs,
+                            MinimizationAlgorithm::Lbfgs => MinimizationAlgorithm::SteepestDescent,
+                        };
+                        None
+                    }
+                };
+                if let Some(scope) = scope {
+                    messages.write(MinimizeMessage { scope });
+                }
+            }
+            Interaction::Hovered => {
+                *bg_color = BackgroundColor(BUTTON_HOVER_COLOR);
+            }
+            Interaction::None => {
+                *bg_color = BackgroundColor(BUTTON_COLOR);
+            }
+        }
+    }
+}
+
+fn update_physics_label(
+    project_res: Res<ProjectResource>,
+    mut label_query: Query<&mut Text, With<PhysicsLabel>>,
+) {
+    if !project_res.is_changed() {
+        return;
+    }
+
+    if let Ok(mut text) = label_query.single_mut() {
+        text.0 = if project_res.project.physics {
+            "Physics: On"
+        } else {
+            "Physics: Off"
+        }
+        .to_string();
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn handle_physics_button_click(
+    mut messages: MessageWriter<TogglePhysicsMessage>,
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<PhysicsButton>),
+    >,
+) {
+    for (interaction, mut bg_color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                messages.write(TogglePhysicsMessage);
+            }
+            Interaction::Hovered => {
+                *bg_color = BackgroundColor(BUTTON_HOVER_COLOR);
+            }
+            Interaction::None => {
+                *bg_color = BackgroundColor(BUTTON_COLOR);
+            }
+        }
+    }
+}
@@ -0,0 +1,490 @@
+use bevy::input::ButtonState;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::prelude::*;
+use std::path::{Path, PathBuf};
+
+use super::tab_bar::{
+    BUTTON_COLOR, BUTTON_HOVER_COLOR, BUTTON_TEXT_COLOR, SECONDARY_TEXT_COLOR, TEXT_COLOR,
+};
+use crate::projects::file::PROJECT_FILE_EXTENSION;
+use crate::projects::{LoadProjectMessage, ProjectDocument, SaveProjectMessage};
+
+const ENTRY_COLOR: Color = Color::srgb(0.16, 0.16, 0.18);
+const DIRECTORY_TEXT_COLOR: Color = Color::srgb(0.6, 0.8, 1.0);
+
+/// What the file browser is choosing a file for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileBrowserMode {
+    Open,
+    Save,
+}
+
+/// State of the in-app file browser used to open and save projects.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct FileBrowser {
+    /// The browser is hidden while this is `None`.
+    pub mode: Option<FileBrowserMode>,
+    pub directory: PathBuf,
+    /// File name typed in save mode.
+    pub file_name: String,
+}
+
+impl FileBrowser {
+    /// Shows the browser next to the current project file, or in the working
+    /// directory for projects that were never saved.
+    pub fn show(&mut self, mode: FileBrowserMode, document: &ProjectDocument, project_name: &str) {
+        let path = document.path.as_deref();
+        self.mode = Some(mode);
+        self.directory = path
+            .and_then(Path::parent)
+            .filter(|directory| directory.is_dir())
+            .map(Path::to_path_buf)
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_default();
+        self.file_name = path
+            .and_then(Path::file_stem)
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| project_name.to_lowercase().replace(' ', "_"));
+    }
+
+    pub fn close(&mut self) {
+        self.mode = None;
+    }
+
+    /// Path the typed file name resolves to, with the project extension added
+    /// if missing.
+    fn save_path(&self) -> Option<PathBuf> {
+        let name = self.file_name.trim();
+        if name.is_empty() {
+            return None;
+        }
+        let path = self.directory.join(name);
+        if path
+            .extension()
+            .is_some_and(|ext| ext == PROJECT_FILE_EXTENSION)
+        {
+            Some(path)
+        } else {
+            Some(path.with_extension(PROJECT_FILE_EXTENSION))
+        }
+    }
+
+    /// Subdirectories and project files of the current directory, directories
+    /// first. Hidden entries are skipped.
+    fn entries(&self) -> Vec<(PathBuf, bool)> {
+        let Ok(read_dir) = std::fs::read_dir(&self.directory) else {
+            return Vec::new();
+        };
+        let mut entries: Vec<(PathBuf, bool)> = read_dir
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                !path
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+            })
+            .filter_map(|path| {
+                let is_directory = path.is_dir();
+                let is_project = path
+                    .extension()
+                    .is_some_and(|ext| ext == PROJECT_FILE_EXTENSION);
+                (is_directory || is_project).then_some((path, is_directory))
+            })
+            .collect();
+        entries.sort_by(|(a, a_dir), (b, b_dir)| b_dir.cmp(a_dir).then_with(|| a.cmp(b)));
+        entries
+    }
+}
+
+#[derive(Component)]
+struct FileBrowserPanel;
+
+#[derive(Component)]
+struct FileBrowserTitle;
+
+#[derive(Component)]
+struct DirectoryLabel;
+
+#[derive(Component)]
+struct FileBrowserList;
+
+#[derive(Component)]
+struct FileNameLabel;
+
+#[derive(Component)]
+struct FileBrowserEntry {
+    path: PathBuf,
+    is_directory: bool,
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+enum FileBrowserButton {
+    Confirm,
+    Cancel,
+}
+
+/// Panel for picking project files to open or save.
+pub struct FileBrowserPlugin;
+
+impl Plugin for FileBrowserPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FileBrowser>()
+            .add_systems(Startup, setup_file_browser)
+            .add_systems(
+                Update,
+                (
+                    handle_entry_click,
+                    handle_file_browser_button_click,
+                    handle_file_name_input,
+                    update_file_browser,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn setup_file_browser(mut commands: Commands) {
+    commands
+        .spawn((
+            Name::new("FileBrowser"),
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                display: Display::None,
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.4)),
+            GlobalZIndex(10),
+            FileBrowserPanel,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Node {
+                        width: Val::Px(460.0),
+                        flex_direction: FlexDirection::Column,
+                        padding: UiRect::all(Val::Px(12.0)),
+                        border_radius: BorderRadius::all(Val::Px(8.0)),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgba(0.12, 0.12, 0.14, 0.98)),
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        Text::new(""),
+                        TextFont {
+                            font_size: 18.0,
+                            ..default()
+                        },
+                        TextColor(TEXT_COLOR),
+                        Node {
+                            margin: UiRect::bottom(Val::Px(6.0)),
+                            ..default()
+                        },
+                        FileBrowserTitle,
+                    ));
+
+                    parent.spawn((
+                        Text::new(""),
+                        TextFont {
+                            font_size: 12.0,
+                            ..default()
+                        },
+                        TextColor(SECONDARY_TEXT_COLOR),
+                        Node {
+                            margin: UiRect::bottom(Val::Px(8.0)),
+                            ..default()
+                        },
+                        DirectoryLabel,
+                    ));
+
+                    parent.spawn((
+                        Node {
+                            width: Val::Percent(100.0),
+                            height: Val::Px(320.0),
+                            flex_direction: FlexDirection::Column,
+                            overflow: Overflow::clip_y(),
+                            margin: UiRect::bottom(Val::Px(8.0)),
+                            ..default()
+                        },
+                        BackgroundColor(Color::srgb(0.09, 0.09, 0.10)),
+                        FileBrowserList,
+                    ));
+
+                    parent.spawn((
+                        Text::new(""),
+                        TextFont {
+                            font_size: 14.0,
+                            ..default()
+                        },
+                        TextColor(TEXT_COLOR),
+                        Node {
+                            margin: UiRect::bottom(Val::Px(8.0)),
+                            ..default()
+                        },
+                        FileNameLabel,
+                    ));
+
+                    parent
+                        .spawn(Node {
+                            flex_direction: FlexDirection::Row,
+                            justify_content: JustifyContent::FlexEnd,
+                            column_gap: Val::Px(8.0),
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            spawn_file_browser_button(parent, FileBrowserButton::Confirm, "Save");
+                            spawn_file_browser_button(parent, FileBrowserButton::Cancel, "Cancel");
+                        });
+                });
+        });
+}
+
+fn spawn_file_browser_button(
+    parent: &mut ChildSpawnerCommands,
+    button: FileBrowserButton,
+    label: &str,
+) {
+    parent
+        .spawn((
+            Node {
+                width: Val::Px(90.0),
+                height: Val::Px(32.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                border_radius: BorderRadius::all(Val::Px(6.0)),
+                ..default()
+            },
+            BackgroundColor(BUTTON_COLOR),
+            button,
+            Interaction::None,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(label),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(BUTTON_TEXT_COLOR),
+            ));
+        });
+}
+
+/// Shows or hides the browser and lists the current directory.
+#[allow(clippy::type_complexity)]
+fn update_file_browser(
+    mut commands: Commands,
+    browser: Res<FileBrowser>,
+    mut panel_query: Query<&mut Node, With<FileBrowserPanel>>,
+    mut confirm_query: Query<(&FileBrowserButton, &mut Node), Without<FileBrowserPanel>>,
+    list_query: Query<Entity, With<FileBrowserList>>,
+    mut text_query: Query<(
+        &mut Text,
+        Has<FileBrowserTitle>,
+        Has<DirectoryLabel>,
+        Has<FileNameLabel>,
+    )>,
+) {
+    if !browser.is_changed() {
+        return;
+    }
+
+    let Ok(mut panel) = panel_query.single_mut() else {
+        return;
+    };
+    let Some(mode) = browser.mode else {
+        panel.display = Display::None;
+        return;
+    };
+    panel.display = Display::Flex;
+
+    for (button, mut node) in &mut confirm_query {
+        if *button == FileBrowserButton::Confirm {
+            node.display = match mode {
+                FileBrowserMode::Open => Display::None,
+                FileBrowserMode::Save => Display::Flex,
+            };
+        }
+    }
+
+    for (mut text, is_title, is_directory, is_file_name) in &mut text_query {
+        if is_title {
+            text.0 = match mode {
+                FileBrowserMode::Open => "Open Project".to_string(),
+                FileBrowserMode::Save => "Save Project As".to_string(),
+            };
+        } else if is_directory {
+            text.0 = browser.directory.display().to_string();
+        } else if is_file_name {
+            text.0 = match mode {
+                FileBrowserMode::Open => String::new(),
+                FileBrowserMode::Save => format!("Name: {}_", browser.file_name),
+            };
+        }
+    }
+
+    let Ok(list) = list_query.single() else {
+        return;
+    };
+    commands.entity(list).despawn_related::<Children>();
+
+    let parent_entry = browser
+        .directory
+        .parent()
+        .map(|parent| (parent.to_path_buf(), true, "..".to_string()));
+    let entries = browser.entries().into_iter().map(|(path, is_directory)| {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let label = if is_directory {
+            format!("{}/", name)
+        } else {
+            name
+        };
+        (path, is_directory, label)
+    });
+
+    commands.entity(list).with_children(|parent| {
+        for (path, is_directory, label) in parent_entry.into_iter().chain(entries) {
+            parent
+                .spawn((
+                    Node {
+                        width: Val::Percent(100.0),
+                        min_height: Val::Px(24.0),
+                        align_items: AlignItems::Center,
+                        padding: UiRect::horizontal(Val::Px(8.0)),
+                        ..default()
+                    },
+                    BackgroundColor(ENTRY_COLOR),
+                    FileBrowserEntry { path, is_directory },
+                    Interaction::None,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        Text::new(label),
+                        TextFont {
+                            font_size: 13.0,
+                            ..default()
+                        },
+                        TextColor(if is_directory {
+                            DIRECTORY_TEXT_COLOR
+                        } else {
+                            TEXT_COLOR
+                        }),
+                    ));
+                });
+        }
+    });
+}
+
+/// Enters directories, opens project files, or picks their name for saving.
+fn handle_entry_click(
+    mut browser: ResMut<FileBrowser>,
+    mut load_messages: MessageWriter<LoadProjectMessage>,
+    mut interaction_query: Query<
+        (&Interaction, &FileBrowserEntry, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+) {
+    for (interaction, entry, mut bg_color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                if entry.is_directory {
+                    browser.directory = entry.path.clone();
+                } else {
+                    match browser.mode {
+                        Some(FileBrowserMode::Open) => {
+                            load_messages.write(LoadProjectMessage {
+                                path: entry.path.clone(),
+                            });
+                            browser.close();
+                        }
+                        Some(FileBrowserMode::Save) => {
+                            if let Some(stem) = entry.path.file_stem() {
+                                browser.file_name = stem.to_string_lossy().into_owned();
+                            }
+                        }
+                        None => {}
+                    }
+                }
+            }
+            Interaction::Hovered => {
+                *bg_color = BackgroundColor(BUTTON_HOVER_COLOR);
+            }
+            Interaction::None => {
+                *bg_color = BackgroundColor(ENTRY_COLOR);
+            }
+        }
+    }
+}
+
+fn handle_file_browser_button_click(
+    mut browser: ResMut<FileBrowser>,
+    mut save_messages: MessageWriter<SaveProjectMessage>,
+    mut interaction_query: Query<
+        (&Interaction, &FileBrowserButton, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+) {
+    for (interaction, button, mut bg_color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => match button {
+                FileBrowserButton::Confirm => confirm_save(&mut browser, &mut save_messages),
+                FileBrowserButton::Cancel => browser.close(),
+            },
+            Interaction::Hovered => {
+                *bg_color = BackgroundColor(BUTTON_HOVER_COLOR);
+            }
+            Interaction::None => {
+                *bg_color = BackgroundColor(BUTTON_COLOR);
+            }
+        }
+    }
+}
+
+/// Edits the file name in save mode. Enter saves and Escape closes the browser.
+fn handle_file_name_input(
+    mut browser: ResMut<FileBrowser>,
+    mut keyboard_messages: MessageReader<KeyboardInput>,
+    mut save_messages: MessageWriter<SaveProjectMessage>,
+) {
+    let Some(mode) = browser.mode else {
+        keyboard_messages.clear();
+        return;
+    };
+
+    for input in keyboard_messages.read() {
+        if input.state != ButtonState::Pressed {
+            continue;
+        }
+        match (&input.logical_key, mode) {
+            (Key::Escape, _) => browser.close(),
+            (Key::Enter, FileBrowserMode::Save) => {
+                confirm_save(&mut browser, &mut save_messages);
+            }
+            (Key::Backspace, FileBrowserMode::Save) => {
+                browser.file_name.pop();
+            }
+            (Key::Space, FileBrowserMode::Save) => browser.file_name.push(' '),
+            (Key::Character(text), FileBrowserMode::Save) => {
+                browser.file_name.extend(
+                    text.chars()
+                        .filter(|c| !c.is_control() && !"/\\".contains(*c)),
+                );
+            }
+            _ => {}
+        }
+    }
+}
+
+fn confirm_save(browser: &mut FileBrowser, save_messages: &mut MessageWriter<SaveProjectMessage>) {
+    if let Some(path) = browser.save_path() {
+        save_messages.write(SaveProjectMessage { path });
+        browser.close();
+    }
+}
@@ -0,0 +1,241 @@
+use bevy::prelude::*;
+
+use super::tab_bar::{BUTTON_COLOR, BUTTON_HOVER_COLOR, BUTTON_TEXT_COLOR, TEXT_COLOR};
+use crate::objects::grid::{GRID_EXTENTS, GRID_SPACINGS, GridConfig, GridMode};
+use crate::user::camera::CameraTransformData;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum GridButton {
+    Visible,
+    Mode,
+    Spacing,
+    Extent,
+    Labels,
+}
+
+/// Text of a grid button, updated from the grid configuration.
+#[derive(Component)]
+struct GridButtonLabel(GridButton);
+
+/// Coordinate of a grid line on an axis, shown in the 3D view.
+#[derive(Component)]
+struct GridTickLabel;
+
+/// Panel toggling the reference grid and its options, and the tick labels
+/// along the axes.
+pub struct GridPanelPlugin;
+
+impl Plugin for GridPanelPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_grid_panel).add_systems(
+            Update,
+            (
+                handle_grid_button_click,
+                update_grid_button_labels,
+                update_grid_tick_labels,
+            ),
+        );
+    }
+}
+
+fn setup_grid_panel(mut commands: Commands) {
+    commands
+        .spawn((
+            Name::new("GridPanel"),
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(44.0),
+                left: Val::Px(12.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Start,
+                padding: UiRect::all(Val::Px(10.0)),
+                border_radius: BorderRadius::all(Val::Px(8.0)),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.12, 0.12, 0.14, 0.98)),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("Grid"),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(TEXT_COLOR),
+                Node {
+                    margin: UiRect::bottom(Val::Px(6.0)),
+                    ..default()
+                },
+            ));
+
+            parent
+                .spawn(Node {
+                    flex_direction: FlexDirection::Row,
+                    column_gap: Val::Px(6.0),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    spawn_grid_button(parent, GridButton::Visible);
+                    spawn_grid_button(parent, GridButton::Mode);
+                    spawn_grid_button(parent, GridButton::Spacing);
+                    spawn_grid_button(parent, GridButton::Extent);
+                    spawn_grid_button(parent, GridButton::Labels);
+                });
+        });
+}
+
+fn spawn_grid_button(parent: &mut ChildSpawnerCommands, button: GridButton) {
+    parent
+        .spawn((
+            Node {
+                width: Val::Px(64.0),
+                height: Val::Px(28.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                border_radius: BorderRadius::all(Val::Px(6.0)),
+                ..default()
+            },
+            BackgroundColor(BUTTON_COLOR),
+            button,
+            Interaction::None,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font_size: 13.0,
+                    ..default()
+                },
+                TextColor(BUTTON_TEXT_COLOR),
+                GridButtonLabel(button),
+            ));
+        });
+}
+
+fn handle_grid_button_click(
+    mut config: ResMut<GridConfig>,
+    mut interaction_query: Query<
+        (&Interaction, &GridButton, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+) {
+    for (interaction, button, mut bg_color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => match *button {
+                GridButton::Visible => config.visible = !config.visible,
+                GridButton::Mode => config.mode = config.mode.next(),
+                GridButton::Spacing => {
+                    let index = GRID_SPACINGS
+                        .iter()
+                        .position(|&spacing| spacing >= config.spacing)
+                        .unwrap_or(0);
+                    config.spacing = GRID_SPACINGS[(index + 1) % GRID_SPACINGS.len()];
+                }
+                GridButton::Extent => {
+                    let index = GRID_EXTENTS
+                        .iter()
+                        .position(|&extent| extent >= config.extent)
+                        .unwrap_or(0);
+                    config.extent = GRID_EXTENTS[(index + 1) % GRID_EXTENTS.len()];
+                }
+                GridButton::Labels => config.labels = !config.labels,
+            },
+            Interaction::Hovered => {
+                *bg_color = BackgroundColor(BUTTON_HOVER_COLOR);
+            }
+            Interaction::None => {
+                *bg_color = BackgroundColor(BUTTON_COLOR);
+            }
+        }
+    }
+}
+
+fn update_grid_button_labels(
+    config: Res<GridConfig>,
+    mut label_query: Query<(&GridButtonLabel, &mut Text)>,
+) {
+    if !config.is_changed() {
+        return;
+    }
+
+    for (label, mut text) in &mut label_query {
+        text.0 = match label.0 {
+            GridButton::Visible if config.visible => "Hide".to_string(),
+            GridButton::Visible => "Show".to_string(),
+            GridButton::Mode => config.mode.name().to_string(),
+            GridButton::Spacing => format!("{} Å", config.spacing),
+            GridButton::Extent => format!("{} Cells", config.extent),
+            GridButton::Labels if config.labels => "No Ticks".to_string(),
+            GridButton::Labels => "Ticks".to_string(),
+        };
+    }
+}
+
+/// Places a label at every grid line crossing an axis near the camera:
+/// along X and Z on the floor, and along all three axes in the lattice.
+fn update_grid_tick_labels(
+    mut commands: Commands,
+    config: Res<GridConfig>,
+    camera_data: Res<CameraTransformData>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    mut label_query: Query<(&mut Node, &mut Text, &mut TextColor), With<GridTickLabel>>,
+) {
+    let camera = camera_data.transform.translation;
+    let center = config.center(camera);
+    let axes: &[Vec3] = match config.mode {
+        GridMode::Floor => &[Vec3::X, Vec3::Z],
+        GridMode::Lattice => &[Vec3::X, Vec3::Y, Vec3::Z],
+    };
+
+    let mut ticks = Vec::new();
+    if config.visible
+        && config.labels
+        && let Ok((camera_component, camera_transform)) = camera_query.single()
+    {
+        for &axis in axes {
+            for value in config.ticks(center.dot(axis)) {
+                let point = axis * value;
+                let alpha = config.fade(point.distance(camera));
+                if value == 0.0 || alpha <= 0.0 {
+                    continue;
+                }
+                if let Ok(screen) = camera_component.world_to_viewport(camera_transform, point) {
+                    ticks.push((screen, format!("{} Å", value), alpha));
+                }
+            }
+        }
+    }
+
+    let mut labels = label_query.iter_mut();
+    for (screen, value, alpha) in ticks {
+        let Some((mut node, mut text, mut color)) = labels.next() else {
+            commands.spawn((
+                Name::new("GridTickLabel"),
+                Text::new(value),
+                TextFont {
+                    font_size: 11.0,
+                    ..default()
+                },
+                TextColor(TEXT_COLOR.with_alpha(alpha)),
+                Node {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(screen.x + 4.0),
+                    top: Val::Px(screen.y + 4.0),
+                    ..default()
+                },
+                GridTickLabel,
+            ));
+            continue;
+        };
+        node.display = Display::Flex;
+        node.left = Val::Px(screen.x + 4.0);
+        node.top = Val::Px(screen.y + 4.0);
+        if text.0 != value {
+            text.0 = value;
+        }
+        color.0 = TEXT_COLOR.with_alpha(alpha);
+    }
+    for (mut node, ..) in labels {
+        node.display = Display::None;
+    }
+}
@@ -0,0 +1,380 @@
+use bevy::prelude::*;
+
+use super::tab_bar::{
+    BUTTON_COLOR, BUTTON_HOVER_COLOR, BUTTON_TEXT_COLOR, SECONDARY_TEXT_COLOR, TEXT_COLOR,
+};
+use crate::chemistry::amino_acids::Residue;
+use crate::chemistry::atoms::Atom;
+use crate::measurements::{
+    MEASUREMENT_COLOR, Measurement, MeasurementKind, MeasurementTool, RemoveMeasurementMessage,
+    label_anchor,
+};
+
+const PANEL_WIDTH: f32 = 340.0;
+
+#[derive(Component)]
+struct MeasurementPanel;
+
+#[derive(Component)]
+struct ToolButton;
+
+#[derive(Component)]
+struct ToolLabel;
+
+#[derive(Component)]
+struct MeasurementList;
+
+#[derive(Component)]
+struct MeasurementRowLabel {
+    measurement: Entity,
+}
+
+#[derive(Component)]
+struct DeleteMeasurementButton {
+    measurement: Entity,
+}
+
+/// Value shown next to a measurement in the 3D view.
+#[derive(Component)]
+struct MeasurementLabel {
+    measurement: Entity,
+}
+
+/// Panel listing the measurements, and their labels in the 3D view.
+pub struct MeasurementPanelPlugin;
+
+impl Plugin for MeasurementPanelPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_measurement_panel)
+            .add_systems(
+                Update,
+                (
+                    handle_tool_button_click,
+                    handle_delete_measurement_click,
+                    update_tool_label,
+                    rebuild_measurement_list,
+                    update_measurement_rows,
+                    update_measurement_labels,
+                ),
+            );
+    }
+}
+
+fn setup_measurement_panel(mut commands: Commands) {
+    commands
+        .spawn((
+            Name::new("MeasurementPanel"),
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(12.0),
+                left: Val::Percent(50.0),
+                width: Val::Px(PANEL_WIDTH),
+                margin: UiRect::left(Val::Px(-PANEL_WIDTH / 2.0)),
+                flex_direction: FlexDirection::Column,
+                padding: UiRect::all(Val::Px(10.0)),
+                border_radius: BorderRadius::all(Val::Px(8.0)),
+                display: Display::None,
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.12, 0.12, 0.14, 0.98)),
+            MeasurementPanel,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(Node {
+                    flex_direction: FlexDirection::Row,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::SpaceBetween,
+                    margin: UiRect::bottom(Val::Px(6.0)),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        Text::new("Measurements"),
+                        TextFont {
+                            font_size: 14.0,
+                            ..default()
+                        },
+                        TextColor(TEXT_COLOR),
+                    ));
+                    parent
+                        .spawn((
+                            Node {
+                                padding: UiRect::axes(Val::Px(8.0), Val::Px(4.0)),
+                                border_radius: BorderRadius::all(Val::Px(6.0)),
+                                ..default()
+                            },
+                            BackgroundColor(BUTTON_COLOR),
+                            ToolButton,
+                            Interaction::None,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn((
+                                Text::new(""),
+                                TextFont {
+                                    font_size: 12.0,
+                                    ..default()
+                                },
+                                TextColor(BUTTON_TEXT_COLOR),
+                                ToolLabel,
+                            ));
+                        });
+                });
+
+            parent.spawn((
+                Node {
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(4.0),
+                    ..default()
+                },
+                MeasurementList,
+            ));
+        });
+}
+
+#[allow(clippy::type_complexity)]
+fn handle_tool_button_click(
+    mut tool: ResMut<MeasurementTool>,
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<ToolButton>),
+    >,
+) {
+    for (interaction, mut bg_color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                tool.kind = MeasurementKind::next(tool.kind);
+                tool.picked.clear();
+            }
+            Interaction::Hovered => {
+                *bg_color = BackgroundColor(BUTTON_HOVER_COLOR);
+            }
+            Interaction::None => {
+                *bg_color = BackgroundColor(BUTTON_COLOR);
+            }
+        }
+    }
+}
+
+fn handle_delete_measurement_click(
+    mut messages: MessageWriter<RemoveMeasurementMessage>,
+    mut interaction_query: Query<
+        (&Interaction, &DeleteMeasurementButton, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+) {
+    for (interaction, button, mut bg_color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                messages.write(RemoveMeasurementMessage {
+                    measurement: button.measurement,
+                });
+            }
+            Interaction::Hovered => {
+                *bg_color = BackgroundColor(BUTTON_HOVER_COLOR);
+            }
+            Interaction::None => {
+                *bg_color = BackgroundColor(BUTTON_COLOR);
+            }
+        }
+    }
+}
+
+/// Shows the active tool and how many atoms it has picked. The panel is shown
+/// while the tool is active or measurements exist.
+fn update_tool_label(
+    tool: Res<MeasurementTool>,
+    measurement_query: Query<(), With<Measurement>>,
+    mut panel_query: Query<&mut Node, With<MeasurementPanel>>,
+    mut label_query: Query<&mut Text, With<ToolLabel>>,
+) {
+    if let Ok(mut panel) = panel_query.single_mut() {
+        let display = if tool.is_active() || !measurement_query.is_empty() {
+            Display::Flex
+        } else {
+            Display::None
+        };
+        if panel.display != display {
+            panel.display = display;
+        }
+    }
+
+    if !tool.is_changed() {
+        return;
+    }
+    if let Ok(mut text) = label_query.single_mut() {
+        text.0 = match tool.kind {
+            Some(kind) => format!(
+                "Tool: {} ({}/{})",
+                kind.name(),
+                tool.picked.len(),
+                kind.atom_count()
+            ),
+            None => "Tool: Off".to_string(),
+        };
+    }
+}
+
+/// Rebuilds the rows of the list, and the labels in the 3D view, when
+/// measurements are added or removed.
+fn rebuild_measurement_list(
+    mut commands: Commands,
+    added_query: Query<(), Added<Measurement>>,
+    mut removed: RemovedComponents<Measurement>,
+    measurement_query: Query<Entity, With<Measurement>>,
+    list_query: Query<Entity, With<MeasurementList>>,
+    label_query: Query<Entity, With<MeasurementLabel>>,
+) {
+    let any_removed = removed.read().count() > 0;
+    if added_query.is_empty() && !any_removed {
+        return;
+    }
+    let Ok(list) = list_query.single() else {
+        return;
+    };
+
+    commands.entity(list).despawn_related::<Children>();
+    for label in &label_query {
+        commands.entity(label).despawn();
+    }
+
+    for measurement in &measurement_query {
+        commands.entity(list).with_children(|parent| {
+            parent
+                .spawn(Node {
+                    flex_direction: FlexDirection::Row,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::SpaceBetween,
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        Text::new(""),
+                        TextFont {
+                            font_size: 12.0,
+                            ..default()
+                        },
+                        TextColor(SECONDARY_TEXT_COLOR),
+                        MeasurementRowLabel { measurement },
+                    ));
+                    parent
+                        .spawn((
+                            Node {
+                                width: Val::Px(22.0),
+                                height: Val::Px(22.0),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                border_radius: BorderRadius::all(Val::Px(4.0)),
+                                ..default()
+                            },
+                            BackgroundColor(BUTTON_COLOR),
+                            DeleteMeasurementButton { measurement },
+                            Interaction::None,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn((
+                                Text::new("x"),
+                                TextFont {
+                                    font_size: 12.0,
+                                    ..default()
+                                },
+                                TextColor(BUTTON_TEXT_COLOR),
+                            ));
+                        });
+                });
+        });
+
+        commands.spawn((
+            Name::new("MeasurementLabel"),
+            Text::new(""),
+            TextFont {
+                font_size: 13.0,
+                ..default()
+            },
+            TextColor(MEASUREMENT_COLOR),
+            Node {
+                position_type: PositionType::Absolute,
+                display: Display::None,
+                ..default()
+            },
+            MeasurementLabel { measurement },
+        ));
+    }
+}
+
+/// Describes each measurement by its kind, atoms and value, e.g.
+/// `Distance CA 12 - CB 12: 1.53 Å`.
+fn update_measurement_rows(
+    measurement_query: Query<&Measurement>,
+    atom_query: Query<(&Atom, &ChildOf)>,
+    residue_query: Query<&Residue>,
+    mut row_query: Query<(&MeasurementRowLabel, &mut Text)>,
+) {
+    for (row, mut text) in &mut row_query {
+        let Ok(measurement) = measurement_query.get(row.measurement) else {
+            continue;
+        };
+        let atoms: Vec<String> = measurement
+            .atoms
+            .iter()
+            .filter_map(|&atom| atom_query.get(atom).ok())
+            .map(
+                |(atom, child_of)| match residue_query.get(child_of.parent()) {
+                    Ok(residue) => format!("{} {}", atom.atom_name, residue.residue_number),
+                    Err(_) => atom.atom_name.clone(),
+                },
+            )
+            .collect();
+        let description = format!(
+            "{} {}: {}",
+            measurement.kind().name(),
+            atoms.join(" - "),
+            measurement.display_value()
+        );
+        if text.0 != description {
+            text.0 = description;
+        }
+    }
+}
+
+/// Places each value label on screen at its measurement, hiding it when the
+/// measurement is behind the camera.
+fn update_measurement_labels(
+    camera_query: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    measurement_query: Query<&Measurement>,
+    atom_query: Query<&GlobalTransform, With<Atom>>,
+    mut label_query: Query<(&MeasurementLabel, &mut Node, &mut Text)>,
+) {
+    let Ok((camera, camera_transform)) = camera_query.single() else {
+        return;
+    };
+
+    for (label, mut node, mut text) in &mut label_query {
+        let screen_position =
+            measurement_query
+                .get(label.measurement)
+                .ok()
+                .and_then(|measurement| {
+                    let positions: Vec<Vec3> = measurement
+                        .atoms
+                        .iter()
+                        .filter_map(|&atom| atom_query.get(atom).ok())
+                        .map(GlobalTransform::translation)
+                        .collect();
+                    let anchor = label_anchor(&positions)?;
+                    let screen = camera.world_to_viewport(camera_transform, anchor).ok()?;
+                    Some((screen, measurement.display_value()))
+                });
+
+        let Some((screen, value)) = screen_position else {
+            node.display = Display::None;
+            continue;
+        };
+        node.display = Display::Flex;
+        node.left = Val::Px(screen.x + 6.0);
+        node.top = Val::Px(screen.y - 6.0);
+        if text.0 != value {
+            text.0 = value;
+        }
+    }
+}
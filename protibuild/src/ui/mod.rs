@@ -1,10 +1,42 @@
 use bevy::prelude::*;
 
+pub(crate) mod atom_inspector;
 pub(crate) mod crosshair;
+pub(crate) mod dynamics_panel;
+pub(crate) mod file_browser;
+pub(crate) mod grid_panel;
+pub(crate) mod measurement_panel;
+pub(crate) mod rotamer_panel;
+pub(crate) mod selection_panel;
+pub(crate) mod surface_panel;
 pub(crate) mod tab_bar;
+pub(crate) mod unsaved_changes;
 
+use atom_inspector::AtomInspectorPlugin;
 use crosshair::CrosshairPlugin;
+use dynamics_panel::DynamicsPanelPlugin;
+use file_browser::FileBrowserPlugin;
+use grid_panel::GridPanelPlugin;
+use measurement_panel::MeasurementPanelPlugin;
+use rotamer_panel::RotamerPanelPlugin;
+use selection_panel::SelectionPanelPlugin;
+use surface_panel::SurfacePanelPlugin;
 use tab_bar::TabBarPlugin;
+use unsaved_changes::UnsavedChangesPlugin;
+
+use crate::selection::SelectionQuery;
+use file_browser::FileBrowser;
+
+/// Whether a text field has keyboard focus, so typed keys are not shortcuts.
+#[derive(Resource, Debug, Default)]
+pub struct TextInputFocus {
+    pub focused: bool,
+}
+
+/// Run condition for systems that read keys as text.
+pub fn text_input_focused(focus: Res<TextInputFocus>) -> bool {
+    focus.focused
+}
 
 pub struct UIPlugin;
 
@@ -12,11 +44,36 @@ impl Plugin for UIPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(CrosshairPlugin)
             .add_plugins(TabBarPlugin)
+            .add_plugins(RotamerPanelPlugin)
+            .add_plugins(DynamicsPanelPlugin)
+            .add_plugins(AtomInspectorPlugin)
+            .add_plugins(MeasurementPanelPlugin)
+            .add_plugins(SurfacePanelPlugin)
+            .add_plugins(GridPanelPlugin)
+            .add_plugins(SelectionPanelPlugin)
+            .add_plugins(FileBrowserPlugin)
+            .add_plugins(UnsavedChangesPlugin)
+            .init_resource::<TextInputFocus>()
+            .add_systems(PreUpdate, Self::update_text_input_focus)
             .add_systems(Update, Self::handle_exit);
     }
 }
 
 impl UIPlugin {
+    /// Focused while the selection query is edited or the file browser is
+    /// open. Updated before `Update`, so the key that leaves a field is not a
+    /// shortcut either.
+    fn update_text_input_focus(
+        query: Res<SelectionQuery>,
+        browser: Res<FileBrowser>,
+        mut focus: ResMut<TextInputFocus>,
+    ) {
+        let focused = query.editing || browser.mode.is_some();
+        if focus.focused != focused {
+            focus.focused = focused;
+        }
+    }
+
     fn handle_exit(keyboard: Res<ButtonInput<KeyCode>>, mut exit: MessageWriter<AppExit>) {
         if keyboard.pressed(KeyCode::ControlLeft) && keyboard.just_pressed(KeyCode::KeyC) {
             exit.write(AppExit::Success);
@@ -0,0 +1,203 @@
+use bevy::prelude::*;
+
+use super::tab_bar::{
+    BUTTON_COLOR, BUTTON_HOVER_COLOR, BUTTON_TEXT_COLOR, SECONDARY_TEXT_COLOR, TEXT_COLOR,
+};
+use crate::chemistry::amino_acids::{
+    ActiveRotamer, AminoAcid, Residue, RotamerChange, RotamerLibrary, SetRotamerMessage,
+};
+use crate::user::interaction::SelectedResidue;
+
+#[derive(Component)]
+struct RotamerPanel;
+
+#[derive(Component)]
+struct ResidueLabel;
+
+#[derive(Component)]
+struct RotamerLabel;
+
+#[derive(Component)]
+struct RotamerButton {
+    change: RotamerChange,
+}
+
+/// Panel for cycling the rotamers of the selected residue.
+pub struct RotamerPanelPlugin;
+
+impl Plugin for RotamerPanelPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_rotamer_panel)
+            .add_systems(Update, (update_rotamer_panel, handle_rotamer_button_click));
+    }
+}
+
+fn setup_rotamer_panel(mut commands: Commands) {
+    commands
+        .spawn((
+            Name::new("RotamerPanel"),
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(12.0),
+                right: Val::Px(12.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                padding: UiRect::all(Val::Px(10.0)),
+                border_radius: BorderRadius::all(Val::Px(8.0)),
+                display: Display::None,
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.12, 0.12, 0.14, 0.98)),
+            RotamerPanel,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(TEXT_COLOR),
+                Node {
+                    margin: UiRect::bottom(Val::Px(6.0)),
+                    ..default()
+                },
+                ResidueLabel,
+            ));
+
+            parent
+                .spawn(Node {
+                    flex_direction: FlexDirection::Row,
+                    align_items: AlignItems::Center,
+                    ..default()
+                })
+                .with_children(|parent| {
+                    spawn_rotamer_button(parent, RotamerChange::Previous, "<");
+                    parent.spawn((
+                        Text::new(""),
+                        TextFont {
+                            font_size: 13.0,
+                            ..default()
+                        },
+                        TextColor(SECONDARY_TEXT_COLOR),
+                        TextLayout::new_with_justify(Justify::Center),
+                        Node {
+                            width: Val::Px(150.0),
+                            ..default()
+                        },
+                        RotamerLabel,
+                    ));
+                    spawn_rotamer_button(parent, RotamerChange::Next, ">");
+                });
+        });
+}
+
+fn spawn_rotamer_button(parent: &mut ChildSpawnerCommands, change: RotamerChange, label: &str) {
+    parent
+        .spawn((
+            Node {
+                width: Val::Px(32.0),
+                height: Val::Px(32.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                border_radius: BorderRadius::all(Val::Px(6.0)),
+                ..default()
+            },
+            BackgroundColor(BUTTON_COLOR),
+            RotamerButton { change },
+            Interaction::None,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(label),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(BUTTON_TEXT_COLOR),
+            ));
+        });
+}
+
+/// Shows the panel while a residue is selected and describes its active rotamer.
+#[allow(clippy::type_complexity)]
+fn update_rotamer_panel(
+    selected: Res<SelectedResidue>,
+    residue_query: Query<(&AminoAcid, &Residue, Option<&ActiveRotamer>)>,
+    changed_rotamers: Query<(), Changed<ActiveRotamer>>,
+    mut panel_query: Query<&mut Node, With<RotamerPanel>>,
+    mut residue_label: Query<&mut Text, (With<ResidueLabel>, Without<RotamerLabel>)>,
+    mut rotamer_label: Query<&mut Text, (With<RotamerLabel>, Without<ResidueLabel>)>,
+) {
+    if !selected.is_changed() && changed_rotamers.is_empty() {
+        return;
+    }
+
+    let Ok(mut panel) = panel_query.single_mut() else {
+        return;
+    };
+
+    let Some((amino_acid, residue, active)) = selected
+        .entity
+        .and_then(|entity| residue_query.get(entity).ok())
+    else {
+        panel.display = Display::None;
+        return;
+    };
+    panel.display = Display::Flex;
+
+    if let Ok(mut text) = residue_label.single_mut() {
+        text.0 = format!(
+            "{} {} {}",
+            amino_acid.code.three_letter(),
+            residue.residue_number,
+            residue.chain_id
+        );
+    }
+
+    if let Ok(mut text) = rotamer_label.single_mut() {
+        let library = RotamerLibrary::get(amino_acid.code);
+        let rotamers = library
+            .as_ref()
+            .map_or(&[][..], |library| &library.rotamers);
+        text.0 = match active.and_then(|active| active.index) {
+            _ if rotamers.is_empty() => "No rotamers".to_string(),
+            Some(index) => format!(
+                "{} ({}/{})",
+                rotamers[index].name,
+                index + 1,
+                rotamers.len()
+            ),
+            None if active.is_some() => "Custom chi angles".to_string(),
+            None => format!("{} rotamers", rotamers.len()),
+        };
+    }
+}
+
+fn handle_rotamer_button_click(
+    mut messages: MessageWriter<SetRotamerMessage>,
+    selected: Res<SelectedResidue>,
+    mut interaction_query: Query<
+        (&Interaction, &RotamerButton, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+) {
+    for (interaction, button, mut bg_color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                if let Some(residue) = selected.entity {
+                    messages.write(SetRotamerMessage {
+                        residue,
+                        change: button.change.clone(),
+                    });
+                }
+            }
+            Interaction::Hovered => {
+                *bg_color = BackgroundColor(BUTTON_HOVER_COLOR);
+            }
+            Interaction::None => {
+                *bg_color = BackgroundColor(BUTTON_COLOR);
+            }
+        }
+    }
+}
@@ -0,0 +1,371 @@
+use bevy::ecs::system::SystemParam;
+use bevy::input::ButtonState;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::prelude::*;
+
+use super::tab_bar::{
+    BUTTON_COLOR, BUTTON_HOVER_COLOR, BUTTON_TEXT_COLOR, SECONDARY_TEXT_COLOR, TEXT_COLOR,
+};
+use crate::chemistry::minimization::{MinimizationScope, MinimizeMessage};
+use crate::measurements::{AddMeasurementMessage, MeasurementKind};
+use crate::projects::{ExportProjectMessage, ProjectResource};
+use crate::selection::{SelectMessage, Selection, SelectionMode, SelectionQuery};
+
+const FIELD_COLOR: Color = Color::srgb(0.16, 0.16, 0.18);
+const FIELD_EDITING_COLOR: Color = Color::srgb(0.22, 0.22, 0.26);
+
+/// Shown in the empty query field.
+const PLACEHOLDER: &str = "e.g. chain A and resid 10-20";
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum SelectionButton {
+    Select,
+    Add,
+    Clear,
+    Measure,
+    Minimize,
+    Export,
+}
+
+/// Text field for selection queries, focused by clicking it.
+#[derive(Component)]
+struct SelectionQueryField;
+
+#[derive(Component)]
+struct SelectionQueryLabel;
+
+#[derive(Component)]
+struct SelectionStatusLabel;
+
+/// Tools the panel runs on the selected atoms.
+#[derive(SystemParam)]
+struct SelectionTools<'w> {
+    measurement_messages: MessageWriter<'w, AddMeasurementMessage>,
+    minimize_messages: MessageWriter<'w, MinimizeMessage>,
+    export_messages: MessageWriter<'w, ExportProjectMessage>,
+    project_res: Res<'w, ProjectResource>,
+}
+
+/// Panel selecting atoms with a query and running tools on the selection.
+pub struct SelectionPanelPlugin;
+
+impl Plugin for SelectionPanelPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_selection_panel).add_systems(
+            Update,
+            (
+                handle_query_field_click,
+                handle_query_input,
+                handle_selection_button_click,
+                update_query_label,
+                update_selection_status,
+            ),
+        );
+    }
+}
+
+fn setup_selection_panel(mut commands: Commands) {
+    commands
+        .spawn((
+            Name::new("SelectionPanel"),
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(128.0),
+                left: Val::Px(12.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Start,
+                padding: UiRect::all(Val::Px(10.0)),
+                border_radius: BorderRadius::all(Val::Px(8.0)),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.12, 0.12, 0.14, 0.98)),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("Selection"),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(TEXT_COLOR),
+                Node {
+                    margin: UiRect::bottom(Val::Px(6.0)),
+                    ..default()
+                },
+            ));
+
+            parent
+                .spawn((
+                    Node {
+                        width: Val::Px(274.0),
+                        height: Val::Px(28.0),
+                        align_items: AlignItems::Center,
+                        padding: UiRect::horizontal(Val::Px(8.0)),
+                        margin: UiRect::bottom(Val::Px(6.0)),
+                        border_radius: BorderRadius::all(Val::Px(6.0)),
+                        overflow: Overflow::clip(),
+                        ..default()
+                    },
+                    BackgroundColor(FIELD_COLOR),
+                    SelectionQueryField,
+                    Interaction::None,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        Text::new(PLACEHOLDER),
+                        TextFont {
+                            font_size: 13.0,
+                            ..default()
+                        },
+                        TextColor(SECONDARY_TEXT_COLOR),
+                        SelectionQueryLabel,
+                    ));
+                });
+
+            for buttons in [
+                [
+                    (SelectionButton::Select, "Select"),
+                    (SelectionButton::Add, "Add"),
+                    (SelectionButton::Clear, "Clear"),
+                ],
+                [
+                    (SelectionButton::Measure, "Measure"),
+                    (SelectionButton::Minimize, "Minimize"),
+                    (SelectionButton::Export, "Export"),
+                ],
+            ] {
+                parent
+                    .spawn(Node {
+                        flex_direction: FlexDirection::Row,
+                        column_gap: Val::Px(6.0),
+                        margin: UiRect::bottom(Val::Px(6.0)),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        for (button, label) in buttons {
+                            spawn_selection_button(parent, button, label);
+                        }
+                    });
+            }
+
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font_size: 12.0,
+                    ..default()
+                },
+                TextColor(SECONDARY_TEXT_COLOR),
+                SelectionStatusLabel,
+            ));
+        });
+}
+
+fn spawn_selection_button(parent: &mut ChildSpawnerCommands, button: SelectionButton, label: &str) {
+    parent
+        .spawn((
+            Node {
+                width: Val::Px(64.0),
+                height: Val::Px(28.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                border_radius: BorderRadius::all(Val::Px(6.0)),
+                ..default()
+            },
+            BackgroundColor(BUTTON_COLOR),
+            button,
+            Interaction::None,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(label),
+                TextFont {
+                    font_size: 13.0,
+                    ..default()
+                },
+                TextColor(BUTTON_TEXT_COLOR),
+            ));
+        });
+}
+
+/// Clicking the query field focuses it, clicking anywhere else unfocuses it.
+fn handle_query_field_click(
+    mouse_button: Res<ButtonInput<MouseButton>>,
+    mut query: ResMut<SelectionQuery>,
+    mut field_query: Query<(&Interaction, &mut BackgroundColor), With<SelectionQueryField>>,
+) {
+    let Ok((interaction, mut bg_color)) = field_query.single_mut() else {
+        return;
+    };
+
+    if mouse_button.just_pressed(MouseButton::Left) {
+        let editing = *interaction == Interaction::Pressed;
+        if query.editing != editing {
+            query.editing = editing;
+        }
+    }
+
+    let color = if query.editing || *interaction != Interaction::None {
+        FIELD_EDITING_COLOR
+    } else {
+        FIELD_COLOR
+    };
+    if bg_color.0 != color {
+        *bg_color = BackgroundColor(color);
+    }
+}
+
+/// Types into the focused query field. Enter selects the matching atoms,
+/// Escape unfocuses the field.
+fn handle_query_input(
+    mut query: ResMut<SelectionQuery>,
+    mut keyboard_messages: MessageReader<KeyboardInput>,
+    mut select_messages: MessageWriter<SelectMessage>,
+) {
+    if !query.editing {
+        keyboard_messages.clear();
+        return;
+    }
+
+    for input in keyboard_messages.read() {
+        if input.state != ButtonState::Pressed {
+            continue;
+        }
+        match &input.logical_key {
+            Key::Escape => query.editing = false,
+            Key::Enter => {
+                select_messages.write(SelectMessage {
+                    query: query.text.clone(),
+                    mode: SelectionMode::Replace,
+                });
+            }
+            Key::Backspace => {
+                query.text.pop();
+            }
+            Key::Space => query.text.push(' '),
+            Key::Character(text) => {
+                query.text.extend(text.chars().filter(|c| !c.is_control()));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Selects with the query or runs a tool on the selection. Tools that need
+/// atoms report an empty selection in the status line.
+fn handle_selection_button_click(
+    mut select_messages: MessageWriter<SelectMessage>,
+    mut selection: ResMut<Selection>,
+    mut query: ResMut<SelectionQuery>,
+    mut tools: SelectionTools,
+    mut interaction_query: Query<
+        (&Interaction, &SelectionButton, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+) {
+    for (interaction, button, mut bg_color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                query.error = None;
+                match *button {
+                    SelectionButton::Select | SelectionButton::Add => {
+                        let mode = if *button == SelectionButton::Add {
+                            SelectionMode::Add
+                        } else {
+                            SelectionMode::Replace
+                        };
+                        select_messages.write(SelectMessage {
+                            query: query.text.clone(),
+                            mode,
+                        });
+                    }
+                    SelectionButton::Clear => selection.clear(),
+                    SelectionButton::Measure => {
+                        if MeasurementKind::from_atom_count(selection.len()).is_some() {
+                            tools.measurement_messages.write(AddMeasurementMessage {
+                                atoms: selection.atoms().to_vec(),
+                            });
+                        } else {
+                            query.error = Some("Select 2 to 4 atoms to measure".to_string());
+                        }
+                    }
+                    SelectionButton::Minimize | SelectionButton::Export if selection.is_empty() => {
+                        query.error = Some("No atoms selected".to_string());
+                    }
+                    SelectionButton::Minimize => {
+                        tools.minimize_messages.write(MinimizeMessage {
+                            scope: MinimizationScope::Atoms(selection.atoms().to_vec()),
+                        });
+                    }
+                    SelectionButton::Export => {
+                        let file_name = tools
+                            .project_res
+                            .project
+                            .name
+                            .to_lowercase()
+                            .replace(' ', "_");
+                        tools.export_messages.write(ExportProjectMessage {
+                            path: format!("{}_selection.pdb", file_name).into(),
+                            atoms: Some(selection.atoms().to_vec()),
+                        });
+                    }
+                }
+            }
+            Interaction::Hovered => {
+                *bg_color = BackgroundColor(BUTTON_HOVER_COLOR);
+            }
+            Interaction::None => {
+                *bg_color = BackgroundColor(BUTTON_COLOR);
+            }
+        }
+    }
+}
+
+/// Shows the query with a cursor while editing, or a placeholder when empty.
+fn update_query_label(
+    query: Res<SelectionQuery>,
+    mut label_query: Query<(&mut Text, &mut TextColor), With<SelectionQueryLabel>>,
+) {
+    if !query.is_changed() {
+        return;
+    }
+
+    let Ok((mut text, mut color)) = label_query.single_mut() else {
+        return;
+    };
+    if query.editing {
+        text.0 = format!("{}_", query.text);
+        color.0 = TEXT_COLOR;
+    } else if query.text.is_empty() {
+        text.0 = PLACEHOLDER.to_string();
+        color.0 = SECONDARY_TEXT_COLOR;
+    } else {
+        text.0 = query.text.clone();
+        color.0 = TEXT_COLOR;
+    }
+}
+
+/// Shows the number of selected atoms and residues, or the last error.
+fn update_selection_status(
+    selection: Res<Selection>,
+    query: Res<SelectionQuery>,
+    mut status_label: Query<&mut Text, With<SelectionStatusLabel>>,
+) {
+    if !selection.is_changed() && !query.is_changed() {
+        return;
+    }
+
+    let Ok(mut text) = status_label.single_mut() else {
+        return;
+    };
+    text.0 = if let Some(error) = &query.error {
+        error.clone()
+    } else if selection.is_empty() {
+        "No atoms selected".to_string()
+    } else {
+        format!(
+            "{} atoms in {} residues",
+            selection.len(),
+            selection.residues().len()
+        )
+    };
+}
@@ -0,0 +1,246 @@
+use bevy::prelude::*;
+
+use super::tab_bar::{
+    BUTTON_COLOR, BUTTON_HOVER_COLOR, BUTTON_TEXT_COLOR, SECONDARY_TEXT_COLOR, TEXT_COLOR,
+};
+use crate::chemistry::amino_acids::{AminoAcid, Residue};
+use crate::chemistry::surface::{
+    ClearSurfaceMessage, ComputeSurfaceMessage, MolecularSurface, SurfaceKind, SurfaceSettings,
+};
+use crate::user::interaction::SelectedResidue;
+
+/// Number of most exposed residues listed under the areas.
+const EXPOSED_RESIDUES: usize = 3;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum SurfaceButton {
+    Compute(SurfaceKind),
+    Clear,
+    Coloring,
+}
+
+/// Label of the button cycling the surface coloring.
+#[derive(Component)]
+struct ColoringLabel;
+
+#[derive(Component)]
+struct SurfaceStatusLabel;
+
+/// Panel computing molecular surfaces and listing their areas.
+pub struct SurfacePanelPlugin;
+
+impl Plugin for SurfacePanelPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_surface_panel).add_systems(
+            Update,
+            (
+                handle_surface_button_click,
+                update_coloring_label,
+                update_surface_status,
+            ),
+        );
+    }
+}
+
+fn setup_surface_panel(mut commands: Commands) {
+    commands
+        .spawn((
+            Name::new("SurfacePanel"),
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(12.0),
+                left: Val::Px(12.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Start,
+                padding: UiRect::all(Val::Px(10.0)),
+                border_radius: BorderRadius::all(Val::Px(8.0)),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.12, 0.12, 0.14, 0.98)),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("Surface"),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(TEXT_COLOR),
+                Node {
+                    margin: UiRect::bottom(Val::Px(6.0)),
+                    ..default()
+                },
+            ));
+
+            parent
+                .spawn(Node {
+                    flex_direction: FlexDirection::Row,
+                    column_gap: Val::Px(6.0),
+                    margin: UiRect::bottom(Val::Px(6.0)),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    let accessible = SurfaceButton::Compute(SurfaceKind::SolventAccessible);
+                    let excluded = SurfaceButton::Compute(SurfaceKind::SolventExcluded);
+                    spawn_surface_button(parent, accessible, "SAS");
+                    spawn_surface_button(parent, excluded, "SES");
+                    spawn_surface_button(parent, SurfaceButton::Clear, "Clear");
+                    spawn_surface_button(parent, SurfaceButton::Coloring, "");
+                });
+
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font_size: 12.0,
+                    ..default()
+                },
+                TextColor(SECONDARY_TEXT_COLOR),
+                SurfaceStatusLabel,
+            ));
+        });
+}
+
+fn spawn_surface_button(parent: &mut ChildSpawnerCommands, button: SurfaceButton, label: &str) {
+    parent
+        .spawn((
+            Node {
+                width: Val::Px(64.0),
+                height: Val::Px(28.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                border_radius: BorderRadius::all(Val::Px(6.0)),
+                ..default()
+            },
+            BackgroundColor(BUTTON_COLOR),
+            button,
+            Interaction::None,
+        ))
+        .with_children(|parent| {
+            let mut text = parent.spawn((
+                Text::new(label),
+                TextFont {
+                    font_size: 13.0,
+                    ..default()
+                },
+                TextColor(BUTTON_TEXT_COLOR),
+            ));
+            if button == SurfaceButton::Coloring {
+                text.insert(ColoringLabel);
+            }
+        });
+}
+
+/// Computes or clears the surface, or cycles its coloring and recomputes the
+/// current surface with the new one.
+fn handle_surface_button_click(
+    mut compute_messages: MessageWriter<ComputeSurfaceMessage>,
+    mut clear_messages: MessageWriter<ClearSurfaceMessage>,
+    mut settings: ResMut<SurfaceSettings>,
+    surface_query: Query<&MolecularSurface>,
+    mut interaction_query: Query<
+        (&Interaction, &SurfaceButton, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+) {
+    for (interaction, button, mut bg_color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => match *button {
+                SurfaceButton::Compute(kind) => {
+                    compute_messages.write(ComputeSurfaceMessage { kind });
+                }
+                SurfaceButton::Clear => {
+                    clear_messages.write(ClearSurfaceMessage);
+                }
+                SurfaceButton::Coloring => {
+                    settings.coloring = settings.coloring.next();
+                    if let Some(surface) = surface_query.iter().next() {
+                        compute_messages.write(ComputeSurfaceMessage { kind: surface.kind });
+                    }
+                }
+            },
+            Interaction::Hovered => {
+                *bg_color = BackgroundColor(BUTTON_HOVER_COLOR);
+            }
+            Interaction::None => {
+                *bg_color = BackgroundColor(BUTTON_COLOR);
+            }
+        }
+    }
+}
+
+fn update_coloring_label(
+    settings: Res<SurfaceSettings>,
+    mut label_query: Query<&mut Text, With<ColoringLabel>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    if let Ok(mut text) = label_query.single_mut() {
+        text.0 = settings.coloring.name().to_string();
+    }
+}
+
+/// Shows the total area, the area of the selected residue and the most
+/// exposed residues.
+fn update_surface_status(
+    selected: Res<SelectedResidue>,
+    surface_query: Query<&MolecularSurface>,
+    changed_surfaces: Query<(), Changed<MolecularSurface>>,
+    mut removed_surfaces: RemovedComponents<MolecularSurface>,
+    residue_query: Query<(&AminoAcid, &Residue)>,
+    mut status_label: Query<&mut Text, With<SurfaceStatusLabel>>,
+) {
+    let any_removed = removed_surfaces.read().count() > 0;
+    if !any_removed && changed_surfaces.is_empty() && !selected.is_changed() {
+        return;
+    }
+
+    let Ok(mut text) = status_label.single_mut() else {
+        return;
+    };
+    let Some(surface) = surface_query.iter().next() else {
+        text.0 = String::new();
+        return;
+    };
+
+    let describe = |entity: Entity| {
+        residue_query
+            .get(entity)
+            .map(|(amino_acid, residue)| {
+                format!(
+                    "{} {}{}",
+                    amino_acid.code.three_letter(),
+                    residue.chain_id,
+                    residue.residue_number
+                )
+            })
+            .ok()
+    };
+
+    let mut lines = vec![format!(
+        "{} ({}): {:.0} Å²",
+        surface.kind.name(),
+        surface.coloring.name(),
+        surface.total_area
+    )];
+    if let Some(entity) = selected.entity
+        && let Some(name) = describe(entity)
+    {
+        lines.push(format!("{}: {:.1} Å²", name, surface.residue_area(entity)));
+    }
+
+    let mut exposed: Vec<(Entity, f32)> = surface
+        .residue_areas
+        .iter()
+        .map(|(&entity, &area)| (entity, area))
+        .collect();
+    exposed.sort_by(|a, b| b.1.total_cmp(&a.1));
+    for (entity, area) in exposed.into_iter().take(EXPOSED_RESIDUES) {
+        if let Some(name) = describe(entity) {
+            lines.push(format!("  {}: {:.1} Å²", name, area));
+        }
+    }
+
+    text.0 = lines.join("\n");
+}
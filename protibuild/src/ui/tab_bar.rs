@@ -1,14 +1,20 @@
 use bevy::prelude::*;
+use std::path::PathBuf;
 
-use crate::projects::{SwitchProjectMessage, templates::ProjectTemplates};
+use super::file_browser::{FileBrowser, FileBrowserMode};
+use crate::chemistry::io::StructureFormat;
+use crate::projects::{
+    ExportProjectMessage, LoadProjectMessage, ProjectDocument, ProjectResource, SaveProjectMessage,
+    SwitchProjectMessage, recent::RecentProjects, templates::TemplateRegistry,
+};
 
 const ACTIVE_TAB_COLOR: Color = Color::srgb(0.30, 0.30, 0.32);
 const INACTIVE_TAB_COLOR: Color = Color::srgb(0.18, 0.18, 0.20);
-const BUTTON_COLOR: Color = Color::srgb(0.25, 0.35, 0.45);
-const BUTTON_HOVER_COLOR: Color = Color::srgb(0.35, 0.45, 0.55);
-const TEXT_COLOR: Color = Color::srgb(0.95, 0.95, 0.95);
-const SECONDARY_TEXT_COLOR: Color = Color::srgb(0.7, 0.7, 0.7);
-const BUTTON_TEXT_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
+pub(crate) const BUTTON_COLOR: Color = Color::srgb(0.25, 0.35, 0.45);
+pub(crate) const BUTTON_HOVER_COLOR: Color = Color::srgb(0.35, 0.45, 0.55);
+pub(crate) const TEXT_COLOR: Color = Color::srgb(0.95, 0.95, 0.95);
+pub(crate) const SECONDARY_TEXT_COLOR: Color = Color::srgb(0.7, 0.7, 0.7);
+pub(crate) const BUTTON_TEXT_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
 
 /// Resource tracking the currently active tab.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Resource)]
@@ -35,15 +41,48 @@ struct TabButton {
 #[derive(Component)]
 struct FileTabContent;
 
+#[derive(Component)]
+struct TemplateList;
+
 #[derive(Component)]
 struct ProjectSelectionButton {
-    template: ProjectTemplate,
+    /// Index into the `TemplateRegistry`.
+    template: usize,
+}
+
+#[derive(Component)]
+struct ExportButton {
+    format: StructureFormat,
+}
+
+#[derive(Component)]
+struct ProjectStatusLabel;
+
+/// Toggles whether the undo history is saved with the project.
+#[derive(Component)]
+struct SaveHistoryButton;
+
+#[derive(Component)]
+struct SaveHistoryLabel;
+
+#[derive(Component)]
+struct FileMenuButton {
+    action: FileMenuAction,
 }
 
 #[derive(Debug, Clone, Copy)]
-enum ProjectTemplate {
-    DevCube,
-    AminoAcids,
+enum FileMenuAction {
+    Open,
+    Save,
+    SaveAs,
+}
+
+#[derive(Component)]
+struct RecentProjectsList;
+
+#[derive(Component)]
+struct RecentProjectButton {
+    path: PathBuf,
 }
 
 /// Plugin for the tab bar UI.
@@ -58,6 +97,14 @@ impl Plugin for TabBarPlugin {
                 (
                     handle_tab_clicks,
                     handle_project_selection,
+                    update_template_list,
+                    handle_export_click,
+                    handle_file_menu_click,
+                    handle_save_history_click,
+                    update_save_history_label,
+                    handle_recent_project_click,
+                    update_project_status,
+                    update_recent_projects,
                     update_tab_visibility,
                 ),
             );
@@ -193,11 +240,11 @@ fn setup_tab_bar(mut commands: Commands) {
                 },
             ));
 
-            // Templates label
+            // Current project and its file
             parent.spawn((
-                Text::new("Templates:"),
+                Text::new(""),
                 TextFont {
-                    font_size: 14.0,
+                    font_size: 12.0,
                     ..default()
                 },
                 TextColor(SECONDARY_TEXT_COLOR),
@@ -205,65 +252,162 @@ fn setup_tab_bar(mut commands: Commands) {
                     margin: UiRect::bottom(Val::Px(10.0)),
                     ..default()
                 },
+                ProjectStatusLabel,
             ));
 
-            // Dev Cube button
+            // Open / Save / Save As buttons
+            parent
+                .spawn(Node {
+                    width: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Row,
+                    column_gap: Val::Px(6.0),
+                    margin: UiRect::bottom(Val::Px(10.0)),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for (action, label) in [
+                        (FileMenuAction::Open, "Open..."),
+                        (FileMenuAction::Save, "Save"),
+                        (FileMenuAction::SaveAs, "Save As..."),
+                    ] {
+                        parent
+                            .spawn((
+                                Node {
+                                    flex_grow: 1.0,
+                                    height: Val::Px(32.0),
+                                    justify_content: JustifyContent::Center,
+                                    align_items: AlignItems::Center,
+                                    border_radius: BorderRadius::all(Val::Px(6.0)),
+                                    ..default()
+                                },
+                                BackgroundColor(BUTTON_COLOR),
+                                FileMenuButton { action },
+                                Interaction::None,
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn((
+                                    Text::new(label),
+                                    TextFont {
+                                        font_size: 13.0,
+                                        ..default()
+                                    },
+                                    TextColor(BUTTON_TEXT_COLOR),
+                                ));
+                            });
+                    }
+                });
+
             parent
                 .spawn((
                     Node {
                         width: Val::Percent(100.0),
-                        height: Val::Px(40.0),
+                        height: Val::Px(28.0),
                         justify_content: JustifyContent::Center,
                         align_items: AlignItems::Center,
                         margin: UiRect::bottom(Val::Px(10.0)),
-                        border_radius: BorderRadius::all(Val::Px(8.0)),
+                        border_radius: BorderRadius::all(Val::Px(6.0)),
                         ..default()
                     },
                     BackgroundColor(BUTTON_COLOR),
-                    ProjectSelectionButton {
-                        template: ProjectTemplate::DevCube,
-                    },
+                    SaveHistoryButton,
                     Interaction::None,
                 ))
                 .with_children(|parent| {
                     parent.spawn((
-                        Text::new("Dev Cube"),
+                        Text::new(""),
                         TextFont {
-                            font_size: 14.0,
+                            font_size: 13.0,
                             ..default()
                         },
                         TextColor(BUTTON_TEXT_COLOR),
+                        SaveHistoryLabel,
                     ));
                 });
 
-            // Amino Acids button
-            parent
-                .spawn((
-                    Node {
-                        width: Val::Percent(100.0),
-                        height: Val::Px(40.0),
-                        justify_content: JustifyContent::Center,
-                        align_items: AlignItems::Center,
-                        margin: UiRect::bottom(Val::Px(10.0)),
-                        border_radius: BorderRadius::all(Val::Px(8.0)),
+            // Recent projects, filled in by update_recent_projects
+            parent.spawn((
+                Node {
+                    width: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    margin: UiRect::bottom(Val::Px(10.0)),
+                    ..default()
+                },
+                RecentProjectsList,
+            ));
+
+            // Templates label
+            parent.spawn((
+                Text::new("Templates:"),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(SECONDARY_TEXT_COLOR),
+                Node {
+                    margin: UiRect::bottom(Val::Px(10.0)),
+                    ..default()
+                },
+            ));
+
+            // Template buttons, filled in by update_template_list
+            parent.spawn((
+                Node {
+                    width: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                TemplateList,
+            ));
+
+            // Export label
+            parent.spawn((
+                Text::new("Export:"),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(SECONDARY_TEXT_COLOR),
+                Node {
+                    margin: UiRect {
+                        top: Val::Px(10.0),
+                        bottom: Val::Px(10.0),
                         ..default()
                     },
-                    BackgroundColor(BUTTON_COLOR),
-                    ProjectSelectionButton {
-                        template: ProjectTemplate::AminoAcids,
-                    },
-                    Interaction::None,
-                ))
-                .with_children(|parent| {
-                    parent.spawn((
-                        Text::new("Amino Acids"),
-                        TextFont {
-                            font_size: 14.0,
+                    ..default()
+                },
+            ));
+
+            // Export buttons
+            for (format, label) in [
+                (StructureFormat::Pdb, "Export PDB"),
+                (StructureFormat::Mmcif, "Export mmCIF"),
+            ] {
+                parent
+                    .spawn((
+                        Node {
+                            width: Val::Percent(100.0),
+                            height: Val::Px(40.0),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            margin: UiRect::bottom(Val::Px(10.0)),
+                            border_radius: BorderRadius::all(Val::Px(8.0)),
                             ..default()
                         },
-                        TextColor(BUTTON_TEXT_COLOR),
-                    ));
-                });
+                        BackgroundColor(BUTTON_COLOR),
+                        ExportButton { format },
+                        Interaction::None,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn((
+                            Text::new(label),
+                            TextFont {
+                                font_size: 14.0,
+                                ..default()
+                            },
+                            TextColor(BUTTON_TEXT_COLOR),
+                        ));
+                    });
+            }
         });
 }
 
@@ -310,33 +454,117 @@ fn update_tab_visibility(
     }
 }
 
+/// Rebuilds the template buttons, grouped by category, when the registry
+/// changes.
+fn update_template_list(
+    mut commands: Commands,
+    registry: Res<TemplateRegistry>,
+    list_query: Query<Entity, With<TemplateList>>,
+) {
+    if !registry.is_changed() {
+        return;
+    }
+    let Ok(list) = list_query.single() else {
+        return;
+    };
+
+    commands.entity(list).despawn_related::<Children>();
+    commands.entity(list).with_children(|parent| {
+        for category in registry.categories() {
+            parent.spawn((
+                Text::new(category),
+                TextFont {
+                    font_size: 12.0,
+                    ..default()
+                },
+                TextColor(SECONDARY_TEXT_COLOR),
+                Node {
+                    margin: UiRect::bottom(Val::Px(6.0)),
+                    ..default()
+                },
+            ));
+
+            for (index, template) in registry.templates().iter().enumerate() {
+                if template.category != category {
+                    continue;
+                }
+                parent
+                    .spawn((
+                        Node {
+                            width: Val::Percent(100.0),
+                            min_height: Val::Px(40.0),
+                            flex_direction: FlexDirection::Column,
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            padding: UiRect::all(Val::Px(6.0)),
+                            margin: UiRect::bottom(Val::Px(10.0)),
+                            border_radius: BorderRadius::all(Val::Px(8.0)),
+                            ..default()
+                        },
+                        BackgroundColor(BUTTON_COLOR),
+                        ProjectSelectionButton { template: index },
+                        Interaction::None,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn((
+                            Text::new(template.name.clone()),
+                            TextFont {
+                                font_size: 14.0,
+                                ..default()
+                            },
+                            TextColor(BUTTON_TEXT_COLOR),
+                        ));
+                        parent.spawn((
+                            Text::new(template.description.clone()),
+                            TextFont {
+                                font_size: 11.0,
+                                ..default()
+                            },
+                            TextColor(SECONDARY_TEXT_COLOR),
+                            TextLayout::new_with_justify(Justify::Center),
+                        ));
+                    });
+            }
+        }
+    });
+}
+
 #[allow(clippy::type_complexity)]
 fn handle_project_selection(
     mut messages: MessageWriter<SwitchProjectMessage>,
     mut active_tab: ResMut<ActiveTab>,
-    interaction_query: Query<(&Interaction, &ProjectSelectionButton), Changed<Interaction>>,
-    mut param_set: ParamSet<(
-        Query<(&TabButton, &mut BackgroundColor, &mut Node)>,
-        Query<(&ProjectSelectionButton, &mut BackgroundColor)>,
-    )>,
+    registry: Res<TemplateRegistry>,
+    mut interaction_query: Query<
+        (&Interaction, &ProjectSelectionButton, &mut BackgroundColor),
+        (Changed<Interaction>, Without<TabButton>),
+    >,
+    mut tab_button_query: Query<(&TabButton, &mut BackgroundColor, &mut Node)>,
 ) {
-    for (interaction, button) in &interaction_query {
+    for (interaction, button, mut bg_color) in &mut interaction_query {
         match *interaction {
             Interaction::Pressed => {
-                let new_project = match button.template {
-                    ProjectTemplate::DevCube => ProjectTemplates::dev_cube(),
-                    ProjectTemplate::AminoAcids => ProjectTemplates::amino_acids(),
+                let Some(template) = registry.templates().get(button.template) else {
+                    continue;
+                };
+                let new_project = match template.build() {
+                    Ok(project) => project,
+                    Err(e) => {
+                        error!("Failed to build template {}: {}", template.name, e);
+                        continue;
+                    }
                 };
 
                 messages.write(SwitchProjectMessage {
                     project: new_project,
+                    path: None,
+                    dirty: false,
                 });
 
                 // Switch back to default tab
                 active_tab.tab = TabType::Default;
 
                 // Update tab button colors and height
-                for (btn, mut bg_color, mut node) in &mut param_set.p0() {
+                for (btn, mut bg_color, mut node) in &mut tab_button_query {
                     if btn.tab == active_tab.tab {
                         *bg_color = BackgroundColor(ACTIVE_TAB_COLOR);
                         node.height = Val::Px(28.0);
@@ -349,25 +577,227 @@ fn handle_project_selection(
                 }
             }
             Interaction::Hovered => {
-                // Highlight on hover
-                for (btn, mut bg_color) in &mut param_set.p1() {
-                    if std::mem::discriminant(&btn.template)
-                        == std::mem::discriminant(&button.template)
-                    {
-                        *bg_color = BackgroundColor(BUTTON_HOVER_COLOR);
-                    }
-                }
+                *bg_color = BackgroundColor(BUTTON_HOVER_COLOR);
             }
             Interaction::None => {
-                // Reset color when not hovering
-                for (btn, mut bg_color) in &mut param_set.p1() {
-                    if std::mem::discriminant(&btn.template)
-                        == std::mem::discriminant(&button.template)
-                    {
-                        *bg_color = BackgroundColor(BUTTON_COLOR);
+                *bg_color = BackgroundColor(BUTTON_COLOR);
+            }
+        }
+    }
+}
+
+fn handle_export_click(
+    mut messages: MessageWriter<ExportProjectMessage>,
+    project_res: Res<ProjectResource>,
+    mut interaction_query: Query<
+        (&Interaction, &ExportButton, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+) {
+    for (interaction, button, mut bg_color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                let file_name = project_res.project.name.to_lowercase().replace(' ', "_");
+                messages.write(ExportProjectMessage {
+                    path: format!("{}.{}", file_name, button.format.extension()).into(),
+                    atoms: None,
+                });
+            }
+            Interaction::Hovered => {
+                *bg_color = BackgroundColor(BUTTON_HOVER_COLOR);
+            }
+            Interaction::None => {
+                *bg_color = BackgroundColor(BUTTON_COLOR);
+            }
+        }
+    }
+}
+
+fn handle_file_menu_click(
+    mut browser: ResMut<FileBrowser>,
+    mut save_messages: MessageWriter<SaveProjectMessage>,
+    document: Res<ProjectDocument>,
+    project_res: Res<ProjectResource>,
+    mut interaction_query: Query<
+        (&Interaction, &FileMenuButton, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+) {
+    for (interaction, button, mut bg_color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                let name = &project_res.project.name;
+                match (button.action, &document.path) {
+                    (FileMenuAction::Open, _) => {
+                        browser.show(FileBrowserMode::Open, &document, name);
+                    }
+                    (FileMenuAction::Save, Some(path)) => {
+                        save_messages.write(SaveProjectMessage { path: path.clone() });
+                    }
+                    (FileMenuAction::Save, None) | (FileMenuAction::SaveAs, _) => {
+                        browser.show(FileBrowserMode::Save, &document, name);
                     }
                 }
             }
+            Interaction::Hovered => {
+                *bg_color = BackgroundColor(BUTTON_HOVER_COLOR);
+            }
+            Interaction::None => {
+                *bg_color = BackgroundColor(BUTTON_COLOR);
+            }
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn handle_save_history_click(
+    mut project_res: ResMut<ProjectResource>,
+    mut document: ResMut<ProjectDocument>,
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<SaveHistoryButton>),
+    >,
+) {
+    for (interaction, mut bg_color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                project_res.project.save_history = !project_res.project.save_history;
+                document.dirty = true;
+            }
+            Interaction::Hovered => {
+                *bg_color = BackgroundColor(BUTTON_HOVER_COLOR);
+            }
+            Interaction::None => {
+                *bg_color = BackgroundColor(BUTTON_COLOR);
+            }
+        }
+    }
+}
+
+fn update_save_history_label(
+    project_res: Res<ProjectResource>,
+    mut label_query: Query<&mut Text, With<SaveHistoryLabel>>,
+) {
+    if !project_res.is_changed() {
+        return;
+    }
+
+    if let Ok(mut text) = label_query.single_mut() {
+        text.0 = if project_res.project.save_history {
+            "Undo History: Saved"
+        } else {
+            "Undo History: Not Saved"
         }
+        .to_string();
     }
 }
+
+fn handle_recent_project_click(
+    mut messages: MessageWriter<LoadProjectMessage>,
+    mut interaction_query: Query<
+        (&Interaction, &RecentProjectButton, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+) {
+    for (interaction, button, mut bg_color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                messages.write(LoadProjectMessage {
+                    path: button.path.clone(),
+                });
+            }
+            Interaction::Hovered => {
+                *bg_color = BackgroundColor(BUTTON_HOVER_COLOR);
+            }
+            Interaction::None => {
+                *bg_color = BackgroundColor(INACTIVE_TAB_COLOR);
+            }
+        }
+    }
+}
+
+/// Shows the project name, a `*` for unsaved changes and the project file.
+fn update_project_status(
+    document: Res<ProjectDocument>,
+    project_res: Res<ProjectResource>,
+    mut label_query: Query<&mut Text, With<ProjectStatusLabel>>,
+) {
+    if !document.is_changed() && !project_res.is_changed() {
+        return;
+    }
+
+    if let Ok(mut text) = label_query.single_mut() {
+        let marker = if document.dirty { " *" } else { "" };
+        let file = document
+            .path
+            .as_ref()
+            .map_or("Not saved".to_string(), |path| path.display().to_string());
+        text.0 = format!("{}{}\n{}", project_res.project.name, marker, file);
+    }
+}
+
+/// Rebuilds the recent projects list when it changes.
+fn update_recent_projects(
+    mut commands: Commands,
+    recent: Res<RecentProjects>,
+    list_query: Query<Entity, With<RecentProjectsList>>,
+) {
+    if !recent.is_changed() {
+        return;
+    }
+    let Ok(list) = list_query.single() else {
+        return;
+    };
+
+    commands.entity(list).despawn_related::<Children>();
+    if recent.paths.is_empty() {
+        return;
+    }
+
+    commands.entity(list).with_children(|parent| {
+        parent.spawn((
+            Text::new("Recent:"),
+            TextFont {
+                font_size: 14.0,
+                ..default()
+            },
+            TextColor(SECONDARY_TEXT_COLOR),
+            Node {
+                margin: UiRect::bottom(Val::Px(6.0)),
+                ..default()
+            },
+        ));
+
+        for path in &recent.paths {
+            let label = path.file_name().map_or_else(
+                || path.display().to_string(),
+                |name| name.to_string_lossy().into_owned(),
+            );
+            parent
+                .spawn((
+                    Node {
+                        width: Val::Percent(100.0),
+                        height: Val::Px(26.0),
+                        align_items: AlignItems::Center,
+                        padding: UiRect::horizontal(Val::Px(8.0)),
+                        margin: UiRect::bottom(Val::Px(2.0)),
+                        border_radius: BorderRadius::all(Val::Px(4.0)),
+                        ..default()
+                    },
+                    BackgroundColor(INACTIVE_TAB_COLOR),
+                    RecentProjectButton { path: path.clone() },
+                    Interaction::None,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        Text::new(label),
+                        TextFont {
+                            font_size: 12.0,
+                            ..default()
+                        },
+                        TextColor(TEXT_COLOR),
+                    ));
+                });
+        }
+    });
+}
@@ -0,0 +1,202 @@
+use bevy::prelude::*;
+
+use super::file_browser::{FileBrowser, FileBrowserMode};
+use super::tab_bar::{
+    BUTTON_COLOR, BUTTON_HOVER_COLOR, BUTTON_TEXT_COLOR, SECONDARY_TEXT_COLOR, TEXT_COLOR,
+};
+use crate::projects::{ProjectDocument, ProjectResource, SaveProjectMessage};
+
+#[derive(Component)]
+struct UnsavedChangesDialog;
+
+#[derive(Component)]
+struct UnsavedChangesLabel;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+enum UnsavedChangesButton {
+    Save,
+    Discard,
+    Cancel,
+}
+
+/// Dialog asking what to do with unsaved changes before switching projects.
+pub struct UnsavedChangesPlugin;
+
+impl Plugin for UnsavedChangesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_unsaved_changes_dialog)
+            .add_systems(
+                Update,
+                (update_unsaved_changes_dialog, handle_unsaved_changes_click),
+            );
+    }
+}
+
+fn setup_unsaved_changes_dialog(mut commands: Commands) {
+    commands
+        .spawn((
+            Name::new("UnsavedChangesDialog"),
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                display: Display::None,
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.4)),
+            GlobalZIndex(9),
+            UnsavedChangesDialog,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Node {
+                        width: Val::Px(360.0),
+                        flex_direction: FlexDirection::Column,
+                        padding: UiRect::all(Val::Px(12.0)),
+                        border_radius: BorderRadius::all(Val::Px(8.0)),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgba(0.12, 0.12, 0.14, 0.98)),
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        Text::new("Unsaved Changes"),
+                        TextFont {
+                            font_size: 18.0,
+                            ..default()
+                        },
+                        TextColor(TEXT_COLOR),
+                        Node {
+                            margin: UiRect::bottom(Val::Px(6.0)),
+                            ..default()
+                        },
+                    ));
+
+                    parent.spawn((
+                        Text::new(""),
+                        TextFont {
+                            font_size: 13.0,
+                            ..default()
+                        },
+                        TextColor(SECONDARY_TEXT_COLOR),
+                        Node {
+                            margin: UiRect::bottom(Val::Px(12.0)),
+                            ..default()
+                        },
+                        UnsavedChangesLabel,
+                    ));
+
+                    parent
+                        .spawn(Node {
+                            flex_direction: FlexDirection::Row,
+                            justify_content: JustifyContent::FlexEnd,
+                            column_gap: Val::Px(8.0),
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            spawn_dialog_button(parent, UnsavedChangesButton::Save, "Save");
+                            spawn_dialog_button(parent, UnsavedChangesButton::Discard, "Discard");
+                            spawn_dialog_button(parent, UnsavedChangesButton::Cancel, "Cancel");
+                        });
+                });
+        });
+}
+
+fn spawn_dialog_button(
+    parent: &mut ChildSpawnerCommands,
+    button: UnsavedChangesButton,
+    label: &str,
+) {
+    parent
+        .spawn((
+            Node {
+                width: Val::Px(90.0),
+                height: Val::Px(32.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                border_radius: BorderRadius::all(Val::Px(6.0)),
+                ..default()
+            },
+            BackgroundColor(BUTTON_COLOR),
+            button,
+            Interaction::None,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(label),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(BUTTON_TEXT_COLOR),
+            ));
+        });
+}
+
+/// Shows the dialog while a project switch waits on unsaved changes.
+fn update_unsaved_changes_dialog(
+    document: Res<ProjectDocument>,
+    project_res: Res<ProjectResource>,
+    mut dialog_query: Query<&mut Node, With<UnsavedChangesDialog>>,
+    mut label_query: Query<&mut Text, With<UnsavedChangesLabel>>,
+) {
+    if !document.is_changed() {
+        return;
+    }
+
+    let Ok(mut dialog) = dialog_query.single_mut() else {
+        return;
+    };
+    let Some(pending) = &document.pending_switch else {
+        dialog.display = Display::None;
+        return;
+    };
+    dialog.display = Display::Flex;
+
+    if let Ok(mut text) = label_query.single_mut() {
+        text.0 = format!(
+            "Save changes to {} before opening {}?",
+            project_res.project.name, pending.project.name
+        );
+    }
+}
+
+fn handle_unsaved_changes_click(
+    mut document: ResMut<ProjectDocument>,
+    mut browser: ResMut<FileBrowser>,
+    project_res: Res<ProjectResource>,
+    mut save_messages: MessageWriter<SaveProjectMessage>,
+    mut interaction_query: Query<
+        (&Interaction, &UnsavedChangesButton, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+) {
+    for (interaction, button, mut bg_color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => match button {
+                UnsavedChangesButton::Save => match document.path.clone() {
+                    Some(path) => {
+                        save_messages.write(SaveProjectMessage { path });
+                    }
+                    None => {
+                        browser.show(FileBrowserMode::Save, &document, &project_res.project.name)
+                    }
+                },
+                UnsavedChangesButton::Discard => {
+                    document.dirty = false;
+                    document.discarded = true;
+                }
+                UnsavedChangesButton::Cancel => document.pending_switch = None,
+            },
+            Interaction::Hovered => {
+                *bg_color = BackgroundColor(BUTTON_HOVER_COLOR);
+            }
+            Interaction::None => {
+                *bg_color = BackgroundColor(BUTTON_COLOR);
+            }
+        }
+    }
+}
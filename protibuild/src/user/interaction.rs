@@ -1,10 +1,29 @@
 use bevy::{
+    ecs::system::SystemParam,
     input::mouse::{MouseMotion, MouseWheel},
     prelude::*,
-    window::{CursorGrabMode, CursorOptions},
+    ui::UiGlobalTransform,
+    window::{CursorGrabMode, CursorOptions, PrimaryWindow},
 };
 
 use super::camera::{CameraController, CameraTransformData};
+use crate::chemistry::amino_acids::{AminoAcid, RotamerChange, SetRotamerMessage};
+use crate::chemistry::atoms::Atom;
+use crate::chemistry::dynamics::{DynamicsControl, DynamicsControlMessage};
+use crate::chemistry::force_field::ComputeEnergyMessage;
+use crate::chemistry::minimization::{MinimizationScope, MinimizeMessage};
+use crate::chemistry::rendering::{ColorScheme, Representation};
+use crate::measurements::{AddMeasurementMessage, MeasurementKind, MeasurementTool};
+use crate::projects::history::{HistoryAction, HistoryMessage};
+use crate::projects::{ProjectEntities, SetColorSchemeMessage, SetRepresentationMessage};
+use crate::selection::{SELECTION_COLOR, SelectableAtoms, Selection, SelectionMode};
+use crate::ui::text_input_focused;
+
+/// Cursor movement in logical pixels below which a drag counts as a click.
+const CLICK_DISTANCE: f32 = 4.0;
+
+/// Distance from the camera at which the drag outline is drawn.
+const OUTLINE_DISTANCE: f32 = 1.0;
 
 #[derive(Component)]
 pub(crate) struct Movable;
@@ -12,6 +31,150 @@ pub(crate) struct Movable;
 #[derive(Component)]
 pub(crate) struct HeldMovable {
     distance: f32,
+    /// Offset from the grabbed point to the object's origin, so the object
+    /// does not jump onto the crosshair.
+    offset: Vec3,
+}
+
+/// Box and bounding sphere a movable object is picked with, in its local
+/// space. Objects made of atoms are then hit tested atom by atom.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub(crate) struct PickBounds {
+    pub center: Vec3,
+    pub half_extents: Vec3,
+    /// Radius of the bounding sphere around `center`.
+    pub radius: f32,
+}
+
+impl PickBounds {
+    pub(crate) fn cuboid(half_extents: Vec3) -> Self {
+        Self {
+            center: Vec3::ZERO,
+            half_extents,
+            radius: half_extents.length(),
+        }
+    }
+
+    /// Bounds enclosing spheres given by center and radius, or `None` if there
+    /// are none.
+    pub(crate) fn from_spheres(spheres: impl IntoIterator<Item = (Vec3, f32)>) -> Option<Self> {
+        let spheres: Vec<(Vec3, f32)> = spheres.into_iter().collect();
+        let min = spheres
+            .iter()
+            .map(|&(center, radius)| center - radius)
+            .reduce(Vec3::min)?;
+        let max = spheres
+            .iter()
+            .map(|&(center, radius)| center + radius)
+            .reduce(Vec3::max)?;
+
+        let center = (min + max) / 2.0;
+        let radius = spheres
+            .iter()
+            .map(|&(position, radius)| position.distance(center) + radius)
+            .fold(0.0, f32::max);
+
+        Some(Self {
+            center,
+            half_extents: (max - min) / 2.0,
+            radius,
+        })
+    }
+}
+
+/// Ray casts against the pick bounds of movable objects.
+#[allow(clippy::type_complexity)]
+#[derive(SystemParam)]
+pub(crate) struct MovablePicker<'w, 's> {
+    movable_query: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static GlobalTransform,
+            &'static PickBounds,
+            Option<&'static Children>,
+            Option<&'static Representation>,
+        ),
+        With<Movable>,
+    >,
+    atom_query: Query<'w, 's, (&'static Atom, &'static GlobalTransform)>,
+}
+
+impl MovablePicker<'_, '_> {
+    /// Closest movable object hit by the ray and the distance along the ray.
+    pub(crate) fn pick(&self, ray_origin: Vec3, ray_dir: Vec3) -> Option<(Entity, f32)> {
+        self.movable_query
+            .iter()
+            .filter_map(|(entity, ..)| {
+                self.distance(entity, ray_origin, ray_dir)
+                    .map(|distance| (entity, distance))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+    }
+
+    /// Grabs `entity` at the point where the ray hits it.
+    pub(crate) fn grab(
+        &self,
+        entity: Entity,
+        ray_origin: Vec3,
+        ray_dir: Vec3,
+    ) -> Option<HeldMovable> {
+        let distance = self.distance(entity, ray_origin, ray_dir)?;
+        let (_, transform, ..) = self.movable_query.get(entity).ok()?;
+        Some(HeldMovable {
+            distance,
+            offset: transform.translation() - (ray_origin + ray_dir * distance),
+        })
+    }
+
+    /// Distance along the ray to `entity`, if the ray hits it.
+    ///
+    /// The bounding sphere and the box are tested first. Objects with atoms
+    /// are only hit where the ray passes through one of their atoms.
+    pub(crate) fn distance(&self, entity: Entity, ray_origin: Vec3, ray_dir: Vec3) -> Option<f32> {
+        let (_, transform, bounds, children, representation) =
+            self.movable_query.get(entity).ok()?;
+        let representation = representation.copied().unwrap_or_default();
+
+        let scale = transform.scale().max_element();
+        ray_sphere_intersection(
+            ray_origin,
+            ray_dir,
+            transform.transform_point(bounds.center),
+            bounds.radius * scale,
+        )?;
+
+        // The local ray direction is not normalized, so distances along it
+        // equal distances along the world ray.
+        let inverse = transform.to_matrix().inverse();
+        let box_distance = ray_box_intersection(
+            inverse.transform_point3(ray_origin),
+            inverse.transform_vector3(ray_dir),
+            bounds.center,
+            bounds.half_extents,
+        )?;
+
+        let mut atoms = children
+            .into_iter()
+            .flatten()
+            .filter_map(|child| self.atom_query.get(*child).ok())
+            .peekable();
+        if atoms.peek().is_none() {
+            return Some(box_distance);
+        }
+
+        atoms
+            .filter_map(|(atom, atom_transform)| {
+                ray_sphere_intersection(
+                    ray_origin,
+                    ray_dir,
+                    atom_transform.translation(),
+                    representation.pick_radius(atom.element),
+                )
+            })
+            .min_by(f32::total_cmp)
+    }
 }
 
 #[derive(Resource)]
@@ -19,10 +182,132 @@ pub(crate) struct HoveredMovable {
     pub entity: Option<Entity>,
 }
 
+/// Atom under the crosshair while the cursor is captured.
+#[derive(Resource, Default)]
+pub(crate) struct HoveredAtom {
+    pub entity: Option<Entity>,
+}
+
+/// Residue used by residue editing tools: the only residue with selected
+/// atoms, or `None` when the selection is empty or spans several residues.
+#[derive(Resource, Default)]
+pub(crate) struct SelectedResidue {
+    pub entity: Option<Entity>,
+}
+
+/// Box or lasso being dragged with the cursor released.
+#[derive(Resource, Default)]
+pub(crate) struct DragSelection {
+    /// Cursor positions in logical pixels since the button was pressed, or
+    /// empty when no drag is in progress.
+    points: Vec<Vec2>,
+    lasso: bool,
+}
+
+impl DragSelection {
+    fn is_click(&self) -> bool {
+        self.points
+            .iter()
+            .all(|point| point.distance(self.points[0]) < CLICK_DISTANCE)
+    }
+
+    /// Corners of the box or points of the lasso, in order.
+    fn outline(&self) -> Vec<Vec2> {
+        match (self.lasso, self.points.first(), self.points.last()) {
+            (true, ..) => self.points.clone(),
+            (false, Some(&start), Some(&end)) => vec![
+                start,
+                Vec2::new(end.x, start.y),
+                end,
+                Vec2::new(start.x, end.y),
+            ],
+            _ => Vec::new(),
+        }
+    }
+
+    fn contains(&self, point: Vec2) -> bool {
+        polygon_contains(&self.outline(), point)
+    }
+}
+
+/// Converts between the cursor, screen positions and rays of the 3D camera.
+#[derive(SystemParam)]
+pub(crate) struct ScreenPicker<'w, 's> {
+    window_query: Query<'w, 's, &'static Window, With<PrimaryWindow>>,
+    camera_query: Query<
+        'w,
+        's,
+        (
+            &'static Camera,
+            &'static GlobalTransform,
+            &'static CameraController,
+        ),
+    >,
+    node_query: Query<
+        'w,
+        's,
+        (
+            &'static ComputedNode,
+            &'static UiGlobalTransform,
+            &'static InheritedVisibility,
+            &'static BackgroundColor,
+        ),
+    >,
+}
+
+impl ScreenPicker<'_, '_> {
+    fn is_captured(&self) -> bool {
+        self.camera_query
+            .single()
+            .is_ok_and(|(.., controller)| controller.is_captured)
+    }
+
+    /// Cursor position in logical pixels, if it is over the window.
+    fn cursor(&self) -> Option<Vec2> {
+        self.window_query.single().ok()?.cursor_position()
+    }
+
+    /// Whether the cursor is over a visible UI panel or button.
+    fn cursor_over_ui(&self) -> bool {
+        let Some(cursor) = self
+            .window_query
+            .single()
+            .ok()
+            .and_then(Window::physical_cursor_position)
+        else {
+            return false;
+        };
+        self.node_query
+            .iter()
+            .any(|(node, transform, visibility, background)| {
+                visibility.get()
+                    && background.0.alpha() > 0.0
+                    && node.contains_point(*transform, cursor)
+            })
+    }
+
+    /// Ray from the camera through a screen position.
+    fn ray(&self, screen: Vec2) -> Option<Ray3d> {
+        let (camera, transform, _) = self.camera_query.single().ok()?;
+        camera.viewport_to_world(transform, screen).ok()
+    }
+
+    /// Screen position of a world-space point in front of the camera.
+    fn project(&self, point: Vec3) -> Option<Vec2> {
+        let (camera, transform, _) = self.camera_query.single().ok()?;
+        camera.world_to_viewport(transform, point).ok()
+    }
+}
+
+/// Camera pose the hovered object was last picked from, and whether it has
+/// to be picked again.
 #[derive(Resource)]
 pub(crate) struct LastCameraPosition {
     position: Vec3,
+    rotation: Quat,
     frame_count: u32,
+    /// Set when the mouse moved or pick bounds changed since the last pick.
+    pending: bool,
 }
 
 fn ray_box_intersection(
@@ -55,28 +340,178 @@ fn ray_box_intersection(
     }
 }
 
+fn ray_sphere_intersection(
+    ray_origin: Vec3,
+    ray_dir: Vec3,
+    center: Vec3,
+    radius: f32,
+) -> Option<f32> {
+    let offset = ray_origin - center;
+    let b = offset.dot(ray_dir);
+    let c = offset.length_squared() - radius * radius;
+    let discriminant = b * b - c;
+
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    let sqrt_discriminant = discriminant.sqrt();
+    let t0 = -b - sqrt_discriminant;
+    let t1 = -b + sqrt_discriminant;
+
+    if t0 > 0.0 {
+        Some(t0)
+    } else if t1 > 0.0 {
+        Some(t1)
+    } else {
+        None
+    }
+}
+
+/// Whether `point` lies inside the polygon, by counting edge crossings.
+fn polygon_contains(polygon: &[Vec2], point: Vec2) -> bool {
+    let mut inside = false;
+    for (index, &a) in polygon.iter().enumerate() {
+        let b = polygon[(index + 1) % polygon.len()];
+        if (a.y > point.y) != (b.y > point.y)
+            && point.x < a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x)
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Selects the residue of the atom hit by the ray. With `toggle`, the residue
+/// is added to or removed from the selection instead; otherwise a miss clears
+/// the selection.
+fn select_residue(
+    selection: &mut Selection,
+    atoms: &SelectableAtoms,
+    ray_origin: Vec3,
+    ray_dir: Vec3,
+    toggle: bool,
+) {
+    let hit = closest_atom(
+        ray_origin,
+        ray_dir,
+        atoms.iter().map(|(entity, residue, atom, transform)| {
+            (
+                entity,
+                transform.translation(),
+                atoms.pick_radius(residue, atom),
+            )
+        }),
+    );
+    match hit.and_then(|(atom, _)| atoms.residue(atom)) {
+        Some(residue) => {
+            let mode = if toggle {
+                SelectionMode::Toggle
+            } else {
+                SelectionMode::Replace
+            };
+            selection.apply(atoms.residue_atoms(residue), mode);
+        }
+        None if !toggle => selection.clear(),
+        None => {}
+    }
+}
+
+fn shift_pressed(keyboard: &ButtonInput<KeyCode>) -> bool {
+    keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight])
+}
+
+/// Closest atom hit by the ray, given each atom's position and pick radius,
+/// and the distance along the ray.
+fn closest_atom(
+    ray_origin: Vec3,
+    ray_dir: Vec3,
+    atoms: impl IntoIterator<Item = (Entity, Vec3, f32)>,
+) -> Option<(Entity, f32)> {
+    atoms
+        .into_iter()
+        .filter_map(|(entity, position, radius)| {
+            ray_sphere_intersection(ray_origin, ray_dir, position, radius)
+                .map(|distance| (entity, distance))
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+}
+
+/// Objects the display keys act on: the index and roots of every object
+/// with selected atoms, or no index and every root when nothing is selected.
+fn key_targets<'a>(
+    selection: &Selection,
+    project_entities: &'a ProjectEntities,
+) -> Vec<(Option<usize>, &'a [Entity])> {
+    let residues = selection.residues();
+    let objects: Vec<(Option<usize>, &[Entity])> = project_entities
+        .objects
+        .iter()
+        .enumerate()
+        .filter(|(_, roots)| residues.iter().any(|residue| roots.contains(residue)))
+        .map(|(index, roots)| (Some(index), &roots[..]))
+        .collect();
+    if objects.is_empty() {
+        return vec![(None, &project_entities.roots[..])];
+    }
+    objects
+}
+
 pub(crate) struct Interaction;
 
 impl Interaction {
     pub(crate) fn init(app: &mut App) {
         app.add_systems(Startup, Self::setup)
-            .add_systems(Update, Self::handle_input)
+            .add_systems(Update, Self::handle_input.run_if(not(text_input_focused)))
+            .add_systems(Update, Self::update_pick_bounds)
             .add_systems(Update, Self::update_hovered_object)
+            .add_systems(Update, Self::update_hovered_atom)
             .add_systems(Update, Self::camera_controls)
-            .add_systems(Update, Self::handle_grab)
+            .add_systems(
+                Update,
+                Self::handle_grab.run_if(|tool: Res<MeasurementTool>| !tool.is_active()),
+            )
+            .add_systems(
+                Update,
+                Self::handle_measurement_key.run_if(not(text_input_focused)),
+            )
+            .add_systems(Update, Self::handle_measurement_pick)
             .add_systems(
                 Update,
                 Self::update_held_objects.run_if(Self::any_object_held),
             )
             .add_systems(Update, Self::handle_scroll_adjust_distance)
-            .add_systems(Last, Self::draw_highlight);
+            .add_systems(Update, Self::handle_residue_selection)
+            .add_systems(Update, Self::handle_drag_selection)
+            .add_systems(PostUpdate, Self::update_selected_residue)
+            .add_systems(
+                Update,
+                (
+                    Self::handle_rotamer_keys,
+                    Self::handle_energy_key,
+                    Self::handle_dynamics_keys,
+                    Self::handle_minimize_key,
+                    Self::handle_representation_key,
+                    Self::handle_color_scheme_key,
+                    Self::handle_history_keys,
+                )
+                    .run_if(not(text_input_focused)),
+            )
+            .add_systems(Last, Self::draw_highlight)
+            .add_systems(Last, Self::draw_selected_residue)
+            .add_systems(Last, Self::draw_drag_selection);
     }
 
     pub(crate) fn setup(mut commands: Commands) {
         commands.insert_resource(HoveredMovable { entity: None });
+        commands.insert_resource(SelectedResidue::default());
+        commands.insert_resource(DragSelection::default());
+        commands.insert_resource(HoveredAtom::default());
         commands.insert_resource(LastCameraPosition {
             position: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
             frame_count: 0,
+            pending: true,
         });
     }
 
@@ -111,12 +546,51 @@ impl Interaction {
         }
     }
 
+    /// Fits the pick bounds of movable objects around their atoms whenever
+    /// an atom is added or moved. The bounds enclose the van der Waals
+    /// spheres, the largest any representation draws atoms with, so they
+    /// hold however the object is drawn.
+    pub(crate) fn update_pick_bounds(
+        mut commands: Commands,
+        changed_atoms: Query<&ChildOf, (With<Atom>, Changed<Transform>)>,
+        movable_query: Query<&Children, With<Movable>>,
+        atom_query: Query<(&Atom, &Transform)>,
+    ) {
+        let mut parents: Vec<Entity> = changed_atoms.iter().map(ChildOf::parent).collect();
+        parents.sort();
+        parents.dedup();
+
+        for parent in parents {
+            let Ok(children) = movable_query.get(parent) else {
+                continue;
+            };
+            let spheres = children
+                .iter()
+                .filter_map(|child| atom_query.get(child).ok())
+                .map(|(atom, transform)| {
+                    (transform.translation, atom.element.van_der_waals_radius())
+                });
+            if let Some(bounds) = PickBounds::from_spheres(spheres) {
+                commands.entity(parent).insert(bounds);
+            }
+        }
+    }
+
+    /// Picks the movable object under the crosshair when the camera or the
+    /// mouse moved, or when pick bounds changed because atoms moved or
+    /// objects were added.
     pub(crate) fn update_hovered_object(
         camera_data: Res<CameraTransformData>,
         mut hovered: ResMut<HoveredMovable>,
-        movable_query: Query<(Entity, &Transform), With<Movable>>,
+        picker: MovablePicker,
         mut last_cam: ResMut<LastCameraPosition>,
+        mut mouse_motion: MessageReader<MouseMotion>,
+        changed_bounds: Query<(), (With<Movable>, Changed<PickBounds>)>,
     ) {
+        if mouse_motion.read().count() > 0 || !changed_bounds.is_empty() {
+            last_cam.pending = true;
+        }
+
         // Throttle: only run every 3rd frame (20 times/sec instead of 60)
         last_cam.frame_count += 1;
         if !last_cam.frame_count.is_multiple_of(3) {
@@ -124,51 +598,78 @@ impl Interaction {
         }
 
         let current_pos = camera_data.transform.translation;
-        let moved = (current_pos - last_cam.position).length_squared() > 0.001;
+        let current_rotation = camera_data.transform.rotation;
+        let moved = (current_pos - last_cam.position).length_squared() > 0.001
+            || !current_rotation.abs_diff_eq(last_cam.rotation, 1e-4);
 
-        if !moved {
+        if !moved && !last_cam.pending {
             return;
         }
 
         last_cam.position = current_pos;
+        last_cam.rotation = current_rotation;
+        last_cam.pending = false;
 
         let ray_origin = camera_data.transform.translation;
         let ray_dir = camera_data.transform.forward().as_vec3();
 
-        let mut closest_entity = None;
-        let mut closest_distance = f32::MAX;
+        hovered.entity = picker.pick(ray_origin, ray_dir).map(|(entity, _)| entity);
+    }
 
-        for (entity, transform) in movable_query.iter() {
-            if let Some(distance) =
-                ray_box_intersection(ray_origin, ray_dir, transform.translation, Vec3::splat(0.5))
-                && distance < closest_distance
-                && distance > 0.0
-            {
-                closest_distance = distance;
-                closest_entity = Some(entity);
-            }
-        }
+    /// Tracks the atom under the crosshair every frame, since atoms move
+    /// during simulations even when the camera does not.
+    pub(crate) fn update_hovered_atom(
+        camera_data: Res<CameraTransformData>,
+        controller_query: Query<&CameraController>,
+        mut hovered: ResMut<HoveredAtom>,
+        atom_query: Query<(Entity, &Atom, &GlobalTransform, Option<&ChildOf>)>,
+        representation_query: Query<&Representation>,
+    ) {
+        let captured = controller_query
+            .single()
+            .is_ok_and(|controller| controller.is_captured);
 
-        hovered.entity = closest_entity;
+        let entity = if captured {
+            let ray_origin = camera_data.transform.translation;
+            let ray_dir = camera_data.transform.forward().as_vec3();
+            let atoms = atom_query
+                .iter()
+                .map(|(entity, atom, transform, child_of)| {
+                    let representation = child_of
+                        .and_then(|child_of| representation_query.get(child_of.parent()).ok())
+                        .copied()
+                        .unwrap_or_default();
+                    (
+                        entity,
+                        transform.translation(),
+                        representation.pick_radius(atom.element),
+                    )
+                });
+            closest_atom(ray_origin, ray_dir, atoms).map(|(entity, _)| entity)
+        } else {
+            None
+        };
+
+        if hovered.entity != entity {
+            hovered.entity = entity;
+        }
     }
 
+    /// Outlines the pick box of the held object, or else the hovered one.
     pub(crate) fn draw_highlight(
         mut gizmos: Gizmos,
         hovered: Res<HoveredMovable>,
-        movable_query: Query<&Transform, With<Movable>>,
-        held_query: Query<(Entity, &Transform), With<HeldMovable>>,
+        movable_query: Query<(&GlobalTransform, &PickBounds), With<Movable>>,
+        held_query: Query<Entity, With<HeldMovable>>,
     ) {
-        match held_query.single() {
-            Ok((_, transform)) => {
-                gizmos.cube(*transform, Color::WHITE);
-            }
-            Err(_) => {
-                if let Some(entity) = hovered.entity
-                    && let Ok(transform) = movable_query.get(entity)
-                {
-                    gizmos.cube(*transform, Color::WHITE);
-                }
-            }
+        let Some(entity) = held_query.iter().next().or(hovered.entity) else {
+            return;
+        };
+
+        if let Ok((transform, bounds)) = movable_query.get(entity) {
+            let local =
+                Transform::from_translation(bounds.center).with_scale(2.0 * bounds.half_extents);
+            gizmos.cube(transform.mul_transform(local), Color::WHITE);
         }
     }
 
@@ -178,7 +679,7 @@ impl Interaction {
         camera_data: Res<CameraTransformData>,
         controller_query: Query<&CameraController>,
         hovered: Res<HoveredMovable>,
-        movable_query: Query<(Entity, &Transform), With<Movable>>,
+        picker: MovablePicker,
         held_query: Query<(Entity, &HeldMovable)>,
     ) {
         let Ok(controller) = controller_query.single() else {
@@ -205,16 +706,398 @@ impl Interaction {
             let ray_origin = camera_data.transform.translation;
             let ray_dir = camera_data.transform.forward().as_vec3();
 
-            if let Ok((_, transform)) = movable_query.get(entity)
-                && let Some(distance) = ray_box_intersection(
-                    ray_origin,
-                    ray_dir,
-                    transform.translation,
-                    Vec3::splat(0.5),
-                )
+            if let Some(held) = picker.grab(entity, ray_origin, ray_dir) {
+                commands.entity(entity).insert(held);
+            }
+        }
+    }
+
+    /// Right click selects the residue of the atom under the crosshair, or
+    /// clears the selection when no atom is hit. Shift+right click adds the
+    /// residue to the selection or removes it.
+    pub(crate) fn handle_residue_selection(
+        mouse_button: Res<ButtonInput<MouseButton>>,
+        keyboard: Res<ButtonInput<KeyCode>>,
+        camera_data: Res<CameraTransformData>,
+        controller_query: Query<&CameraController>,
+        mut selection: ResMut<Selection>,
+        atoms: SelectableAtoms,
+    ) {
+        let Ok(controller) = controller_query.single() else {
+            return;
+        };
+
+        if !controller.is_captured || !mouse_button.just_pressed(MouseButton::Right) {
+            return;
+        }
+
+        select_residue(
+            &mut selection,
+            &atoms,
+            camera_data.transform.translation,
+            camera_data.transform.forward().as_vec3(),
+            shift_pressed(&keyboard),
+        );
+    }
+
+    /// With the cursor released, left click selects the residue under the
+    /// cursor and dragging selects every atom inside a box, or a lasso while
+    /// Alt is held. Shift adds to the selection instead of replacing it.
+    pub(crate) fn handle_drag_selection(
+        mouse_button: Res<ButtonInput<MouseButton>>,
+        keyboard: Res<ButtonInput<KeyCode>>,
+        picker: ScreenPicker,
+        mut drag: ResMut<DragSelection>,
+        mut selection: ResMut<Selection>,
+        atoms: SelectableAtoms,
+    ) {
+        if picker.is_captured() {
+            drag.points.clear();
+            return;
+        }
+
+        if mouse_button.just_pressed(MouseButton::Left) {
+            drag.points.clear();
+            if let Some(cursor) = picker.cursor()
+                && !picker.cursor_over_ui()
             {
-                commands.entity(entity).insert(HeldMovable { distance });
+                drag.points.push(cursor);
+                drag.lasso = keyboard.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]);
             }
+            return;
+        }
+        let Some(&last) = drag.points.last() else {
+            return;
+        };
+
+        if mouse_button.pressed(MouseButton::Left) {
+            if let Some(cursor) = picker.cursor()
+                && cursor.distance(last) >= 2.0
+            {
+                drag.points.push(cursor);
+            }
+            return;
+        }
+
+        let shift = shift_pressed(&keyboard);
+        if drag.is_click() {
+            if let Some(ray) = picker.ray(drag.points[0]) {
+                select_residue(&mut selection, &atoms, ray.origin, *ray.direction, shift);
+            }
+        } else {
+            let inside: Vec<(Entity, Entity)> = atoms
+                .iter()
+                .filter(|(.., transform)| {
+                    picker
+                        .project(transform.translation())
+                        .is_some_and(|screen| drag.contains(screen))
+                })
+                .map(|(atom, residue, ..)| (atom, residue))
+                .collect();
+            let mode = if shift {
+                SelectionMode::Add
+            } else {
+                SelectionMode::Replace
+            };
+            selection.apply(inside, mode);
+        }
+        drag.points.clear();
+    }
+
+    /// Keeps the residue of residue editing tools in sync with the selection.
+    pub(crate) fn update_selected_residue(
+        selection: Res<Selection>,
+        mut selected: ResMut<SelectedResidue>,
+    ) {
+        if !selection.is_changed() {
+            return;
+        }
+
+        let residues = selection.residues();
+        let entity = match residues[..] {
+            [residue] => Some(residue),
+            _ => None,
+        };
+        if selected.entity != entity {
+            selected.entity = entity;
+        }
+    }
+
+    /// R cycles the selected residue through its rotamers.
+    pub(crate) fn handle_rotamer_keys(
+        keyboard: Res<ButtonInput<KeyCode>>,
+        controller_query: Query<&CameraController>,
+        selected: Res<SelectedResidue>,
+        mut messages: MessageWriter<SetRotamerMessage>,
+    ) {
+        let Ok(controller) = controller_query.single() else {
+            return;
+        };
+
+        if controller.is_captured
+            && keyboard.just_pressed(KeyCode::KeyR)
+            && let Some(residue) = selected.entity
+        {
+            messages.write(SetRotamerMessage {
+                residue,
+                change: RotamerChange::Next,
+            });
+        }
+    }
+
+    /// T cycles the measurement tool through distance, angle, dihedral and
+    /// off.
+    pub(crate) fn handle_measurement_key(
+        keyboard: Res<ButtonInput<KeyCode>>,
+        controller_query: Query<&CameraController>,
+        mut tool: ResMut<MeasurementTool>,
+    ) {
+        let Ok(controller) = controller_query.single() else {
+            return;
+        };
+
+        if controller.is_captured && keyboard.just_pressed(KeyCode::KeyT) {
+            tool.kind = MeasurementKind::next(tool.kind);
+            tool.picked.clear();
+        }
+    }
+
+    /// Left click picks the hovered atom while the measurement tool is active,
+    /// and adds the measurement once enough atoms are picked.
+    pub(crate) fn handle_measurement_pick(
+        mouse_button: Res<ButtonInput<MouseButton>>,
+        controller_query: Query<&CameraController>,
+        hovered: Res<HoveredAtom>,
+        mut tool: ResMut<MeasurementTool>,
+        mut messages: MessageWriter<AddMeasurementMessage>,
+    ) {
+        let Ok(controller) = controller_query.single() else {
+            return;
+        };
+        let Some(kind) = tool.kind else {
+            return;
+        };
+
+        if !controller.is_captured || !mouse_button.just_pressed(MouseButton::Left) {
+            return;
+        }
+
+        let Some(atom) = hovered.entity else {
+            return;
+        };
+        if tool.picked.last() == Some(&atom) {
+            return;
+        }
+
+        tool.picked.push(atom);
+        if tool.picked.len() == kind.atom_count() {
+            messages.write(AddMeasurementMessage {
+                atoms: std::mem::take(&mut tool.picked),
+            });
+        }
+    }
+
+    /// E evaluates the force field on the scene.
+    pub(crate) fn handle_energy_key(
+        keyboard: Res<ButtonInput<KeyCode>>,
+        controller_query: Query<&CameraController>,
+        mut messages: MessageWriter<ComputeEnergyMessage>,
+    ) {
+        let Ok(controller) = controller_query.single() else {
+            return;
+        };
+
+        if controller.is_captured && keyboard.just_pressed(KeyCode::KeyE) {
+            messages.write(ComputeEnergyMessage);
+        }
+    }
+
+    /// P starts or pauses the simulation, N integrates a single step.
+    pub(crate) fn handle_dynamics_keys(
+        keyboard: Res<ButtonInput<KeyCode>>,
+        controller_query: Query<&CameraController>,
+        mut messages: MessageWriter<DynamicsControlMessage>,
+    ) {
+        let Ok(controller) = controller_query.single() else {
+            return;
+        };
+        if !controller.is_captured {
+            return;
+        }
+
+        if keyboard.just_pressed(KeyCode::KeyP) {
+            messages.write(DynamicsControlMessage {
+                control: DynamicsControl::Toggle,
+            });
+        }
+        if keyboard.just_pressed(KeyCode::KeyN) {
+            messages.write(DynamicsControlMessage {
+                control: DynamicsControl::Step,
+            });
+        }
+    }
+
+    /// M minimizes the selected atoms, or the whole project when nothing is
+    /// selected.
+    pub(crate) fn handle_minimize_key(
+        keyboard: Res<ButtonInput<KeyCode>>,
+        controller_query: Query<&CameraController>,
+        selection: Res<Selection>,
+        mut messages: MessageWriter<MinimizeMessage>,
+    ) {
+        let Ok(controller) = controller_query.single() else {
+            return;
+        };
+
+        if controller.is_captured && keyboard.just_pressed(KeyCode::KeyM) {
+            let scope = if selection.is_empty() {
+                MinimizationScope::Project
+            } else {
+                MinimizationScope::Atoms(selection.atoms().to_vec())
+            };
+            messages.write(MinimizeMessage { scope });
+        }
+    }
+
+    /// V switches the objects with selected atoms to their next
+    /// representation, or every object when nothing is selected.
+    pub(crate) fn handle_representation_key(
+        keyboard: Res<ButtonInput<KeyCode>>,
+        controller_query: Query<&CameraController>,
+        selection: Res<Selection>,
+        project_entities: Res<ProjectEntities>,
+        representation_query: Query<&Representation>,
+        mut messages: MessageWriter<SetRepresentationMessage>,
+    ) {
+        let Ok(controller) = controller_query.single() else {
+            return;
+        };
+        if !controller.is_captured || !keyboard.just_pressed(KeyCode::KeyV) {
+            return;
+        }
+
+        for (object, roots) in key_targets(&selection, &project_entities) {
+            let current = roots
+                .iter()
+                .find_map(|&root| representation_query.get(root).ok())
+                .copied()
+                .unwrap_or_default();
+
+            messages.write(SetRepresentationMessage {
+                object,
+                representation: current.next(),
+            });
+        }
+    }
+
+    /// K switches the objects with selected atoms to their next color
+    /// scheme, or every object when nothing is selected.
+    pub(crate) fn handle_color_scheme_key(
+        keyboard: Res<ButtonInput<KeyCode>>,
+        controller_query: Query<&CameraController>,
+        selection: Res<Selection>,
+        project_entities: Res<ProjectEntities>,
+        color_scheme_query: Query<&ColorScheme>,
+        mut messages: MessageWriter<SetColorSchemeMessage>,
+    ) {
+        let Ok(controller) = controller_query.single() else {
+            return;
+        };
+        if !controller.is_captured || !keyboard.just_pressed(KeyCode::KeyK) {
+            return;
+        }
+
+        for (object, roots) in key_targets(&selection, &project_entities) {
+            let current = roots
+                .iter()
+                .find_map(|&root| color_scheme_query.get(root).ok())
+                .copied()
+                .unwrap_or_default();
+
+            messages.write(SetColorSchemeMessage {
+                object,
+                color_scheme: current.next(),
+            });
+        }
+    }
+
+    /// Ctrl+Z undoes the last scene edit, Ctrl+Shift+Z or Ctrl+Y redoes it.
+    /// Works with and without the cursor captured.
+    pub(crate) fn handle_history_keys(
+        keyboard: Res<ButtonInput<KeyCode>>,
+        mut messages: MessageWriter<HistoryMessage>,
+    ) {
+        if !keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+            return;
+        }
+
+        let shift = keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+        let action = if keyboard.just_pressed(KeyCode::KeyZ) && !shift {
+            HistoryAction::Undo
+        } else if keyboard.just_pressed(KeyCode::KeyZ) || keyboard.just_pressed(KeyCode::KeyY) {
+            HistoryAction::Redo
+        } else {
+            return;
+        };
+        messages.write(HistoryMessage { action });
+    }
+
+    pub(crate) fn draw_selected_residue(
+        mut gizmos: Gizmos,
+        mut selected: ResMut<SelectedResidue>,
+        residue_query: Query<&Children, With<AminoAcid>>,
+        atom_query: Query<&GlobalTransform, With<Atom>>,
+    ) {
+        let Some(entity) = selected.entity else {
+            return;
+        };
+
+        // The residue may have been despawned by a project switch.
+        let Ok(children) = residue_query.get(entity) else {
+            selected.entity = None;
+            return;
+        };
+
+        let positions: Vec<Vec3> = children
+            .iter()
+            .filter_map(|child| atom_query.get(child).ok())
+            .map(GlobalTransform::translation)
+            .collect();
+        if positions.is_empty() {
+            return;
+        }
+
+        let center = positions.iter().sum::<Vec3>() / positions.len() as f32;
+        let radius = positions
+            .iter()
+            .map(|position| position.distance(center))
+            .fold(0.0, f32::max);
+
+        gizmos.sphere(
+            Isometry3d::from_translation(center),
+            radius + 0.5,
+            SELECTION_COLOR,
+        );
+    }
+
+    /// Outlines the box or lasso being dragged, just in front of the camera.
+    pub(crate) fn draw_drag_selection(
+        mut gizmos: Gizmos,
+        drag: Res<DragSelection>,
+        picker: ScreenPicker,
+    ) {
+        if drag.is_click() {
+            return;
+        }
+
+        let outline: Vec<Vec3> = drag
+            .outline()
+            .into_iter()
+            .filter_map(|screen| picker.ray(screen))
+            .map(|ray| ray.get_point(OUTLINE_DISTANCE))
+            .collect();
+        if let Some(&first) = outline.first() {
+            gizmos.linestrip(outline.into_iter().chain([first]), SELECTION_COLOR);
         }
     }
 
@@ -226,7 +1109,7 @@ impl Interaction {
         let cam_pos = camera_data.transform.translation;
 
         for (held, mut transform) in held_query.iter_mut() {
-            let target_pos = cam_pos + forward * held.distance;
+            let target_pos = cam_pos + forward * held.distance + held.offset;
             transform.translation = transform.translation.lerp(target_pos, 0.3);
         }
     }
@@ -313,3 +1196,91 @@ impl Interaction {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pick_bounds_enclose_every_sphere() {
+        let spheres = [
+            (Vec3::new(-1.0, 0.0, 0.0), 0.5),
+            (Vec3::new(2.0, 1.0, 0.0), 1.0),
+            (Vec3::new(0.0, -1.0, 3.0), 0.25),
+        ];
+        let bounds = PickBounds::from_spheres(spheres).unwrap();
+
+        assert_eq!(bounds.center, Vec3::new(0.75, 0.375, 1.125));
+        assert_eq!(bounds.half_extents, Vec3::new(2.25, 1.625, 2.125));
+        for (center, radius) in spheres {
+            let offset = (center - bounds.center).abs() + radius;
+            assert!(offset.cmple(bounds.half_extents + 1e-5).all());
+            assert!(center.distance(bounds.center) + radius <= bounds.radius + 1e-5);
+        }
+        // The sphere is tighter than the box's circumscribed sphere.
+        assert!(bounds.radius < bounds.half_extents.length());
+
+        let single = PickBounds::from_spheres([(Vec3::ONE, 1.5)]).unwrap();
+        assert_eq!(single.center, Vec3::ONE);
+        assert_eq!(single.half_extents, Vec3::splat(1.5));
+        assert_eq!(single.radius, 1.5);
+
+        assert_eq!(PickBounds::from_spheres([]), None);
+    }
+
+    #[test]
+    fn rays_hit_the_near_side_of_spheres_in_front() {
+        let center = Vec3::new(0.0, 0.0, -5.0);
+
+        let hit = ray_sphere_intersection(Vec3::ZERO, Vec3::NEG_Z, center, 1.0);
+        assert!((hit.unwrap() - 4.0).abs() < 1e-5);
+
+        // A ray grazing the sphere at 0.8 off its center.
+        let grazing = ray_sphere_intersection(Vec3::new(0.8, 0.0, 0.0), Vec3::NEG_Z, center, 1.0);
+        assert!((grazing.unwrap() - 4.4).abs() < 1e-5);
+
+        assert_eq!(
+            ray_sphere_intersection(Vec3::new(1.5, 0.0, 0.0), Vec3::NEG_Z, center, 1.0),
+            None
+        );
+        // Spheres behind the ray are missed.
+        assert_eq!(
+            ray_sphere_intersection(Vec3::ZERO, Vec3::Z, center, 1.0),
+            None
+        );
+        // From inside, the ray hits the far side.
+        let inside = ray_sphere_intersection(center, Vec3::NEG_Z, center, 1.0);
+        assert!((inside.unwrap() - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn polygons_contain_points_inside_their_edges() {
+        let square = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(4.0, 0.0),
+            Vec2::new(4.0, 4.0),
+            Vec2::new(0.0, 4.0),
+        ];
+        assert!(polygon_contains(&square, Vec2::new(2.0, 2.0)));
+        assert!(!polygon_contains(&square, Vec2::new(5.0, 2.0)));
+        assert!(!polygon_contains(&square, Vec2::new(2.0, -1.0)));
+
+        // A lasso shaped like a U does not contain the gap between its arms.
+        let u_shape = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(3.0, 0.0),
+            Vec2::new(3.0, 3.0),
+            Vec2::new(2.0, 3.0),
+            Vec2::new(2.0, 1.0),
+            Vec2::new(1.0, 1.0),
+            Vec2::new(1.0, 3.0),
+            Vec2::new(0.0, 3.0),
+        ];
+        assert!(polygon_contains(&u_shape, Vec2::new(0.5, 2.0)));
+        assert!(polygon_contains(&u_shape, Vec2::new(2.5, 2.0)));
+        assert!(polygon_contains(&u_shape, Vec2::new(1.5, 0.5)));
+        assert!(!polygon_contains(&u_shape, Vec2::new(1.5, 2.0)));
+
+        assert!(!polygon_contains(&[], Vec2::ZERO));
+    }
+}
@@ -2,8 +2,11 @@ use bevy::prelude::*;
 
 use crate::{
     chemistry::ChemistryPlugin,
+    measurements::MeasurementPlugin,
     objects::{dev_cube::DevCubePlugin, grid::GridPlugin},
+    physics::PhysicsPlugin,
     projects::{ProjectPlugin, ProjectResource, templates::ProjectTemplates},
+    selection::SelectionPlugin,
     ui::UIPlugin,
     user::UserPlugin,
 };
@@ -16,6 +19,9 @@ impl World {
         app.add_plugins(DevCubePlugin);
         app.add_plugins(ChemistryPlugin);
         app.add_plugins(ProjectPlugin);
+        app.add_plugins(PhysicsPlugin);
+        app.add_plugins(MeasurementPlugin);
+        app.add_plugins(SelectionPlugin);
         app.add_plugins(UserPlugin);
         app.add_plugins(UIPlugin);
 